@@ -71,7 +71,10 @@ mod tests {
                 entry_type::{AppEntryType, EntryType},
                 Entry,
             },
-            validation::{EntryLifecycle, EntryValidationData, ValidationData, ValidationPackage},
+            validation::{
+                EntryLifecycle, EntryValidationData, ValidationData, ValidationOrigin,
+                ValidationPackage,
+            },
         },
         holochain_json_api::json::JsonString,
     };
@@ -117,6 +120,12 @@ mod tests {
         let validation_data = ValidationData {
             package: ValidationPackage::only_header(test_chain_header()),
             lifecycle: EntryLifecycle::Chain,
+            origin: ValidationOrigin::Local,
+            dna_properties: JsonString::empty_object(),
+            seed: 0,
+            timestamp: None,
+            zome_name: None,
+            dna_hash: None,
         };
         assert_eq!(
             (memo_definition.validator)(EntryValidationData::Create {
@@ -119,7 +119,10 @@ mod tests {
                 entry_type::{AppEntryType, EntryType},
                 Entry,
             },
-            validation::{EntryLifecycle, EntryValidationData, ValidationData, ValidationPackage},
+            validation::{
+                EntryLifecycle, EntryValidationData, ValidationData, ValidationOrigin,
+                ValidationPackage,
+            },
         },
         holochain_json_api::json::JsonString,
         holochain_wasm_utils::api_serialization::validation::LinkDirection,
@@ -151,10 +154,12 @@ mod tests {
                 LinkedFrom {
                     base_type: "%agent_id".to_string(),
                     link_type: "authored_posts".to_string(),
+                    validation_package: None,
                 },
                 LinkedFrom {
                     base_type: "%agent_id".to_string(),
                     link_type: "recommended_posts".to_string(),
+                    validation_package: None,
                 },
             ],
             links_to: Vec::new(),
@@ -176,6 +181,12 @@ mod tests {
         let validation_data = ValidationData {
             package: ValidationPackage::only_header(test_chain_header()),
             lifecycle: EntryLifecycle::Chain,
+            origin: ValidationOrigin::Local,
+            dna_properties: JsonString::empty_object(),
+            seed: 0,
+            timestamp: None,
+            zome_name: None,
+            dna_hash: None,
         };
         assert_eq!(
             (post_definition.validator)(EntryValidationData::Create {
@@ -197,6 +208,12 @@ mod tests {
         let validation_data = ValidationData {
             package: ValidationPackage::only_header(test_chain_header()),
             lifecycle: EntryLifecycle::Chain,
+            origin: ValidationOrigin::Local,
+            dna_properties: JsonString::empty_object(),
+            seed: 0,
+            timestamp: None,
+            zome_name: None,
+            dna_hash: None,
         };
         assert_eq!(
             (post_definition.validator)(EntryValidationData::Create {
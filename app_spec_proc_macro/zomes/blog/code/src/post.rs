@@ -118,7 +118,10 @@ mod tests {
                 entry_type::{AppEntryType, EntryType},
                 Entry,
             },
-            validation::{EntryLifecycle, EntryValidationData, ValidationData, ValidationPackage},
+            validation::{
+                EntryLifecycle, EntryValidationData, ValidationData, ValidationOrigin,
+                ValidationPackage,
+            },
         },
         holochain_json_api::json::JsonString,
         holochain_wasm_utils::api_serialization::validation::LinkDirection,
@@ -150,10 +153,12 @@ mod tests {
                 LinkedFrom {
                     base_type: "%agent_id".to_string(),
                     link_type: "authored_posts".to_string(),
+                    validation_package: None,
                 },
                 LinkedFrom {
                     base_type: "%agent_id".to_string(),
                     link_type: "recommended_posts".to_string(),
+                    validation_package: None,
                 },
             ],
             links_to: Vec::new(),
@@ -175,6 +180,12 @@ mod tests {
         let validation_data = ValidationData {
             package: ValidationPackage::only_header(test_chain_header()),
             lifecycle: EntryLifecycle::Chain,
+            origin: ValidationOrigin::Local,
+            dna_properties: JsonString::empty_object(),
+            seed: 0,
+            timestamp: None,
+            zome_name: None,
+            dna_hash: None,
         };
         assert_eq!(
             (post_definition.validator)(EntryValidationData::Create {
@@ -196,6 +207,12 @@ mod tests {
         let validation_data = ValidationData {
             package: ValidationPackage::only_header(test_chain_header()),
             lifecycle: EntryLifecycle::Chain,
+            origin: ValidationOrigin::Local,
+            dna_properties: JsonString::empty_object(),
+            seed: 0,
+            timestamp: None,
+            zome_name: None,
+            dna_hash: None,
         };
         assert_eq!(
             (post_definition.validator)(EntryValidationData::Create {
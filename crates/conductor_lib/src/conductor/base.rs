@@ -14,7 +14,9 @@ use crate::{
 };
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use holochain_common::paths::DNA_EXTENSION;
-use holochain_core::{logger::Logger, signal::Signal};
+use holochain_core::{
+    logger::Logger, signal::Signal, wasm_engine::api::validation_oracle::ValidationOracle,
+};
 use holochain_core_types::{
     agent::AgentId,
     dna::Dna,
@@ -128,6 +130,7 @@ pub struct Conductor {
     pub(in crate::conductor) dna_loader: DnaLoader,
     pub(in crate::conductor) ui_dir_copier: UiDirCopier,
     signal_tx: Option<SignalSender>,
+    validation_oracle: Option<ValidationOracle>,
     logger: FastLogger,
     p2p_config: Option<P2pConfig>,
     pub passphrase_manager: Arc<PassphraseManager>,
@@ -233,6 +236,7 @@ impl Conductor {
             dna_loader: Arc::new(Box::new(Self::load_dna)),
             ui_dir_copier: Arc::new(Box::new(Self::copy_ui_dir)),
             signal_tx: None,
+            validation_oracle: None,
             logger,
             p2p_config: None,
             passphrase_manager: Arc::new(PassphraseManager::new(passphrase_service)),
@@ -302,6 +306,13 @@ impl Conductor {
         self
     }
 
+    /// Sets the oracle that answers the requests validators of instances created
+    /// from now on make with `hdk::validation_oracle_query()`, e.g. by calling a bridged service.
+    pub fn with_validation_oracle(mut self, oracle: ValidationOracle) -> Self {
+        self.validation_oracle = Some(oracle);
+        self
+    }
+
     pub fn config(&self) -> Configuration {
         self.config.clone()
     }
@@ -842,6 +853,10 @@ impl Conductor {
 
                 context_builder = context_builder.with_signals(sender);
 
+                if let Some(oracle) = self.validation_oracle.clone() {
+                    context_builder = context_builder.with_validation_oracle(oracle);
+                }
+
                 if let Some((tracer, span_rx, reporter)) = self.build_tracer_and_reporter_for_instance(&instance_name) {
                     context_builder = context_builder.with_tracer(tracer);
                     self.trace_reporters.write().unwrap().insert(instance_name.clone(), (span_rx, reporter));
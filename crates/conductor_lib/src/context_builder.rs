@@ -1,4 +1,7 @@
-use holochain_core::{context::Context, persister::SimplePersister, signal::SignalSender};
+use holochain_core::{
+    context::Context, persister::SimplePersister, signal::SignalSender,
+    wasm_engine::api::validation_oracle::ValidationOracle,
+};
 use holochain_core_types::{agent::AgentId, eav::Attribute, error::HolochainError};
 use holochain_locksmith::RwLock;
 use holochain_net::p2p_config::P2pConfig;
@@ -43,6 +46,7 @@ pub struct ContextBuilder {
     tracer: Option<holochain_tracing::Tracer>,
     state_dump_logging: bool,
     metric_publisher: Option<Arc<RwLock<dyn MetricPublisher>>>,
+    validation_oracle: Option<ValidationOracle>,
 }
 
 impl ContextBuilder {
@@ -59,6 +63,7 @@ impl ContextBuilder {
             tracer: None,
             state_dump_logging: false,
             metric_publisher: None,
+            validation_oracle: None,
         }
     }
 
@@ -195,6 +200,13 @@ impl ContextBuilder {
         self
     }
 
+    /// Sets the oracle that answers the requests validators make
+    /// with `hdk::validation_oracle_query()`.
+    pub fn with_validation_oracle(mut self, oracle: ValidationOracle) -> Self {
+        self.validation_oracle = Some(oracle);
+        self
+    }
+
     /// Actually creates the context.
     /// Defaults to memory storages, an in-memory network config and a fake agent called "alice".
     /// The persister gets set to SimplePersister based on the chain storage.
@@ -212,7 +224,7 @@ impl ContextBuilder {
             .metric_publisher
             .unwrap_or_else(|| Arc::new(RwLock::new(DefaultMetricPublisher::default())));
 
-        Context::new(
+        let context = Context::new(
             &self
                 .instance_name
                 .unwrap_or_else(|| "Anonymous-instance".to_string()),
@@ -233,7 +245,9 @@ impl ContextBuilder {
                 self.tracer
                     .unwrap_or_else(|| holochain_tracing::null_tracer()),
             ),
-        )
+        );
+        context.set_validation_oracle(self.validation_oracle);
+        context
     }
 }
 
@@ -853,7 +853,7 @@ mod tests {
             let msg_publish = signal_rx
                 .recv_timeout(Duration::from_millis(timeout))
                 .expect("no more signals to receive (outer)");
-            if let Signal::Trace(Action::Publish(address)) = msg_publish {
+            if let Signal::Trace(Action::Publish((address, _, _))) = msg_publish {
                 loop {
                     let msg_hold = signal_rx
                         .recv_timeout(Duration::from_millis(timeout))
@@ -2,6 +2,9 @@ use crate::{
     agent::state::AgentState,
     dht::pending_validations::PendingValidation,
     network::{
+        actions::publish::PublishPriority,
+        correlation::CorrelationId,
+        dht_arc::DhtArc,
         direct_message::DirectMessage,
         entry_aspect::EntryAspect,
         entry_with_header::EntryWithHeader,
@@ -17,8 +20,13 @@ use crate::{
 };
 
 use holochain_core_types::{
-    chain_header::ChainHeader, crud_status::CrudStatus, dna::Dna, entry::Entry,
-    signature::Provenance, validation::ValidationPackage,
+    chain_header::ChainHeader,
+    crud_status::CrudStatus,
+    dna::Dna,
+    entry::Entry,
+    network::aspect_chunk::{AspectChunk, AspectChunkManifest},
+    signature::Provenance,
+    validation::ValidationPackage,
 };
 use holochain_net::{connection::net_connection::NetHandler, p2p_config::P2pConfig};
 use holochain_persistence_api::cas::content::Address;
@@ -61,6 +69,16 @@ impl ActionWrapper {
     pub fn id(&self) -> &String {
         &self.id
     }
+
+    /// Key for looking up the response to the action with the given id.
+    /// Wrappers compare by id only, so the wrapped action doesn't matter.
+    #[cfg(test)]
+    pub(crate) fn key_for_id(id: &str) -> Self {
+        ActionWrapper {
+            action: Action::Prune,
+            id: id.to_string(),
+        }
+    }
 }
 
 impl PartialEq for ActionWrapper {
@@ -120,7 +138,32 @@ pub enum Action {
 
     /// Adds an entry aspect to the local DHT shard.
     /// Does not validate, assumes referenced entry is valid.
-    HoldAspect(EntryAspect),
+    /// Optionally carries the address of the agent we got the aspect from,
+    /// and the time the hold got dispatched at, which failed holds get retried relative to.
+    HoldAspect((EntryAspect, Option<Address>, SystemTime)),
+
+    /// Adds an entry aspect to the local DHT shard even if storing it fails,
+    /// e.g. because entries it references are missing. Unsafe, only meant for
+    /// seeding a node with known-good data (see `force_hold_aspect()`).
+    ForceHoldAspect(EntryAspect),
+
+    /// Adds all of the entry aspects to the local DHT shard, or none of them
+    /// if any of them can't be stored (see `hold_bundle()`).
+    HoldBundle(Vec<EntryAspect>),
+
+    /// Stores the entry aspect without validating or holding it yet
+    /// (see `HoldPolicy::LazyValidate`).
+    HoldUnvalidatedAspect(EntryAspect),
+
+    /// Rejects an aspect that was stored unvalidated and turned out to be invalid.
+    RejectUnvalidatedAspect((EntryAspect, String)),
+
+    /// Adds one chunk of a large aspect after verifying it against the manifest.
+    /// Once all chunks of the manifest arrived, the reassembled aspect gets queued
+    /// for validation like one that arrived whole.
+    /// The time is when the chunk arrived, incomplete aspects whose chunks stopped
+    /// arriving get dropped relative to it.
+    HoldAspectChunk((AspectChunkManifest, AspectChunk, SystemTime)),
 
     //action for updating crudstatus
     CrudStatus((EntryWithHeader, CrudStatus)),
@@ -138,7 +181,19 @@ pub enum Action {
     /// Distinguishes between different entry types and does
     /// the right thing respectively.
     /// (only publish for AppEntryType, publish and publish_meta for links etc)
-    Publish(Address),
+    /// The correlation id shows up on the holds this publish results in on other nodes.
+    /// Only queues the publish, by its priority, see `Action::ProcessPublishQueue`.
+    Publish((Address, CorrelationId, PublishPriority)),
+
+    /// Processes the queued publish with the highest priority, the oldest of them
+    /// if there are several. Gets dispatched once for every `Action::Publish`.
+    ProcessPublishQueue,
+
+    /// Makes the network PUT the given entry to the nodes holding the given arc of the DHT,
+    /// right away instead of through the publish queue.
+    /// The network protocol can't address arcs yet, so until it can, the entry goes
+    /// out like with `Action::Publish`.
+    PublishToArc((Address, DhtArc)),
 
     /// Publish to the network the header entry for the entry at the given address.
     /// Note that the given address is that of the entry NOT the address of the header itself
@@ -239,6 +294,22 @@ pub enum Action {
     Ping,
 }
 
+impl Action {
+    /// The correlation id of the publish-to-hold flow this action belongs to, if any.
+    /// Holds derive it from the header of the aspect they hold.
+    pub fn correlation_id(&self) -> Option<CorrelationId> {
+        match self {
+            Action::Publish((_, correlation_id, _)) => Some(correlation_id.clone()),
+            Action::HoldAspect((aspect, _, _))
+            | Action::ForceHoldAspect(aspect)
+            | Action::HoldUnvalidatedAspect(aspect) => {
+                Some(CorrelationId::for_header(aspect.header()))
+            }
+            _ => None,
+        }
+    }
+}
+
 /// function signature for action handler functions
 // @TODO merge these into a single signature
 // @see https://github.com/holochain/holochain-rust/issues/194
@@ -149,7 +149,7 @@ impl ConsistencyModel {
                 }
                 None
             }
-            Action::Publish(address) => {
+            Action::Publish((address, _, _)) => {
                 // Emit the signal that was created when observing the corresponding Commit
                 let maybe_signal = self.commit_cache.remove(address);
                 maybe_signal.or_else(|| {
@@ -160,7 +160,7 @@ impl ConsistencyModel {
                     None
                 })
             }
-            Action::HoldAspect(aspect) => match aspect {
+            Action::HoldAspect((aspect, _, _)) => match aspect {
                 EntryAspect::Content(entry, _) => Some(ConsistencySignal::new_terminal(Hold(entry.address()))),
                 EntryAspect::Update(_, header) => {
                     header.link_update_delete().map(|old| {
@@ -1,17 +1,39 @@
 use crate::{
     action::{Action, ActionWrapper},
     content_store::GetContent,
+    dht::{
+        actions::hold_aspect::{HoldPolicy, MemoryPressurePolicy, RequestedHolds},
+        dht_store::DEFAULT_MAX_HOLD_ATTEMPTS,
+        held_callbacks::{HeldCallback, HeldCallbacks},
+        storage_tiers::{StorageTierPolicy, StorageTiers},
+    },
+    futures_util::FutureTimeouts,
     instance::Observer,
-    network::state::NetworkState,
+    network::{
+        actions::publish::{PublishCache, PublishGate, PublishHooks, PublishRetryQueue},
+        handler::get_content_aspect,
+        state::NetworkState,
+    },
+    nucleus::validation::{
+        concurrency::ValidationSemaphore,
+        deferrals::Deferrals,
+        events::{ValidationEvent, ValidationEvents},
+        index_hints::IndexHints,
+        pool::ValidationPool,
+    },
     persister::Persister,
     signal::{Signal, SignalSender},
     state::StateWrapper,
+    wasm_engine::api::{
+        sleep::{RealTimer, TimerProvider},
+        validation_oracle::ValidationOracle,
+    },
 };
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use futures::{
     executor::ThreadPool,
     task::{noop_waker_ref, Poll},
-    Future,
+    Future, Stream,
 };
 use holochain_conductor_lib_api::ConductorApi;
 use holochain_core_types::{
@@ -24,6 +46,8 @@ use holochain_core_types::{
         Entry,
     },
     error::{HcResult, HolochainError},
+    network::entry_aspect::EntryAspect,
+    validation::Locale,
 };
 use holochain_json_api::{error::JsonError, json::JsonString};
 use holochain_locksmith::{Mutex, MutexGuard, RwLock, RwLockReadGuard};
@@ -40,14 +64,16 @@ use holochain_tracing as ht;
 use jsonrpc_core::{self, IoHandler};
 use std::{
     sync::{
-        atomic::{AtomicBool, Ordering::Relaxed},
+        atomic::{AtomicBool, AtomicUsize, Ordering::Relaxed},
         Arc,
     },
     thread::sleep,
     time::Duration,
 };
 
-use crate::instance::WakerRequest;
+use crate::instance::{WakerRegistry, WakerRequest};
+#[cfg(test)]
+use crate::{dht::aspect_map::AspectMap, network::actions::NetworkActionResponse};
 use futures::task::Waker;
 use snowflake::ProcessUniqueId;
 #[cfg(test)]
@@ -85,6 +111,11 @@ pub struct InstanceStats {
     pub offline: bool,
 }
 
+/// A copy of the DHT's holding map, see `Context::snapshot_holding_map()`.
+#[cfg(test)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HoldingMapSnapshot(pub AspectMap);
+
 /// Context holds the components that parts of a Holochain instance need in order to operate.
 /// This includes components that are injected from the outside like persister
 /// but also the store of the instance that gets injected before passing on the context
@@ -110,6 +141,28 @@ pub struct Context {
     pub redux_wants_write: Arc<AtomicBool>,
     pub metric_publisher: Arc<RwLock<dyn MetricPublisher>>,
     pub tracer: Arc<ht::Tracer>,
+    pub(crate) validation_semaphore: Arc<ValidationSemaphore>,
+    pub(crate) publish_gate: Arc<PublishGate>,
+    pub(crate) validation_pool: Arc<ValidationPool>,
+    pub(crate) held_callbacks: Arc<HeldCallbacks>,
+    pub(crate) publish_retry_queue: Arc<PublishRetryQueue>,
+    pub(crate) publish_hooks: Arc<PublishHooks>,
+    pub(crate) requested_holds: Arc<RequestedHolds>,
+    pub(crate) validation_locale: Arc<RwLock<Option<Locale>>>,
+    pub(crate) max_hold_attempts: Arc<AtomicUsize>,
+    pub(crate) index_hints: Arc<IndexHints>,
+    pub(crate) deferrals: Arc<Deferrals>,
+    pub(crate) trusted_bootstrap: Arc<AtomicBool>,
+    pub(crate) future_timeouts: Arc<RwLock<FutureTimeouts>>,
+    pub(crate) hold_policy: Arc<RwLock<HoldPolicy>>,
+    pub(crate) memory_pressure: Arc<AtomicUsize>,
+    pub(crate) memory_pressure_policy: Arc<RwLock<MemoryPressurePolicy>>,
+    pub(crate) validation_events: Arc<ValidationEvents>,
+    pub(crate) publish_cache: Arc<PublishCache>,
+    pub(crate) wakers: Arc<WakerRegistry>,
+    pub(crate) validation_oracle: Arc<RwLock<Option<ValidationOracle>>>,
+    pub(crate) timer_provider: Arc<RwLock<Arc<dyn TimerProvider>>>,
+    pub(crate) storage_tiers: Arc<StorageTiers>,
 }
 
 #[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
@@ -178,6 +231,28 @@ impl Context {
             redux_wants_write: Arc::new(AtomicBool::new(false)),
             metric_publisher,
             tracer,
+            validation_semaphore: Arc::new(ValidationSemaphore::default()),
+            publish_gate: Arc::new(PublishGate::default()),
+            validation_pool: Arc::new(ValidationPool::default()),
+            held_callbacks: Arc::new(HeldCallbacks::default()),
+            publish_retry_queue: Arc::new(PublishRetryQueue::default()),
+            publish_hooks: Arc::new(PublishHooks::default()),
+            requested_holds: Arc::new(RequestedHolds::default()),
+            validation_locale: Arc::new(RwLock::new(None)),
+            max_hold_attempts: Arc::new(AtomicUsize::new(DEFAULT_MAX_HOLD_ATTEMPTS)),
+            index_hints: Arc::new(IndexHints::default()),
+            deferrals: Arc::new(Deferrals::default()),
+            trusted_bootstrap: Arc::new(AtomicBool::new(false)),
+            future_timeouts: Arc::new(RwLock::new(FutureTimeouts::default())),
+            hold_policy: Arc::new(RwLock::new(HoldPolicy::default())),
+            memory_pressure: Arc::new(AtomicUsize::new(0)),
+            memory_pressure_policy: Arc::new(RwLock::new(MemoryPressurePolicy::default())),
+            validation_events: Arc::new(ValidationEvents::default()),
+            publish_cache: Arc::new(PublishCache::default()),
+            wakers: Arc::new(WakerRegistry::default()),
+            validation_oracle: Arc::new(RwLock::new(None)),
+            timer_provider: Arc::new(RwLock::new(Arc::new(RealTimer))),
+            storage_tiers: Arc::new(StorageTiers::default()),
         }
     }
 
@@ -216,9 +291,224 @@ impl Context {
             redux_wants_write: Arc::new(AtomicBool::new(false)),
             metric_publisher,
             tracer,
+            validation_semaphore: Arc::new(ValidationSemaphore::default()),
+            publish_gate: Arc::new(PublishGate::default()),
+            validation_pool: Arc::new(ValidationPool::default()),
+            held_callbacks: Arc::new(HeldCallbacks::default()),
+            publish_retry_queue: Arc::new(PublishRetryQueue::default()),
+            publish_hooks: Arc::new(PublishHooks::default()),
+            requested_holds: Arc::new(RequestedHolds::default()),
+            validation_locale: Arc::new(RwLock::new(None)),
+            max_hold_attempts: Arc::new(AtomicUsize::new(DEFAULT_MAX_HOLD_ATTEMPTS)),
+            index_hints: Arc::new(IndexHints::default()),
+            deferrals: Arc::new(Deferrals::default()),
+            trusted_bootstrap: Arc::new(AtomicBool::new(false)),
+            future_timeouts: Arc::new(RwLock::new(FutureTimeouts::default())),
+            hold_policy: Arc::new(RwLock::new(HoldPolicy::default())),
+            memory_pressure: Arc::new(AtomicUsize::new(0)),
+            memory_pressure_policy: Arc::new(RwLock::new(MemoryPressurePolicy::default())),
+            validation_events: Arc::new(ValidationEvents::default()),
+            publish_cache: Arc::new(PublishCache::default()),
+            wakers: Arc::new(WakerRegistry::default()),
+            validation_oracle: Arc::new(RwLock::new(None)),
+            timer_provider: Arc::new(RwLock::new(Arc::new(RealTimer))),
+            storage_tiers: Arc::new(StorageTiers::default()),
         })
     }
 
+    /// Sets how many validation callbacks may run at the same time.
+    /// `None` (the default) means no limit.
+    pub fn set_validation_concurrency_limit(&self, limit: Option<usize>) {
+        self.validation_semaphore.set_limit(limit);
+    }
+
+    pub fn validation_concurrency_limit(&self) -> Option<usize> {
+        self.validation_semaphore.limit()
+    }
+
+    /// Sets the number of worker threads validation callbacks run on.
+    pub fn set_validation_workers(&self, workers: usize) {
+        self.validation_pool.set_workers(workers);
+    }
+
+    pub fn validation_workers(&self) -> usize {
+        self.validation_pool.workers()
+    }
+
+    /// Sets the locale that validators get asked to translate their failure messages to.
+    /// `None` (the default) leaves the messages as the validators wrote them.
+    pub fn set_validation_locale(&self, locale: Option<Locale>) {
+        *self.validation_locale.write().unwrap() = locale;
+    }
+
+    pub fn validation_locale(&self) -> Option<Locale> {
+        self.validation_locale.read().unwrap().clone()
+    }
+
+    /// Sets how often holding an aspect gets attempted before it is rejected,
+    /// if it keeps failing transiently.
+    pub fn set_max_hold_attempts(&self, attempts: usize) {
+        self.max_hold_attempts.store(attempts.max(1), Relaxed);
+    }
+
+    pub fn max_hold_attempts(&self) -> usize {
+        self.max_hold_attempts.load(Relaxed)
+    }
+
+    /// Allows (or forbids again) holding aspects without validating them through
+    /// `force_hold_aspect()`. Only switch this on while seeding the node with data
+    /// that is known to be valid.
+    pub fn set_trusted_bootstrap(&self, trusted: bool) {
+        self.trusted_bootstrap.store(trusted, Relaxed);
+    }
+
+    pub fn trusted_bootstrap(&self) -> bool {
+        self.trusted_bootstrap.load(Relaxed)
+    }
+
+    /// Sets whether aspects the network asks us to hold get validated on arrival
+    /// or only once they are read, see `HoldPolicy`.
+    pub fn set_hold_policy(&self, policy: HoldPolicy) {
+        *self.hold_policy.write().unwrap() = policy;
+    }
+
+    pub fn hold_policy(&self) -> HoldPolicy {
+        *self.hold_policy.read().unwrap()
+    }
+
+    /// Tells the context how much of the memory it may use is in use, in percent.
+    /// Meant to be updated by whoever watches the memory. While it is at or above the
+    /// threshold of the `MemoryPressurePolicy`, new holds get throttled.
+    pub fn set_memory_pressure(&self, percent: usize) {
+        self.memory_pressure.store(percent.min(100), Relaxed);
+    }
+
+    pub fn memory_pressure(&self) -> usize {
+        self.memory_pressure.load(Relaxed)
+    }
+
+    /// Sets from which memory pressure on, and how, new holds get throttled.
+    pub fn set_memory_pressure_policy(&self, policy: MemoryPressurePolicy) {
+        *self.memory_pressure_policy.write().unwrap() = policy;
+    }
+
+    pub fn memory_pressure_policy(&self) -> MemoryPressurePolicy {
+        self.memory_pressure_policy.read().unwrap().clone()
+    }
+
+    /// Sets the oracle that answers `hdk::validation_oracle_query()` calls of validators.
+    /// Passing `None` removes it, the calls fail then.
+    pub fn set_validation_oracle(&self, oracle: Option<ValidationOracle>) {
+        *self.validation_oracle.write().unwrap() = oracle;
+    }
+
+    pub fn validation_oracle(&self) -> Option<ValidationOracle> {
+        self.validation_oracle.read().unwrap().clone()
+    }
+
+    /// Sets which storage tier aspects get held in, by aspect.
+    /// Passing `None` (the default) holds all of them in `StorageTier::Durable`.
+    /// Aspects held already stay in the tier they are in.
+    pub fn set_storage_tier_policy(&self, policy: Option<StorageTierPolicy>) {
+        self.storage_tiers.set_policy(policy);
+    }
+
+    /// Sets the clock the `sleep` zome API functions use, e.g. a `VirtualTimer` in tests.
+    pub fn set_timer_provider(&self, timer: Arc<dyn TimerProvider>) {
+        *self.timer_provider.write().unwrap() = timer;
+    }
+
+    pub fn timer_provider(&self) -> Arc<dyn TimerProvider> {
+        self.timer_provider.read().unwrap().clone()
+    }
+
+    /// Sets the timeouts of publishing, holding aspects and sleeping.
+    /// Futures that got polled already keep the timeout they started with.
+    pub fn set_future_timeouts(&self, timeouts: FutureTimeouts) {
+        *self.future_timeouts.write().unwrap() = timeouts;
+    }
+
+    pub fn future_timeouts(&self) -> FutureTimeouts {
+        self.future_timeouts.read().unwrap().clone()
+    }
+
+    /// A stream of all validations of app entries that happen from now on,
+    /// see [ValidationEvents](crate::nucleus::validation::events::ValidationEvents).
+    pub fn validation_stream(&self) -> impl Stream<Item = ValidationEvent> {
+        self.validation_events.subscribe()
+    }
+
+    /// Stops publishes from leaving the node. Commits still work locally,
+    /// their publishes are buffered until `resume_publishing()` gets called.
+    pub fn pause_publishing(&self) {
+        self.publish_gate.pause();
+    }
+
+    /// Sends out all publishes buffered while paused, in order, and lets
+    /// new publishes through again.
+    pub fn resume_publishing(&self) {
+        self.publish_gate.resume(self.action_channel());
+    }
+
+    pub fn is_publishing_paused(&self) -> bool {
+        self.publish_gate.is_paused()
+    }
+
+    /// Stashes a publish that failed so it can be retried later
+    /// with `network::actions::publish::retry_queued_publishes()`.
+    pub fn requeue_publish(&self, address: Address) {
+        self.publish_retry_queue.push(address);
+    }
+
+    /// Takes all addresses out of the publish retry queue, oldest first.
+    pub fn drain_publish_queue(&self) -> Vec<Address> {
+        self.publish_retry_queue.drain()
+    }
+
+    /// Sets for how long after a successful publish, publishing the same address again
+    /// resolves right away without publishing anything. `Duration::from_secs(0)` switches
+    /// that off.
+    pub fn set_publish_dedup_window(&self, window: Duration) {
+        self.publish_cache.set_window(window);
+    }
+
+    pub fn publish_dedup_window(&self) -> Duration {
+        self.publish_cache.window()
+    }
+
+    /// How the last publish of `address` ended, if it completed within the publish
+    /// dedup window. Lets callers decide whether a publish needs to be retried
+    /// without awaiting it again. `None` if there was no such publish.
+    pub fn last_publish_result(&self, address: &Address) -> Option<HcResult<Address>> {
+        self.publish_cache.last_result(address)
+    }
+
+    /// Registers a callback that runs with the address of every publish that succeeded,
+    /// when its publish future resolves. Hooks run in the order they were registered.
+    pub fn register_publish_hook<F>(&self, hook: F)
+    where
+        F: Fn(Address) + Send + Sync + 'static,
+    {
+        self.publish_hooks.register(Box::new(hook));
+    }
+
+    /// Registers a callback that runs once, right after `aspect` got added to the holding map.
+    /// Register it before dispatching the hold, it won't fire for aspects that are held already.
+    pub fn on_held(&self, aspect: EntryAspect, callback: HeldCallback) {
+        self.held_callbacks.register(&aspect, callback);
+    }
+
+    /// Aspects a hold got requested for that are not in the holding map yet.
+    /// A hold that stays in here points to a `HoldAspectFuture` that got stuck.
+    pub fn pending_holds(&self) -> Vec<EntryAspect> {
+        match self.state() {
+            Some(state) => self
+                .requested_holds
+                .pending(|aspect| state.dht().get_holding_map().contains(aspect)),
+            None => self.requested_holds.pending(|_| false),
+        }
+    }
+
     /// Returns the name of this context instance.
     pub fn get_instance_name(&self) -> String {
         self.instance_name.clone()
@@ -368,6 +658,13 @@ impl Context {
             .map(|c| c.send(WakerRequest::Remove(future_id)));
     }
 
+    /// Wakes every registered waker one last time and empties the registry.
+    /// Called when the instance's action loop stops, so futures that never unregistered
+    /// get polled again, see that the instance is not alive anymore and resolve.
+    pub fn shutdown_wakers(&self) {
+        self.wakers.shutdown()
+    }
+
     /// Custom future executor that enables nested futures and nested calls of `block_on`.
     /// This makes use of the redux action loop and the observers.
     /// The given future gets polled everytime the instance's state got changed.
@@ -435,6 +732,48 @@ impl Context {
         ))
     }
 
+    /// Assembles the content aspect for an entry (or header) we hold locally,
+    /// i.e. the aspect that would get held after publishing the given address.
+    /// Returns an error if the content is not available locally.
+    pub fn aspect_for_address(&self, address: &Address) -> HcResult<EntryAspect> {
+        get_content_aspect(address, Arc::new(self.clone()))
+    }
+
+    /// Captures the current holding map so tests can restore it later.
+    #[cfg(test)]
+    pub fn snapshot_holding_map(&self) -> HoldingMapSnapshot {
+        let state = self
+            .state()
+            .expect("Can't snapshot holding map without state");
+        HoldingMapSnapshot(state.dht().get_holding_map().clone())
+    }
+
+    /// Overwrites the holding map with the given snapshot without going through
+    /// the action pipeline, so tests can seed a known holding state.
+    #[cfg(test)]
+    pub fn restore_holding_map(&self, snapshot: HoldingMapSnapshot) {
+        let state = self
+            .state
+            .as_ref()
+            .expect("Can't restore holding map without state");
+        state.write().unwrap().set_holding_map(snapshot.0);
+    }
+
+    /// Places `response` into the network state as the response to the action with id
+    /// `action_id`, so futures waiting for it (like `PublishFuture`) resolve without
+    /// any networking.
+    #[cfg(test)]
+    pub fn inject_network_response(&self, action_id: &str, response: NetworkActionResponse) {
+        let state = self
+            .state
+            .as_ref()
+            .expect("Can't inject network response without state");
+        state
+            .write()
+            .unwrap()
+            .set_network_action_response(action_id, response);
+    }
+
     pub fn get_stats(&self) -> HcResult<InstanceStats> {
         let state = self
             .state()
@@ -491,7 +830,11 @@ pub fn test_memory_network_config(network_name: Option<&str>) -> P2pConfig {
 pub mod tests {
     use self::tempfile::tempdir;
     use super::*;
-    use crate::persister::SimplePersister;
+    use crate::{
+        nucleus::actions::tests::{instance_by_name, test_dna},
+        persister::SimplePersister,
+        workflows::author_entry::author_entry,
+    };
     use holochain_core_types::agent::AgentId;
     use holochain_locksmith::RwLock;
     use holochain_persistence_file::{cas::file::FilesystemStorage, eav::file::EavFileStorage};
@@ -544,4 +887,144 @@ pub mod tests {
 
         guard.flush();
     }
+
+    #[test]
+    fn aspect_for_address_finds_authored_entry() {
+        let (_instance, context) = instance_by_name("jill", test_dna(), None);
+        let entry = holochain_core_types::entry::test_entry();
+        let entry_address = context
+            .block_on(author_entry(&entry, None, &context, &vec![]))
+            .unwrap()
+            .address();
+
+        match context.aspect_for_address(&entry_address) {
+            Ok(EntryAspect::Content(aspect_entry, header)) => {
+                assert_eq!(aspect_entry, entry);
+                assert_eq!(header.entry_address(), &entry_address);
+            }
+            other => panic!("Expected content aspect, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn dispatched_hold_is_pending_until_reduced() {
+        use crate::{
+            dht::actions::hold_aspect::hold_aspect, futures_util::with_timeout,
+            instance::tests::test_context_and_state,
+        };
+        use holochain_core_types::{chain_header::test_chain_header, entry::test_entry};
+
+        // Nobody reduces the dispatched actions, so the hold never lands
+        let (context, _state, _receiver) = test_context_and_state("jane");
+        assert!(context.pending_holds().is_empty());
+
+        let aspect = EntryAspect::Content(test_entry(), test_chain_header());
+        let result = futures::executor::block_on(with_timeout(
+            hold_aspect(aspect.clone(), context.clone()),
+            &context,
+            Duration::from_millis(50),
+        ));
+        assert!(result.is_err(), "hold_aspect() result = {:?}", result);
+
+        assert_eq!(context.pending_holds(), vec![aspect]);
+    }
+
+    #[test]
+    fn on_held_callback_runs_once_when_aspect_is_held() {
+        use crate::dht::actions::hold_aspect::hold_aspect;
+        use holochain_core_types::{chain_header::test_chain_header, entry::test_entry};
+
+        let (_instance, context) = instance_by_name("jill", test_dna(), None);
+        let aspect = EntryAspect::Content(test_entry(), test_chain_header());
+
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        context.on_held(
+            aspect.clone(),
+            Box::new(move || sender.send(()).expect("Test receiver dropped")),
+        );
+        assert_eq!(context.held_callbacks.pending(), 1);
+
+        context
+            .block_on(hold_aspect(aspect.clone(), context.clone()))
+            .unwrap();
+        receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("on_held callback did not run");
+        assert_eq!(context.held_callbacks.pending(), 0);
+
+        // holding it again must not call it a second time
+        context
+            .block_on(hold_aspect(aspect, context.clone()))
+            .unwrap();
+        assert!(receiver.recv_timeout(Duration::from_millis(200)).is_err());
+    }
+
+    #[test]
+    fn aspect_for_address_errors_for_unknown_address() {
+        let (_instance, context) = instance_by_name("jill", test_dna(), None);
+        assert_eq!(
+            context.aspect_for_address(&Address::from("QmUnknownAddress")),
+            Err(HolochainError::EntryNotFoundLocally),
+        );
+    }
+
+    #[test]
+    fn can_snapshot_clear_and_restore_holding_map() {
+        let (_instance, context) = instance_by_name("jill", test_dna(), None);
+        let aspect = EntryAspect::Content(
+            holochain_core_types::entry::test_entry(),
+            holochain_core_types::chain_header::test_chain_header(),
+        );
+
+        let mut seeded = AspectMap::new();
+        seeded.add(&aspect);
+        context.restore_holding_map(HoldingMapSnapshot(seeded));
+        let holding = |context: &Arc<Context>| {
+            context
+                .state()
+                .unwrap()
+                .dht()
+                .get_holding_map()
+                .contains(&aspect)
+        };
+        assert!(holding(&context));
+
+        let snapshot = context.snapshot_holding_map();
+        context.restore_holding_map(HoldingMapSnapshot::default());
+        assert!(!holding(&context));
+
+        context.restore_holding_map(snapshot);
+        assert!(holding(&context));
+    }
+
+    #[test]
+    fn shutdown_wakers_wakes_and_forgets_registered_wakers() {
+        use crate::instance::dispatch_action;
+        use futures::task::{waker, ArcWake};
+        use snowflake::ProcessUniqueId;
+
+        struct FlagWaker(AtomicBool);
+        impl ArcWake for FlagWaker {
+            fn wake_by_ref(arc_self: &Arc<Self>) {
+                arc_self.0.store(true, Relaxed);
+            }
+        }
+
+        let (_instance, context) = instance_by_name("jill", test_dna(), None);
+        let flag = Arc::new(FlagWaker(AtomicBool::new(false)));
+        context.register_waker(ProcessUniqueId::new(), waker(flag.clone()));
+        // The action loop picks up waker requests with the next action
+        dispatch_action(context.action_channel(), ActionWrapper::new(Action::Ping));
+        let mut tries = 0;
+        while context.wakers.is_empty() {
+            assert!(tries < 500, "Waker never got registered");
+            sleep(Duration::from_millis(10));
+            tries += 1;
+        }
+
+        flag.0.store(false, Relaxed);
+        context.shutdown_wakers();
+        assert!(flag.0.load(Relaxed));
+        assert!(context.wakers.is_empty());
+    }
 }
@@ -1,47 +1,534 @@
 use crate::{
     action::{Action, ActionWrapper},
     context::Context,
+    futures_util::{cancellable, wake_at, CancelHandle, FutureDeadline},
     instance::dispatch_action,
 };
 use futures::{future::Future, task::Poll};
 use holochain_core_types::{error::HolochainError, network::entry_aspect::EntryAspect};
+use holochain_locksmith::Mutex;
+use holochain_persistence_api::cas::content::{Address, AddressableContent};
 use snowflake::ProcessUniqueId;
-use std::{pin::Pin, sync::Arc};
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// How aspects the network asks us to hold get validated, see `Context::set_hold_policy()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HoldPolicy {
+    /// Aspects are validated when they arrive and only held once they are valid.
+    Validate,
+    /// Aspects are stored unvalidated when they arrive and only validated once they
+    /// are read (see `validate_held_aspect()`), so nodes short on resources don't
+    /// spend validations on aspects nobody asks for.
+    LazyValidate,
+}
+
+impl Default for HoldPolicy {
+    fn default() -> Self {
+        HoldPolicy::Validate
+    }
+}
+
+/// How new holds get throttled while the memory pressure is high,
+/// see `Context::set_memory_pressure_policy()`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemoryPressurePolicy {
+    /// Memory pressure, in percent, from which on holds get throttled
+    pub threshold: usize,
+    pub throttle: HoldThrottle,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HoldThrottle {
+    /// Fail new holds with `HolochainError::MemoryPressure` right away
+    Reject,
+    /// Wait up to the given time for the pressure to drop below the threshold,
+    /// then fail with `HolochainError::MemoryPressure` if it did not
+    Delay(Duration),
+}
+
+impl Default for MemoryPressurePolicy {
+    fn default() -> Self {
+        MemoryPressurePolicy {
+            threshold: 90,
+            throttle: HoldThrottle::Reject,
+        }
+    }
+}
 
+/// How often a delayed hold checks whether the memory pressure dropped.
+const MEMORY_PRESSURE_CHECK_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Holds the aspect after making sure its entry hashes to the address its header claims.
+/// A tampered aspect fails with `HolochainError::IntegrityCheckFailed` and never gets held.
+/// While the context's memory pressure is high, the hold gets throttled as its
+/// `MemoryPressurePolicy` says, failing with `HolochainError::MemoryPressure`.
 pub async fn hold_aspect(aspect: EntryAspect, context: Arc<Context>) -> Result<(), HolochainError> {
-    let action_wrapper = ActionWrapper::new(Action::HoldAspect(aspect.clone()));
-    dispatch_action(context.action_channel(), action_wrapper.clone());
+    hold_aspect_with_source(aspect, None, context).await
+}
+
+/// Like [hold_aspect](hold_aspect), but records `source` as the agent we got the aspect
+/// from. It can be looked up with `AspectMap::source_of()` once the aspect is held.
+pub async fn hold_aspect_from(
+    aspect: EntryAspect,
+    source: Address,
+    context: Arc<Context>,
+) -> Result<(), HolochainError> {
+    hold_aspect_with_source(aspect, Some(source), context).await
+}
+
+async fn hold_aspect_with_source(
+    aspect: EntryAspect,
+    source: Option<Address>,
+    context: Arc<Context>,
+) -> Result<(), HolochainError> {
+    if let Err(error) = aspect.verify_integrity() {
+        log_warn!(
+            context,
+            "dht/hold_aspect: refusing to hold tampered aspect {:?}",
+            aspect
+        );
+        return Err(error);
+    }
+    if let Err(error) = memory_pressure_relief(&context).await {
+        log_warn!(
+            context,
+            "dht/hold_aspect: not holding {} under memory pressure of {}%",
+            aspect.address(),
+            context.memory_pressure()
+        );
+        return Err(error);
+    }
+    // Holds of the same aspect requested before it got held all wait for the first one
+    let (dispatch, stale_rejection) = context
+        .requested_holds
+        .start_hold(&aspect, rejection_id(&context, &aspect));
+    if dispatch {
+        let action_wrapper = ActionWrapper::new(Action::HoldAspect((
+            aspect.clone(),
+            source,
+            SystemTime::now(),
+        )));
+        dispatch_action(context.action_channel(), action_wrapper);
+    }
+    let id = ProcessUniqueId::new();
+    HoldAspectFuture {
+        context,
+        aspect,
+        id,
+        forced: false,
+        started_hold: true,
+        stale_rejection,
+        deadline: FutureDeadline::default(),
+    }
+    .await
+}
+
+/// Like [hold_aspect](hold_aspect), but hands out a handle to cancel the hold with.
+/// A hold that stops before the aspect got held fails with `HolochainError::Cancelled`,
+/// telling whether it got cancelled through the handle, by the instance shutting down
+/// or by the hold timeout.
+pub fn hold_aspect_cancellable(
+    aspect: EntryAspect,
+    context: Arc<Context>,
+) -> (
+    CancelHandle,
+    impl Future<Output = Result<(), HolochainError>>,
+) {
+    cancellable(hold_aspect(aspect, context.clone()), &context)
+}
+
+/// Resolves once the memory pressure is below the threshold of the context's
+/// `MemoryPressurePolicy`, or fails with `HolochainError::MemoryPressure` as the policy says.
+fn memory_pressure_relief(context: &Arc<Context>) -> MemoryPressureRelief {
+    let policy = context.memory_pressure_policy();
+    let deadline = match policy.throttle {
+        HoldThrottle::Reject => Instant::now(),
+        HoldThrottle::Delay(max_wait) => Instant::now() + max_wait,
+    };
+    MemoryPressureRelief {
+        context: context.clone(),
+        threshold: policy.threshold,
+        deadline,
+    }
+}
+
+struct MemoryPressureRelief {
+    context: Arc<Context>,
+    threshold: usize,
+    deadline: Instant,
+}
+
+impl Future for MemoryPressureRelief {
+    type Output = Result<(), HolochainError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+        if self.context.memory_pressure() < self.threshold {
+            return Poll::Ready(Ok(()));
+        }
+        let now = Instant::now();
+        if now >= self.deadline {
+            return Poll::Ready(Err(HolochainError::MemoryPressure));
+        }
+        // Nothing wakes us when the pressure drops, so check again in a bit
+        let wait = MEMORY_PRESSURE_CHECK_INTERVAL.min(self.deadline - now);
+        wake_at(now + wait, cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// Stores the aspect as unvalidated, without holding it yet (see `HoldPolicy::LazyValidate`).
+/// It gets held, or rejected, once `validate_held_aspect()` validated it.
+/// Like `hold_aspect()`, refuses tampered aspects with `HolochainError::IntegrityCheckFailed`.
+pub async fn hold_aspect_unvalidated(
+    aspect: EntryAspect,
+    context: Arc<Context>,
+) -> Result<(), HolochainError> {
+    if let Err(error) = aspect.verify_integrity() {
+        log_warn!(
+            context,
+            "dht/hold_aspect_unvalidated: refusing to store tampered aspect {:?}",
+            aspect
+        );
+        return Err(error);
+    }
+    dispatch_action(
+        context.action_channel(),
+        ActionWrapper::new(Action::HoldUnvalidatedAspect(aspect.clone())),
+    );
+    let id = ProcessUniqueId::new();
+    UnvalidatedAspectFuture {
+        context,
+        aspect,
+        id,
+        stored: true,
+    }
+    .await
+}
+
+/// Rejects an aspect that was stored unvalidated and turned out to be invalid.
+pub(crate) async fn reject_unvalidated_aspect(
+    aspect: EntryAspect,
+    reason: String,
+    context: Arc<Context>,
+) -> Result<(), HolochainError> {
+    dispatch_action(
+        context.action_channel(),
+        ActionWrapper::new(Action::RejectUnvalidatedAspect((aspect.clone(), reason))),
+    );
+    let id = ProcessUniqueId::new();
+    UnvalidatedAspectFuture {
+        context,
+        aspect,
+        id,
+        stored: false,
+    }
+    .await
+}
+
+/// Holds the aspect without validating it, even if what it references is missing.
+/// This is unsafe: invalid data held this way gets served to other nodes like valid data.
+/// It is only allowed while the context is in trusted bootstrap mode
+/// (see `Context::set_trusted_bootstrap()`), and every use gets logged.
+pub async fn force_hold_aspect(
+    aspect: EntryAspect,
+    context: Arc<Context>,
+) -> Result<(), HolochainError> {
+    if !context.trusted_bootstrap() {
+        return Err(HolochainError::ErrorGeneric(
+            "Aspects can only be force-held in trusted bootstrap mode".into(),
+        ));
+    }
+    log_warn!(
+        context,
+        "dht/force_hold_aspect: holding {} WITHOUT VALIDATION",
+        aspect.address()
+    );
+    context.requested_holds.record(&aspect);
+    dispatch_action(
+        context.action_channel(),
+        ActionWrapper::new(Action::ForceHoldAspect(aspect.clone())),
+    );
     let id = ProcessUniqueId::new();
     HoldAspectFuture {
         context,
         aspect,
         id,
+        forced: true,
+        started_hold: false,
+        stale_rejection: None,
+        deadline: FutureDeadline::default(),
     }
     .await
 }
 
+/// Dispatches holds for all given aspects and resolves with the first one that shows up
+/// in the holding map, or fails once all of them got rejected. The holds of the other
+/// aspects are not cancelled, they still get held once they are reduced, but their
+/// requests stop counting as pending.
+/// Like `hold_aspect()`, fails once the instance shut down or the hold timeout passed.
+pub async fn hold_any(
+    aspects: Vec<EntryAspect>,
+    context: Arc<Context>,
+) -> Result<EntryAspect, HolochainError> {
+    if aspects.is_empty() {
+        return Err(HolochainError::ErrorGeneric(
+            "hold_any() needs at least one aspect to hold".into(),
+        ));
+    }
+    let mut stale_rejections = Vec::with_capacity(aspects.len());
+    for aspect in aspects.iter() {
+        let (dispatch, stale_rejection) = context
+            .requested_holds
+            .start_hold(aspect, rejection_id(&context, aspect));
+        if dispatch {
+            dispatch_action(
+                context.action_channel(),
+                ActionWrapper::new(Action::HoldAspect((
+                    aspect.clone(),
+                    None,
+                    SystemTime::now(),
+                ))),
+            );
+        }
+        stale_rejections.push(stale_rejection);
+    }
+    let id = ProcessUniqueId::new();
+    HoldAnyFuture {
+        context,
+        aspects,
+        stale_rejections,
+        id,
+        deadline: FutureDeadline::default(),
+    }
+    .await
+}
+
+/// Holds all of the aspects, or none of them. Fails with
+/// `HolochainError::IntegrityCheckFailed`, without holding anything, if any aspect was
+/// tampered with. Otherwise all aspects get held in one go and the result is `Ok`
+/// once every one of them is held, or an error if any of them could not be stored,
+/// in which case none of them is held.
+pub async fn hold_bundle(
+    aspects: Vec<EntryAspect>,
+    context: Arc<Context>,
+) -> Result<(), HolochainError> {
+    for aspect in aspects.iter() {
+        if let Err(error) = aspect.verify_integrity() {
+            log_warn!(
+                context,
+                "dht/hold_bundle: refusing to hold bundle with tampered aspect {:?}",
+                aspect
+            );
+            return Err(error);
+        }
+    }
+    let stale_rejections = aspects
+        .iter()
+        .map(|aspect| rejection_id(&context, aspect))
+        .collect();
+    for aspect in aspects.iter() {
+        context.requested_holds.record(aspect);
+    }
+    dispatch_action(
+        context.action_channel(),
+        ActionWrapper::new(Action::HoldBundle(aspects.clone())),
+    );
+    let id = ProcessUniqueId::new();
+    HoldBundleFuture {
+        context,
+        aspects,
+        stale_rejections,
+        id,
+        deadline: FutureDeadline::default(),
+    }
+    .await
+}
+
+/// Id of the action that rejected an earlier hold of the aspect, if there was one.
+/// Futures ignore that rejection, it is not about the hold they wait for.
+fn rejection_id(context: &Arc<Context>, aspect: &EntryAspect) -> Option<String> {
+    context
+        .state()
+        .and_then(|state| state.dht().hold_rejection_id(aspect).cloned())
+}
+
+/// Dispatches holds again for the aspects whose earlier holds failed transiently
+/// and whose back-off has elapsed (see `DhtStore::due_hold_retries()`).
+/// `retried` remembers the attempt each aspect was last retried after, so every failed
+/// attempt gets retried once, even if the retry is not reduced by the next call.
+pub(crate) fn retry_due_holds(context: &Arc<Context>, retried: &mut HashMap<Address, usize>) {
+    let dht_store = match context.state() {
+        Some(state) => state.dht(),
+        None => return,
+    };
+    for retry in dht_store.due_hold_retries(SystemTime::now()) {
+        let address = retry.aspect.address();
+        if retried.get(&address) != Some(&retry.attempts) {
+            retried.insert(address, retry.attempts);
+            dispatch_action(
+                context.action_channel(),
+                ActionWrapper::new(Action::HoldAspect((
+                    retry.aspect,
+                    retry.source,
+                    SystemTime::now(),
+                ))),
+            );
+        }
+    }
+    retried.retain(|address, _| dht_store.hold_retries.contains_key(address));
+}
+
+/// Aspects a hold got dispatched for, until they are found in the holding map
+/// (see [Context::pending_holds](crate::context::Context::pending_holds)).
+pub struct RequestedHolds {
+    aspects: Mutex<Vec<EntryAspect>>,
+    /// Addresses of the aspects `hold_aspect()` dispatched an `Action::HoldAspect` for,
+    /// so further holds of them wait for that one instead of dispatching their own,
+    /// with the number of holds still waiting for it and the id of the rejection
+    /// that was there before it got dispatched
+    in_flight: Mutex<HashMap<Address, (usize, Option<String>)>>,
+}
+
+impl Default for RequestedHolds {
+    fn default() -> Self {
+        RequestedHolds {
+            aspects: Mutex::new(Vec::new()),
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl RequestedHolds {
+    pub(crate) fn record(&self, aspect: &EntryAspect) {
+        let mut aspects = self.aspects.lock().expect("RequestedHolds poisoned");
+        if !aspects.contains(aspect) {
+            aspects.push(aspect.clone());
+        }
+    }
+
+    /// Records the aspect like `record()`. Returns false if a hold of the aspect
+    /// is in flight already, i.e. no action needs to be dispatched for it, and the
+    /// id of the rejection the hold should ignore: `stale_rejection` for a new hold,
+    /// or the one that was stale when the hold in flight got started.
+    /// Every started hold has to be ended with `end_hold()`.
+    pub(crate) fn start_hold(
+        &self,
+        aspect: &EntryAspect,
+        stale_rejection: Option<String>,
+    ) -> (bool, Option<String>) {
+        self.record(aspect);
+        let mut in_flight = self.in_flight.lock().expect("RequestedHolds poisoned");
+        let (waiting, stale_rejection) = in_flight
+            .entry(aspect.address())
+            .or_insert((0, stale_rejection));
+        *waiting += 1;
+        (*waiting == 1, stale_rejection.clone())
+    }
+
+    /// Once the last hold waiting for the aspect ended, however it ended,
+    /// the next hold of the aspect dispatches an action again.
+    pub(crate) fn end_hold(&self, aspect: &EntryAspect) {
+        let mut in_flight = self.in_flight.lock().expect("RequestedHolds poisoned");
+        let address = aspect.address();
+        if let Some((waiting, _)) = in_flight.get_mut(&address) {
+            *waiting -= 1;
+            if *waiting == 0 {
+                in_flight.remove(&address);
+            }
+        }
+    }
+
+    pub(crate) fn remove(&self, aspect: &EntryAspect) {
+        self.aspects
+            .lock()
+            .expect("RequestedHolds poisoned")
+            .retain(|requested| requested != aspect);
+    }
+
+    /// Forgets all aspects for which `is_held` is true and returns the remaining ones,
+    /// in the order their holds got requested.
+    pub(crate) fn pending<F: Fn(&EntryAspect) -> bool>(&self, is_held: F) -> Vec<EntryAspect> {
+        let mut aspects = self.aspects.lock().expect("RequestedHolds poisoned");
+        aspects.retain(|aspect| !is_held(aspect));
+        self.in_flight
+            .lock()
+            .expect("RequestedHolds poisoned")
+            .retain(|address, _| aspects.iter().any(|aspect| aspect.address() == *address));
+        aspects.clone()
+    }
+}
+
 pub struct HoldAspectFuture {
     context: Arc<Context>,
     aspect: EntryAspect,
     id: ProcessUniqueId,
+    /// Rejections of earlier holds don't count, the forced hold clears them.
+    forced: bool,
+    /// Whether the hold got started with `RequestedHolds::start_hold()`
+    started_hold: bool,
+    /// Id of the rejection of an earlier hold, which doesn't count either
+    stale_rejection: Option<String>,
+    deadline: FutureDeadline,
+}
+
+/// Holds that got cancelled or dropped before they resolved don't stay in flight.
+impl Drop for HoldAspectFuture {
+    fn drop(&mut self) {
+        self.context.unregister_waker(self.id.clone());
+        if self.started_hold {
+            self.context.requested_holds.end_hold(&self.aspect);
+        }
+    }
 }
 
 #[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
 impl Future for HoldAspectFuture {
     type Output = Result<(), HolochainError>;
 
-    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
         if let Some(err) = self.context.action_channel_error("HoldAspectFuture") {
             return Poll::Ready(Err(err));
         }
+        if !self.context.instance_still_alive() {
+            return Poll::Ready(Err(HolochainError::LifecycleError(
+                "HoldAspectFuture: instance was shut down".to_string(),
+            )));
+        }
         self.context
             .register_waker(self.id.clone(), cx.waker().clone());
+        let timeout = self.context.future_timeouts().hold_aspect;
+        if self.deadline.expired(timeout, cx.waker()) {
+            self.context.unregister_waker(self.id.clone());
+            self.context.requested_holds.remove(&self.aspect);
+            return Poll::Ready(Err(HolochainError::Timeout(format!(
+                "Aspect {} was not held in time",
+                self.aspect.address()
+            ))));
+        }
         if let Some(state) = self.context.try_state() {
             // TODO: wait for it to show up in the holding list
             // i.e. once we write the reducer we'll know
             if state.dht().get_holding_map().contains(&self.aspect) {
                 self.context.unregister_waker(self.id.clone());
+                self.context.requested_holds.remove(&self.aspect);
                 Poll::Ready(Ok(()))
+            } else if let Some(reason) = state
+                .dht()
+                .hold_rejection_after(&self.aspect, self.stale_rejection.as_ref())
+                .filter(|_| !self.forced)
+            {
+                self.context.unregister_waker(self.id.clone());
+                self.context.requested_holds.remove(&self.aspect);
+                Poll::Ready(Err(HolochainError::ErrorGeneric(format!(
+                    "Gave up holding aspect {}: {}",
+                    self.aspect.address(),
+                    reason
+                ))))
             } else {
                 Poll::Pending
             }
@@ -50,3 +537,638 @@ impl Future for HoldAspectFuture {
         }
     }
 }
+
+pub struct HoldAnyFuture {
+    context: Arc<Context>,
+    aspects: Vec<EntryAspect>,
+    /// Ids of the rejections of earlier holds of the aspects, which don't count
+    stale_rejections: Vec<Option<String>>,
+    id: ProcessUniqueId,
+    deadline: FutureDeadline,
+}
+
+impl HoldAnyFuture {
+    fn done(&self) {
+        self.context.unregister_waker(self.id.clone());
+        for aspect in self.aspects.iter() {
+            self.context.requested_holds.remove(aspect);
+        }
+    }
+}
+
+/// Like `HoldAspectFuture`, the holds don't stay in flight once the future is gone.
+impl Drop for HoldAnyFuture {
+    fn drop(&mut self) {
+        self.context.unregister_waker(self.id.clone());
+        for aspect in self.aspects.iter() {
+            self.context.requested_holds.end_hold(aspect);
+        }
+    }
+}
+
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+impl Future for HoldAnyFuture {
+    type Output = Result<EntryAspect, HolochainError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+        if let Some(err) = self.context.action_channel_error("HoldAnyFuture") {
+            return Poll::Ready(Err(err));
+        }
+        if !self.context.instance_still_alive() {
+            return Poll::Ready(Err(HolochainError::LifecycleError(
+                "HoldAnyFuture: instance was shut down".to_string(),
+            )));
+        }
+        self.context
+            .register_waker(self.id.clone(), cx.waker().clone());
+        let timeout = self.context.future_timeouts().hold_aspect;
+        if self.deadline.expired(timeout, cx.waker()) {
+            self.done();
+            return Poll::Ready(Err(HolochainError::Timeout(
+                "None of the aspects was held in time".to_string(),
+            )));
+        }
+        if let Some(state) = self.context.try_state() {
+            let holding_map = state.dht().get_holding_map();
+            if let Some(held) = self
+                .aspects
+                .iter()
+                .find(|aspect| holding_map.contains(aspect))
+            {
+                self.done();
+                Poll::Ready(Ok(held.clone()))
+            } else if self.aspects.iter().zip(self.stale_rejections.iter()).all(
+                |(aspect, stale)| {
+                    state
+                        .dht()
+                        .hold_rejection_after(aspect, stale.as_ref())
+                        .is_some()
+                },
+            ) {
+                self.done();
+                Poll::Ready(Err(HolochainError::ErrorGeneric(
+                    "Gave up holding all of the aspects".to_string(),
+                )))
+            } else {
+                Poll::Pending
+            }
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+pub struct HoldBundleFuture {
+    context: Arc<Context>,
+    aspects: Vec<EntryAspect>,
+    /// Ids of the rejections of earlier holds of the aspects, which don't count
+    stale_rejections: Vec<Option<String>>,
+    id: ProcessUniqueId,
+    deadline: FutureDeadline,
+}
+
+impl HoldBundleFuture {
+    fn done(&self) {
+        self.context.unregister_waker(self.id.clone());
+        for aspect in self.aspects.iter() {
+            self.context.requested_holds.remove(aspect);
+        }
+    }
+}
+
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+impl Future for HoldBundleFuture {
+    type Output = Result<(), HolochainError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+        if let Some(err) = self.context.action_channel_error("HoldBundleFuture") {
+            return Poll::Ready(Err(err));
+        }
+        if !self.context.instance_still_alive() {
+            return Poll::Ready(Err(HolochainError::LifecycleError(
+                "HoldBundleFuture: instance was shut down".to_string(),
+            )));
+        }
+        self.context
+            .register_waker(self.id.clone(), cx.waker().clone());
+        let timeout = self.context.future_timeouts().hold_aspect;
+        if self.deadline.expired(timeout, cx.waker()) {
+            self.done();
+            return Poll::Ready(Err(HolochainError::Timeout(
+                "Bundle was not held in time".to_string(),
+            )));
+        }
+        if let Some(state) = self.context.try_state() {
+            let dht = state.dht();
+            if self
+                .aspects
+                .iter()
+                .all(|aspect| dht.get_holding_map().contains(aspect))
+            {
+                self.done();
+                Poll::Ready(Ok(()))
+            } else if let Some(reason) = self
+                .aspects
+                .iter()
+                .zip(self.stale_rejections.iter())
+                .find_map(|(aspect, stale)| dht.hold_rejection_after(aspect, stale.as_ref()))
+            {
+                self.done();
+                Poll::Ready(Err(HolochainError::ErrorGeneric(format!(
+                    "Gave up holding bundle: {}",
+                    reason
+                ))))
+            } else {
+                Poll::Pending
+            }
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// Waits for the aspect to be stored as unvalidated (or held already) if `stored`
+/// is true, or for it to not be stored as unvalidated anymore otherwise.
+pub struct UnvalidatedAspectFuture {
+    context: Arc<Context>,
+    aspect: EntryAspect,
+    id: ProcessUniqueId,
+    stored: bool,
+}
+
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+impl Future for UnvalidatedAspectFuture {
+    type Output = Result<(), HolochainError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+        if let Some(err) = self.context.action_channel_error("UnvalidatedAspectFuture") {
+            return Poll::Ready(Err(err));
+        }
+        self.context
+            .register_waker(self.id.clone(), cx.waker().clone());
+        if let Some(state) = self.context.try_state() {
+            let dht = state.dht();
+            let unvalidated = dht.unvalidated_aspect(&self.aspect.address()).is_some();
+            let done = if self.stored {
+                unvalidated || dht.get_holding_map().contains(&self.aspect)
+            } else {
+                !unvalidated
+            };
+            if done {
+                self.context.unregister_waker(self.id.clone());
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        dht::dht_reducers::tests::link_aspect_with_missing_base,
+        instance::tests::test_context_and_state,
+    };
+    use futures::{executor::block_on, task::noop_waker_ref};
+    use holochain_core_types::{
+        chain_header::test_chain_header,
+        entry::{test_entry, test_entry_b},
+        error::CancelReason,
+    };
+    use std::thread;
+
+    #[test]
+    fn hold_any_resolves_with_first_aspect_held() {
+        // Nobody receives the dispatched actions, so the test decides which hold lands
+        let (context, state, _receiver) = test_context_and_state("jane");
+
+        let first = EntryAspect::Content(test_entry(), test_chain_header());
+        let second = EntryAspect::Content(test_entry_b(), test_chain_header());
+        let mut future = Box::pin(hold_any(
+            vec![first.clone(), second.clone()],
+            context.clone(),
+        ));
+        let mut cx = std::task::Context::from_waker(noop_waker_ref());
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(context.pending_holds(), vec![first.clone(), second.clone()]);
+
+        let new_state = state
+            .read()
+            .unwrap()
+            .reduce(ActionWrapper::new(Action::HoldAspect((
+                second.clone(),
+                None,
+                SystemTime::now(),
+            ))));
+        *state.write().unwrap() = new_state;
+
+        assert_eq!(future.as_mut().poll(&mut cx), Poll::Ready(Ok(second)));
+        assert!(context.pending_holds().is_empty());
+    }
+
+    #[test]
+    fn timed_out_hold_any_stops_holding_in_flight() {
+        use crate::futures_util::FutureTimeouts;
+
+        let (context, _state, receiver) = test_context_and_state("jane");
+        context.set_future_timeouts(FutureTimeouts {
+            hold_aspect: Some(Duration::from_millis(0)),
+            ..FutureTimeouts::default()
+        });
+        let aspect = EntryAspect::Content(test_entry(), test_chain_header());
+        let is_hold = |action_wrapper: &ActionWrapper| match action_wrapper.action() {
+            Action::HoldAspect((held, _, _)) => *held == aspect,
+            _ => false,
+        };
+
+        match block_on(hold_any(vec![aspect.clone()], context.clone())) {
+            Err(HolochainError::Timeout(_)) => (),
+            other => panic!("Expected a timeout, got {:?}", other),
+        }
+        assert!(context.pending_holds().is_empty());
+        assert_eq!(
+            receiver
+                .try_iter()
+                .filter(|action_wrapper| is_hold(&action_wrapper.data))
+                .count(),
+            1
+        );
+
+        // The next hold doesn't wait for the one that got dropped
+        let mut future = Box::pin(hold_aspect(aspect.clone(), context.clone()));
+        let mut cx = std::task::Context::from_waker(noop_waker_ref());
+        future.as_mut().poll(&mut cx);
+        assert!(receiver
+            .try_iter()
+            .any(|action_wrapper| is_hold(&action_wrapper.data)));
+    }
+
+    #[test]
+    fn hold_aspect_from_records_source_of_held_aspect() {
+        let (context, state, receiver) = test_context_and_state("jane");
+        let aspect = EntryAspect::Header(test_chain_header());
+        let source = Address::from("bob");
+
+        let mut future = Box::pin(hold_aspect_from(
+            aspect.clone(),
+            source.clone(),
+            context.clone(),
+        ));
+        let mut cx = std::task::Context::from_waker(noop_waker_ref());
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+
+        let hold = receiver
+            .try_iter()
+            .map(|action_wrapper| action_wrapper.data)
+            .find(|action_wrapper| match action_wrapper.action() {
+                Action::HoldAspect(_) => true,
+                _ => false,
+            })
+            .expect("hold should have been dispatched");
+        let new_state = state.read().unwrap().reduce(hold);
+        *state.write().unwrap() = new_state;
+
+        assert_eq!(future.as_mut().poll(&mut cx), Poll::Ready(Ok(())));
+        let dht = state.read().unwrap().dht();
+        assert!(dht.get_holding_map().contains(&aspect));
+        assert_eq!(
+            dht.get_holding_map().source_of(&aspect.address()),
+            Some(&source)
+        );
+    }
+
+    #[test]
+    fn concurrent_holds_of_same_aspect_dispatch_one_action() {
+        let (context, state, receiver) = test_context_and_state("jane");
+        let aspect = EntryAspect::Header(test_chain_header());
+
+        let mut first = Box::pin(hold_aspect(aspect.clone(), context.clone()));
+        let mut second = Box::pin(hold_aspect(aspect.clone(), context.clone()));
+        let mut cx = std::task::Context::from_waker(noop_waker_ref());
+        assert!(first.as_mut().poll(&mut cx).is_pending());
+        assert!(second.as_mut().poll(&mut cx).is_pending());
+
+        let holds = receiver
+            .try_iter()
+            .map(|action_wrapper| action_wrapper.data)
+            .filter(|action_wrapper| match action_wrapper.action() {
+                Action::HoldAspect(_) => true,
+                _ => false,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(holds.len(), 1);
+
+        let new_state = state.read().unwrap().reduce(holds[0].clone());
+        *state.write().unwrap() = new_state;
+        assert_eq!(first.as_mut().poll(&mut cx), Poll::Ready(Ok(())));
+        assert_eq!(second.as_mut().poll(&mut cx), Poll::Ready(Ok(())));
+    }
+
+    #[test]
+    fn hold_aspect_rejects_aspect_not_hashing_to_its_address() {
+        let (context, state, receiver) = test_context_and_state("jane");
+
+        // the header claims the address of test_entry()
+        let tampered = EntryAspect::Content(test_entry_b(), test_chain_header());
+        assert_eq!(
+            block_on(hold_aspect(tampered.clone(), context.clone())),
+            Err(HolochainError::IntegrityCheckFailed)
+        );
+        assert!(!receiver
+            .try_iter()
+            .any(|action_wrapper| match action_wrapper.data.action() {
+                Action::HoldAspect((aspect, _, _)) => *aspect == tampered,
+                _ => false,
+            }));
+        assert!(context.pending_holds().is_empty());
+        assert!(!state
+            .read()
+            .unwrap()
+            .dht()
+            .get_holding_map()
+            .contains(&tampered));
+    }
+
+    #[test]
+    fn intact_aspect_is_held_after_tampered_one_got_rejected() {
+        let (context, state, receiver) = test_context_and_state("jane");
+
+        let intact = EntryAspect::Content(test_entry(), test_chain_header());
+        // the header claims the address of test_entry()
+        let tampered = EntryAspect::Content(test_entry_b(), test_chain_header());
+        assert_eq!(
+            block_on(hold_aspect(tampered.clone(), context.clone())),
+            Err(HolochainError::IntegrityCheckFailed)
+        );
+        let new_state =
+            state
+                .read()
+                .unwrap()
+                .reduce(ActionWrapper::new(Action::RejectUnvalidatedAspect((
+                    tampered.clone(),
+                    "tampered".to_string(),
+                ))));
+        *state.write().unwrap() = new_state;
+
+        // A rejection only stands for the content that got rejected
+        let mut dht = (*state.read().unwrap().dht()).clone();
+        dht.rejected_aspects.insert(
+            intact.address(),
+            dht.rejected_aspects[&tampered.address()].clone(),
+        );
+        assert_eq!(dht.hold_rejection_after(&intact, None), None);
+        assert!(dht.hold_rejection_after(&tampered, None).is_some());
+
+        let mut future = Box::pin(hold_aspect(intact.clone(), context.clone()));
+        let mut cx = std::task::Context::from_waker(noop_waker_ref());
+        assert_eq!(future.as_mut().poll(&mut cx), Poll::Pending);
+        let hold = receiver
+            .try_iter()
+            .map(|action_wrapper| action_wrapper.data)
+            .find(|action_wrapper| match action_wrapper.action() {
+                Action::HoldAspect((held, _, _)) => *held == intact,
+                _ => false,
+            })
+            .expect("There must be a dispatched hold");
+        let new_state = state.read().unwrap().reduce(hold);
+        *state.write().unwrap() = new_state;
+        assert_eq!(future.as_mut().poll(&mut cx), Poll::Ready(Ok(())));
+        assert!(!state
+            .read()
+            .unwrap()
+            .dht()
+            .get_holding_map()
+            .contains(&tampered));
+    }
+
+    #[test]
+    fn bundle_with_corrupt_aspect_holds_nothing() {
+        let (context, state, receiver) = test_context_and_state("jane");
+
+        let valid = EntryAspect::Content(test_entry(), test_chain_header());
+        // the header claims the address of test_entry()
+        let corrupt = EntryAspect::Content(test_entry_b(), test_chain_header());
+        assert_eq!(
+            block_on(hold_bundle(
+                vec![valid.clone(), corrupt.clone()],
+                context.clone()
+            )),
+            Err(HolochainError::IntegrityCheckFailed)
+        );
+        assert!(!receiver
+            .try_iter()
+            .any(|action_wrapper| match action_wrapper.data.action() {
+                Action::HoldBundle(_) => true,
+                _ => false,
+            }));
+        assert!(context.pending_holds().is_empty());
+
+        // A bundle with an aspect that can't be stored gets rolled back as a whole
+        let (_base, unstorable) = link_aspect_with_missing_base();
+        let new_state = state
+            .read()
+            .unwrap()
+            .reduce(ActionWrapper::new(Action::HoldBundle(vec![
+                valid.clone(),
+                unstorable.clone(),
+            ])));
+        *state.write().unwrap() = new_state;
+        let dht = state.read().unwrap().dht();
+        assert!(!dht.get_holding_map().contains(&valid));
+        assert!(!dht.get_holding_map().contains(&unstorable));
+        assert!(dht.hold_rejection(&valid.address()).is_some());
+    }
+
+    #[test]
+    fn rejected_aspect_can_be_held_again() {
+        let (context, state, receiver) = test_context_and_state("jane");
+
+        // The aspect gets rejected with the bundle it was part of
+        let aspect = EntryAspect::Content(test_entry(), test_chain_header());
+        let (_base, unstorable) = link_aspect_with_missing_base();
+        let new_state = state
+            .read()
+            .unwrap()
+            .reduce(ActionWrapper::new(Action::HoldBundle(vec![
+                aspect.clone(),
+                unstorable,
+            ])));
+        *state.write().unwrap() = new_state;
+        assert!(state
+            .read()
+            .unwrap()
+            .dht()
+            .hold_rejection(&aspect.address())
+            .is_some());
+
+        // A new hold doesn't fail because of the earlier rejection
+        let mut future = Box::pin(hold_aspect(aspect.clone(), context.clone()));
+        let mut cx = std::task::Context::from_waker(noop_waker_ref());
+        assert_eq!(future.as_mut().poll(&mut cx), Poll::Pending);
+
+        let hold = receiver
+            .try_iter()
+            .map(|action_wrapper| action_wrapper.data)
+            .find(|action_wrapper| match action_wrapper.action() {
+                Action::HoldAspect((held, _, _)) => *held == aspect,
+                _ => false,
+            })
+            .expect("There must be a dispatched hold");
+        let new_state = state.read().unwrap().reduce(hold);
+        *state.write().unwrap() = new_state;
+        assert_eq!(future.as_mut().poll(&mut cx), Poll::Ready(Ok(())));
+        assert_eq!(
+            state
+                .read()
+                .unwrap()
+                .dht()
+                .hold_rejection(&aspect.address()),
+            None
+        );
+    }
+
+    #[test]
+    fn force_hold_aspect_holds_aspect_that_fails_validation() {
+        let (context, state, _receiver) = test_context_and_state("jane");
+        let (_base, aspect) = link_aspect_with_missing_base();
+
+        assert!(block_on(force_hold_aspect(aspect.clone(), context.clone())).is_err());
+
+        // A regular hold of the aspect fails
+        let new_state = state
+            .read()
+            .unwrap()
+            .reduce(ActionWrapper::new(Action::HoldAspect((
+                aspect.clone(),
+                None,
+                SystemTime::now(),
+            ))));
+        *state.write().unwrap() = new_state;
+        assert!(!state
+            .read()
+            .unwrap()
+            .dht()
+            .get_holding_map()
+            .contains(&aspect));
+
+        context.set_trusted_bootstrap(true);
+        let mut future = Box::pin(force_hold_aspect(aspect.clone(), context.clone()));
+        let mut cx = std::task::Context::from_waker(noop_waker_ref());
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+
+        let new_state = state
+            .read()
+            .unwrap()
+            .reduce(ActionWrapper::new(Action::ForceHoldAspect(aspect.clone())));
+        *state.write().unwrap() = new_state;
+
+        assert_eq!(future.as_mut().poll(&mut cx), Poll::Ready(Ok(())));
+        assert!(state
+            .read()
+            .unwrap()
+            .dht()
+            .get_holding_map()
+            .contains(&aspect));
+    }
+
+    #[test]
+    fn cancelled_holds_tell_user_cancel_from_shutdown() {
+        // Nobody reduces the dispatched holds, so they wait until they get cancelled
+        let (context, _state, _receiver) = test_context_and_state("jane");
+        let aspect = EntryAspect::Header(test_chain_header());
+
+        let (handle, hold) = hold_aspect_cancellable(aspect.clone(), context.clone());
+        handle.cancel();
+        assert_eq!(
+            block_on(hold),
+            Err(HolochainError::Cancelled(CancelReason::User))
+        );
+
+        let (_handle, hold) = hold_aspect_cancellable(aspect, context.clone());
+        context
+            .instance_is_alive
+            .store(false, std::sync::atomic::Ordering::Relaxed);
+        assert_eq!(
+            block_on(hold),
+            Err(HolochainError::Cancelled(CancelReason::Shutdown))
+        );
+    }
+
+    #[test]
+    fn aspect_can_be_held_again_after_cancelled_hold() {
+        let (context, state, receiver) = test_context_and_state("jane");
+        let aspect = EntryAspect::Content(test_entry(), test_chain_header());
+        let dispatched_holds = || {
+            receiver
+                .try_iter()
+                .map(|action_wrapper| action_wrapper.data)
+                .filter(|action_wrapper| match action_wrapper.action() {
+                    Action::HoldAspect(_) => true,
+                    _ => false,
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut cx = std::task::Context::from_waker(noop_waker_ref());
+
+        // The dispatched hold gets lost, nobody reduces it
+        let (handle, mut cancelled) = hold_aspect_cancellable(aspect.clone(), context.clone());
+        assert!(Pin::new(&mut cancelled).poll(&mut cx).is_pending());
+        assert_eq!(dispatched_holds().len(), 1);
+        handle.cancel();
+        assert_eq!(
+            block_on(cancelled),
+            Err(HolochainError::Cancelled(CancelReason::User))
+        );
+
+        let mut hold = Box::pin(hold_aspect(aspect.clone(), context.clone()));
+        assert!(hold.as_mut().poll(&mut cx).is_pending());
+        let holds = dispatched_holds();
+        assert_eq!(holds.len(), 1);
+
+        let new_state = state.read().unwrap().reduce(holds[0].clone());
+        *state.write().unwrap() = new_state;
+        assert_eq!(hold.as_mut().poll(&mut cx), Poll::Ready(Ok(())));
+    }
+
+    #[test]
+    fn holds_get_throttled_under_memory_pressure() {
+        let (context, _state, _receiver) = test_context_and_state("jane");
+        let aspect = EntryAspect::Header(test_chain_header());
+
+        context.set_memory_pressure(95);
+        assert_eq!(
+            block_on(hold_aspect(aspect.clone(), context.clone())),
+            Err(HolochainError::MemoryPressure)
+        );
+        assert!(context.pending_holds().is_empty());
+
+        // Delayed holds give up if the pressure stays high...
+        context.set_memory_pressure_policy(MemoryPressurePolicy {
+            threshold: 90,
+            throttle: HoldThrottle::Delay(Duration::from_millis(100)),
+        });
+        let start = Instant::now();
+        assert_eq!(
+            block_on(memory_pressure_relief(&context)),
+            Err(HolochainError::MemoryPressure)
+        );
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        // ...and go ahead once it drops
+        context.set_memory_pressure_policy(MemoryPressurePolicy {
+            threshold: 90,
+            throttle: HoldThrottle::Delay(Duration::from_secs(5)),
+        });
+        let relieving_context = context.clone();
+        let relief = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(30));
+            relieving_context.set_memory_pressure(50);
+        });
+        assert_eq!(block_on(memory_pressure_relief(&context)), Ok(()));
+        relief.join().unwrap();
+    }
+}
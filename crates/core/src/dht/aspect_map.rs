@@ -1,4 +1,7 @@
-use crate::holochain_wasm_utils::holochain_persistence_api::cas::content::AddressableContent;
+use crate::dht::storage_tiers::StorageTier;
+use crate::holochain_wasm_utils::holochain_persistence_api::cas::content::{
+    Address, AddressableContent,
+};
 use holochain_core_types::network::entry_aspect::EntryAspect;
 use im::{HashMap, HashSet};
 use lib3h_protocol::types::{AspectHash, EntryHash};
@@ -7,8 +10,24 @@ pub type AspectSet = HashSet<AspectHash>;
 
 pub type AspectMapBare = HashMap<EntryHash, AspectSet>;
 
-#[derive(Clone, Debug, Default, PartialEq)]
-pub struct AspectMap(AspectMapBare);
+/// Agents we received aspects from, by aspect address
+pub type AspectSources = HashMap<AspectHash, Address>;
+
+/// Storage tiers of the aspects not held in `StorageTier::Durable`, by aspect address
+pub type AspectTiers = HashMap<AspectHash, StorageTier>;
+
+/// The held aspects by entry, plus where we got them from if we know it and which
+/// storage tier they are held in. Sources and tiers are not part of the bare map and
+/// so neither of snapshots nor of comparisons.
+#[derive(Clone, Debug, Default)]
+pub struct AspectMap(AspectMapBare, AspectSources, AspectTiers);
+
+impl PartialEq for AspectMap {
+    fn eq(&self, other: &AspectMap) -> bool {
+        self.0 == other.0
+    }
+}
+
 #[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
 impl AspectMap {
     pub fn new() -> Self {
@@ -39,6 +58,12 @@ impl AspectMap {
             .unwrap_or_default()
     }
 
+    /// Like `contains()` when all we know is the address of the aspect.
+    pub fn contains_address(&self, aspect_address: &Address) -> bool {
+        let aspect_hash = AspectHash::from(aspect_address.clone());
+        self.0.values().any(|set| set.contains(&aspect_hash))
+    }
+
     pub fn add(&mut self, aspect: &EntryAspect) {
         let entry_address = aspect
             .entry_address()
@@ -52,6 +77,33 @@ impl AspectMap {
             .insert(entry_aspect_address);
     }
 
+    /// Like `add()` but also remembers the agent we got the aspect from.
+    pub fn add_from(&mut self, aspect: &EntryAspect, source: Address) {
+        self.add(aspect);
+        self.1.insert(aspect.address().into(), source);
+    }
+
+    /// Records that the held `aspect` got stored in `tier`.
+    pub fn set_tier(&mut self, aspect: &EntryAspect, tier: StorageTier) {
+        match tier {
+            StorageTier::Durable => self.2.remove(&aspect.address().into()),
+            tier => self.2.insert(aspect.address().into(), tier),
+        };
+    }
+
+    /// The storage tier the aspect with the given address is held in.
+    pub fn tier_of(&self, aspect_address: &Address) -> StorageTier {
+        self.2
+            .get(&AspectHash::from(aspect_address.clone()))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// The agent we got the aspect with the given address from, if it was recorded.
+    pub fn source_of(&self, aspect_address: &Address) -> Option<&Address> {
+        self.1.get(&AspectHash::from(aspect_address.clone()))
+    }
+
     pub fn entry_addresses(&self) -> impl Iterator<Item = &EntryHash> {
         self.0.keys()
     }
@@ -103,7 +155,7 @@ impl AspectMap {
 
 impl From<AspectMapBare> for AspectMap {
     fn from(map: AspectMapBare) -> AspectMap {
-        AspectMap { 0: map }
+        AspectMap(map, AspectSources::new(), AspectTiers::new())
     }
 }
 
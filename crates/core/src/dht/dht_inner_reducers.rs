@@ -1,4 +1,4 @@
-use crate::content_store::{AddContent, GetContent};
+use crate::content_store::GetContent;
 ///
 /// Inner DHT reducers are not pure functions but rather functions designed to make the required
 /// mutations to a newly cloned DhtState object. Unlike the reducers they do not need a specific signature.
@@ -8,7 +8,7 @@ use crate::content_store::{AddContent, GetContent};
 ///
 /// It is up to the calling reducer function whether the new state object should be kept and what to do with the return value
 ///
-use crate::dht::dht_store::DhtStore;
+use crate::dht::{dht_store::DhtStore, storage_tiers::StorageTier};
 use holochain_core_types::{
     crud_status::{create_crud_link_eav, create_crud_status_eav, CrudStatus},
     eav::{Attribute, EaviQuery, EntityAttributeValueIndex},
@@ -30,10 +30,15 @@ pub(crate) enum LinkModification {
     Remove,
 }
 
-/// Used as the inner function for both commit and hold reducers
+/// Used as the inner function for both commit and hold reducers.
+/// The entry goes to the CAS of `tier`, its CRUD status to the EAV.
 #[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
-pub(crate) fn reduce_store_entry_inner(store: &mut DhtStore, entry: &Entry) -> HcResult<()> {
-    match store.add(entry) {
+pub(crate) fn reduce_store_entry_inner(
+    store: &mut DhtStore,
+    entry: &Entry,
+    tier: StorageTier,
+) -> HcResult<()> {
+    match store.add_to_tier(entry, tier) {
         Ok(()) => create_crud_status_eav(&entry.address(), CrudStatus::Live).map(|status_eav| {
             store.add_eavi(&status_eav).map(|_| ()).map_err(|e| {
                 format!("err/dht: dht::reduce_store_entry_inner() FAILED {:?}", e).into()
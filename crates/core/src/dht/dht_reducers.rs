@@ -4,10 +4,13 @@ use crate::{
     action::{Action, ActionWrapper},
     dht::{
         dht_store::DhtStore,
-        pending_validations::{PendingValidationWithTimeout, ValidationTimeout},
+        pending_validations::{
+            PendingValidationStruct, PendingValidationWithTimeout, ValidationTimeout,
+        },
+        storage_tiers::StorageTier,
     },
 };
-use std::sync::Arc;
+use std::{convert::TryFrom, sync::Arc};
 
 use super::dht_inner_reducers::{
     reduce_add_remove_link_inner, reduce_remove_entry_inner, reduce_store_entry_inner,
@@ -45,6 +48,11 @@ fn resolve_reducer(action_wrapper: &ActionWrapper) -> Option<DhtReducer> {
     match action_wrapper.action() {
         Action::Commit(_) => Some(reduce_commit_entry),
         Action::HoldAspect(_) => Some(reduce_hold_aspect),
+        Action::ForceHoldAspect(_) => Some(reduce_force_hold_aspect),
+        Action::HoldAspectChunk(_) => Some(reduce_hold_aspect_chunk),
+        Action::HoldBundle(_) => Some(reduce_hold_bundle),
+        Action::HoldUnvalidatedAspect(_) => Some(reduce_hold_unvalidated_aspect),
+        Action::RejectUnvalidatedAspect(_) => Some(reduce_reject_unvalidated_aspect),
         Action::QueueHoldingWorkflow(_) => Some(reduce_queue_holding_workflow),
         Action::RemoveQueuedHoldingWorkflow(_) => Some(reduce_remove_queued_holding_workflow),
         Action::Prune => Some(reduce_prune),
@@ -59,7 +67,7 @@ pub(crate) fn reduce_commit_entry(
 ) -> Option<DhtStore> {
     let (entry, _, _) = unwrap_to!(action_wrapper.action() => Action::Commit);
     let mut new_store = (*old_store).clone();
-    match reduce_store_entry_inner(&mut new_store, entry) {
+    match reduce_store_entry_inner(&mut new_store, entry, StorageTier::Durable) {
         Ok(()) => Some(new_store),
         Err(e) => {
             println!("{}", e);
@@ -68,14 +76,157 @@ pub(crate) fn reduce_commit_entry(
     }
 }
 
+/// Why an aspect could not be held
+enum HoldFailure {
+    /// Might work when tried again later
+    Transient(String),
+    /// Will never work
+    Permanent(String),
+}
+
+/// Holds the aspect. Failures don't leave the aspect pending forever: transient ones
+/// get the aspect retried later (see `DhtStore::due_hold_retries()`), until it ran out
+/// of attempts and gets rejected like after a permanent failure.
 #[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
 pub(crate) fn reduce_hold_aspect(
     old_store: &DhtStore,
     action_wrapper: &ActionWrapper,
 ) -> Option<DhtStore> {
-    let aspect = unwrap_to!(action_wrapper.action() => Action::HoldAspect);
+    let (aspect, source, attempted_at) = unwrap_to!(action_wrapper.action() => Action::HoldAspect);
+    match hold_aspect_inner(old_store, aspect) {
+        Ok(mut new_store) => {
+            new_store.clear_failed_hold(&aspect.address());
+            wake_dependent_validations(&mut new_store, aspect);
+            if let Some(source) = source {
+                new_store.record_aspect_source(aspect, source.clone());
+            }
+            Some(new_store)
+        }
+        Err(failure) => {
+            let mut new_store = (*old_store).clone();
+            match failure {
+                HoldFailure::Transient(e) => {
+                    error!("{}", e);
+                    new_store.record_failed_hold(
+                        aspect,
+                        source.clone(),
+                        e,
+                        *attempted_at,
+                        action_wrapper.id(),
+                    );
+                }
+                HoldFailure::Permanent(e) => {
+                    error!("{}", e);
+                    new_store.reject_hold(aspect, e, action_wrapper.id());
+                }
+            }
+            Some(new_store)
+        }
+    }
+}
+
+/// Validations deferred on the content of an entry can run once that content is held.
+fn wake_dependent_validations(store: &mut DhtStore, aspect: &EntryAspect) {
+    if let EntryAspect::Content(entry, _) = aspect {
+        store.wake_validations_waiting_for(&entry.address());
+    }
+}
+
+/// Holds the aspect like `reduce_hold_aspect` but puts it into the holding map
+/// even if storing what it contains failed.
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+pub(crate) fn reduce_force_hold_aspect(
+    old_store: &DhtStore,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore> {
+    let aspect = unwrap_to!(action_wrapper.action() => Action::ForceHoldAspect);
+    warn!(
+        "Force-holding aspect {} without validation",
+        aspect.address()
+    );
+    let mut new_store = hold_aspect_inner(old_store, aspect).unwrap_or_else(|failure| {
+        let e = match failure {
+            HoldFailure::Transient(e) | HoldFailure::Permanent(e) => e,
+        };
+        warn!("Force-holding aspect {} although: {}", aspect.address(), e);
+        let mut new_store = (*old_store).clone();
+        new_store.mark_aspect_as_held(aspect);
+        new_store
+    });
+    new_store.clear_failed_hold(&aspect.address());
+    Some(new_store)
+}
+
+/// Holds all aspects of the bundle, or if any of them fails, none of them.
+/// Failures are not retried, every aspect of a failed bundle gets rejected.
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+pub(crate) fn reduce_hold_bundle(
+    old_store: &DhtStore,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore> {
+    let aspects = unwrap_to!(action_wrapper.action() => Action::HoldBundle);
+    let held = aspects.iter().try_fold(old_store.clone(), |store, aspect| {
+        hold_aspect_inner(&store, aspect).map_err(|failure| (aspect, failure))
+    });
+    match held {
+        Ok(mut new_store) => {
+            for aspect in aspects.iter() {
+                new_store.clear_failed_hold(&aspect.address());
+                wake_dependent_validations(&mut new_store, aspect);
+            }
+            Some(new_store)
+        }
+        Err((failed, failure)) => {
+            let e = match failure {
+                HoldFailure::Transient(e) | HoldFailure::Permanent(e) => e,
+            };
+            let reason = format!(
+                "Aspect {} of the bundle could not be held: {}",
+                failed.address(),
+                e
+            );
+            error!("{}", reason);
+            let mut new_store = (*old_store).clone();
+            for aspect in aspects.iter() {
+                new_store.reject_hold(aspect, reason.clone(), action_wrapper.id());
+            }
+            Some(new_store)
+        }
+    }
+}
+
+/// Stores the aspect as unvalidated, unless it is held already.
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+pub(crate) fn reduce_hold_unvalidated_aspect(
+    old_store: &DhtStore,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore> {
+    let aspect = unwrap_to!(action_wrapper.action() => Action::HoldUnvalidatedAspect);
+    if old_store.get_holding_map().contains(aspect) {
+        return None;
+    }
+    let mut new_store = (*old_store).clone();
+    new_store.store_unvalidated(aspect);
+    Some(new_store)
+}
+
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+pub(crate) fn reduce_reject_unvalidated_aspect(
+    old_store: &DhtStore,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore> {
+    let (aspect, reason) = unwrap_to!(action_wrapper.action() => Action::RejectUnvalidatedAspect);
     let mut new_store = (*old_store).clone();
+    new_store.reject_hold(aspect, reason.clone(), action_wrapper.id());
+    Some(new_store)
+}
+
+/// Holds the aspect in the storage tier the context's `StorageTierPolicy` picks for it.
+fn hold_aspect_inner(old_store: &DhtStore, aspect: &EntryAspect) -> Result<DhtStore, HoldFailure> {
+    let mut new_store = (*old_store).clone();
+    let tier = old_store.storage_tiers.tier_for(aspect);
     new_store.mark_aspect_as_held(&aspect);
+    new_store.set_aspect_tier(&aspect, tier);
 
     // TODO: we think we don't need this but not 100%
     // new_store.actions_mut().insert(
@@ -84,67 +235,107 @@ pub(crate) fn reduce_hold_aspect(
     // );
     match aspect {
         EntryAspect::Content(entry, header) => {
-            match reduce_store_entry_inner(&mut new_store, entry) {
-                Ok(()) => {
-                    new_store.add_header_for_entry(&entry, &header).ok()?;
-                    Some(new_store)
-                }
-                Err(e) => {
-                    error!("{}", e);
-                    None
-                }
-            }
+            reduce_store_entry_inner(&mut new_store, entry, tier)
+                .and_then(|()| new_store.add_header_for_entry_to_tier(&entry, &header, tier))
+                .map_err(|e| HoldFailure::Transient(e.to_string()))?;
+            Ok(new_store)
         }
         EntryAspect::LinkAdd(link_data, _header) => {
             let entry = Entry::LinkAdd(link_data.clone());
-            match reduce_add_remove_link_inner(
+            reduce_add_remove_link_inner(
                 &mut new_store,
                 link_data,
                 &entry.address(),
                 LinkModification::Add,
-            ) {
-                Ok(_) => Some(new_store),
-                Err(e) => {
-                    error!("{}", e);
-                    None
-                }
-            }
+            )
+            .map_err(|e| HoldFailure::Transient(e.to_string()))?;
+            Ok(new_store)
         }
-        EntryAspect::LinkRemove((link_data, links_to_remove), _header) => Some(
-            links_to_remove
-                .iter()
-                .fold(new_store, |mut store, link_addresses| {
-                    let _ = reduce_add_remove_link_inner(
-                        &mut store,
-                        link_data,
-                        link_addresses,
-                        LinkModification::Remove,
-                    );
-                    store
-                }),
-        ),
+        EntryAspect::LinkRemove((link_data, links_to_remove), _header) => Ok(links_to_remove
+            .iter()
+            .fold(new_store, |mut store, link_addresses| {
+                let _ = reduce_add_remove_link_inner(
+                    &mut store,
+                    link_data,
+                    link_addresses,
+                    LinkModification::Remove,
+                );
+                store
+            })),
         EntryAspect::Update(entry, header) => {
             if let Some(crud_link) = header.link_update_delete() {
                 let _ = reduce_update_entry_inner(&mut new_store, &crud_link, &entry.address());
-                Some(new_store)
+                Ok(new_store)
             } else {
-                error!("EntryAspect::Update without crud_link in header received!");
-                None
+                Err(HoldFailure::Permanent(
+                    "EntryAspect::Update without crud_link in header received!".to_string(),
+                ))
             }
         }
         EntryAspect::Deletion(header) => {
             if let Some(crud_link) = header.link_update_delete() {
                 let _ =
                     reduce_remove_entry_inner(&mut new_store, &crud_link, &header.entry_address());
-                Some(new_store)
+                Ok(new_store)
             } else {
-                error!("EntryAspect::Update without crud_link in header received!");
-                None
+                Err(HoldFailure::Permanent(
+                    "EntryAspect::Update without crud_link in header received!".to_string(),
+                ))
             }
         }
-        EntryAspect::Header(_) => {
-            error!("Got EntryAspect::Header which is not implemented.");
-            None
+        EntryAspect::Header(_) => Err(HoldFailure::Permanent(
+            "Got EntryAspect::Header which is not implemented.".to_string(),
+        )),
+    }
+}
+
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+pub(crate) fn reduce_hold_aspect_chunk(
+    old_store: &DhtStore,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore> {
+    let (manifest, chunk, received_at) =
+        unwrap_to!(action_wrapper.action() => Action::HoldAspectChunk);
+    let mut new_store = (*old_store).clone();
+    let key = (manifest.aspect_address.clone(), manifest.hash());
+    let partial = new_store.aspect_chunks.entry(key.clone()).or_default();
+    partial.updated_at = Some(*received_at);
+    if let Err(e) = manifest.verify(chunk) {
+        // Only this chunk gets rejected, the ones verified before stay
+        error!("Rejected aspect chunk: {}", e);
+        partial.rejected_chunks.insert(chunk.index, e.to_string());
+        new_store.drop_stale_aspect_chunks(*received_at);
+        return Some(new_store);
+    }
+    partial.rejected_chunks.remove(&chunk.index);
+    partial.chunks.insert(chunk.index, chunk.clone());
+    if partial.chunks.len() < manifest.chunk_hashes.len() {
+        new_store.drop_stale_aspect_chunks(*received_at);
+        return Some(new_store);
+    }
+
+    let chunks = new_store
+        .aspect_chunks
+        .remove(&key)
+        .unwrap_or_default()
+        .chunks
+        .into_iter()
+        .map(|(_, chunk)| chunk)
+        .collect::<Vec<_>>();
+    new_store.drop_stale_aspect_chunks(*received_at);
+    // The chunks came from the network, so the aspect gets validated like any other
+    match manifest
+        .reassemble(&chunks)
+        .and_then(PendingValidationStruct::try_from)
+    {
+        Ok(pending) => reduce_queue_holding_workflow(
+            &new_store,
+            &ActionWrapper::new(Action::QueueHoldingWorkflow((Arc::new(pending), None))),
+        )
+        .or(Some(new_store)),
+        Err(e) => {
+            error!("Could not reassemble aspect from chunks: {}", e);
+            Some(new_store)
         }
     }
 }
@@ -242,11 +433,14 @@ pub mod tests {
         content_store::{AddContent, GetContent},
         dht::{
             dht_reducers::{
-                reduce, reduce_hold_aspect, reduce_queue_holding_workflow,
-                reduce_remove_queued_holding_workflow,
+                reduce, reduce_hold_aspect, reduce_hold_aspect_chunk,
+                reduce_queue_holding_workflow, reduce_remove_queued_holding_workflow,
+            },
+            dht_store::{
+                create_get_links_eavi_query, DhtStore, ASPECT_CHUNKS_MAX_AGE, MAX_PARTIAL_ASPECTS,
             },
-            dht_store::{create_get_links_eavi_query, DhtStore},
             pending_validations::{PendingValidation, PendingValidationStruct, ValidatingWorkflow},
+            storage_tiers::StorageTier,
         },
         instance::tests::test_context,
         network::entry_with_header::EntryWithHeader,
@@ -257,12 +451,15 @@ pub mod tests {
         agent::{test_agent_id, test_agent_id_with_name},
         chain_header::{test_chain_header, test_chain_header_with_sig},
         eav::Attribute,
-        entry::{test_entry, test_sys_entry, Entry},
+        entry::{test_entry, test_entry_unique, test_sys_entry, Entry},
         link::{link_data::LinkData, Link, LinkActionKind},
-        network::entry_aspect::EntryAspect,
+        network::{
+            aspect_chunk::{AspectChunk, AspectChunkManifest},
+            entry_aspect::EntryAspect,
+        },
     };
     use holochain_persistence_api::cas::content::{Address, AddressableContent};
-    use std::{sync::Arc, time::SystemTime};
+    use std::{convert::TryFrom, sync::Arc, time::SystemTime};
     // TODO do this for all crate tests somehow
     #[allow(dead_code)]
     fn enable_logging_for_test() {
@@ -286,9 +483,10 @@ pub mod tests {
 
         let new_dht_store = reduce_hold_aspect(
             &store.dht(),
-            &ActionWrapper::new(Action::HoldAspect(EntryAspect::Content(
-                sys_entry.clone(),
-                test_chain_header(),
+            &ActionWrapper::new(Action::HoldAspect((
+                EntryAspect::Content(sys_entry.clone(), test_chain_header()),
+                None,
+                SystemTime::now(),
             ))),
         )
         .expect("there should be a new store for committing a sys entry");
@@ -306,6 +504,286 @@ pub mod tests {
         );
     }
 
+    /// A link aspect that fails to be held until its base entry got stored.
+    pub fn link_aspect_with_missing_base() -> (Entry, EntryAspect) {
+        let base = test_entry_unique();
+        let link = Link::new(&base.address(), &base.address(), "test_link", "retry");
+        let link_data = LinkData::from_link(
+            &link,
+            LinkActionKind::ADD,
+            test_chain_header(),
+            test_agent_id(),
+        );
+        (base, EntryAspect::LinkAdd(link_data, test_chain_header()))
+    }
+
+    #[test]
+    fn transiently_failing_hold_succeeds_on_retry() {
+        let context = test_context("bob", None);
+        let store = test_store(context);
+        // The link's base is not stored yet, so the first attempt fails
+        let (base, aspect) = link_aspect_with_missing_base();
+        let hold = ActionWrapper::new(Action::HoldAspect((
+            aspect.clone(),
+            None,
+            SystemTime::now(),
+        )));
+
+        let dht = reduce_hold_aspect(&store.dht(), &hold).expect("failure should be recorded");
+        assert!(!dht.get_holding_map().contains(&aspect));
+        assert_eq!(dht.hold_rejection(&aspect.address()), None);
+        // not before the back-off elapsed
+        assert!(dht.due_hold_retries(SystemTime::now()).is_empty());
+        let retries = dht.due_hold_retries(SystemTime::now() + Duration::from_secs(1));
+        assert_eq!(retries.len(), 1);
+        assert_eq!(retries[0].aspect, aspect);
+        assert_eq!(retries[0].attempts, 1);
+
+        let mut dht = dht;
+        dht.add(&base).unwrap();
+        let dht = reduce_hold_aspect(
+            &dht,
+            &ActionWrapper::new(Action::HoldAspect((
+                retries[0].aspect.clone(),
+                None,
+                SystemTime::now(),
+            ))),
+        )
+        .expect("retry should succeed");
+        assert!(dht.get_holding_map().contains(&aspect));
+        assert!(dht
+            .due_hold_retries(SystemTime::now() + Duration::from_secs(3600))
+            .is_empty());
+    }
+
+    #[test]
+    fn failed_hold_backs_off_from_time_in_action() {
+        let context = test_context("bob", None);
+        let store = test_store(context);
+        let (_base, aspect) = link_aspect_with_missing_base();
+        let attempted_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let hold = ActionWrapper::new(Action::HoldAspect((aspect.clone(), None, attempted_at)));
+
+        // Reducing the same action twice gives the same retry
+        let first = reduce_hold_aspect(&store.dht(), &hold).unwrap();
+        let second = reduce_hold_aspect(&store.dht(), &hold).unwrap();
+        assert_eq!(
+            first.due_hold_retries(attempted_at + Duration::from_secs(1)),
+            second.due_hold_retries(attempted_at + Duration::from_secs(1))
+        );
+        assert!(first.due_hold_retries(attempted_at).is_empty());
+        assert_eq!(
+            first
+                .due_hold_retries(attempted_at + Duration::from_secs(1))
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn hold_gets_rejected_after_max_attempts() {
+        let context = test_context("bob", None);
+        context.set_max_hold_attempts(2);
+        let store = test_store(context);
+        let (_base, aspect) = link_aspect_with_missing_base();
+        let hold = ActionWrapper::new(Action::HoldAspect((
+            aspect.clone(),
+            None,
+            SystemTime::now(),
+        )));
+
+        let dht = reduce_hold_aspect(&store.dht(), &hold).unwrap();
+        assert_eq!(dht.hold_rejection(&aspect.address()), None);
+        let dht = reduce_hold_aspect(&dht, &hold).unwrap();
+        assert!(dht.hold_rejection(&aspect.address()).is_some());
+        assert!(dht
+            .due_hold_retries(SystemTime::now() + Duration::from_secs(3600))
+            .is_empty());
+    }
+
+    #[test]
+    fn hold_aspect_places_aspects_in_tier_picked_by_policy() {
+        let context = test_context("bob", None);
+        context.set_storage_tier_policy(Some(Arc::new(|aspect: &EntryAspect| match aspect {
+            EntryAspect::Content(..) => StorageTier::Volatile,
+            _ => StorageTier::Durable,
+        })));
+        let store = test_store(context.clone());
+        let entry = test_entry_unique();
+        let content = EntryAspect::Content(entry.clone(), test_chain_header());
+        let (base, link) = link_aspect_with_missing_base();
+        let mut dht = (*store.dht()).clone();
+        dht.add(&base).unwrap();
+
+        let dht = reduce_hold_aspect(
+            &dht,
+            &ActionWrapper::new(Action::HoldAspect((
+                content.clone(),
+                None,
+                SystemTime::now(),
+            ))),
+        )
+        .unwrap();
+        let dht = reduce_hold_aspect(
+            &dht,
+            &ActionWrapper::new(Action::HoldAspect((link.clone(), None, SystemTime::now()))),
+        )
+        .unwrap();
+
+        let holding_map = dht.get_holding_map();
+        assert!(holding_map.contains(&content));
+        assert!(holding_map.contains(&link));
+        assert_eq!(
+            holding_map.tier_of(&content.address()),
+            StorageTier::Volatile
+        );
+        assert_eq!(holding_map.tier_of(&link.address()), StorageTier::Durable);
+
+        let in_durable = |address: Address| {
+            let storage = context.dht_storage.read().unwrap();
+            storage.fetch(&address).unwrap().is_some()
+        };
+        let in_volatile = |address: Address| {
+            let volatile_storage = context.storage_tiers.volatile_storage();
+            let storage = volatile_storage.read().unwrap();
+            storage.fetch(&address).unwrap().is_some()
+        };
+        assert!(!in_durable(entry.address()));
+        assert!(in_volatile(entry.address()));
+        assert!(in_durable(base.address()));
+        assert!(!in_volatile(base.address()));
+        // Reading the store finds entries in either tier
+        assert_eq!(dht.get(&entry.address()).unwrap(), Some(entry));
+    }
+
+    #[test]
+    fn reduce_hold_aspect_chunk_rejects_chunk_with_wrong_hash() {
+        let context = test_context("bob", None);
+        let store = test_store(context);
+        let aspect = EntryAspect::Content(test_entry(), test_chain_header());
+        let (manifest, chunks) = AspectChunkManifest::split(&aspect, 64);
+        assert!(chunks.len() > 2);
+
+        let hold_chunk = |dht: &DhtStore, chunk| {
+            reduce_hold_aspect_chunk(
+                dht,
+                &ActionWrapper::new(Action::HoldAspectChunk((
+                    manifest.clone(),
+                    chunk,
+                    SystemTime::now(),
+                ))),
+            )
+        };
+
+        let mut corrupt = chunks[1].clone();
+        corrupt.data = corrupt.data.to_uppercase() + "!";
+
+        let mut dht = (*store.dht()).clone();
+        for (index, chunk) in chunks.iter().enumerate() {
+            let chunk = if index == 1 {
+                corrupt.clone()
+            } else {
+                chunk.clone()
+            };
+            dht = hold_chunk(&dht, chunk).expect("chunk should be recorded");
+        }
+
+        // the corrupt chunk got rejected, so the aspect can't be complete
+        let pending = Arc::new(PendingValidationStruct::try_from(aspect.clone()).unwrap());
+        assert!(!dht.has_same_queued_holding_worfkow(&pending));
+        assert_eq!(dht.received_aspect_chunks(&manifest), chunks.len() - 1);
+        assert!(dht.aspect_chunk_rejection(&manifest, 1).is_some());
+        assert_eq!(dht.aspect_chunk_rejection(&manifest, 0), None);
+
+        // the other chunks were not poisoned, resending the good chunk completes the aspect,
+        // which then gets validated before it is held
+        let dht = hold_chunk(&dht, chunks[1].clone()).expect("valid chunk should be accepted");
+        assert!(dht.has_same_queued_holding_worfkow(&pending));
+        assert!(!dht.get_holding_map().contains(&aspect));
+        assert_eq!(dht.received_aspect_chunks(&manifest), 0);
+        assert_eq!(dht.aspect_chunk_rejection(&manifest, 1), None);
+    }
+
+    #[test]
+    fn reduce_hold_aspect_chunk_drops_stale_incomplete_aspects() {
+        let context = test_context("bob", None);
+        let store = test_store(context);
+        let aspect = EntryAspect::Content(test_entry(), test_chain_header());
+        // every chunk size makes a manifest of its own
+        let hold_first_chunk = |dht: &DhtStore, chunk_size, received_at| {
+            let (manifest, chunks) = AspectChunkManifest::split(&aspect, chunk_size);
+            let dht = reduce_hold_aspect_chunk(
+                dht,
+                &ActionWrapper::new(Action::HoldAspectChunk((
+                    manifest.clone(),
+                    chunks[0].clone(),
+                    received_at,
+                ))),
+            )
+            .expect("valid chunk should be accepted");
+            (dht, manifest)
+        };
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        // chunks that stopped arriving get dropped once they are too old
+        let (dht, old_manifest) = hold_first_chunk(&store.dht(), 32, start);
+        assert_eq!(dht.received_aspect_chunks(&old_manifest), 1);
+        let (dht, recent_manifest) = hold_first_chunk(&dht, 33, start + ASPECT_CHUNKS_MAX_AGE);
+        assert_eq!(dht.received_aspect_chunks(&old_manifest), 0);
+        assert_eq!(dht.received_aspect_chunks(&recent_manifest), 1);
+
+        // beyond the limit, the least recently updated incomplete aspects get dropped
+        let mut dht = dht;
+        let mut manifests = Vec::new();
+        for index in 0..MAX_PARTIAL_ASPECTS {
+            let received_at = start + ASPECT_CHUNKS_MAX_AGE + Duration::from_secs(index as u64 + 1);
+            let (new_dht, manifest) = hold_first_chunk(&dht, 34 + index, received_at);
+            dht = new_dht;
+            manifests.push(manifest);
+        }
+        assert_eq!(dht.aspect_chunks.len(), MAX_PARTIAL_ASPECTS);
+        assert_eq!(dht.received_aspect_chunks(&recent_manifest), 0);
+        assert!(manifests
+            .iter()
+            .all(|manifest| dht.received_aspect_chunks(manifest) == 1));
+    }
+
+    #[test]
+    fn reduce_hold_aspect_chunk_keeps_chunks_of_different_manifests_apart() {
+        let context = test_context("bob", None);
+        let store = test_store(context);
+        let aspect = EntryAspect::Content(test_entry(), test_chain_header());
+        let (small, small_chunks) = AspectChunkManifest::split(&aspect, 32);
+        let (large, large_chunks) = AspectChunkManifest::split(&aspect, 64);
+
+        let hold_chunk = |dht: &DhtStore, manifest: &AspectChunkManifest, chunk: &AspectChunk| {
+            reduce_hold_aspect_chunk(
+                dht,
+                &ActionWrapper::new(Action::HoldAspectChunk((
+                    manifest.clone(),
+                    chunk.clone(),
+                    SystemTime::now(),
+                ))),
+            )
+            .expect("valid chunk should be accepted")
+        };
+
+        // all but the last chunk of both splits, interleaved
+        let mut dht = (*store.dht()).clone();
+        for index in 0..large_chunks.len() - 1 {
+            dht = hold_chunk(&dht, &small, &small_chunks[index]);
+            dht = hold_chunk(&dht, &large, &large_chunks[index]);
+        }
+        assert_eq!(dht.received_aspect_chunks(&large), large_chunks.len() - 1);
+        assert_eq!(dht.received_aspect_chunks(&small), large_chunks.len() - 1);
+
+        let dht = hold_chunk(&dht, &large, large_chunks.last().unwrap());
+        let pending = Arc::new(PendingValidationStruct::try_from(aspect).unwrap());
+        assert!(dht.has_same_queued_holding_worfkow(&pending));
+        assert_eq!(dht.received_aspect_chunks(&large), 0);
+        assert_eq!(dht.received_aspect_chunks(&small), large_chunks.len() - 1);
+    }
+
     #[test]
     fn can_add_links() {
         enable_logging_for_test();
@@ -328,9 +806,10 @@ pub mod tests {
             test_chain_header(),
             test_agent_id(),
         );
-        let action = ActionWrapper::new(Action::HoldAspect(EntryAspect::LinkAdd(
-            link_data.clone(),
-            test_chain_header(),
+        let action = ActionWrapper::new(Action::HoldAspect((
+            EntryAspect::LinkAdd(link_data.clone(), test_chain_header()),
+            None,
+            SystemTime::now(),
         )));
         let link_entry = Entry::LinkAdd(link_data.clone());
 
@@ -376,9 +855,10 @@ pub mod tests {
 
         //add link to dht
         let entry_link_add = Entry::LinkAdd(link_data.clone());
-        let action_link_add = ActionWrapper::new(Action::HoldAspect(EntryAspect::LinkAdd(
-            link_data.clone(),
-            test_chain_header(),
+        let action_link_add = ActionWrapper::new(Action::HoldAspect((
+            EntryAspect::LinkAdd(link_data.clone(), test_chain_header()),
+            None,
+            SystemTime::now(),
         )));
 
         let new_dht_store = reduce(store.dht(), &action_link_add);
@@ -391,12 +871,16 @@ pub mod tests {
         );
 
         //remove added link from dht
-        let action_link_remove = ActionWrapper::new(Action::HoldAspect(EntryAspect::LinkRemove(
-            (
-                link_remove_data.clone(),
-                vec![entry_link_add.clone().address()],
+        let action_link_remove = ActionWrapper::new(Action::HoldAspect((
+            EntryAspect::LinkRemove(
+                (
+                    link_remove_data.clone(),
+                    vec![entry_link_add.clone().address()],
+                ),
+                test_chain_header(),
             ),
-            test_chain_header(),
+            None,
+            SystemTime::now(),
         )));
         let new_dht_store = reduce(new_dht_store, &action_link_remove);
 
@@ -423,9 +907,10 @@ pub mod tests {
         );
 
         //add new link with same chain header
-        let action_link_add = ActionWrapper::new(Action::HoldAspect(EntryAspect::LinkAdd(
-            link_data.clone(),
-            test_chain_header(),
+        let action_link_add = ActionWrapper::new(Action::HoldAspect((
+            EntryAspect::LinkAdd(link_data.clone(), test_chain_header()),
+            None,
+            SystemTime::now(),
         )));
         let new_dht_store = reduce(store.dht(), &action_link_add);
 
@@ -459,9 +944,10 @@ pub mod tests {
             test_agent_id_with_name("new_agent"),
         );
         let entry_link_add = Entry::LinkAdd(link_data.clone());
-        let action_link_add = ActionWrapper::new(Action::HoldAspect(EntryAspect::LinkAdd(
-            link_data.clone(),
-            test_chain_header(),
+        let action_link_add = ActionWrapper::new(Action::HoldAspect((
+            EntryAspect::LinkAdd(link_data.clone(), test_chain_header()),
+            None,
+            SystemTime::now(),
         )));
         let new_dht_store_2 = reduce(store.dht(), &action_link_add);
 
@@ -505,9 +991,10 @@ pub mod tests {
             test_chain_header(),
             test_agent_id(),
         );
-        let action = ActionWrapper::new(Action::HoldAspect(EntryAspect::LinkAdd(
-            link_data.clone(),
-            test_chain_header(),
+        let action = ActionWrapper::new(Action::HoldAspect((
+            EntryAspect::LinkAdd(link_data.clone(), test_chain_header()),
+            None,
+            SystemTime::now(),
         )));
 
         let new_dht_store = reduce(store.dht(), &action);
@@ -530,9 +1017,10 @@ pub mod tests {
         let store = test_store(context.clone());
 
         let entry = test_entry();
-        let action_wrapper = ActionWrapper::new(Action::HoldAspect(EntryAspect::Content(
-            entry.clone(),
-            test_chain_header(),
+        let action_wrapper = ActionWrapper::new(Action::HoldAspect((
+            EntryAspect::Content(entry.clone(), test_chain_header()),
+            None,
+            SystemTime::now(),
         )));
 
         store.reduce(action_wrapper);
@@ -561,6 +1049,36 @@ pub mod tests {
         Arc::new(PendingValidationStruct::new(entry_with_header, workflow))
     }
 
+    #[test]
+    fn holding_dependency_wakes_deferred_validation() {
+        let context = test_context("test", None);
+        let store = DhtStore::new(context.dht_storage.clone(), context.eav_storage.clone());
+        let post = test_entry_unique();
+
+        // A comment whose validation got deferred until the post it comments on is held
+        let mut deferred =
+            (*create_pending_validation(test_entry(), ValidatingWorkflow::HoldEntry, None)).clone();
+        deferred.dependencies.push(post.address());
+        let deferred: PendingValidation = Arc::new(deferred);
+        let action = ActionWrapper::new(Action::QueueHoldingWorkflow((
+            deferred.clone(),
+            Some((SystemTime::now(), Duration::from_secs(10000))),
+        )));
+        let store = reduce_queue_holding_workflow(&store, &action).unwrap();
+        assert_eq!(store.next_queued_holding_workflow(), None);
+
+        let store = reduce_hold_aspect(
+            &store,
+            &ActionWrapper::new(Action::HoldAspect((
+                EntryAspect::Content(post, test_chain_header()),
+                None,
+                SystemTime::now(),
+            ))),
+        )
+        .unwrap();
+        assert_eq!(store.next_queued_holding_workflow(), Some((deferred, None)));
+    }
+
     #[test]
     pub fn test_holding_queue() {
         let context = test_context("test", None);
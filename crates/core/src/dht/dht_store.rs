@@ -1,20 +1,25 @@
 use crate::{
     content_store::{AddContent, GetContent},
+    context::Context,
     dht::{
         aspect_map::{AspectMap, AspectMapBare},
         pending_validations::{PendingValidationWithTimeout, ValidationTimeout},
+        storage_tiers::{StorageTier, StorageTiers},
     },
 };
 use holochain_core_types::{
+    agent::AgentId,
     chain_header::ChainHeader,
     crud_status::CrudStatus,
     eav::{Attribute, EaviQuery, EntityAttributeValueIndex},
-    entry::Entry,
+    entry::{entry_type::EntryType, Entry},
     error::{HcResult, HolochainError},
     network::{
+        aspect_chunk::{AspectChunk, AspectChunkManifest},
         entry_aspect::EntryAspect,
         query::{GetLinksQueryConfiguration, Pagination, SortOrder},
     },
+    validation::LinkSpec,
 };
 use holochain_json_api::{error::JsonError, json::JsonString};
 use holochain_locksmith::RwLock;
@@ -31,12 +36,60 @@ use chrono::{offset::FixedOffset, DateTime};
 use holochain_json_api::error::JsonResult;
 use holochain_persistence_api::error::PersistenceResult;
 use std::{
-    collections::{BTreeSet, VecDeque},
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
     convert::TryFrom,
-    sync::Arc,
-    time::Duration,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
 };
 
+/// How often holding an aspect gets attempted before it is rejected.
+pub const DEFAULT_MAX_HOLD_ATTEMPTS: usize = 5;
+/// Delay before the first retry of a failed hold, doubled with every further failure.
+pub(crate) const HOLD_RETRY_DELAY_MIN: Duration = Duration::from_millis(500);
+pub(crate) const HOLD_RETRY_DELAY_MAX: Duration = Duration::from_secs(60);
+/// How long chunks of an incomplete aspect are kept after the last of them arrived.
+pub(crate) const ASPECT_CHUNKS_MAX_AGE: Duration = Duration::from_secs(300);
+/// How many incomplete aspects we keep chunks of, the least recently updated get dropped.
+pub(crate) const MAX_PARTIAL_ASPECTS: usize = 64;
+
+/// Chunks of an aspect that did not arrive completely yet.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PartialAspect {
+    /// Verified chunks, by chunk index
+    pub(crate) chunks: BTreeMap<usize, AspectChunk>,
+    /// Why chunks got rejected, by chunk index, until a valid one arrives in their place
+    pub(crate) rejected_chunks: BTreeMap<usize, String>,
+    /// When the last chunk, valid or not, arrived
+    pub(crate) updated_at: Option<SystemTime>,
+}
+
+/// An aspect that could not be held (yet) because of a transient failure,
+/// e.g. busy storage or a link base that did not arrive yet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HoldRetry {
+    pub aspect: EntryAspect,
+    /// The agent we got the aspect from, if known
+    pub source: Option<Address>,
+    /// Number of failed attempts so far
+    pub attempts: usize,
+    /// When to attempt holding the aspect again
+    pub retry_at: SystemTime,
+}
+
+/// Why we gave up holding an aspect.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HoldRejection {
+    /// The rejected aspect, so the rejection only applies to that very content
+    pub aspect: EntryAspect,
+    pub reason: String,
+    /// Id of the action whose reduction rejected the aspect, so holds started
+    /// after it can tell an earlier rejection from one of their own
+    pub action_id: String,
+}
+
 /// The state-slice for the DHT.
 /// Holds the CAS and EAVi that's used for the agent's local shard
 /// as well as the holding list, i.e. list of all entries held for the DHT.
@@ -50,6 +103,26 @@ pub struct DhtStore {
     holding_map: AspectMap,
 
     pub(crate) queued_holding_workflows: VecDeque<PendingValidationWithTimeout>,
+
+    /// Chunks of aspects that did not arrive completely yet,
+    /// by aspect address and manifest hash
+    pub(crate) aspect_chunks: HashMap<(Address, Address), PartialAspect>,
+
+    /// Aspects that failed to be held and get retried, by aspect address
+    pub(crate) hold_retries: HashMap<Address, HoldRetry>,
+
+    /// Aspects we gave up holding, by aspect address
+    pub(crate) rejected_aspects: HashMap<Address, HoldRejection>,
+
+    /// Aspects stored without validation (see `HoldPolicy::LazyValidate`),
+    /// by aspect address, until they get validated and held or rejected
+    pub(crate) unvalidated_aspects: HashMap<Address, EntryAspect>,
+
+    /// Shared with the context, see `Context::set_max_hold_attempts()`
+    pub(crate) max_hold_attempts: Arc<AtomicUsize>,
+
+    /// Shared with the context, see `Context::set_storage_tier_policy()`
+    pub(crate) storage_tiers: Arc<StorageTiers>,
 }
 
 impl PartialEq for DhtStore {
@@ -143,9 +216,23 @@ impl DhtStore {
             meta_storage,
             holding_map: AspectMap::new(),
             queued_holding_workflows: VecDeque::new(),
+            aspect_chunks: HashMap::new(),
+            hold_retries: HashMap::new(),
+            rejected_aspects: HashMap::new(),
+            unvalidated_aspects: HashMap::new(),
+            max_hold_attempts: Arc::new(AtomicUsize::new(DEFAULT_MAX_HOLD_ATTEMPTS)),
+            storage_tiers: Arc::new(StorageTiers::default()),
         }
     }
 
+    /// Makes the store follow the settings of the context, i.e.
+    /// `Context::set_max_hold_attempts()` and `Context::set_storage_tier_policy()`.
+    pub(crate) fn with_context_config(mut self, context: &Context) -> Self {
+        self.max_hold_attempts = context.max_hold_attempts.clone();
+        self.storage_tiers = context.storage_tiers.clone();
+        self
+    }
+
     pub fn new_from_snapshot(
         content_storage: Arc<RwLock<dyn ContentAddressableStorage>>,
         meta_storage: Arc<RwLock<dyn EntityAttributeValueStorage<Attribute>>>,
@@ -211,6 +298,23 @@ impl DhtStore {
             .collect())
     }
 
+    /// Whether the given link is stored in this shard and not removed.
+    pub(crate) fn has_live_link(&self, link: &LinkSpec) -> bool {
+        self.get_links(
+            link.base.clone(),
+            Some(link.link_type.clone()),
+            Some(link.tag.clone()),
+            Some(CrudStatus::Live),
+            GetLinksQueryConfiguration::default(),
+        )
+        .unwrap_or_default()
+        .into_iter()
+        .any(|(eavi, _)| match self.get(&eavi.value()) {
+            Ok(Some(Entry::LinkAdd(link_data))) => *link_data.link().target() == link.target,
+            _ => false,
+        })
+    }
+
     pub fn get_all_metas(
         &self,
         address: &Address,
@@ -275,25 +379,283 @@ impl DhtStore {
         &mut self,
         entry: &Entry,
         header: &ChainHeader,
+    ) -> Result<(), HolochainError> {
+        self.add_header_for_entry_to_tier(entry, header, StorageTier::Durable)
+    }
+
+    /// Like `add_header_for_entry()`, but stores the header in the CAS of `tier`.
+    pub(crate) fn add_header_for_entry_to_tier(
+        &mut self,
+        entry: &Entry,
+        header: &ChainHeader,
+        tier: StorageTier,
     ) -> Result<(), HolochainError> {
         let eavi = EntityAttributeValueIndex::new(
             &entry.address(),
             &Attribute::EntryHeader,
             &header.address(),
         )?;
-        self.add(header)?;
+        self.add_to_tier(header, tier)?;
         self.meta_storage.write().unwrap().add_eavi(&eavi)?;
         Ok(())
     }
 
+    /// Stores `content` in the CAS of `tier`.
+    pub(crate) fn add_to_tier<T: AddressableContent>(
+        &mut self,
+        content: &T,
+        tier: StorageTier,
+    ) -> HcResult<()> {
+        let storage = match tier {
+            StorageTier::Durable => self.content_storage.clone(),
+            StorageTier::Volatile => self.storage_tiers.volatile_storage(),
+        };
+        let result = (*storage.write().unwrap()).add(content);
+        result.map_err(|e| e.into())
+    }
+
+    /// Records that the held `aspect` got stored in `tier`.
+    pub(crate) fn set_aspect_tier(&mut self, aspect: &EntryAspect, tier: StorageTier) {
+        self.holding_map.set_tier(aspect, tier);
+    }
+
     pub fn mark_aspect_as_held(&mut self, aspect: &EntryAspect) {
+        self.unvalidated_aspects.remove(&aspect.address());
         self.holding_map.add(aspect);
     }
 
+    /// Remembers `source` as the agent we got the held `aspect` from.
+    pub(crate) fn record_aspect_source(&mut self, aspect: &EntryAspect, source: Address) {
+        self.holding_map.add_from(aspect, source);
+    }
+
+    /// Stores `aspect` without holding it, until it gets validated.
+    pub(crate) fn store_unvalidated(&mut self, aspect: &EntryAspect) {
+        self.unvalidated_aspects
+            .insert(aspect.address(), aspect.clone());
+    }
+
+    /// The aspect with the given address, if it is stored but not validated yet.
+    pub fn unvalidated_aspect(&self, aspect_address: &Address) -> Option<&EntryAspect> {
+        self.unvalidated_aspects.get(aspect_address)
+    }
+
+    /// Addresses of the aspects of the given entry that are stored but not validated yet.
+    pub(crate) fn unvalidated_aspects_of_entry(&self, entry_address: &Address) -> Vec<Address> {
+        self.unvalidated_aspects
+            .iter()
+            .filter(|(_, aspect)| aspect.entry_address().ok().as_ref() == Some(entry_address))
+            .map(|(address, _)| address.clone())
+            .collect()
+    }
+
+    /// Records a transient failure of the hold `action_id` of `aspect`, attempted at `now`.
+    /// The aspect gets retried with an exponential back-off, until it failed
+    /// `max_hold_attempts` times and is rejected.
+    pub(crate) fn record_failed_hold(
+        &mut self,
+        aspect: &EntryAspect,
+        source: Option<Address>,
+        error: String,
+        now: SystemTime,
+        action_id: &str,
+    ) {
+        let address = aspect.address();
+        let attempts = self
+            .hold_retries
+            .remove(&address)
+            .map(|retry| retry.attempts)
+            .unwrap_or(0)
+            + 1;
+        if attempts >= self.max_hold_attempts.load(Ordering::Relaxed).max(1) {
+            self.reject_hold(aspect, error, action_id);
+            return;
+        }
+        let delay = (HOLD_RETRY_DELAY_MIN * 2u32.pow((attempts - 1).min(16) as u32))
+            .min(HOLD_RETRY_DELAY_MAX);
+        self.hold_retries.insert(
+            address,
+            HoldRetry {
+                aspect: aspect.clone(),
+                source,
+                attempts,
+                retry_at: now + delay,
+            },
+        );
+    }
+
+    /// Gives up holding `aspect`, as the reduction of the action `action_id` decided.
+    pub(crate) fn reject_hold(&mut self, aspect: &EntryAspect, error: String, action_id: &str) {
+        let address = aspect.address();
+        self.hold_retries.remove(&address);
+        self.unvalidated_aspects.remove(&address);
+        self.rejected_aspects.insert(
+            address,
+            HoldRejection {
+                aspect: aspect.clone(),
+                reason: error,
+                action_id: action_id.to_string(),
+            },
+        );
+    }
+
+    /// Forgets earlier failures to hold the aspect with the given address.
+    pub(crate) fn clear_failed_hold(&mut self, aspect_address: &Address) {
+        self.hold_retries.remove(aspect_address);
+        self.rejected_aspects.remove(aspect_address);
+    }
+
+    /// Failed holds whose back-off has elapsed at `now`.
+    pub(crate) fn due_hold_retries(&self, now: SystemTime) -> Vec<HoldRetry> {
+        self.hold_retries
+            .values()
+            .filter(|retry| retry.retry_at <= now)
+            .cloned()
+            .collect()
+    }
+
+    /// Why we gave up holding the aspect with the given address, if we did.
+    pub fn hold_rejection(&self, aspect_address: &Address) -> Option<&String> {
+        self.rejected_aspects
+            .get(aspect_address)
+            .map(|rejection| &rejection.reason)
+    }
+
+    /// The rejection of exactly this aspect, if we gave up holding it.
+    fn rejection_of(&self, aspect: &EntryAspect) -> Option<&HoldRejection> {
+        self.rejected_aspects
+            .get(&aspect.address())
+            .filter(|rejection| rejection.aspect == *aspect)
+    }
+
+    /// Id of the action that rejected the aspect, if we gave up holding it.
+    pub(crate) fn hold_rejection_id(&self, aspect: &EntryAspect) -> Option<&String> {
+        self.rejection_of(aspect)
+            .map(|rejection| &rejection.action_id)
+    }
+
+    /// Why we gave up holding the aspect, ignoring the rejection by the action
+    /// `stale_action_id`, i.e. the one that was there already when the hold we
+    /// wait for got dispatched.
+    pub(crate) fn hold_rejection_after(
+        &self,
+        aspect: &EntryAspect,
+        stale_action_id: Option<&String>,
+    ) -> Option<&String> {
+        self.rejection_of(aspect)
+            .filter(|rejection| Some(&rejection.action_id) != stale_action_id)
+            .map(|rejection| &rejection.reason)
+    }
+
+    /// Number of verified chunks of the given manifest we have
+    /// while waiting for the rest of them.
+    pub fn received_aspect_chunks(&self, manifest: &AspectChunkManifest) -> usize {
+        self.aspect_chunks
+            .get(&(manifest.aspect_address.clone(), manifest.hash()))
+            .map(|partial| partial.chunks.len())
+            .unwrap_or_default()
+    }
+
+    /// Why the chunk with the given index of the manifest got rejected,
+    /// if it did and no valid chunk arrived in its place since.
+    pub fn aspect_chunk_rejection(
+        &self,
+        manifest: &AspectChunkManifest,
+        index: usize,
+    ) -> Option<&String> {
+        self.aspect_chunks
+            .get(&(manifest.aspect_address.clone(), manifest.hash()))
+            .and_then(|partial| partial.rejected_chunks.get(&index))
+    }
+
+    /// Drops the chunks of incomplete aspects that did not get any chunk within
+    /// `ASPECT_CHUNKS_MAX_AGE` before `now`, and of the least recently updated ones
+    /// beyond `MAX_PARTIAL_ASPECTS`.
+    pub(crate) fn drop_stale_aspect_chunks(&mut self, now: SystemTime) {
+        self.aspect_chunks.retain(|_, partial| {
+            partial
+                .updated_at
+                .map(|updated_at| updated_at + ASPECT_CHUNKS_MAX_AGE > now)
+                .unwrap_or(false)
+        });
+        if self.aspect_chunks.len() > MAX_PARTIAL_ASPECTS {
+            let mut by_age = self
+                .aspect_chunks
+                .iter()
+                .map(|(key, partial)| (partial.updated_at, key.clone()))
+                .collect::<Vec<_>>();
+            by_age.sort_by_key(|(updated_at, _)| *updated_at);
+            let excess = self.aspect_chunks.len() - MAX_PARTIAL_ASPECTS;
+            for (_, key) in by_age.into_iter().take(excess) {
+                self.aspect_chunks.remove(&key);
+            }
+        }
+    }
+
     pub fn get_holding_map(&self) -> &AspectMap {
         &self.holding_map
     }
 
+    /// True if we hold at least one aspect of the entry with the given address.
+    pub fn holds_entry(&self, address: &Address) -> bool {
+        self.holding_map
+            .per_entry(&address.clone().into())
+            .map(|aspects| !aspects.is_empty())
+            .unwrap_or(false)
+    }
+
+    /// Number of held aspects by the type of the entry they are held for.
+    /// The holding map only knows that, so links count towards the type of their base entry.
+    /// Aspects of entries not stored in this shard are left out.
+    pub fn holding_counts_by_type(&self) -> HashMap<EntryType, usize> {
+        let mut counts = HashMap::new();
+        for (entry_address, aspects) in self.holding_map.bare().iter() {
+            if let Ok(Some(entry)) = self.get(&Address::from(entry_address.clone())) {
+                *counts.entry(entry.entry_type()).or_insert(0) += aspects.len();
+            }
+        }
+        counts
+    }
+
+    /// Number of links of the given type that `agent` created, from any base,
+    /// leaving out the link entry at `excluding` (i.e. the one being validated).
+    /// Only links stored in this shard are counted.
+    pub(crate) fn agent_link_count(
+        &self,
+        link_type: &str,
+        agent: &AgentId,
+        excluding: &Address,
+    ) -> usize {
+        let link_type = link_type.to_string();
+        let query = EaviQuery::new(
+            None.into(),
+            EavFilter::predicate(move |attr: Attribute| match attr {
+                Attribute::LinkTag(query_link_type, _) => query_link_type == link_type,
+                _ => false,
+            }),
+            None.into(),
+            IndexFilter::LatestByAttribute,
+            None,
+        );
+        self.fetch_eavi(&query)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|eavi| eavi.value())
+            .filter(|link_address| link_address != excluding)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter(|link_address| match self.get(link_address) {
+                Ok(Some(Entry::LinkAdd(link_data))) => link_data.agent_id() == agent,
+                _ => false,
+            })
+            .count()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn set_holding_map(&mut self, holding_map: AspectMap) {
+        self.holding_map = holding_map;
+    }
+
     pub(crate) fn fetch_eavi(
         &self,
         query: &EaviQuery,
@@ -362,6 +724,16 @@ impl DhtStore {
         &self.queued_holding_workflows
     }
 
+    /// Lets the queued validations that wait for the entry at `address` run again
+    /// right away, instead of after their retry delay.
+    pub(crate) fn wake_validations_waiting_for(&mut self, address: &Address) {
+        for queued in self.queued_holding_workflows.iter_mut() {
+            if queued.pending.dependencies.contains(address) {
+                queued.timeout = None;
+            }
+        }
+    }
+
     pub(crate) fn remove_holding_workflow(
         &mut self,
         item: &PendingValidation,
@@ -395,16 +767,22 @@ where
 }
 
 impl GetContent for DhtStore {
+    /// Looks in the volatile tier for content the durable one does not have.
     fn get_raw(&self, address: &Address) -> HcResult<Option<Content>> {
-        Ok((*self.content_storage.read().unwrap()).fetch(address)?)
+        match (*self.content_storage.read().unwrap()).fetch(address)? {
+            Some(content) => Ok(Some(content)),
+            None => {
+                let volatile_storage = self.storage_tiers.volatile_storage();
+                let content = (*volatile_storage.read().unwrap()).fetch(address)?;
+                Ok(content)
+            }
+        }
     }
 }
 
 impl AddContent for DhtStore {
     fn add<T: AddressableContent>(&mut self, content: &T) -> HcResult<()> {
-        (*self.content_storage.write().unwrap())
-            .add(content)
-            .map_err(|e| e.into())
+        self.add_to_tier(content, StorageTier::Durable)
     }
 }
 
@@ -416,8 +794,9 @@ pub mod tests {
         network::entry_with_header::EntryWithHeader,
     };
     use holochain_core_types::{
-        chain_header::test_chain_header_with_sig,
-        entry::{test_entry, test_entry_a, test_entry_b, test_entry_c},
+        chain_header::{test_chain_header, test_chain_header_with_sig, test_provenances},
+        entry::{test_entry, test_entry_a, test_entry_b, test_entry_c, test_entry_with_value},
+        time::test_iso_8601,
     };
 
     use holochain_persistence_api::{
@@ -441,6 +820,61 @@ pub mod tests {
         assert_eq!(headers, vec![header1, header2]);
     }
 
+    #[test]
+    fn holds_entry_if_any_aspect_is_held() {
+        let mut store = DhtStore::new(
+            Arc::new(RwLock::new(
+                ExampleContentAddressableStorage::new().unwrap(),
+            )),
+            Arc::new(RwLock::new(ExampleEntityAttributeValueStorage::new())),
+        );
+        let entry = test_entry();
+        assert!(!store.holds_entry(&entry.address()));
+
+        store.mark_aspect_as_held(&EntryAspect::Content(entry.clone(), test_chain_header()));
+        assert!(store.holds_entry(&entry.address()));
+        assert!(!store.holds_entry(&test_entry_b().address()));
+    }
+
+    #[test]
+    fn counts_held_aspects_by_entry_type() {
+        let mut store = DhtStore::new(
+            Arc::new(RwLock::new(
+                ExampleContentAddressableStorage::new().unwrap(),
+            )),
+            Arc::new(RwLock::new(ExampleEntityAttributeValueStorage::new())),
+        );
+        let content_aspect = |entry: &Entry, sig: &'static str| {
+            let header = ChainHeader::new(
+                &entry.entry_type(),
+                &entry.address(),
+                &test_provenances(sig),
+                &None,
+                &None,
+                &None,
+                &test_iso_8601(),
+            );
+            EntryAspect::Content(entry.clone(), header)
+        };
+        for entry in vec![test_entry(), test_entry_b(), test_entry_c()] {
+            store.add(&entry).unwrap();
+        }
+        store.mark_aspect_as_held(&content_aspect(&test_entry(), "sig1"));
+        store.mark_aspect_as_held(&content_aspect(&test_entry_b(), "sig1"));
+        store.mark_aspect_as_held(&content_aspect(&test_entry_b(), "sig2"));
+        store.mark_aspect_as_held(&content_aspect(&test_entry_c(), "sig1"));
+        // not stored, so its type is unknown
+        store.mark_aspect_as_held(&content_aspect(
+            &test_entry_with_value("\"not stored\""),
+            "sig1",
+        ));
+
+        let counts = store.holding_counts_by_type();
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts.get(&test_entry().entry_type()), Some(&1));
+        assert_eq!(counts.get(&test_entry_b().entry_type()), Some(&3));
+    }
+
     fn pending_validation_for_entry(
         entry: Entry,
         dependencies: Vec<Address>,
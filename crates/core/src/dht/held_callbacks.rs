@@ -0,0 +1,56 @@
+//! One-shot callbacks that fire once a specific aspect got added to the holding map.
+use holochain_core_types::network::entry_aspect::EntryAspect;
+use holochain_locksmith::Mutex;
+use holochain_persistence_api::cas::content::AddressableContent;
+use lib3h_protocol::types::AspectHash;
+use std::collections::HashMap;
+
+pub type HeldCallback = Box<dyn FnOnce() + Send>;
+
+/// Callbacks waiting for their aspect to be held, keyed by aspect hash
+/// like the holding map.
+pub struct HeldCallbacks {
+    callbacks: Mutex<HashMap<AspectHash, Vec<HeldCallback>>>,
+}
+
+impl Default for HeldCallbacks {
+    fn default() -> Self {
+        HeldCallbacks {
+            callbacks: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl HeldCallbacks {
+    pub fn register(&self, aspect: &EntryAspect, callback: HeldCallback) {
+        self.callbacks
+            .lock()
+            .expect("HeldCallbacks poisoned")
+            .entry(AspectHash::from(aspect.address()))
+            .or_insert_with(Vec::new)
+            .push(callback);
+    }
+
+    /// Number of callbacks that have not fired yet.
+    pub fn pending(&self) -> usize {
+        self.callbacks
+            .lock()
+            .expect("HeldCallbacks poisoned")
+            .values()
+            .map(Vec::len)
+            .sum()
+    }
+
+    /// Runs and forgets all callbacks registered for the aspect with the given hash.
+    pub(crate) fn fire(&self, aspect_hash: &AspectHash) {
+        let callbacks = self
+            .callbacks
+            .lock()
+            .expect("HeldCallbacks poisoned")
+            .remove(aspect_hash);
+        // Called without holding the lock so callbacks can register new ones
+        for callback in callbacks.into_iter().flatten() {
+            callback();
+        }
+    }
+}
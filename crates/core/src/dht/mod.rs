@@ -6,7 +6,9 @@ pub mod aspect_map;
 pub mod dht_reducers;
 #[autotrace]
 pub mod dht_store;
+pub mod held_callbacks;
 pub mod pending_validations;
+pub mod storage_tiers;
 
 #[autotrace]
 mod dht_inner_reducers;
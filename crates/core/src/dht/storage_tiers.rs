@@ -0,0 +1,69 @@
+//! Storage tiers of different durability for held aspects.
+//!
+//! Nodes with fast volatile and slow durable storage can hold aspects that are cheap
+//! to get again from the network in the volatile tier, see `Context::set_storage_tier_policy()`.
+//! Which tier an aspect went to is recorded in the holding map (`AspectMap::tier_of()`).
+use holochain_core_types::network::entry_aspect::EntryAspect;
+use holochain_locksmith::RwLock;
+use holochain_persistence_api::cas::storage::ContentAddressableStorage;
+use holochain_persistence_mem::cas::memory::MemoryStorage;
+use std::{fmt, sync::Arc};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StorageTier {
+    /// The DHT storage of the instance. The default.
+    Durable,
+    /// Kept in memory, lost when the instance stops.
+    Volatile,
+}
+
+impl Default for StorageTier {
+    fn default() -> Self {
+        StorageTier::Durable
+    }
+}
+
+/// Picks the tier an aspect gets held in.
+pub type StorageTierPolicy = Arc<dyn Fn(&EntryAspect) -> StorageTier + Send + Sync>;
+
+pub struct StorageTiers {
+    policy: RwLock<Option<StorageTierPolicy>>,
+    volatile_storage: Arc<RwLock<dyn ContentAddressableStorage>>,
+}
+
+impl Default for StorageTiers {
+    fn default() -> Self {
+        StorageTiers {
+            policy: RwLock::new(None),
+            volatile_storage: Arc::new(RwLock::new(MemoryStorage::new())),
+        }
+    }
+}
+
+impl fmt::Debug for StorageTiers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StorageTiers")
+            .field("has_policy", &self.policy.read().unwrap().is_some())
+            .finish()
+    }
+}
+
+impl StorageTiers {
+    pub(crate) fn set_policy(&self, policy: Option<StorageTierPolicy>) {
+        *self.policy.write().unwrap() = policy;
+    }
+
+    /// The tier the policy picks for `aspect`, `StorageTier::Durable` without a policy.
+    pub(crate) fn tier_for(&self, aspect: &EntryAspect) -> StorageTier {
+        self.policy
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|policy| policy(aspect))
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn volatile_storage(&self) -> Arc<RwLock<dyn ContentAddressableStorage>> {
+        self.volatile_storage.clone()
+    }
+}
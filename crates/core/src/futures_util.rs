@@ -0,0 +1,346 @@
+//! Helpers shared by the futures used throughout core.
+
+use crate::context::Context;
+use futures::task::Waker;
+use futures::{future::Future, task::Poll};
+use holochain_core_types::error::{CancelReason, HolochainError};
+use holochain_locksmith::Mutex;
+use lazy_static::lazy_static;
+use std::{
+    pin::Pin,
+    sync::{
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long the futures of some actions wait before they give up,
+/// see `Context::set_future_timeouts()`. `None` (the default) waits as long as it takes.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FutureTimeouts {
+    /// How long a publish waits for the network's response
+    pub publish: Option<Duration>,
+    /// How long holding an aspect waits for it to show up in the holding map
+    pub hold_aspect: Option<Duration>,
+    /// The longest the `sleep` zome API function sleeps
+    pub sleep_max: Option<Duration>,
+}
+
+lazy_static! {
+    static ref TIMER: Mutex<Sender<(Instant, Waker)>> = Mutex::new(start_timer());
+}
+
+/// Wakes `waker` once `deadline` passed, for futures that nothing else wakes in time.
+/// All of them share one timer thread.
+pub(crate) fn wake_at(deadline: Instant, waker: Waker) {
+    // The timer thread never stops, so sending can't fail
+    let _ = TIMER
+        .lock()
+        .expect("TIMER poisoned")
+        .send((deadline, waker));
+}
+
+fn start_timer() -> Sender<(Instant, Waker)> {
+    let (sender, receiver) = mpsc::channel::<(Instant, Waker)>();
+    thread::Builder::new()
+        .name("future_timer".to_string())
+        .spawn(move || {
+            let mut waiting: Vec<(Instant, Waker)> = Vec::new();
+            loop {
+                let now = Instant::now();
+                let (due, later): (Vec<_>, Vec<_>) = waiting
+                    .drain(..)
+                    .partition(|(deadline, _)| *deadline <= now);
+                waiting = later;
+                due.into_iter().for_each(|(_, waker)| waker.wake());
+
+                let received = match waiting.iter().map(|(deadline, _)| *deadline).min() {
+                    Some(next) => receiver.recv_timeout(next - now),
+                    None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                };
+                match received {
+                    Ok(timer) => waiting.push(timer),
+                    Err(RecvTimeoutError::Timeout) => (),
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        })
+        .expect("Could not spawn the future timer thread");
+    sender
+}
+
+/// Deadline of a future that gets set from its timeout when the future is polled first.
+#[derive(Default)]
+pub(crate) struct FutureDeadline {
+    deadline: Option<Option<Instant>>,
+}
+
+impl FutureDeadline {
+    /// True once the deadline passed. Only the `timeout` given with the first call counts.
+    /// Like `with_timeout()`, starts a timer that wakes `waker` at the deadline.
+    pub(crate) fn expired(&mut self, timeout: Option<Duration>, waker: &Waker) -> bool {
+        let deadline = *self.deadline.get_or_insert_with(|| {
+            timeout.map(|timeout| {
+                let deadline = Instant::now() + timeout;
+                wake_at(deadline, waker.clone());
+                deadline
+            })
+        });
+        deadline
+            .map(|deadline| Instant::now() >= deadline)
+            .unwrap_or(false)
+    }
+}
+
+/// Resolves to the output of `future` or to `HolochainError::Timeout` if `future`
+/// did not resolve within `timeout`.
+///
+/// The deadline starts with the call, not with the first poll.
+pub fn with_timeout<F: Future>(
+    future: F,
+    context: &Arc<Context>,
+    timeout: Duration,
+) -> impl Future<Output = Result<F::Output, HolochainError>> {
+    TimeoutFuture {
+        future: Box::pin(future),
+        context: context.clone(),
+        timeout,
+        deadline: Instant::now() + timeout,
+        timer_started: false,
+    }
+}
+
+/// Races the wrapped future against a timer, see [with_timeout](with_timeout).
+pub struct TimeoutFuture<F: Future> {
+    future: Pin<Box<F>>,
+    context: Arc<Context>,
+    timeout: Duration,
+    deadline: Instant,
+    timer_started: bool,
+}
+
+impl<F: Future> Future for TimeoutFuture<F> {
+    type Output = Result<F::Output, HolochainError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+        if let Poll::Ready(output) = self.future.as_mut().poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+
+        let now = Instant::now();
+        if now >= self.deadline {
+            log_debug!(
+                self.context,
+                "futures_util: future timed out after {:?}",
+                self.timeout
+            );
+            return Poll::Ready(Err(HolochainError::Timeout(format!(
+                "future did not resolve within {:?}",
+                self.timeout
+            ))));
+        }
+
+        // The wrapped future only gets woken by state changes, so make sure
+        // we get polled again at the deadline even if nothing else happens.
+        if !self.timer_started {
+            self.timer_started = true;
+            wake_at(self.deadline, cx.waker().clone());
+        }
+
+        Poll::Pending
+    }
+}
+
+/// Cancels the future it was handed out with, see [cancellable](cancellable).
+#[derive(Clone)]
+pub struct CancelHandle {
+    state: Arc<Mutex<(bool, Option<Waker>)>>,
+}
+
+impl Default for CancelHandle {
+    fn default() -> Self {
+        CancelHandle {
+            state: Arc::new(Mutex::new((false, None))),
+        }
+    }
+}
+
+impl CancelHandle {
+    /// Makes the future fail with `CancelReason::User` the next time it gets polled,
+    /// waking it up if it is waiting.
+    pub fn cancel(&self) {
+        let mut state = self.state.lock().expect("CancelHandle poisoned");
+        state.0 = true;
+        if let Some(waker) = state.1.take() {
+            waker.wake();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.lock().expect("CancelHandle poisoned").0
+    }
+
+    fn set_waker(&self, waker: &Waker) {
+        self.state.lock().expect("CancelHandle poisoned").1 = Some(waker.clone());
+    }
+}
+
+/// Wraps `future` so it can be cancelled through the returned handle.
+/// A future that stops early fails with `HolochainError::Cancelled`, carrying why:
+/// `CancelReason::User` after `CancelHandle::cancel()`, `CancelReason::Shutdown`
+/// once the instance got shut down and `CancelReason::Timeout` if `future` timed out.
+pub fn cancellable<F, T>(future: F, context: &Arc<Context>) -> (CancelHandle, CancellableFuture<F>)
+where
+    F: Future<Output = Result<T, HolochainError>>,
+{
+    let handle = CancelHandle::default();
+    let future = CancellableFuture {
+        future: Box::pin(future),
+        context: context.clone(),
+        handle: handle.clone(),
+    };
+    (handle, future)
+}
+
+/// See [cancellable](cancellable).
+pub struct CancellableFuture<F: Future> {
+    future: Pin<Box<F>>,
+    context: Arc<Context>,
+    handle: CancelHandle,
+}
+
+impl<F, T> Future for CancellableFuture<F>
+where
+    F: Future<Output = Result<T, HolochainError>>,
+{
+    type Output = Result<T, HolochainError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+        if self.handle.is_cancelled() {
+            return Poll::Ready(Err(HolochainError::Cancelled(CancelReason::User)));
+        }
+        if !self.context.instance_still_alive() {
+            return Poll::Ready(Err(HolochainError::Cancelled(CancelReason::Shutdown)));
+        }
+        self.handle.set_waker(cx.waker());
+        match self.future.as_mut().poll(cx) {
+            Poll::Ready(Err(HolochainError::Timeout(_))) => {
+                Poll::Ready(Err(HolochainError::Cancelled(CancelReason::Timeout)))
+            }
+            poll => poll,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::instance::tests::test_context;
+    use futures::executor::block_on;
+
+    /// Becomes ready once the given instant has passed.
+    /// Polls itself again right away, like a busy future would.
+    struct ReadyAt(Instant);
+
+    impl Future for ReadyAt {
+        type Output = ();
+        fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<()> {
+            if Instant::now() >= self.0 {
+                Poll::Ready(())
+            } else {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Pending until it got woken once.
+    struct WokenOnce {
+        deadline: Instant,
+        registered: bool,
+    }
+
+    impl Future for WokenOnce {
+        type Output = ();
+        fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<()> {
+            if self.registered {
+                Poll::Ready(())
+            } else {
+                self.registered = true;
+                wake_at(self.deadline, cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    #[test]
+    fn shared_timer_wakes_futures_at_their_deadline() {
+        let start = Instant::now();
+        let woken: Vec<_> = [30, 10, 20]
+            .iter()
+            .map(|millis| {
+                let deadline = start + Duration::from_millis(*millis);
+                thread::spawn(move || {
+                    block_on(WokenOnce {
+                        deadline,
+                        registered: false,
+                    });
+                    Instant::now() >= deadline
+                })
+            })
+            .collect();
+        for woken in woken {
+            assert!(woken.join().unwrap());
+        }
+    }
+
+    #[test]
+    fn fast_future_resolves_with_its_output() {
+        let context = test_context("alice", None);
+        let result = block_on(with_timeout(
+            async { 42 },
+            &context,
+            Duration::from_millis(500),
+        ));
+        assert_eq!(result, Ok(42));
+    }
+
+    #[test]
+    fn slow_future_times_out() {
+        let context = test_context("alice", None);
+        let slow = ReadyAt(Instant::now() + Duration::from_secs(10));
+        let start = Instant::now();
+        let result = block_on(with_timeout(slow, &context, Duration::from_millis(50)));
+        match result {
+            Err(HolochainError::Timeout(_)) => (),
+            other => panic!("Expected a timeout, got {:?}", other),
+        }
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn timed_out_cancellable_future_fails_with_timeout_reason() {
+        let context = test_context("alice", None);
+        let (_handle, future) = cancellable(
+            async { Err::<(), _>(HolochainError::Timeout("publish".to_string())) },
+            &context,
+        );
+        assert_eq!(
+            block_on(future),
+            Err(HolochainError::Cancelled(CancelReason::Timeout))
+        );
+    }
+
+    #[test]
+    fn pending_future_times_out_without_being_woken() {
+        let context = test_context("alice", None);
+        let result = block_on(with_timeout(
+            futures::future::pending::<()>(),
+            &context,
+            Duration::from_millis(50),
+        ));
+        assert!(result.is_err());
+    }
+}
@@ -3,7 +3,7 @@ use crate::{
     consistency::ConsistencyModel,
     context::{ActionReceiver, ActionSender, Context},
     dht::actions::{
-        queue_holding_workflow::queue_holding_workflow,
+        hold_aspect::retry_due_holds, queue_holding_workflow::queue_holding_workflow,
         remove_queued_holding_workflow::remove_queued_holding_workflow,
     },
     network,
@@ -25,9 +25,9 @@ use holochain_core_types::{
     dna::Dna,
     error::{HcResult, HolochainError},
 };
-use holochain_locksmith::RwLock;
+use holochain_locksmith::{Mutex, RwLock};
 #[cfg(test)]
-use holochain_persistence_api::cas::content::Address;
+use holochain_persistence_api::cas::content::{Address, AddressableContent};
 use holochain_tracing::{self as ht, channel::lax_send_wrapped};
 use snowflake::ProcessUniqueId;
 use std::{
@@ -50,6 +50,65 @@ pub enum WakerRequest {
     Remove(ProcessUniqueId),
 }
 
+/// Wakers of the futures that wait for the instance's state to change, by future id.
+/// Filled by the action loop from the `WakerRequest`s sent through `Context::register_waker()`.
+pub struct WakerRegistry {
+    wakers: Mutex<HashMap<ProcessUniqueId, Waker>>,
+}
+
+impl Default for WakerRegistry {
+    fn default() -> Self {
+        WakerRegistry {
+            wakers: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl WakerRegistry {
+    pub(crate) fn apply(&self, request: WakerRequest) {
+        let mut wakers = self.wakers.lock().expect("WakerRegistry poisoned");
+        match request {
+            WakerRequest::Add(id, waker) => wakers.insert(id, waker),
+            WakerRequest::Remove(id) => wakers.remove(&id),
+        };
+    }
+
+    pub(crate) fn wake_all(&self) {
+        let wakers: Vec<Waker> = self
+            .wakers
+            .lock()
+            .expect("WakerRegistry poisoned")
+            .values()
+            .cloned()
+            .collect();
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Takes all wakers out of the registry and wakes them one last time.
+    pub(crate) fn shutdown(&self) {
+        let wakers: Vec<Waker> = self
+            .wakers
+            .lock()
+            .expect("WakerRegistry poisoned")
+            .drain()
+            .map(|(_, waker)| waker)
+            .collect();
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.wakers
+            .lock()
+            .expect("WakerRegistry poisoned")
+            .is_empty()
+    }
+}
+
 /// Object representing a Holochain instance, i.e. a running holochain (DNA + DHT + source-chain)
 /// Holds the Event loop and processes it with the redux pattern.
 #[derive(Clone)]
@@ -216,17 +275,13 @@ impl Instance {
             .spawn(move || {
                 let mut state_observers: Vec<Observer> = Vec::new();
                 let mut unprocessed_action: Option<ht::SpanWrap<ActionWrapper>> = None;
-                let mut wakers: HashMap::<ProcessUniqueId, Waker> = HashMap::new();
                 while kill_receiver.try_recv().is_err() {
                     if let Some(action_wrapper) = unprocessed_action.take().or_else(|| rx_action.recv_timeout(Duration::from_secs(1)).ok()) {
                         // Add new observers
                         state_observers.extend(rx_observer.try_iter());
                         // Process waker requests
                         for waker_request in rx_waker.try_iter() {
-                            match waker_request {
-                                WakerRequest::Add(id, waker) => wakers.insert(id, waker),
-                                WakerRequest::Remove(id) => wakers.remove(&id),
-                            };
+                            sub_context.wakers.apply(waker_request);
                         }
                         let action = action_wrapper.action();
                         // Ping can happen often, and should be as lightweight as possible
@@ -246,9 +301,7 @@ impl Instance {
                                         .filter(|observer| observer.ticker.send(()).is_ok())
                                         .collect();
                                     // Tick all wakers
-                                    for waker in wakers.values() {
-                                        waker.clone().wake();
-                                    }
+                                    sub_context.wakers.wake_all();
                                 },
                                 Err(HolochainError::Timeout(s)) => {
                                     warn!("Instance::process_action() couldn't get lock on state. Trying again next loop. Timeout string: {}", s);
@@ -264,6 +317,11 @@ impl Instance {
                     }
                 }
                 instance_is_alive.store(false, Relaxed);
+                // Let the waiting futures see that the instance is gone
+                for waker_request in rx_waker.try_iter() {
+                    sub_context.wakers.apply(waker_request);
+                }
+                sub_context.shutdown_wakers();
             });
     }
 
@@ -283,18 +341,34 @@ impl Instance {
         action_wrapper: &ht::SpanWrap<ActionWrapper>,
         context: &Arc<Context>,
     ) -> Result<(), HolochainError> {
+        // Lets the spans of a publish and of the holds it results in be found together
+        let correlation_tag = action_wrapper
+            .action()
+            .correlation_id()
+            .map(|correlation_id| ht::Tag::new("correlation_id", correlation_id.to_string()));
         let span = action_wrapper
-            .follower(&context.tracer, "begin process_action")
+            .follower_(&context.tracer, "begin process_action", |s| {
+                match correlation_tag.clone() {
+                    Some(tag) => s.tag(tag),
+                    None => s,
+                }
+                .start()
+            })
             .unwrap_or_else(|| {
-                context
+                let s = context
                     .tracer
                     .span("ROOT: process_action")
-                    .tag(ht::debug_tag("action_wrapper", action_wrapper))
-                    .start()
-                    .into()
+                    .tag(ht::debug_tag("action_wrapper", action_wrapper));
+                match correlation_tag.clone() {
+                    Some(tag) => s.tag(tag),
+                    None => s,
+                }
+                .start()
+                .into()
             });
         let _trace_guard = ht::push_span(span);
         context.redux_wants_write.store(true, Relaxed);
+        let newly_held;
         // Mutate state
         {
             let new_state: StateWrapper;
@@ -307,11 +381,28 @@ impl Instance {
                     HolochainError::Timeout(format!("timeout src: {}:{}", file!(), line!()))
                 })?;
 
+            let old_dht = state.dht();
             new_state = state.reduce(action_wrapper.data.clone());
 
             // Change the state
             *state = new_state;
 
+            // Whatever action added aspects to the holding map, e.g. reassembled chunks
+            // or lazily validated aspects, their callbacks fire
+            let new_dht = state.dht();
+            newly_held = if context.held_callbacks.pending() == 0 || Arc::ptr_eq(&old_dht, &new_dht)
+            {
+                Vec::new()
+            } else {
+                new_dht
+                    .get_holding_map()
+                    .diff(old_dht.get_holding_map())
+                    .aspect_hashes()
+                    .into_iter()
+                    .cloned()
+                    .collect::<Vec<_>>()
+            };
+
             if let Err(e) = self.save(&state) {
                 log_error!(
                     context,
@@ -329,6 +420,11 @@ impl Instance {
 
         context.redux_wants_write.store(false, Relaxed);
 
+        // Only now that the state lock is released, so callbacks may look at the state
+        for aspect_hash in newly_held {
+            context.held_callbacks.fire(&aspect_hash);
+        }
+
         Ok(())
     }
 
@@ -341,7 +437,9 @@ impl Instance {
                 ProcessUniqueId::new().to_string()
             ))
             .spawn(move || {
+                let mut retried_holds = HashMap::new();
                 while kill_receiver.try_recv().is_err() {
+                    retry_due_holds(&context, &mut retried_holds);
                     log_trace!(context, "Checking holding queue...");
                     loop {
                         // TODO: TRACING: it would be ideal to be able to associate a tracing Span with each queued holding workflow.
@@ -385,8 +483,15 @@ impl Instance {
                                             delay = RETRY_VALIDATION_DURATION_MAX
                                         }
                                         log_debug!(c, "re-queuing pending validation for {:?} with a delay of {:?}", pending, delay);
+                                        // Holding what the validation got deferred on ends the delay early
+                                        let mut requeued = pending.same();
+                                        for dependency in c.deferrals.take(&pending.entry_with_header.entry.address()) {
+                                            if !requeued.dependencies.contains(&dependency) {
+                                                requeued.dependencies.push(dependency);
+                                            }
+                                        }
                                         queue_holding_workflow(
-                                            Arc::new(pending.same()),
+                                            Arc::new(requeued),
                                             Some(delay),
                                             c.clone(),
                                         )
@@ -538,6 +643,7 @@ pub mod tests {
         },
         context::{test_memory_network_config, Context},
         logger::{test_logger, TestLogger},
+        state::test_store,
     };
     use holochain_core_types::{
         agent::AgentId,
@@ -655,6 +761,28 @@ pub mod tests {
         )
     }
 
+    /// Creates a test context with a state of its own but no instance running.
+    /// Nothing reduces the actions it dispatches, so tests get them through the returned
+    /// receiver and decide which of them land in the returned state.
+    #[cfg_attr(tarpaulin, skip)]
+    pub fn test_context_and_state(
+        agent_name: &str,
+    ) -> (
+        Arc<Context>,
+        Arc<RwLock<StateWrapper>>,
+        Receiver<ht::SpanWrap<ActionWrapper>>,
+    ) {
+        let (sender, receiver) = unbounded::<ht::SpanWrap<ActionWrapper>>();
+        let (tx_observer, _observer) = unbounded::<Observer>();
+        let mut context =
+            (*test_context_with_channels(agent_name, &sender.into(), &tx_observer, None)).clone();
+        let state = Arc::new(RwLock::new(StateWrapper::from(test_store(Arc::new(
+            context.clone(),
+        )))));
+        context.set_state(state.clone());
+        (Arc::new(context), state, receiver)
+    }
+
     #[cfg_attr(tarpaulin, skip)]
     pub fn test_context_with_state(network_name: Option<&str>) -> Arc<Context> {
         let file_storage = Arc::new(RwLock::new(
@@ -46,6 +46,7 @@ pub mod content_store;
 pub mod context;
 pub mod dht;
 pub mod entry;
+pub mod futures_util;
 #[autotrace]
 pub mod instance;
 #[cfg(test)]
@@ -22,6 +22,7 @@ use std::ops::Deref;
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize, DefaultJson)]
 pub enum NetworkActionResponse {
     Publish(HcResult<Address>),
+    PublishToArc(HcResult<Address>),
     PublishHeaderEntry(HcResult<Address>),
     Respond(HcResult<()>),
 }
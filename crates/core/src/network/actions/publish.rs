@@ -1,52 +1,548 @@
 use crate::{
     action::{Action, ActionWrapper},
-    context::Context,
+    agent::find_chain_header,
+    context::{ActionSender, Context},
+    futures_util::{cancellable, CancelHandle, FutureDeadline},
     instance::dispatch_action,
-    network::actions::NetworkActionResponse,
+    network::{actions::NetworkActionResponse, correlation::CorrelationId, dht_arc::DhtArc},
+    nucleus::{
+        actions::{
+            build_validation_package::build_validation_package,
+            get_entry::get_entry_from_agent_chain,
+        },
+        validation::validate_entry,
+    },
 };
-use futures::{future::Future, task::Poll};
-use holochain_core_types::error::HcResult;
+use crossbeam_channel::TrySendError;
+use futures::{channel::oneshot, future::Future, task::Poll};
+use holochain_core_types::{
+    error::{HcResult, HolochainError},
+    validation::{EntryLifecycle, ValidationData, ValidationOrigin},
+};
+use holochain_locksmith::{Mutex, RwLock};
 use holochain_persistence_api::cas::content::Address;
+use holochain_tracing as ht;
 use snowflake::ProcessUniqueId;
-use std::{pin::Pin, sync::Arc};
+use std::{
+    collections::{HashMap, VecDeque},
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 /// Publish Action Creator
 /// This is the high-level publish function that wraps the whole publish process and is what should
 /// be called from zome api functions and other contexts that don't care about implementation details.
 ///
 /// Returns a future that resolves to an ActionResponse.
+/// Resolves right away if the address got published successfully within the context's
+/// publish dedup window (see `Context::set_publish_dedup_window()`).
+/// Fails with `HolochainError::NetworkNotInitialized`, without dispatching anything,
+/// if the network is not initialized yet.
 #[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
 pub async fn publish(address: Address, context: &Arc<Context>) -> HcResult<Address> {
-    let action_wrapper = ActionWrapper::new(Action::Publish(address));
-    dispatch_action(context.action_channel(), action_wrapper.clone());
+    publish_prioritized(address, context, PublishPriority::Normal).await
+}
+
+/// How urgent a publish is. The network processes queued publishes of higher
+/// priority first, e.g. so a user-visible post goes out before a background index update.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum PublishPriority {
+    Low,
+    Normal,
+    High,
+}
+
+impl Default for PublishPriority {
+    fn default() -> Self {
+        PublishPriority::Normal
+    }
+}
+
+/// Like [publish](publish), but with the given priority instead of `PublishPriority::Normal`.
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+pub async fn publish_prioritized(
+    address: Address,
+    context: &Arc<Context>,
+    priority: PublishPriority,
+) -> HcResult<Address> {
+    // Nothing could process the publish without a network, so don't even dispatch it
+    context
+        .state()
+        .ok_or(HolochainError::NetworkNotInitialized)?
+        .network()
+        .initialized()?;
+    // Publishing again what just got published wouldn't change anything
+    if context.publish_cache.recently_published(&address) {
+        return Ok(address);
+    }
+    let action_wrapper = publish_action(address, context, priority);
+    if let Some(action_wrapper) = context.publish_gate.hold_back(action_wrapper.clone()) {
+        dispatch_publish(context.action_channel(), action_wrapper);
+    }
     let id = ProcessUniqueId::new();
     PublishFuture {
         context: context.clone(),
         action: action_wrapper,
         id,
+        deadline: FutureDeadline::default(),
+    }
+    .await
+}
+
+/// The publish action for `address`, with a fresh correlation id for tracing
+/// the publish through to the holds it results in.
+pub(crate) fn publish_action(
+    address: Address,
+    context: &Arc<Context>,
+    priority: PublishPriority,
+) -> ActionWrapper {
+    let correlation_id = CorrelationId::for_publish(&address, context);
+    ActionWrapper::new(Action::Publish((address, correlation_id, priority)))
+}
+
+/// Like [publish](publish), but only to the nodes holding `arc` of a sharded DHT,
+/// see `Action::PublishToArc`. Skips the publish queue and the publish dedup cache.
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+pub async fn publish_to_arc(
+    address: Address,
+    context: &Arc<Context>,
+    arc: DhtArc,
+) -> HcResult<Address> {
+    context
+        .state()
+        .ok_or(HolochainError::NetworkNotInitialized)?
+        .network()
+        .initialized()?;
+    let action_wrapper = ActionWrapper::new(Action::PublishToArc((address, arc)));
+    if let Some(action_wrapper) = context.publish_gate.hold_back(action_wrapper.clone()) {
+        dispatch_action(context.action_channel(), action_wrapper);
+    }
+    PublishFuture {
+        context: context.clone(),
+        action: action_wrapper,
+        id: ProcessUniqueId::new(),
+        deadline: FutureDeadline::default(),
     }
     .await
 }
 
+/// Dispatches the publish action followed by the `Action::ProcessPublishQueue` that
+/// processes it, or a publish of higher priority that got queued in the meantime.
+fn dispatch_publish(action_channel: &ActionSender, action_wrapper: ActionWrapper) {
+    dispatch_action(action_channel, action_wrapper);
+    dispatch_action(
+        action_channel,
+        ActionWrapper::new(Action::ProcessPublishQueue),
+    );
+}
+
+/// Dispatches the publish and returns right away, without waiting for the network's response.
+/// Meant for data nobody has to know got published; errors that happen while publishing
+/// go unnoticed. Fails only if the action could not be dispatched.
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+pub fn publish_async(address: Address, context: &Arc<Context>) -> HcResult<()> {
+    if let Some(error) = context.action_channel_error("publish_async") {
+        return Err(error);
+    }
+    let action_wrapper = publish_action(address, context, PublishPriority::Normal);
+    if let Some(action_wrapper) = context.publish_gate.hold_back(action_wrapper) {
+        dispatch_publish(context.action_channel(), action_wrapper);
+    }
+    Ok(())
+}
+
+/// Like [publish](publish), but hands out a handle to cancel the publish with.
+/// A publish that stops before the network responded fails with
+/// `HolochainError::Cancelled`, telling whether it got cancelled through the handle,
+/// by the instance shutting down or by the publish timeout.
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+pub fn publish_cancellable(
+    address: Address,
+    context: Arc<Context>,
+) -> (CancelHandle, impl Future<Output = HcResult<Address>>) {
+    let publish_context = context.clone();
+    cancellable(
+        async move { publish(address, &publish_context).await },
+        &context,
+    )
+}
+
+/// Runs the publish on the context's thread pool, e.g. for task supervisors that
+/// keep track of background work. The returned handle joins or aborts the publish.
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+pub fn spawn_publish(address: Address, context: Arc<Context>) -> PublishHandle {
+    let (cancel_handle, publish) = publish_cancellable(address, context.clone());
+    let (sender, receiver) = oneshot::channel();
+    context.spawn_task(async move {
+        // The handle might be gone already, nobody is interested in the result then
+        let _ = sender.send(publish.await);
+    });
+    PublishHandle {
+        cancel_handle,
+        result: receiver,
+    }
+}
+
+/// A publish running in the background, see [spawn_publish](spawn_publish).
+pub struct PublishHandle {
+    cancel_handle: CancelHandle,
+    result: oneshot::Receiver<HcResult<Address>>,
+}
+
+impl PublishHandle {
+    /// Waits for the publish to finish and returns its result.
+    pub async fn join(self) -> HcResult<Address> {
+        self.result.await?
+    }
+
+    /// Cancels the publish, joining it fails with `CancelReason::User`
+    /// unless it finished already.
+    pub fn abort(&self) {
+        self.cancel_handle.cancel();
+    }
+}
+
+/// Like [publish](publish), but never blocks on a full action channel.
+/// Fails with `HolochainError::WouldBlock`, without dispatching anything, if the action
+/// channel has no room for the publish action. Otherwise returns the future to await the
+/// network's response with. Doesn't look into the publish dedup cache, every successful
+/// call dispatches a publish.
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+pub fn try_publish(address: Address, context: &Arc<Context>) -> HcResult<PublishFuture> {
+    context
+        .state()
+        .ok_or(HolochainError::NetworkNotInitialized)?
+        .network()
+        .initialized()?;
+    let action_wrapper = publish_action(address, context, PublishPriority::Normal);
+    if let Some(action_wrapper) = context.publish_gate.hold_back(action_wrapper.clone()) {
+        context
+            .action_channel()
+            .try_send(ht::SpanWrap::new(action_wrapper, None))
+            .map_err(|error| match error {
+                TrySendError::Full(_) => HolochainError::WouldBlock,
+                TrySendError::Disconnected(_) => {
+                    HolochainError::LifecycleError("try_publish".to_string())
+                }
+            })?;
+        let process = ActionWrapper::new(Action::ProcessPublishQueue);
+        if let Err(TrySendError::Full(_)) = context
+            .action_channel()
+            .try_send(ht::SpanWrap::new(process.clone(), None))
+        {
+            // The publish is queued already, so it has to get processed eventually
+            let action_channel = context.action_channel().clone();
+            context.spawn_task(async move {
+                dispatch_action(&action_channel, process);
+            });
+        }
+    }
+    Ok(PublishFuture {
+        context: context.clone(),
+        action: action_wrapper,
+        id: ProcessUniqueId::new(),
+        deadline: FutureDeadline::default(),
+    })
+}
+
+/// Like [publish](publish), but first runs the validator of the entry's type locally
+/// and only publishes if the entry is valid. Otherwise the validation error is returned.
+/// This keeps authors from pushing invalid content to the network. It doesn't replace
+/// the validation that every node holding the entry does.
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+pub async fn publish_validated(address: Address, context: &Arc<Context>) -> HcResult<Address> {
+    let entry = get_entry_from_agent_chain(context, &address)?.ok_or_else(|| {
+        HolochainError::ErrorGeneric(format!(
+            "Entry {} is not in the source chain, can't validate it for publishing",
+            address
+        ))
+    })?;
+    let state = context
+        .state()
+        .ok_or(HolochainError::NetworkNotInitialized)?;
+    let link_update_delete =
+        find_chain_header(&entry, &state).and_then(|header| header.link_update_delete());
+
+    let package = build_validation_package(&entry, context.clone(), &Vec::new())?;
+    let validation_data =
+        ValidationData::new(package, EntryLifecycle::Chain, ValidationOrigin::Local);
+    validate_entry(entry, link_update_delete, validation_data, context).await?;
+
+    publish(address, context).await
+}
+
+/// Holds back publish actions while publishing is paused
+/// (see [Context::pause_publishing](crate::context::Context::pause_publishing)).
+/// Held back actions get dispatched in their original order on resume.
+pub struct PublishGate {
+    state: Mutex<PublishGateState>,
+}
+
+#[derive(Default)]
+struct PublishGateState {
+    paused: bool,
+    buffered: VecDeque<ActionWrapper>,
+}
+
+impl Default for PublishGate {
+    fn default() -> Self {
+        PublishGate {
+            state: Mutex::new(PublishGateState::default()),
+        }
+    }
+}
+
+impl PublishGate {
+    pub fn pause(&self) {
+        self.state.lock().expect("PublishGate poisoned").paused = true;
+    }
+
+    /// Dispatches all buffered publish actions, oldest first, and lets new
+    /// publishes through again.
+    pub fn resume(&self, action_channel: &ActionSender) {
+        let mut state = self.state.lock().expect("PublishGate poisoned");
+        state.paused = false;
+        // Dispatching while holding the lock keeps new publishes from overtaking buffered ones
+        while let Some(action_wrapper) = state.buffered.pop_front() {
+            dispatch_publish(action_channel, action_wrapper);
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state.lock().expect("PublishGate poisoned").paused
+    }
+
+    /// Addresses of the publishes that wait for publishing to be resumed, oldest first.
+    pub fn buffered_addresses(&self) -> Vec<Address> {
+        self.state
+            .lock()
+            .expect("PublishGate poisoned")
+            .buffered
+            .iter()
+            .filter_map(|action_wrapper| match action_wrapper.action() {
+                Action::Publish((address, _, _)) | Action::PublishToArc((address, _)) => {
+                    Some(address.clone())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Buffers the action if publishing is paused.
+    /// Otherwise the action is handed back to be dispatched right away.
+    fn hold_back(&self, action_wrapper: ActionWrapper) -> Option<ActionWrapper> {
+        let mut state = self.state.lock().expect("PublishGate poisoned");
+        if state.paused {
+            state.buffered.push_back(action_wrapper);
+            None
+        } else {
+            Some(action_wrapper)
+        }
+    }
+}
+
+/// Addresses whose publish failed and that should be published again later,
+/// see [retry_queued_publishes](retry_queued_publishes).
+pub struct PublishRetryQueue {
+    addresses: Mutex<VecDeque<Address>>,
+}
+
+impl Default for PublishRetryQueue {
+    fn default() -> Self {
+        PublishRetryQueue {
+            addresses: Mutex::new(VecDeque::new()),
+        }
+    }
+}
+
+impl PublishRetryQueue {
+    /// Appends the address unless it is queued already.
+    pub fn push(&self, address: Address) {
+        let mut addresses = self.addresses.lock().expect("PublishRetryQueue poisoned");
+        if !addresses.contains(&address) {
+            addresses.push_back(address);
+        }
+    }
+
+    /// Empties the queue, returning its addresses oldest first.
+    pub fn drain(&self) -> Vec<Address> {
+        self.addresses
+            .lock()
+            .expect("PublishRetryQueue poisoned")
+            .drain(..)
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.addresses
+            .lock()
+            .expect("PublishRetryQueue poisoned")
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// How long a successful publish keeps repeated publishes of the same address
+/// from being dispatched, unless changed with `Context::set_publish_dedup_window()`.
+pub const DEFAULT_PUBLISH_DEDUP_WINDOW: Duration = Duration::from_secs(1);
+
+/// Addresses that recently got published, with the time and the result of their publish.
+pub struct PublishCache {
+    completed: Mutex<HashMap<Address, (Instant, HcResult<Address>)>>,
+    window: RwLock<Duration>,
+}
+
+impl Default for PublishCache {
+    fn default() -> Self {
+        PublishCache {
+            completed: Mutex::new(HashMap::new()),
+            window: RwLock::new(DEFAULT_PUBLISH_DEDUP_WINDOW),
+        }
+    }
+}
+
+impl PublishCache {
+    pub fn window(&self) -> Duration {
+        *self.window.read().unwrap()
+    }
+
+    pub fn set_window(&self, window: Duration) {
+        *self.window.write().unwrap() = window;
+    }
+
+    /// Remembers that the publish of `address` completed just now with `result`
+    /// and forgets publishes that are older than the window.
+    pub(crate) fn record(&self, address: Address, result: HcResult<Address>) {
+        let window = self.window();
+        let mut completed = self.completed.lock().expect("PublishCache poisoned");
+        completed.retain(|_, (published_at, _)| published_at.elapsed() < window);
+        completed.insert(address, (Instant::now(), result));
+    }
+
+    /// The result of the last publish of `address`, if it completed within the window.
+    pub(crate) fn last_result(&self, address: &Address) -> Option<HcResult<Address>> {
+        let window = self.window();
+        self.completed
+            .lock()
+            .expect("PublishCache poisoned")
+            .get(address)
+            .filter(|(published_at, _)| published_at.elapsed() < window)
+            .map(|(_, result)| result.clone())
+    }
+
+    pub(crate) fn recently_published(&self, address: &Address) -> bool {
+        self.last_result(address)
+            .map(|result| result.is_ok())
+            .unwrap_or(false)
+    }
+}
+
+/// Tries to publish everything in the context's retry queue.
+/// Addresses that fail again go back into the queue.
+/// Returns the outcome per address, in queue order.
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+pub async fn retry_queued_publishes(context: &Arc<Context>) -> Vec<HcResult<Address>> {
+    let addresses = context.drain_publish_queue();
+    let mut results = Vec::with_capacity(addresses.len());
+    for address in addresses {
+        let result = publish(address.clone(), context).await;
+        if result.is_err() {
+            context.requeue_publish(address);
+        }
+        results.push(result);
+    }
+    results
+}
+
+/// Publishes all given addresses one after the other.
+/// A failure to publish one address does not stop the remaining ones from getting published.
+/// The outer result only fails if publishing could not be attempted at all (e.g. because the
+/// network is not initialized). Otherwise it holds the outcome for every address, in the same
+/// order as the given addresses.
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+pub async fn publish_many(
+    addresses: Vec<Address>,
+    context: &Arc<Context>,
+) -> HcResult<Vec<Result<Address, HolochainError>>> {
+    context
+        .state()
+        .ok_or(HolochainError::NetworkNotInitialized)?
+        .network()
+        .initialized()?;
+
+    let mut results = Vec::with_capacity(addresses.len());
+    for address in addresses {
+        results.push(publish(address, context).await);
+    }
+    Ok(results)
+}
+
+/// Callback that runs with the address of every successful publish.
+pub type PublishHook = Box<dyn Fn(Address) + Send + Sync>;
+
+/// Hooks registered through [Context::register_publish_hook](crate::context::Context::register_publish_hook).
+pub struct PublishHooks {
+    hooks: RwLock<Vec<PublishHook>>,
+}
+
+impl Default for PublishHooks {
+    fn default() -> Self {
+        PublishHooks {
+            hooks: RwLock::new(Vec::new()),
+        }
+    }
+}
+
+impl PublishHooks {
+    pub fn register(&self, hook: PublishHook) {
+        self.hooks.write().unwrap().push(hook);
+    }
+
+    /// Runs all hooks in registration order.
+    /// Hooks must not register other hooks, that would deadlock.
+    pub(crate) fn run(&self, address: &Address) {
+        for hook in self.hooks.read().unwrap().iter() {
+            hook(address.clone());
+        }
+    }
+}
+
 /// PublishFuture resolves to ActionResponse
 /// Tracks the state for a response to its ActionWrapper
 pub struct PublishFuture {
     context: Arc<Context>,
     action: ActionWrapper,
     id: ProcessUniqueId,
+    deadline: FutureDeadline,
 }
 
 #[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
 impl Future for PublishFuture {
     type Output = HcResult<Address>;
 
-    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
         if let Some(err) = self.context.action_channel_error("PublishFuture") {
             return Poll::Ready(Err(err));
         }
+        if !self.context.instance_still_alive() {
+            return Poll::Ready(Err(HolochainError::LifecycleError(
+                "PublishFuture: instance was shut down".to_string(),
+            )));
+        }
 
         self.context
             .register_waker(self.id.clone(), cx.waker().clone());
+        let timeout = self.context.future_timeouts().publish;
+        if self.deadline.expired(timeout, cx.waker()) {
+            self.context.unregister_waker(self.id.clone());
+            return Poll::Ready(Err(HolochainError::Timeout(
+                "Publishing did not complete in time".to_string(),
+            )));
+        }
 
         if let Some(state) = self.context.try_state() {
             let state = state.network();
@@ -56,7 +552,8 @@ impl Future for PublishFuture {
 
             match state.actions().get(&self.action) {
                 Some(r) => match r.response() {
-                    NetworkActionResponse::Publish(result) => {
+                    NetworkActionResponse::Publish(result)
+                    | NetworkActionResponse::PublishToArc(result) => {
                         dispatch_action(
                             self.context.action_channel(),
                             ActionWrapper::new(Action::ClearActionResponse(
@@ -64,6 +561,14 @@ impl Future for PublishFuture {
                             )),
                         );
                         self.context.unregister_waker(self.id.clone());
+                        if let Action::Publish((address, _, _)) = self.action.action() {
+                            self.context
+                                .publish_cache
+                                .record(address.clone(), result.clone());
+                        }
+                        if let Ok(address) = result {
+                            self.context.publish_hooks.run(address);
+                        }
                         Poll::Ready(result.clone())
                     }
                     _ => unreachable!(),
@@ -75,3 +580,82 @@ impl Future for PublishFuture {
         }
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{instance::tests::test_context_and_state, network::dht_arc::Location};
+    use futures::task::noop_waker_ref;
+    use holochain_core_types::{
+        chain_header::test_chain_header,
+        entry::{test_entry, Entry},
+        link::link_data::example_link_add,
+    };
+    use holochain_persistence_api::cas::content::AddressableContent;
+
+    #[test]
+    fn publish_future_resolves_with_injected_network_response() {
+        let (context, _state, receiver) = test_context_and_state("jane");
+        let address = test_entry().address();
+
+        let action_wrapper = publish_action(address.clone(), &context, PublishPriority::Normal);
+        dispatch_action(context.action_channel(), action_wrapper.clone());
+        assert!(receiver
+            .try_iter()
+            .any(|dispatched| dispatched.data == action_wrapper));
+        let mut future = Box::pin(PublishFuture {
+            context: context.clone(),
+            action: action_wrapper.clone(),
+            id: ProcessUniqueId::new(),
+            deadline: FutureDeadline::default(),
+        });
+
+        context.inject_network_response(
+            action_wrapper.id(),
+            NetworkActionResponse::Publish(Ok(address.clone())),
+        );
+        let mut cx = std::task::Context::from_waker(noop_waker_ref());
+        assert_eq!(future.as_mut().poll(&mut cx), Poll::Ready(Ok(address)));
+    }
+
+    #[test]
+    fn publish_to_arc_only_addresses_targets_inside_the_arc() {
+        let (context, state, receiver) = test_context_and_state("jane");
+        state.write().unwrap().set_network_initialized();
+        let link_data = example_link_add();
+        let entry = Entry::LinkAdd(link_data.clone());
+        let base = link_data.link().base().clone();
+        let arc = DhtArc::new(Location::of(&base), 0);
+
+        let mut future = Box::pin(publish_to_arc(entry.address(), &context, arc));
+        let mut cx = std::task::Context::from_waker(noop_waker_ref());
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+        let action_wrapper = receiver
+            .try_iter()
+            .map(|dispatched| dispatched.data)
+            .find(|action_wrapper| {
+                *action_wrapper.action() == Action::PublishToArc((entry.address(), arc))
+            })
+            .expect("publish to the arc should have been dispatched");
+
+        // Of the link entry and the link on its base, only the base lies in the arc
+        let targets = arc_targets(
+            &EntryWithHeader::new(entry.clone(), test_chain_header()),
+            &arc,
+        )
+        .unwrap()
+        .into_iter()
+        .map(|(basis, _)| basis)
+        .collect::<Vec<_>>();
+        assert_eq!(targets, vec![base]);
+
+        context.inject_network_response(
+            action_wrapper.id(),
+            NetworkActionResponse::PublishToArc(Ok(entry.address())),
+        );
+        assert_eq!(
+            future.as_mut().poll(&mut cx),
+            Poll::Ready(Ok(entry.address()))
+        );
+    }
+}
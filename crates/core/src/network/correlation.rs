@@ -0,0 +1,94 @@
+//! Correlation ids tie the publish of an entry on its author to the holds of
+//! what got published on the holders, so one commit-to-hold flow can be followed
+//! through the tracing spans of all nodes involved.
+//!
+//! The id is the address of the chain header the author committed for the entry.
+//! Every aspect sent to holders carries that header, so holders get the id
+//! without anything extra going over the wire.
+use crate::context::Context;
+use holochain_core_types::chain_header::ChainHeader;
+use holochain_persistence_api::cas::content::{Address, AddressableContent};
+use std::{fmt, sync::Arc};
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize)]
+pub struct CorrelationId(Address);
+
+impl CorrelationId {
+    /// The id of the flow started by committing `header`.
+    pub fn for_header(header: &ChainHeader) -> Self {
+        CorrelationId(header.address())
+    }
+
+    /// The id for publishing the entry at `address`, taken from the most recent
+    /// header for it in the agent's source chain. Falls back to the entry address
+    /// for entries that are not in the source chain.
+    pub fn for_publish(address: &Address, context: &Arc<Context>) -> Self {
+        context
+            .state()
+            .and_then(|state| {
+                state
+                    .agent()
+                    .iter_chain()
+                    .find(|header| header.entry_address() == address)
+            })
+            .map(|header| CorrelationId::for_header(&header))
+            .unwrap_or_else(|| CorrelationId(address.clone()))
+    }
+}
+
+impl fmt::Display for CorrelationId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        action::Action,
+        agent::actions::commit::commit_entry,
+        instance::tests::test_instance_and_context_by_name,
+        network::{
+            actions::publish::{publish_action, PublishPriority},
+            entry_aspect::EntryAspect,
+        },
+    };
+    use holochain_core_types::entry::test_entry;
+    use std::time::SystemTime;
+    use test_utils::create_test_dna_with_wat;
+
+    #[test]
+    fn hold_derived_from_publish_has_its_correlation_id() {
+        let netname = Some("hold_derived_from_publish_has_its_correlation_id");
+        let mut dna = create_test_dna_with_wat("test_zome", None);
+        dna.uuid = netname.unwrap().to_string();
+        let (_instance, context) =
+            test_instance_and_context_by_name(dna, "alice_correlation_id", netname).unwrap();
+
+        let entry = test_entry();
+        let result = context.block_on(commit_entry(entry.clone(), None, &context));
+        assert!(result.is_ok(), "commit_entry() result = {:?}", result);
+        let publish = publish_action(entry.address(), &context, PublishPriority::Normal);
+        let correlation_id = publish
+            .action()
+            .correlation_id()
+            .expect("Publish must have a correlation id");
+
+        // What a holder receives for the published entry
+        let header = context
+            .state()
+            .unwrap()
+            .agent()
+            .get_most_recent_header_for_entry(&entry)
+            .expect("There must be a header in the author's source chain after commit");
+        let hold = Action::HoldAspect((
+            EntryAspect::Content(entry, header.clone()),
+            None,
+            SystemTime::now(),
+        ));
+
+        assert_eq!(hold.correlation_id(), Some(correlation_id.clone()));
+        assert_eq!(correlation_id, CorrelationId::for_header(&header));
+    }
+}
@@ -0,0 +1,70 @@
+//! Storage arcs of a sharded DHT.
+//!
+//! Like lib3h's rrdht, the DHT space is a ring of `u32` locations. Every node holds
+//! the data whose locations fall into its storage arc.
+use holochain_persistence_api::cas::content::Address;
+use std::cmp::min;
+
+/// A point on the DHT ring.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Location(pub u32);
+
+impl Location {
+    /// Where data published at `address` lives on the ring:
+    /// the bytes of the address folded into a `u32` by xor.
+    pub fn of(address: &Address) -> Location {
+        let mut location = [0u8; 4];
+        for (index, byte) in String::from(address.clone()).bytes().enumerate() {
+            location[index % 4] ^= byte;
+        }
+        Location(u32::from_be_bytes(location))
+    }
+
+    /// Distance to `other` going whichever way round the ring is shorter.
+    pub fn distance_to(self, other: Location) -> u32 {
+        min(other.0.wrapping_sub(self.0), self.0.wrapping_sub(other.0))
+    }
+}
+
+/// The locations at most `half_length` away from `center`, in both directions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DhtArc {
+    pub center: Location,
+    pub half_length: u32,
+}
+
+impl DhtArc {
+    pub fn new(center: Location, half_length: u32) -> Self {
+        DhtArc {
+            center,
+            half_length,
+        }
+    }
+
+    pub fn contains(&self, location: Location) -> bool {
+        self.center.distance_to(location) <= self.half_length
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn arc_contains_locations_on_both_sides_of_its_center() {
+        let arc = DhtArc::new(Location(10), 100);
+        assert!(arc.contains(Location(10)));
+        assert!(arc.contains(Location(110)));
+        assert!(!arc.contains(Location(111)));
+        // the ring wraps around at the end of the u32 range
+        assert!(arc.contains(Location(u32::max_value() - 89)));
+        assert!(!arc.contains(Location(u32::max_value() - 90)));
+    }
+
+    #[test]
+    fn location_of_address_is_stable() {
+        let address = Address::from("QmYbza1Fc6Fy8rkEhJCiKBWzxXdfvyXa4EEq4nUXgNkC2B");
+        assert_eq!(Location::of(&address), Location::of(&address.clone()));
+        assert_ne!(Location::of(&address), Location::of(&Address::from("bob")));
+    }
+}
@@ -7,7 +7,7 @@ use crate::{
         GetLinksNetworkQuery, GetLinksNetworkResult, NetworkQuery, NetworkQueryResult,
     },
     nucleus,
-    workflows::get_entry_result::get_entry_result_workflow_local,
+    workflows::{get_entry_result::get_entry_result_workflow_local, validate_held_aspect},
 };
 use holochain_core_types::{
     crud_status::CrudStatus,
@@ -209,7 +209,38 @@ pub fn handle_query_entry_data(query_data: QueryEntryData, context: Arc<Context>
             }
         }
         Ok(NetworkQuery::GetEntry) => {
-            let maybe_entry = get_entry(&context, query_data.entry_address.clone().into());
+            let entry_address: Address = query_data.entry_address.clone().into();
+            let unvalidated = context
+                .state()
+                .unwrap()
+                .dht()
+                .unvalidated_aspects_of_entry(&entry_address);
+            if !unvalidated.is_empty() {
+                // Aspects held with HoldPolicy::LazyValidate get validated on their first read
+                let task_context = context.clone();
+                context.spawn_task(async move {
+                    for aspect_address in unvalidated {
+                        if let Err(e) =
+                            validate_held_aspect(aspect_address.clone(), task_context.clone()).await
+                        {
+                            log_debug!(
+                                task_context,
+                                "net: Lazily held aspect {} did not validate: {:?}",
+                                aspect_address,
+                                e
+                            );
+                        }
+                    }
+                    let maybe_entry = get_entry(&task_context, entry_address);
+                    let respond_get = NetworkQueryResult::Entry(maybe_entry);
+                    dispatch_action(
+                        task_context.action_channel(),
+                        ActionWrapper::new(Action::RespondQuery((query_data, respond_get))),
+                    );
+                });
+                return;
+            }
+            let maybe_entry = get_entry(&context, entry_address);
             let respond_get = NetworkQueryResult::Entry(maybe_entry);
             ActionWrapper::new(Action::RespondQuery((query_data, respond_get)))
         }
@@ -1,19 +1,27 @@
 use crate::{
+    action::{Action, ActionWrapper},
     context::Context,
     dht::{
-        actions::queue_holding_workflow::dispatch_queue_holding_workflow,
+        actions::{
+            hold_aspect::{hold_aspect_unvalidated, HoldPolicy},
+            queue_holding_workflow::dispatch_queue_holding_workflow,
+        },
         pending_validations::PendingValidationStruct,
     },
+    instance::dispatch_action,
 };
-use holochain_core_types::network::entry_aspect::EntryAspect;
+use holochain_core_types::network::{aspect_chunk::AspectChunkMessage, entry_aspect::EntryAspect};
 use holochain_json_api::json::JsonString;
+use holochain_persistence_api::cas::content::AddressableContent;
 use lib3h_protocol::data_types::StoreEntryAspectData;
 use std::{
     convert::{TryFrom, TryInto},
     sync::Arc,
+    time::SystemTime,
 };
 
 /// The network requests us to store (i.e. hold) the given entry aspect data.
+/// Large aspects arrive as `AspectChunkMessage`s, one chunk at a time.
 #[autotrace]
 #[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
 pub fn handle_store(dht_data: StoreEntryAspectData, context: Arc<Context>) {
@@ -35,6 +43,20 @@ pub fn handle_store(dht_data: StoreEntryAspectData, context: Arc<Context>) {
             );
             return;
         }
+        if context.hold_policy() == HoldPolicy::LazyValidate {
+            log_debug!(
+                context,
+                "net/handle: handle_store: Storing {} unvalidated",
+                aspect.address(),
+            );
+            let task_context = context.clone();
+            context.spawn_task(async move {
+                if let Err(e) = hold_aspect_unvalidated(aspect, task_context.clone()).await {
+                    log_error!(task_context, "net/handle: handle_store: {:?}", e);
+                }
+            });
+            return;
+        }
         match PendingValidationStruct::try_from(aspect) {
             Err(e) => log_error!(
                 context,
@@ -50,6 +72,23 @@ pub fn handle_store(dht_data: StoreEntryAspectData, context: Arc<Context>) {
                 dispatch_queue_holding_workflow(Arc::new(pending), None, context);
             }
         }
+    } else if let Ok(AspectChunkMessage { manifest, chunk }) =
+        AspectChunkMessage::try_from(aspect_json.clone())
+    {
+        log_debug!(
+            context,
+            "net/handle: handle_store: Received chunk {} of {}",
+            chunk.index,
+            manifest.aspect_address,
+        );
+        dispatch_action(
+            context.action_channel(),
+            ActionWrapper::new(Action::HoldAspectChunk((
+                manifest,
+                chunk,
+                SystemTime::now(),
+            ))),
+        );
     } else {
         log_error!(
             context,
@@ -1,4 +1,6 @@
 pub mod actions;
+pub mod correlation;
+pub mod dht_arc;
 pub mod direct_message;
 pub mod entry_with_header;
 #[autotrace]
@@ -18,26 +20,34 @@ pub mod tests {
         instance::tests::test_instance_and_context_by_name,
         network::{
             actions::{
-                publish::publish,
+                publish::{
+                    publish, publish_async, publish_many, publish_validated,
+                    retry_queued_publishes, spawn_publish, try_publish,
+                },
                 query::{query, QueryMethod},
             },
             query::{
                 GetLinksNetworkQuery, GetLinksNetworkResult, GetLinksQueryConfiguration,
                 NetworkQueryResult,
             },
-            test_utils::test_wat_always_valid,
+            test_utils::{test_wat_always_invalid, test_wat_always_valid},
         },
     };
     use holochain_core_types::{
         agent::test_agent_id,
         chain_header::test_chain_header,
         crud_status::CrudStatus,
-        entry::{entry_type::test_app_entry_type, test_entry, Entry, EntryWithMetaAndHeader},
+        entry::{
+            entry_type::test_app_entry_type, test_entry, test_entry_b, Entry,
+            EntryWithMetaAndHeader,
+        },
+        error::HolochainError,
         link::link_data::LinkData,
     };
     use holochain_json_api::json::JsonString;
     use holochain_persistence_api::cas::content::{Address, AddressableContent};
     use holochain_wasm_utils::api_serialization::get_links::GetLinksArgs;
+    use std::sync::{Arc, Mutex};
     use test_utils::*;
 
     // TODO: Bring the old in-memory network up to speed and turn on this test again!
@@ -56,6 +66,346 @@ pub mod tests {
             .try_init();
     }
 
+    #[test]
+    fn publish_many_reports_result_per_address() {
+        let netname = Some("publish_many_reports_result_per_address");
+        let mut dna = create_test_dna_with_wat("test_zome", None);
+        dna.uuid = netname.unwrap().to_string();
+        let (_instance, context) =
+            test_instance_and_context_by_name(dna, "alice_publish_many", netname).unwrap();
+
+        let entry = test_entry();
+        let entry_b = test_entry_b();
+        for e in vec![entry.clone(), entry_b.clone()] {
+            let result = context.block_on(commit_entry(e, None, &context));
+            assert!(result.is_ok(), "commit_entry() result = {:?}", result);
+        }
+
+        // The middle address was never committed, so it can't be published
+        let unknown_address = Address::from("QmNotCommittedAnywhere");
+        let results = context
+            .block_on(publish_many(
+                vec![entry.address(), unknown_address, entry_b.address()],
+                &context,
+            ))
+            .expect("publish_many() could not be run");
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0], Ok(entry.address()));
+        assert!(results[1].is_err(), "results[1] = {:?}", results[1]);
+        assert_eq!(results[2], Ok(entry_b.address()));
+    }
+
+    #[test]
+    fn failed_publish_can_be_requeued_and_retried() {
+        let netname = Some("failed_publish_can_be_requeued_and_retried");
+        let mut dna = create_test_dna_with_wat("test_zome", None);
+        dna.uuid = netname.unwrap().to_string();
+        let (_instance, context) =
+            test_instance_and_context_by_name(dna, "alice_requeue_publish", netname).unwrap();
+
+        let unknown_address = Address::from("QmNotCommittedAnywhere");
+        let result = context.block_on(publish(unknown_address.clone(), &context));
+        assert!(result.is_err(), "publish() result = {:?}", result);
+        context.requeue_publish(unknown_address.clone());
+        // queuing twice doesn't publish twice
+        context.requeue_publish(unknown_address.clone());
+
+        let entry = test_entry();
+        let result = context.block_on(commit_entry(entry.clone(), None, &context));
+        assert!(result.is_ok(), "commit_entry() result = {:?}", result);
+        context.requeue_publish(entry.address());
+
+        let results = context.block_on(retry_queued_publishes(&context));
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_err(), "results[0] = {:?}", results[0]);
+        assert_eq!(results[1], Ok(entry.address()));
+
+        // only the publish that failed again is still queued
+        assert_eq!(context.drain_publish_queue(), vec![unknown_address]);
+        assert!(context.drain_publish_queue().is_empty());
+    }
+
+    #[test]
+    fn invalid_entry_is_not_published() {
+        let netname = Some("invalid_entry_is_not_published");
+        let mut dna = create_test_dna_with_wat("test_zome", Some(&test_wat_always_invalid()));
+        dna.uuid = netname.unwrap().to_string();
+        let (_instance, context) =
+            test_instance_and_context_by_name(dna, "alice_publish_validated", netname).unwrap();
+
+        // committing directly skips validation, so the chain holds an invalid entry
+        let entry = test_entry();
+        let result = context.block_on(commit_entry(entry.clone(), None, &context));
+        assert!(result.is_ok(), "commit_entry() result = {:?}", result);
+
+        let result = context.block_on(publish_validated(entry.address(), &context));
+        assert!(
+            match result {
+                Err(HolochainError::ValidationFailed(_)) => true,
+                _ => false,
+            },
+            "publish_validated() result = {:?}",
+            result
+        );
+        assert!(!context.state().unwrap().dht().holds_entry(&entry.address()));
+    }
+
+    #[test]
+    fn publish_hooks_fire_once_per_successful_publish() {
+        let netname = Some("publish_hooks_fire_once_per_successful_publish");
+        let mut dna = create_test_dna_with_wat("test_zome", None);
+        dna.uuid = netname.unwrap().to_string();
+        let (_instance, context) =
+            test_instance_and_context_by_name(dna, "alice_publish_hooks", netname).unwrap();
+
+        let published = Arc::new(Mutex::new(Vec::new()));
+        let first = published.clone();
+        context
+            .register_publish_hook(move |address| first.lock().unwrap().push(("first", address)));
+        let second = published.clone();
+        context
+            .register_publish_hook(move |address| second.lock().unwrap().push(("second", address)));
+
+        let entry = test_entry();
+        let result = context.block_on(commit_entry(entry.clone(), None, &context));
+        assert!(result.is_ok(), "commit_entry() result = {:?}", result);
+        assert_eq!(
+            context.block_on(publish(entry.address(), &context)),
+            Ok(entry.address())
+        );
+        let result = context.block_on(publish(Address::from("QmNotCommittedAnywhere"), &context));
+        assert!(result.is_err(), "publish() result = {:?}", result);
+
+        assert_eq!(
+            *published.lock().unwrap(),
+            vec![("first", entry.address()), ("second", entry.address())]
+        );
+    }
+
+    #[test]
+    fn publish_async_returns_before_response_is_recorded() {
+        use crate::{action::Action, instance::tests::test_context_and_state};
+
+        // Nothing processes the dispatched actions unless the test does
+        let (context, _state, receiver) = test_context_and_state("alice_publish_async");
+
+        let address = test_entry().address();
+        assert_eq!(publish_async(address.clone(), &context), Ok(()));
+
+        let publish_action = receiver
+            .try_iter()
+            .map(|action_wrapper| action_wrapper.data)
+            .find(|action_wrapper| match action_wrapper.action() {
+                Action::Publish((published, _, _)) => *published == address,
+                _ => false,
+            })
+            .expect("Publish action should have been dispatched");
+        let state = context.state().unwrap();
+        assert!(state.network().actions().get(&publish_action).is_none());
+
+        // the dispatched actions still get the publish a response once they are reduced
+        let state = receiver.try_iter().fold(
+            state.reduce(publish_action.clone()),
+            |state, action_wrapper| state.reduce(action_wrapper.data),
+        );
+        assert!(state.network().actions().get(&publish_action).is_some());
+    }
+
+    #[test]
+    fn publish_without_initialized_network_dispatches_nothing() {
+        use crate::{action::Action, instance::tests::test_context_and_state};
+
+        let (context, _state, receiver) = test_context_and_state("alice_publish_uninitialized");
+
+        let address = test_entry().address();
+        assert_eq!(
+            context.block_on(publish(address.clone(), &context)),
+            Err(HolochainError::NetworkNotInitialized)
+        );
+        assert!(!receiver
+            .try_iter()
+            .any(|action_wrapper| match action_wrapper.data.action() {
+                Action::Publish((published, _, _)) => *published == address,
+                _ => false,
+            }));
+    }
+
+    #[test]
+    fn try_publish_fails_fast_on_full_action_channel() {
+        use crate::action::{Action, ActionWrapper};
+        use holochain_tracing as ht;
+
+        let netname = Some("try_publish_fails_fast_on_full_action_channel");
+        let mut dna = create_test_dna_with_wat("test_zome", None);
+        dna.uuid = netname.unwrap().to_string();
+        let (_instance, context) =
+            test_instance_and_context_by_name(dna, "alice_try_publish", netname).unwrap();
+
+        // Nobody reads from this channel, so it stays full after the first action
+        let (sender, _receiver) = crossbeam_channel::bounded::<ht::SpanWrap<ActionWrapper>>(1);
+        sender
+            .send(ht::SpanWrap::new(ActionWrapper::new(Action::Ping), None))
+            .unwrap();
+        let mut full_context = (*context).clone();
+        full_context.action_channel = Some(sender.into());
+        let full_context = Arc::new(full_context);
+
+        match try_publish(test_entry().address(), &full_context) {
+            Err(HolochainError::WouldBlock) => (),
+            Err(other) => panic!("Expected WouldBlock, got {:?}", other),
+            Ok(_) => panic!("Expected WouldBlock, got a PublishFuture"),
+        }
+    }
+
+    #[test]
+    fn repeated_publish_resolves_from_cache() {
+        let netname = Some("repeated_publish_resolves_from_cache");
+        let mut dna = create_test_dna_with_wat("test_zome", None);
+        dna.uuid = netname.unwrap().to_string();
+        let (_instance, context) =
+            test_instance_and_context_by_name(dna, "alice_publish_cache", netname).unwrap();
+        context.set_publish_dedup_window(std::time::Duration::from_secs(60));
+
+        let entry = test_entry();
+        let result = context.block_on(commit_entry(entry.clone(), None, &context));
+        assert!(result.is_ok(), "commit_entry() result = {:?}", result);
+        assert_eq!(
+            context.block_on(publish(entry.address(), &context)),
+            Ok(entry.address())
+        );
+
+        // A dispatched publish would now wait in the gate until publishing gets resumed
+        context.pause_publishing();
+        assert_eq!(
+            context.block_on(publish(entry.address(), &context)),
+            Ok(entry.address())
+        );
+        assert!(context.publish_gate.buffered_addresses().is_empty());
+    }
+
+    #[test]
+    fn last_publish_result_reports_completed_publish() {
+        let netname = Some("last_publish_result_reports_completed_publish");
+        let mut dna = create_test_dna_with_wat("test_zome", None);
+        dna.uuid = netname.unwrap().to_string();
+        let (_instance, context) =
+            test_instance_and_context_by_name(dna, "alice_last_publish_result", netname).unwrap();
+        context.set_publish_dedup_window(std::time::Duration::from_secs(60));
+
+        let entry = test_entry();
+        assert_eq!(context.last_publish_result(&entry.address()), None);
+        let result = context.block_on(commit_entry(entry.clone(), None, &context));
+        assert!(result.is_ok(), "commit_entry() result = {:?}", result);
+        let published = context.block_on(publish(entry.address(), &context));
+        assert_eq!(published, Ok(entry.address()));
+
+        assert_eq!(
+            context.last_publish_result(&entry.address()),
+            Some(published)
+        );
+        assert_eq!(context.last_publish_result(&test_entry_b().address()), None);
+    }
+
+    #[test]
+    fn spawned_publish_can_be_joined_or_aborted() {
+        use holochain_core_types::error::CancelReason;
+
+        let netname = Some("spawned_publish_can_be_joined_or_aborted");
+        let mut dna = create_test_dna_with_wat("test_zome", None);
+        dna.uuid = netname.unwrap().to_string();
+        let (_instance, context) =
+            test_instance_and_context_by_name(dna, "alice_spawn_publish", netname).unwrap();
+
+        let entry = test_entry();
+        let result = context.block_on(commit_entry(entry.clone(), None, &context));
+        assert!(result.is_ok(), "commit_entry() result = {:?}", result);
+        let handle = spawn_publish(entry.address(), context.clone());
+        assert_eq!(context.block_on(handle.join()), Ok(entry.address()));
+
+        // A paused publish never gets a response, so it is still running when aborted
+        context.pause_publishing();
+        let handle = spawn_publish(test_entry_b().address(), context.clone());
+        handle.abort();
+        assert_eq!(
+            context.block_on(handle.join()),
+            Err(HolochainError::Cancelled(CancelReason::User))
+        );
+    }
+
+    #[test]
+    fn publish_times_out_as_set_in_future_timeouts() {
+        use crate::futures_util::FutureTimeouts;
+        use std::time::Duration;
+
+        let netname = Some("publish_times_out_as_set_in_future_timeouts");
+        let mut dna = create_test_dna_with_wat("test_zome", None);
+        dna.uuid = netname.unwrap().to_string();
+        let (_instance, context) =
+            test_instance_and_context_by_name(dna, "alice_publish_timeout", netname).unwrap();
+        context.set_future_timeouts(FutureTimeouts {
+            publish: Some(Duration::from_millis(100)),
+            ..FutureTimeouts::default()
+        });
+
+        // A paused publish never gets a response
+        context.pause_publishing();
+        match context.block_on(publish(test_entry().address(), &context)) {
+            Err(HolochainError::Timeout(_)) => (),
+            other => panic!("Expected a timeout, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn paused_publishes_complete_in_order_after_resume() {
+        let netname = Some("paused_publishes_complete_in_order_after_resume");
+        let mut dna = create_test_dna_with_wat("test_zome", None);
+        dna.uuid = netname.unwrap().to_string();
+        let (_instance, context) =
+            test_instance_and_context_by_name(dna, "alice_paused_publish", netname).unwrap();
+
+        context.pause_publishing();
+        assert!(context.is_publishing_paused());
+
+        // committing still works while paused
+        let entry = test_entry();
+        let entry_b = test_entry_b();
+        for e in vec![entry.clone(), entry_b.clone()] {
+            let result = context.block_on(commit_entry(e, None, &context));
+            assert!(result.is_ok(), "commit_entry() result = {:?}", result);
+        }
+
+        let wait_for_buffered = |count: usize| {
+            let mut tries = 0;
+            while context.publish_gate.buffered_addresses().len() < count && tries < 100 {
+                tries += 1;
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+        };
+
+        let spawn_publish = |address: Address| {
+            let context = context.clone();
+            std::thread::spawn(move || context.block_on(publish(address, &context)))
+        };
+
+        let first = spawn_publish(entry.address());
+        wait_for_buffered(1);
+        let second = spawn_publish(entry_b.address());
+        wait_for_buffered(2);
+
+        assert_eq!(
+            context.publish_gate.buffered_addresses(),
+            vec![entry.address(), entry_b.address()]
+        );
+
+        context.resume_publishing();
+        assert!(!context.is_publishing_paused());
+        assert!(context.publish_gate.buffered_addresses().is_empty());
+
+        assert_eq!(first.join().unwrap(), Ok(entry.address()));
+        assert_eq!(second.join().unwrap(), Ok(entry_b.address()));
+    }
+
     // TODO: Should wait for a success or saturation response from the network module after Publish
     #[test]
     #[ignore]
@@ -31,7 +31,7 @@ use crate::{
             handle_get_validation_package::reduce_handle_get_validation_package,
             init::reduce_init,
             prune::reduce_prune,
-            publish::reduce_publish,
+            publish::{reduce_process_publish_queue, reduce_publish, reduce_publish_to_arc},
             publish_header_entry::reduce_publish_header_entry,
             query::{reduce_query, reduce_query_timeout},
             resolve_direct_connection::reduce_resolve_direct_connection,
@@ -77,7 +77,9 @@ fn resolve_reducer(action_wrapper: &ActionWrapper) -> Option<NetworkReduceFn> {
         Action::HandleGetValidationPackage(_) => Some(reduce_handle_get_validation_package),
         Action::InitNetwork(_) => Some(reduce_init),
         Action::Prune => Some(reduce_prune),
+        Action::ProcessPublishQueue => Some(reduce_process_publish_queue),
         Action::Publish(_) => Some(reduce_publish),
+        Action::PublishToArc(_) => Some(reduce_publish_to_arc),
         Action::PublishHeaderEntry(_) => Some(reduce_publish_header_entry),
         Action::ResolveDirectConnection(_) => Some(reduce_resolve_direct_connection),
         Action::RespondAuthoringList(_) => Some(reduce_respond_authoring_list),
@@ -2,6 +2,7 @@ use crate::{
     action::ActionWrapper,
     network::{
         actions::NetworkActionResponse,
+        dht_arc::{DhtArc, Location},
         entry_aspect::EntryAspect,
         entry_with_header::{fetch_entry_with_header, EntryWithHeader},
         reducers::send,
@@ -38,11 +39,12 @@ pub fn entry_data_to_entry_aspect_data(ea: &EntryAspect) -> EntryAspectData {
     }
 }
 
-/// Send to network a PublishDhtData message
+/// Send to network a PublishDhtData message with `aspect`, to the nodes holding `basis`
 #[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
-fn publish_entry(
+fn publish_aspect(
     network_state: &mut NetworkState,
-    entry_with_header: &EntryWithHeader,
+    basis: Address,
+    aspect: &EntryAspect,
 ) -> Result<(), HolochainError> {
     send(
         network_state,
@@ -50,26 +52,30 @@ fn publish_entry(
             space_address: network_state.dna_address.clone().unwrap().into(),
             provider_agent_id: network_state.agent_id.clone().unwrap().into(),
             entry: EntryData {
-                entry_address: entry_with_header.entry.address().into(),
-                aspect_list: vec![entry_data_to_entry_aspect_data(&EntryAspect::Content(
-                    entry_with_header.entry.clone(),
-                    entry_with_header.header.clone(),
-                ))],
+                entry_address: basis.into(),
+                aspect_list: vec![entry_data_to_entry_aspect_data(aspect)],
             },
         }),
     )
 }
 
-/// Send to network a publish request for either delete or update aspect information
-#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
-fn publish_update_delete_meta(
-    network_state: &mut NetworkState,
+/// The content aspect of the entry, published at the entry's address
+fn entry_target(entry_with_header: &EntryWithHeader) -> (Address, EntryAspect) {
+    (
+        entry_with_header.entry.address(),
+        EntryAspect::Content(
+            entry_with_header.entry.clone(),
+            entry_with_header.header.clone(),
+        ),
+    )
+}
+
+/// Either delete or update aspect information, published at the original entry's address
+fn update_delete_meta_target(
     orig_entry_address: Address,
     crud_status: CrudStatus,
     entry_with_header: &EntryWithHeader,
-) -> Result<(), HolochainError> {
-    // publish crud-status
-
+) -> Result<(Address, EntryAspect), HolochainError> {
     let aspect = match crud_status {
         CrudStatus::Modified => EntryAspect::Update(
             entry_with_header.entry.clone(),
@@ -83,59 +89,80 @@ fn publish_update_delete_meta(
             )));
         }
     };
-
-    send(
-        network_state,
-        Lib3hClientProtocol::PublishEntry(ProvidedEntryData {
-            space_address: network_state.dna_address.clone().unwrap().into(),
-            provider_agent_id: network_state.agent_id.clone().unwrap().into(),
-            entry: EntryData {
-                entry_address: orig_entry_address.into(),
-                aspect_list: vec![entry_data_to_entry_aspect_data(&aspect)],
-            },
-        }),
-    )?;
-
-    // publish crud-link if there is one
-    Ok(())
+    Ok((orig_entry_address, aspect))
 }
 
-/// Send to network a PublishMeta message holding a link metadata to `entry_with_header`
-#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
-fn publish_link_meta(
-    network_state: &mut NetworkState,
+/// Link metadata of `entry_with_header`, published at the link's base
+fn link_meta_target(
     entry_with_header: &EntryWithHeader,
-) -> Result<(), HolochainError> {
-    let (base, aspect) = match entry_with_header.entry.clone() {
-        Entry::LinkAdd(link_data) => (
+) -> Result<(Address, EntryAspect), HolochainError> {
+    match entry_with_header.entry.clone() {
+        Entry::LinkAdd(link_data) => Ok((
             link_data.link().base().clone(),
             EntryAspect::LinkAdd(link_data, entry_with_header.header.clone()),
-        ),
-        Entry::LinkRemove((link_data, links_to_remove)) => (
+        )),
+        Entry::LinkRemove((link_data, links_to_remove)) => Ok((
             link_data.link().base().clone(),
             EntryAspect::LinkRemove(
                 (link_data, links_to_remove),
                 entry_with_header.header.clone(),
             ),
-        ),
+        )),
+        _ => Err(HolochainError::ErrorGeneric(format!(
+            "Received bad entry type. Expected Entry::LinkAdd/Remove received {:?}",
+            entry_with_header.entry,
+        ))),
+    }
+}
+
+/// The aspects publishing the entry sends out, each with the address (basis)
+/// whose holders it goes to.
+pub(crate) fn publish_targets(
+    entry_with_header: &EntryWithHeader,
+) -> Result<Vec<(Address, EntryAspect)>, HolochainError> {
+    let mut targets = vec![entry_target(entry_with_header)];
+    match entry_with_header.entry.entry_type() {
+        EntryType::AgentId => (),
+        EntryType::App(_) => {
+            if let Some(modified_entry) = entry_with_header.header.link_update_delete() {
+                targets.push(update_delete_meta_target(
+                    modified_entry,
+                    CrudStatus::Modified,
+                    entry_with_header,
+                )?);
+            }
+        }
+        EntryType::LinkAdd | EntryType::LinkRemove => {
+            targets.push(link_meta_target(entry_with_header)?)
+        }
+        EntryType::Deletion => {
+            if let Some(modified_entry) = entry_with_header.header.link_update_delete() {
+                targets.push(update_delete_meta_target(
+                    modified_entry,
+                    CrudStatus::Deleted,
+                    entry_with_header,
+                )?);
+            }
+        }
         _ => {
-            return Err(HolochainError::ErrorGeneric(format!(
-                "Received bad entry type. Expected Entry::LinkAdd/Remove received {:?}",
-                entry_with_header.entry,
-            )));
+            return Err(HolochainError::NotImplemented(format!(
+                "reduce_publish_inner not implemented for {}",
+                entry_with_header.entry.entry_type()
+            )))
         }
-    };
-    send(
-        network_state,
-        Lib3hClientProtocol::PublishEntry(ProvidedEntryData {
-            space_address: network_state.dna_address.clone().unwrap().into(),
-            provider_agent_id: network_state.agent_id.clone().unwrap().into(),
-            entry: EntryData {
-                entry_address: base.into(),
-                aspect_list: vec![entry_data_to_entry_aspect_data(&aspect)],
-            },
-        }),
-    )
+    }
+    Ok(targets)
+}
+
+/// The targets of `publish_targets()` whose basis lies in `arc`.
+pub(crate) fn arc_targets(
+    entry_with_header: &EntryWithHeader,
+    arc: &DhtArc,
+) -> Result<Vec<(Address, EntryAspect)>, HolochainError> {
+    Ok(publish_targets(entry_with_header)?
+        .into_iter()
+        .filter(|(basis, _)| arc.contains(Location::of(basis)))
+        .collect())
 }
 
 #[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
@@ -143,56 +170,63 @@ fn reduce_publish_inner(
     network_state: &mut NetworkState,
     root_state: &State,
     address: &Address,
+    arc: Option<&DhtArc>,
 ) -> Result<(), HolochainError> {
     network_state.initialized()?;
 
     let entry_with_header = fetch_entry_with_header(&address, root_state)?;
-
-    match entry_with_header.entry.entry_type() {
-        EntryType::AgentId => publish_entry(network_state, &entry_with_header),
-        EntryType::App(_) => publish_entry(network_state, &entry_with_header).and_then(|_| {
-            match entry_with_header.header.link_update_delete() {
-                Some(modified_entry) => publish_update_delete_meta(
-                    network_state,
-                    modified_entry,
-                    CrudStatus::Modified,
-                    &entry_with_header.clone(),
-                ),
-                None => Ok(()),
-            }
-        }),
-        EntryType::LinkAdd => publish_entry(network_state, &entry_with_header)
-            .and_then(|_| publish_link_meta(network_state, &entry_with_header)),
-        EntryType::LinkRemove => publish_entry(network_state, &entry_with_header)
-            .and_then(|_| publish_link_meta(network_state, &entry_with_header)),
-        EntryType::Deletion => publish_entry(network_state, &entry_with_header).and_then(|_| {
-            match entry_with_header.header.link_update_delete() {
-                Some(modified_entry) => publish_update_delete_meta(
-                    network_state,
-                    modified_entry,
-                    CrudStatus::Deleted,
-                    &entry_with_header.clone(),
-                ),
-                None => Ok(()),
+    let targets = match arc {
+        None => publish_targets(&entry_with_header)?,
+        Some(arc) => {
+            let targets = arc_targets(&entry_with_header, arc)?;
+            if targets.is_empty() {
+                return Err(HolochainError::ErrorGeneric(format!(
+                    "No aspect of {} is held in {:?}",
+                    address, arc
+                )));
             }
-        }),
-        _ => Err(HolochainError::NotImplemented(format!(
-            "reduce_publish_inner not implemented for {}",
-            entry_with_header.entry.entry_type()
-        ))),
-    }
+            targets
+        }
+    };
+    targets
+        .into_iter()
+        .map(|(basis, aspect)| publish_aspect(network_state, basis, &aspect))
+        .collect()
 }
 
+/// Queues the publish, it gets processed by the next `Action::ProcessPublishQueue`
+/// unless publishes of higher priority come in first.
 #[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
 pub fn reduce_publish(
     network_state: &mut NetworkState,
-    root_state: &State,
+    _root_state: &State,
     action_wrapper: &ActionWrapper,
 ) {
     let action = action_wrapper.action();
-    let address = unwrap_to!(action => crate::action::Action::Publish);
+    let (_address, _correlation_id, priority) =
+        unwrap_to!(action => crate::action::Action::Publish);
+    network_state
+        .publish_queue
+        .push(action_wrapper.clone(), *priority);
+}
+
+/// Publishes the queued publish with the highest priority and stores the response
+/// under its `Action::Publish`.
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+pub fn reduce_process_publish_queue(
+    network_state: &mut NetworkState,
+    root_state: &State,
+    _action_wrapper: &ActionWrapper,
+) {
+    let action_wrapper = match network_state.publish_queue.pop() {
+        Some(action_wrapper) => action_wrapper,
+        None => return,
+    };
+    let action = action_wrapper.action();
+    let (address, _correlation_id, _priority) =
+        unwrap_to!(action => crate::action::Action::Publish);
 
-    let result = reduce_publish_inner(network_state, root_state, &address);
+    let result = reduce_publish_inner(network_state, root_state, &address, None);
     network_state.actions.insert(
         action_wrapper.clone(),
         Response::from(NetworkActionResponse::Publish(match result {
@@ -202,6 +236,27 @@ pub fn reduce_publish(
     );
 }
 
+/// Publishes right away, but only the aspects whose basis lies in the arc,
+/// and stores the response under the `Action::PublishToArc`.
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+pub fn reduce_publish_to_arc(
+    network_state: &mut NetworkState,
+    root_state: &State,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let (address, arc) = unwrap_to!(action => crate::action::Action::PublishToArc);
+
+    let result = reduce_publish_inner(network_state, root_state, &address, Some(arc));
+    network_state.actions.insert(
+        action_wrapper.clone(),
+        Response::from(NetworkActionResponse::PublishToArc(match result {
+            Ok(_) => Ok(address.clone()),
+            Err(e) => Err(HolochainError::ErrorGeneric(e.to_string())),
+        })),
+    );
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -209,10 +264,15 @@ mod tests {
     use crate::{
         action::{Action, ActionWrapper},
         instance::tests::test_context,
+        network::{actions::publish::PublishPriority, correlation::CorrelationId},
         state::test_store,
     };
     use chrono::{offset::FixedOffset, DateTime};
-    use holochain_core_types::{chain_header::test_chain_header, entry::test_entry};
+    use holochain_core_types::link::link_data::example_link_add;
+    use holochain_core_types::{
+        chain_header::test_chain_header,
+        entry::{test_entry, test_entry_with_value},
+    };
     use holochain_persistence_api::cas::content::AddressableContent;
     use lib3h_protocol::types::AspectHash;
 
@@ -222,11 +282,42 @@ mod tests {
         let store = test_store(context.clone());
 
         let entry = test_entry();
-        let action_wrapper = ActionWrapper::new(Action::Publish(entry.address()));
+        let action_wrapper = ActionWrapper::new(Action::Publish((
+            entry.address(),
+            CorrelationId::for_publish(&entry.address(), &context),
+            PublishPriority::Normal,
+        )));
 
         store.reduce(action_wrapper);
     }
 
+    #[test]
+    fn high_priority_publish_gets_processed_before_low_priority_one() {
+        let context = test_context("alice", None);
+        let store = test_store(context.clone());
+        let publish = |value: &str, priority| {
+            let address = test_entry_with_value(value).address();
+            ActionWrapper::new(Action::Publish((
+                address.clone(),
+                CorrelationId::for_publish(&address, &context),
+                priority,
+            )))
+        };
+        let low = publish("\"index update\"", PublishPriority::Low);
+        let high = publish("\"post\"", PublishPriority::High);
+
+        let store = store.reduce(low.clone()).reduce(high.clone());
+        assert_eq!(store.network().publish_queue.len(), 2);
+
+        let store = store.reduce(ActionWrapper::new(Action::ProcessPublishQueue));
+        assert!(store.network().actions().contains_key(&high));
+        assert!(!store.network().actions().contains_key(&low));
+
+        let store = store.reduce(ActionWrapper::new(Action::ProcessPublishQueue));
+        assert!(store.network().actions().contains_key(&low));
+        assert!(store.network().publish_queue.is_empty());
+    }
+
     #[test]
     fn can_convert_into_entry_aspect_data() {
         let chain_header = test_chain_header();
@@ -242,4 +333,36 @@ mod tests {
         assert_eq!(*aspect_data.aspect, aspect_json.to_bytes());
         assert_eq!(aspect_data.publish_ts, ts.timestamp() as u64);
     }
+
+    #[test]
+    fn arc_targets_only_include_bases_in_the_arc() {
+        let link_data = example_link_add();
+        let entry = Entry::LinkAdd(link_data.clone());
+        let entry_with_header = EntryWithHeader::new(entry.clone(), test_chain_header());
+        let base = link_data.link().base().clone();
+        assert_ne!(Location::of(&entry.address()), Location::of(&base));
+
+        let bases = |targets: Vec<(Address, EntryAspect)>| {
+            targets
+                .into_iter()
+                .map(|(basis, _)| basis)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            bases(publish_targets(&entry_with_header).unwrap()),
+            vec![entry.address(), base.clone()]
+        );
+
+        let around_base = DhtArc::new(Location::of(&base), 0);
+        let targets = arc_targets(&entry_with_header, &around_base).unwrap();
+        match &targets[..] {
+            [(basis, EntryAspect::LinkAdd(..))] => assert_eq!(*basis, base),
+            other => panic!("Expected only the link on its base, got {:?}", other),
+        }
+
+        let elsewhere = DhtArc::new(Location(Location::of(&base).0.wrapping_add(1 << 31)), 0);
+        assert!(arc_targets(&entry_with_header, &elsewhere)
+            .unwrap()
+            .is_empty());
+    }
 }
@@ -1,13 +1,21 @@
 use crate::{
     action::{ActionWrapper, QueryKey, ValidationKey},
-    network::{actions::Response, direct_message::DirectMessage, query::NetworkQueryResult},
+    network::{
+        actions::{publish::PublishPriority, Response},
+        direct_message::DirectMessage,
+        query::NetworkQueryResult,
+    },
 };
 use boolinator::*;
 use holochain_core_types::{error::HolochainError, validation::ValidationPackage};
 use holochain_net::p2p_network::P2pNetwork;
 use holochain_persistence_api::cas::content::Address;
 use im::HashMap;
-use std::time::{Duration, SystemTime};
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    time::{Duration, SystemTime},
+};
 
 type Actions = HashMap<ActionWrapper, Response>;
 
@@ -47,6 +55,9 @@ pub struct NetworkState {
 
     pub custom_direct_message_replys: HashMap<String, Result<String, HolochainError>>,
 
+    /// Publishes waiting for an `Action::ProcessPublishQueue`.
+    pub publish_queue: PublishQueue,
+
     id: String,
 }
 
@@ -56,6 +67,68 @@ impl PartialEq for NetworkState {
     }
 }
 
+/// Publish actions by priority. Publishes of the same priority come out
+/// in the order they went in.
+#[derive(Clone, Debug, Default)]
+pub struct PublishQueue {
+    queued: BinaryHeap<QueuedPublish>,
+    next_sequence: u64,
+}
+
+#[derive(Clone, Debug)]
+struct QueuedPublish {
+    priority: PublishPriority,
+    sequence: u64,
+    action_wrapper: ActionWrapper,
+}
+
+impl PartialEq for QueuedPublish {
+    fn eq(&self, other: &QueuedPublish) -> bool {
+        self.priority == other.priority && self.sequence == other.sequence
+    }
+}
+
+impl Eq for QueuedPublish {}
+
+impl Ord for QueuedPublish {
+    fn cmp(&self, other: &QueuedPublish) -> Ordering {
+        // Older publishes have lower sequence numbers and come first
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+impl PartialOrd for QueuedPublish {
+    fn partial_cmp(&self, other: &QueuedPublish) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PublishQueue {
+    pub fn push(&mut self, action_wrapper: ActionWrapper, priority: PublishPriority) {
+        self.queued.push(QueuedPublish {
+            priority,
+            sequence: self.next_sequence,
+            action_wrapper,
+        });
+        self.next_sequence += 1;
+    }
+
+    /// Takes the oldest of the publishes with the highest priority.
+    pub fn pop(&mut self) -> Option<ActionWrapper> {
+        self.queued.pop().map(|queued| queued.action_wrapper)
+    }
+
+    pub fn len(&self) -> usize {
+        self.queued.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queued.is_empty()
+    }
+}
+
 #[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
 impl NetworkState {
     pub fn new() -> Self {
@@ -71,6 +144,7 @@ impl NetworkState {
             direct_message_connections: HashMap::new(),
             direct_message_timeouts: HashMap::new(),
             custom_direct_message_replys: HashMap::new(),
+            publish_queue: PublishQueue::default(),
 
             id: nanoid::simple(),
         }
@@ -81,8 +155,7 @@ impl NetworkState {
     }
 
     pub fn initialized(&self) -> Result<(), HolochainError> {
-        (self.network.is_some() && self.dna_address.is_some() && self.agent_id.is_some()).ok_or(
-            HolochainError::ErrorGeneric("Network not initialized".to_string()),
-        )
+        (self.network.is_some() && self.dna_address.is_some() && self.agent_id.is_some())
+            .ok_or(HolochainError::NetworkNotInitialized)
     }
 }
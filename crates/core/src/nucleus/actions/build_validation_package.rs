@@ -13,8 +13,11 @@ use holochain_core_types::{
     entry::{entry_type::EntryType, Entry},
     error::HolochainError,
     signature::Provenance,
-    validation::{ValidationPackage, ValidationPackageDefinition::*},
+    validation::{
+        LinkSpec, ValidationPackage, ValidationPackageDefinition, ValidationPackageDefinition::*,
+    },
 };
+use holochain_persistence_api::cas::content::Address;
 use std::{sync::Arc, vec::Vec};
 
 #[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
@@ -114,41 +117,106 @@ pub fn build_validation_package<'a>(
             _ => unreachable!(),
         })
         .and_then(|package_definition| {
-            Ok(match package_definition {
-                Entry => ValidationPackage::only_header(entry_header),
-                ChainEntries => {
-                    let mut package = ValidationPackage::only_header(entry_header);
-                    package.source_chain_entries = Some(public_chain_entries_from_headers(
-                        &context,
-                        &all_chain_headers_before_header(&context, &package.chain_header),
-                    ));
-                    package
-                }
-                ChainHeaders => {
-                    let mut package = ValidationPackage::only_header(entry_header);
-                    package.source_chain_headers = Some(all_chain_headers_before_header(
-                        &context,
-                        &package.chain_header,
-                    ));
-                    package
-                }
-                ChainFull => {
-                    let mut package = ValidationPackage::only_header(entry_header);
-                    let headers = all_chain_headers_before_header(&context, &package.chain_header);
-                    package.source_chain_entries =
-                        Some(public_chain_entries_from_headers(&context, &headers));
-                    package.source_chain_headers = Some(headers);
-                    package
-                }
-                Custom(string) => {
-                    let mut package = ValidationPackage::only_header(entry_header);
-                    package.custom = Some(string);
-                    package
-                }
-            })
+            package_for_definition(&context, entry_header, package_definition)
         })
 }
 
+/// Assembles what `definition` asks for, the union of what its parts ask for
+/// if it is `Combined`.
+fn package_for_definition(
+    context: &Arc<Context>,
+    entry_header: ChainHeader,
+    definition: ValidationPackageDefinition,
+) -> Result<ValidationPackage, HolochainError> {
+    let mut package = ValidationPackage::only_header(entry_header);
+    for part in definition.parts() {
+        let mut part_package = ValidationPackage::only_header(package.chain_header.clone());
+        match part {
+            Entry => {}
+            ChainEntries => {
+                part_package.source_chain_entries = Some(public_chain_entries_from_headers(
+                    context,
+                    &all_chain_headers_before_header(context, &package.chain_header),
+                ));
+            }
+            ChainHeaders => {
+                part_package.source_chain_headers = Some(all_chain_headers_before_header(
+                    context,
+                    &package.chain_header,
+                ));
+            }
+            ChainFull => {
+                let headers = all_chain_headers_before_header(context, &package.chain_header);
+                part_package.source_chain_entries =
+                    Some(public_chain_entries_from_headers(context, &headers));
+                part_package.source_chain_headers = Some(headers);
+            }
+            Custom(string) => part_package.custom = Some(string),
+            WithLinks(links) => part_package.links = Some(existing_links(context, &links)?),
+            WithReferences(addresses) => {
+                part_package.references = Some(existing_references(context, &addresses)?)
+            }
+            // parts() flattens combined definitions
+            Combined(_) => unreachable!(),
+        }
+        package.union(part_package);
+    }
+    Ok(package)
+}
+
+/// Replaces the DHT parts of a `package` we got from its author with what our own
+/// DHT shard holds of what `definition` asks for.
+/// The author can't prove which links and referenced entries exist, so validators
+/// look them up themselves.
+pub(crate) fn resolve_dht_parts_locally(
+    context: &Arc<Context>,
+    package: ValidationPackage,
+    definition: &ValidationPackageDefinition,
+) -> Result<ValidationPackage, HolochainError> {
+    let mut package = ValidationPackage {
+        links: None,
+        references: None,
+        ..package
+    };
+    for part in definition.parts() {
+        let mut part_package = ValidationPackage::only_header(package.chain_header.clone());
+        match part {
+            WithLinks(links) => part_package.links = Some(existing_links(context, &links)?),
+            WithReferences(addresses) => {
+                part_package.references = Some(existing_references(context, &addresses)?)
+            }
+            _ => continue,
+        }
+        package.union(part_package);
+    }
+    Ok(package)
+}
+
+/// The links out of `links` that are stored in our DHT shard.
+pub(crate) fn existing_links(
+    context: &Arc<Context>,
+    links: &[LinkSpec],
+) -> Result<Vec<LinkSpec>, HolochainError> {
+    let dht = context.state()?.dht();
+    Ok(links
+        .iter()
+        .filter(|link| dht.has_live_link(link))
+        .cloned()
+        .collect())
+}
+
+/// The entries at those of the `addresses` that are stored in our DHT shard.
+pub(crate) fn existing_references(
+    context: &Arc<Context>,
+    addresses: &[Address],
+) -> Result<Vec<Entry>, HolochainError> {
+    let dht = context.state()?.dht();
+    Ok(addresses
+        .iter()
+        .filter_map(|address| dht.get(address).ok().and_then(|entry| entry))
+        .collect())
+}
+
 // given a slice of headers return the entries for those marked public
 #[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
 fn public_chain_entries_from_headers(
@@ -187,10 +255,24 @@ fn all_chain_headers_before_header(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::nucleus::actions::tests::*;
+    use crate::{
+        action::{Action, ActionWrapper},
+        content_store::AddContent,
+        dht::dht_reducers,
+        nucleus::actions::tests::*,
+    };
 
-    use holochain_core_types::{time::Iso8601, validation::ValidationPackage};
+    use holochain_core_types::{
+        agent::test_agent_id,
+        chain_header::test_chain_header,
+        entry::test_entry_with_value,
+        link::{link_data::LinkData, Link, LinkActionKind},
+        network::entry_aspect::EntryAspect,
+        time::Iso8601,
+        validation::ValidationPackage,
+    };
     use holochain_persistence_api::cas::content::{Address, AddressableContent};
+    use std::time::SystemTime;
 
     #[test]
     fn test_building_validation_package_entry() {
@@ -213,6 +295,8 @@ mod tests {
             source_chain_entries: None,
             source_chain_headers: None,
             custom: None,
+            links: None,
+            references: None,
         };
 
         assert_eq!(maybe_validation_package.unwrap(), expected);
@@ -245,6 +329,8 @@ mod tests {
             )),
             source_chain_headers: None,
             custom: None,
+            links: None,
+            references: None,
         };
 
         assert_eq!(maybe_validation_package.unwrap(), expected);
@@ -273,6 +359,8 @@ mod tests {
             source_chain_entries: None,
             source_chain_headers: Some(all_chain_headers_before_header(&context, &chain_header)),
             custom: None,
+            links: None,
+            references: None,
         };
 
         assert_eq!(maybe_validation_package.unwrap(), expected);
@@ -300,11 +388,129 @@ mod tests {
             source_chain_entries: Some(public_chain_entries_from_headers(&context, &headers)),
             source_chain_headers: Some(headers),
             custom: None,
+            links: None,
+            references: None,
         };
 
         assert_eq!(maybe_validation_package.unwrap(), expected);
     }
 
+    /// Holds a comment link to the entry of `chain_header` in our DHT shard.
+    /// Returns that link and one with the same base and target that we don't hold.
+    fn hold_comment_link(
+        context: &Arc<Context>,
+        chain_header: &ChainHeader,
+    ) -> (LinkSpec, LinkSpec) {
+        let base = test_entry_with_value("\"comment\"");
+        let mut dht = (*context.state().unwrap().dht()).clone();
+        dht.add(&base).unwrap();
+        let link = Link::new(
+            &base.address(),
+            chain_header.entry_address(),
+            "comment_on",
+            "",
+        );
+        let link_data = LinkData::from_link(
+            &link,
+            LinkActionKind::ADD,
+            test_chain_header(),
+            test_agent_id(),
+        );
+        let _ = dht_reducers::reduce(
+            Arc::new(dht),
+            &ActionWrapper::new(Action::HoldAspect((
+                EntryAspect::LinkAdd(link_data, test_chain_header()),
+                None,
+                SystemTime::now(),
+            ))),
+        );
+
+        let held_link = LinkSpec {
+            base: base.address(),
+            target: chain_header.entry_address().clone(),
+            link_type: "comment_on".to_string(),
+            tag: "".to_string(),
+        };
+        let missing_link = LinkSpec {
+            tag: "missing".to_string(),
+            ..held_link.clone()
+        };
+
+        (held_link, missing_link)
+    }
+
+    #[test]
+    fn test_building_validation_package_combined() {
+        let (_instance, context) = instance(None);
+
+        commit(test_entry_package_chain_entries(), &context);
+        let chain_header = commit(test_entry_package_entry(), &context);
+        let (held_link, missing_link) = hold_comment_link(&context, &chain_header);
+
+        let package = package_for_definition(
+            &context,
+            chain_header.clone(),
+            Combined(vec![
+                ChainEntries,
+                WithLinks(vec![held_link.clone(), missing_link]),
+            ]),
+        )
+        .unwrap();
+
+        let expected = ValidationPackage {
+            chain_header: chain_header.clone(),
+            source_chain_entries: Some(public_chain_entries_from_headers(
+                &context,
+                &all_chain_headers_before_header(&context, &chain_header),
+            )),
+            source_chain_headers: None,
+            custom: None,
+            links: Some(vec![held_link]),
+            references: None,
+        };
+
+        assert_eq!(package, expected);
+    }
+
+    #[test]
+    fn validator_does_not_trust_links_claimed_by_the_author() {
+        let (_instance, context) = instance(None);
+
+        let chain_header = commit(test_entry_package_entry(), &context);
+        let (held_link, missing_link) = hold_comment_link(&context, &chain_header);
+        let links = vec![held_link.clone(), missing_link];
+
+        // the author claims both links exist
+        let mut package = ValidationPackage::only_header(chain_header);
+        package.links = Some(links.clone());
+
+        let package = resolve_dht_parts_locally(&context, package, &WithLinks(links)).unwrap();
+        assert_eq!(package.links, Some(vec![held_link]));
+    }
+
+    #[test]
+    fn validator_does_not_trust_references_claimed_by_the_author() {
+        let (_instance, context) = instance(None);
+
+        let chain_header = commit(test_entry_package_entry(), &context);
+        let held = test_entry_with_value("\"held\"");
+        let missing = test_entry_with_value("\"missing\"");
+        let mut dht = (*context.state().unwrap().dht()).clone();
+        dht.add(&held).unwrap();
+
+        // the author claims both referenced entries exist
+        let mut package = ValidationPackage::only_header(chain_header);
+        package.references = Some(vec![held.clone(), missing.clone()]);
+
+        let package = resolve_dht_parts_locally(
+            &context,
+            package,
+            &WithReferences(vec![held.address(), missing.address()]),
+        )
+        .unwrap();
+        assert_eq!(package.references, Some(vec![held]));
+    }
+
     // test can make validation package with empty chain
     #[test]
     fn test_all_chain_headers_before_header_empty_chain() {
@@ -8,59 +8,94 @@ use crate::{
 };
 use holochain_core_types::error::HolochainError;
 use holochain_persistence_api::cas::content::Address;
-use std::sync::Arc;
+use holochain_wasm_utils::api_serialization::validation::{
+    DeferredValidation, EntryValidationSuccess, LinkSpec,
+};
+use std::{convert::TryFrom, sync::Arc};
 
 use holochain_metrics::with_latency_publishing;
 
 /// Validation callback action creator.
-/// Spawns a thread in which a WASM Ribosome runs the custom validation function defined by
-/// `zome_call`.
+/// Runs the custom validation function defined by `zome_call` in a WASM Ribosome
+/// on one of the context's validation workers.
 /// Dispatches an `Action::ReturnValidationResult` after completion of the WASM call.
+/// Waits for a slot first if the context's validation concurrency limit is reached.
 /// Returns a future that waits for the result to appear in the nucleus state.
+/// Dropping the future cancels the validation, see `ValidationPool::run()`.
+pub async fn run_validation_callback(
+    address: Address,
+    call: CallbackFnCall,
+    context: &Arc<Context>,
+) -> ValidationResult {
+    run_indexing_validation_callback(address, call, context)
+        .await
+        .map(|_| ())
+}
+
+/// Like [run_validation_callback](run_validation_callback), but a valid entry comes with
+/// the links its app validator asked to get created (see `EntryValidationSuccess`).
 #[no_autotrace] // TODO: get autotrace working for this future
 #[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
-pub async fn run_validation_callback(
+pub async fn run_indexing_validation_callback(
     _address: Address,
     call: CallbackFnCall,
     context: &Arc<Context>,
-) -> ValidationResult {
+) -> Result<Vec<LinkSpec>, ValidationError> {
     let metric_name_prefix = format!(
         "run_validation_callback.{}.{}",
         call.zome_name, call.fn_name
     );
 
-    with_latency_publishing!(
-        metric_name_prefix,
-        context.metric_publisher,
-        |()| {
-            let cloned_context = context.clone();
+    let context = context.clone();
+    let validation_pool = context.validation_pool.clone();
+    let validation_semaphore = context.validation_semaphore.clone();
+    // Waits here if too many validations are running already, without taking up a worker
+    let _permit = validation_semaphore.acquire().await;
+    validation_pool
+        .run(move || {
+            with_latency_publishing!(
+                metric_name_prefix,
+                context.metric_publisher,
+                |()| {
+                    let cloned_context = context.clone();
 
-            match wasm_engine::run_dna(
-                Some(call.clone().parameters.to_bytes()),
-                WasmCallData::new_callback_call(cloned_context, call),
-            ) {
-                Ok(call_result) => {
-                    if call_result.is_null() {
-                        Ok(())
-                    } else {
-                        Err(ValidationError::Fail(call_result.to_string()))
-                    }
-                }
-                // TODO: have "not matching schema" be its own error
-                Err(HolochainError::RibosomeFailed(error_string)) => {
-                    if error_string == "Argument deserialization failed" {
-                        Err(ValidationError::Error(
-                            String::from("JSON object does not match entry schema").into(),
-                        ))
-                    } else {
-                        // an unknown error from the ribosome should panic rather than
-                        // silently failing validation
-                        panic!(error_string)
+                    match wasm_engine::run_dna(
+                        Some(call.clone().parameters.to_bytes()),
+                        WasmCallData::new_callback_call(cloned_context, call),
+                    ) {
+                        Ok(call_result) => {
+                            if call_result.is_null() {
+                                Ok(Vec::new())
+                            } else if let Ok(EntryValidationSuccess::IndexHints(hints)) =
+                                EntryValidationSuccess::try_from(call_result.clone())
+                            {
+                                Ok(hints)
+                            } else if let Ok(DeferredValidation { dependencies }) =
+                                DeferredValidation::try_from(call_result.clone())
+                            {
+                                Err(ValidationError::UnresolvedDependencies(dependencies))
+                            } else {
+                                Err(ValidationError::Fail(call_result.to_string()))
+                            }
+                        }
+                        // TODO: have "not matching schema" be its own error
+                        Err(HolochainError::RibosomeFailed(error_string)) => {
+                            if error_string == "Argument deserialization failed" {
+                                Err(ValidationError::Error(
+                                    String::from("JSON object does not match entry schema").into(),
+                                ))
+                            } else {
+                                // an unknown error from the ribosome should panic rather than
+                                // silently failing validation
+                                panic!(error_string)
+                            }
+                        }
+                        Err(error) => panic!(error.to_string()), // same here
                     }
-                }
-                Err(error) => panic!(error.to_string()), // same here
-            }
-        },
-        ()
-    )
+                },
+                ()
+            )
+        })
+        .await
+        .unwrap_or_else(|error| Err(ValidationError::Error(error)))
 }
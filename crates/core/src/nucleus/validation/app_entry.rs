@@ -2,20 +2,25 @@ use crate::{
     context::Context,
     nucleus::{
         actions::{
-            get_entry::get_entry_from_dht, run_validation_callback::run_validation_callback,
+            get_entry::get_entry_from_dht,
+            run_validation_callback::run_indexing_validation_callback,
+        },
+        validation::{
+            entry_to_validation_data, events::ValidationEvent, ValidationError, ValidationResult,
         },
-        validation::{entry_to_validation_data, ValidationError, ValidationResult},
         CallbackFnCall,
     },
 };
 use holochain_core_types::{
+    dna::Dna,
     entry::{entry_type::AppEntryType, Entry},
-    validation::ValidationData,
+    validation::{validation_seed, EntryValidationData, ValidationData, ValidationOrigin},
 };
 use holochain_persistence_api::cas::content::{Address, AddressableContent};
 
+use holochain_json_api::json::JsonString;
 use holochain_wasm_utils::api_serialization::validation::EntryValidationArgs;
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 
 #[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
 pub async fn validate_app_entry(
@@ -36,11 +41,86 @@ pub async fn validate_app_entry(
         })?;
     };
 
-    let params = EntryValidationArgs {
-        validation_data: entry_to_validation_data(context.clone(), &entry, link, validation_data)
-            .map_err(|e| ValidationError::Error(e))?,
-    };
+    let origin = validation_data.origin.clone();
+    let validation_data = entry_to_validation_data(context.clone(), &entry, link, validation_data)
+        .map_err(|e| ValidationError::Error(e))?;
+    let params =
+        entry_validation_args(context, &dna, &zome_name, &entry.address(), validation_data);
     let call = CallbackFnCall::new(&zome_name, "__hdk_validate_app_entry", params);
 
-    run_validation_callback(entry.address(), call, &context).await
+    let start = Instant::now();
+    let result = run_indexing_validation_callback(entry.address(), call, &context).await;
+    context.validation_events.emit(ValidationEvent {
+        entry_type: app_entry_type,
+        address: entry.address(),
+        outcome: result.clone().map(|_| ()),
+        duration: start.elapsed(),
+    });
+    let hints = result?;
+    // Only the author creates the links the validator asked for,
+    // nodes validating entries from the network just check them.
+    if origin != ValidationOrigin::Remote && !hints.is_empty() {
+        context.index_hints.record(entry.address(), hints);
+    }
+    Ok(())
+}
+
+/// What `__hdk_validate_app_entry` gets to validate the entry with the given address
+/// in the given zome, with everything the host knows about the entry filled in.
+pub(crate) fn entry_validation_args(
+    context: &Arc<Context>,
+    dna: &Dna,
+    zome_name: &str,
+    entry_address: &Address,
+    validation_data: EntryValidationData<Entry>,
+) -> EntryValidationArgs {
+    let origin = validation_data.validation_data().origin.clone();
+    let timestamp = validation_data
+        .validation_data()
+        .package
+        .chain_header
+        .timestamp()
+        .clone();
+    let rule_version = validation_data.declared_rule_version();
+    EntryValidationArgs {
+        validation_data,
+        origin,
+        dna_properties: JsonString::from(dna.properties.clone()),
+        seed: validation_seed(entry_address),
+        locale: context.validation_locale(),
+        rule_version,
+        timestamp: Some(timestamp),
+        zome_name: Some(zome_name.to_string()),
+        dna_hash: Some(dna.address()),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::instance::tests::test_context;
+
+    #[test]
+    fn validation_args_carry_rule_version_the_entry_declares() {
+        let context = test_context("alice", None);
+        let dna = Dna::new();
+        let args = |content: &str| {
+            let entry = Entry::App(AppEntryType::from("post"), JsonString::from_json(content));
+            entry_validation_args(
+                &context,
+                &dna,
+                "blog",
+                &entry.address(),
+                EntryValidationData::Create {
+                    entry,
+                    validation_data: ValidationData::default(),
+                },
+            )
+        };
+
+        assert_eq!(args(r#"{"content":"old post"}"#).rule_version, None);
+        let args = args(r#"{"content":"new post","rule_version":2}"#);
+        assert_eq!(args.rule_version, Some(2));
+        assert_eq!(args.zome_name, Some("blog".to_string()));
+    }
 }
@@ -0,0 +1,98 @@
+//! Validation of several entries at once, e.g. all that arrived with one gossip round,
+//! within a time budget shared by the whole batch.
+//!
+//! Every validation's run time counts against the batch budget. Once it is spent, the
+//! entries that were not validated yet get a budget error without running their
+//! validators, so a few slow validators can't take up unbounded time together.
+use crate::{
+    context::Context,
+    nucleus::validation::{validate_entry, ValidationError, ValidationResult},
+};
+use futures::Future;
+use holochain_core_types::{entry::Entry, error::HolochainError, validation::ValidationData};
+use holochain_persistence_api::cas::content::Address;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// The error the entries get that were left over when the batch budget was spent.
+pub fn budget_exceeded() -> ValidationError {
+    ValidationError::Error(HolochainError::Timeout(
+        "Validation batch budget exceeded".to_string(),
+    ))
+}
+
+/// Validates the entries in order, like `validate_entry()` would each of them
+/// (the `Option<Address>` is its `link` argument), and returns their results in the same order.
+/// Entries reached after `batch_budget` got used up are not validated but fail with
+/// [budget_exceeded()](budget_exceeded).
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+pub async fn validate_entry_batch(
+    entries: Vec<(Entry, Option<Address>, ValidationData)>,
+    batch_budget: Duration,
+    context: &Arc<Context>,
+) -> Vec<ValidationResult> {
+    validate_within_budget(entries, batch_budget, |(entry, link, validation_data)| {
+        validate_entry(entry, link, validation_data, context)
+    })
+    .await
+}
+
+async fn validate_within_budget<T, F, Fut>(
+    items: Vec<T>,
+    batch_budget: Duration,
+    mut validate: F,
+) -> Vec<ValidationResult>
+where
+    F: FnMut(T) -> Fut,
+    Fut: Future<Output = ValidationResult>,
+{
+    let mut spent = Duration::from_secs(0);
+    let mut results = Vec::with_capacity(items.len());
+    for item in items {
+        if spent >= batch_budget {
+            results.push(Err(budget_exceeded()));
+            continue;
+        }
+        let start = Instant::now();
+        results.push(validate(item).await);
+        spent += start.elapsed();
+    }
+    results
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use std::thread;
+
+    #[test]
+    fn entries_after_spent_budget_report_budget_error() {
+        let slow_validator = |valid: bool| async move {
+            thread::sleep(Duration::from_millis(30));
+            if valid {
+                Ok(())
+            } else {
+                Err(ValidationError::Fail("invalid".to_string()))
+            }
+        };
+
+        let results = block_on(validate_within_budget(
+            vec![true, false, true, true],
+            Duration::from_millis(50),
+            slow_validator,
+        ));
+
+        assert_eq!(
+            results,
+            vec![
+                Ok(()),
+                Err(ValidationError::Fail("invalid".to_string())),
+                Err(budget_exceeded()),
+                Err(budget_exceeded()),
+            ]
+        );
+    }
+}
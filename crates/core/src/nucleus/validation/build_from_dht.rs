@@ -1,5 +1,8 @@
 use crate::{
-    context::Context, entry::CanPublish, network::entry_with_header::EntryWithHeader,
+    context::Context,
+    entry::CanPublish,
+    network::entry_with_header::EntryWithHeader,
+    nucleus::actions::build_validation_package::{existing_links, existing_references},
     workflows::get_entry_result::get_entry_with_meta_workflow,
 };
 use holochain_core_types::{
@@ -105,24 +108,38 @@ pub(crate) async fn try_make_validation_package_dht(
 
     let mut package = ValidationPackage::only_header(entry_header.clone());
 
-    match validation_package_definition {
-        ValidationPackageDefinition::Entry => {
-            // this should never happen but it will produce the correct package anyway
-        }
-        ValidationPackageDefinition::ChainEntries => {
-            package.source_chain_entries =
-                Some(public_chain_entries_from_headers_dht(context.clone(), &chain_headers).await?);
-        }
-        ValidationPackageDefinition::ChainHeaders => {
-            package.source_chain_headers = Some(chain_headers)
-        }
-        ValidationPackageDefinition::ChainFull => {
-            package.source_chain_headers = Some(chain_headers.clone());
-            package.source_chain_entries =
-                Some(public_chain_entries_from_headers_dht(context.clone(), &chain_headers).await?);
-        }
-        ValidationPackageDefinition::Custom(string) => package.custom = Some(string.to_string()),
-    };
+    for part in validation_package_definition.parts() {
+        let mut part_package = ValidationPackage::only_header(entry_header.clone());
+        match part {
+            ValidationPackageDefinition::Entry => {
+                // this should never happen but it will produce the correct package anyway
+            }
+            ValidationPackageDefinition::ChainEntries => {
+                part_package.source_chain_entries = Some(
+                    public_chain_entries_from_headers_dht(context.clone(), &chain_headers).await?,
+                );
+            }
+            ValidationPackageDefinition::ChainHeaders => {
+                part_package.source_chain_headers = Some(chain_headers.clone())
+            }
+            ValidationPackageDefinition::ChainFull => {
+                part_package.source_chain_headers = Some(chain_headers.clone());
+                part_package.source_chain_entries = Some(
+                    public_chain_entries_from_headers_dht(context.clone(), &chain_headers).await?,
+                );
+            }
+            ValidationPackageDefinition::Custom(string) => part_package.custom = Some(string),
+            ValidationPackageDefinition::WithLinks(links) => {
+                part_package.links = Some(existing_links(&context, &links)?)
+            }
+            ValidationPackageDefinition::WithReferences(addresses) => {
+                part_package.references = Some(existing_references(&context, &addresses)?)
+            }
+            // parts() flattens combined definitions
+            ValidationPackageDefinition::Combined(_) => unreachable!(),
+        };
+        package.union(part_package);
+    }
     Ok(package)
 }
 
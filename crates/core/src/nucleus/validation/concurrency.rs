@@ -0,0 +1,233 @@
+//! A counting semaphore that bounds how many validation callbacks run at once.
+//!
+//! Waiting for a free slot is a future, so validations waiting for their turn
+//! don't take up any of the validation workers.
+use futures::{
+    future::Future,
+    task::{Context, Poll, Waker},
+};
+use holochain_locksmith::Mutex;
+use std::{collections::HashMap, pin::Pin};
+
+struct SemaphoreState {
+    limit: Option<usize>,
+    running: usize,
+    /// Wakers of the `Acquire` futures waiting for a slot, by waiter id.
+    waiting: HashMap<u64, Waker>,
+    next_waiter: u64,
+}
+
+impl SemaphoreState {
+    fn has_free_slot(&self) -> bool {
+        self.limit
+            .map(|limit| self.running < limit.max(1))
+            .unwrap_or(true)
+    }
+
+    /// Takes the wakers of all waiters, for them to race for the freed slots.
+    /// Losers register again.
+    fn take_waiting(&mut self) -> Vec<Waker> {
+        self.waiting.drain().map(|(_, waker)| waker).collect()
+    }
+}
+
+/// Limits the number of concurrently running validation callbacks.
+/// Without a limit (the default) every validation runs immediately.
+pub struct ValidationSemaphore {
+    state: Mutex<SemaphoreState>,
+}
+
+/// Holds one of the semaphore's slots until it gets dropped.
+pub struct ValidationPermit<'a> {
+    semaphore: &'a ValidationSemaphore,
+}
+
+/// Future returned by [acquire](ValidationSemaphore::acquire).
+/// Dropping it gives up waiting for a slot.
+pub struct Acquire<'a> {
+    semaphore: &'a ValidationSemaphore,
+    waiter: Option<u64>,
+}
+
+impl ValidationSemaphore {
+    pub fn new(limit: Option<usize>) -> Self {
+        ValidationSemaphore {
+            state: Mutex::new(SemaphoreState {
+                limit,
+                running: 0,
+                waiting: HashMap::new(),
+                next_waiter: 0,
+            }),
+        }
+    }
+
+    /// Changes the maximum number of concurrent validations.
+    /// `None` removes the limit. Validations already running are not affected.
+    pub fn set_limit(&self, limit: Option<usize>) {
+        let waiting = {
+            let mut state = self.state.lock().expect("ValidationSemaphore poisoned");
+            state.limit = limit;
+            state.take_waiting()
+        };
+        waiting.into_iter().for_each(Waker::wake);
+    }
+
+    pub fn limit(&self) -> Option<usize> {
+        self.state
+            .lock()
+            .expect("ValidationSemaphore poisoned")
+            .limit
+    }
+
+    /// Number of validations currently holding a permit.
+    pub fn running(&self) -> usize {
+        self.state
+            .lock()
+            .expect("ValidationSemaphore poisoned")
+            .running
+    }
+
+    /// Resolves to a permit once a slot is free.
+    pub fn acquire(&self) -> Acquire<'_> {
+        Acquire {
+            semaphore: self,
+            waiter: None,
+        }
+    }
+}
+
+impl Default for ValidationSemaphore {
+    fn default() -> Self {
+        ValidationSemaphore::new(None)
+    }
+}
+
+impl<'a> Future for Acquire<'a> {
+    type Output = ValidationPermit<'a>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let semaphore = self.semaphore;
+        let mut state = semaphore
+            .state
+            .lock()
+            .expect("ValidationSemaphore poisoned");
+        if state.has_free_slot() {
+            state.running += 1;
+            if let Some(waiter) = self.waiter.take() {
+                state.waiting.remove(&waiter);
+            }
+            return Poll::Ready(ValidationPermit { semaphore });
+        }
+        let waiter = match self.waiter {
+            Some(waiter) => waiter,
+            None => {
+                let waiter = state.next_waiter;
+                state.next_waiter += 1;
+                waiter
+            }
+        };
+        state.waiting.insert(waiter, cx.waker().clone());
+        drop(state);
+        self.waiter = Some(waiter);
+        Poll::Pending
+    }
+}
+
+impl<'a> Drop for Acquire<'a> {
+    fn drop(&mut self) {
+        if let Some(waiter) = self.waiter.take() {
+            self.semaphore
+                .state
+                .lock()
+                .expect("ValidationSemaphore poisoned")
+                .waiting
+                .remove(&waiter);
+        }
+    }
+}
+
+impl<'a> Drop for ValidationPermit<'a> {
+    fn drop(&mut self) {
+        let waiting = {
+            let mut state = self
+                .semaphore
+                .state
+                .lock()
+                .expect("ValidationSemaphore poisoned");
+            state.running -= 1;
+            state.take_waiting()
+        };
+        waiting.into_iter().for_each(Waker::wake);
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use futures::{executor::block_on, task::noop_waker_ref};
+    use std::{sync::Arc, thread, time::Duration};
+
+    #[test]
+    fn concurrent_validations_never_exceed_limit() {
+        let semaphore = Arc::new(ValidationSemaphore::new(Some(2)));
+        // (currently running, peak)
+        let counters = Arc::new(Mutex::new((0usize, 0usize)));
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let semaphore = semaphore.clone();
+                let counters = counters.clone();
+                thread::spawn(move || {
+                    let _permit = block_on(semaphore.acquire());
+                    {
+                        let mut counters = counters.lock().unwrap();
+                        counters.0 += 1;
+                        counters.1 = counters.1.max(counters.0);
+                    }
+                    // pretend to be a heavy validator
+                    thread::sleep(Duration::from_millis(20));
+                    counters.lock().unwrap().0 -= 1;
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let peak = counters.lock().unwrap().1;
+        assert!(peak <= 2, "peak concurrency was {}", peak);
+        assert!(peak >= 1);
+        assert_eq!(semaphore.running(), 0);
+    }
+
+    #[test]
+    fn no_limit_does_not_block() {
+        let semaphore = ValidationSemaphore::default();
+        let permits: Vec<_> = (0..16).map(|_| block_on(semaphore.acquire())).collect();
+        assert_eq!(semaphore.running(), 16);
+        drop(permits);
+        assert_eq!(semaphore.running(), 0);
+    }
+
+    #[test]
+    fn waiting_for_a_slot_does_not_block_and_gets_woken_by_release() {
+        let semaphore = ValidationSemaphore::new(Some(1));
+        let permit = block_on(semaphore.acquire());
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        let mut waiting = semaphore.acquire();
+        assert!(Pin::new(&mut waiting).poll(&mut cx).is_pending());
+        let mut given_up = semaphore.acquire();
+        assert!(Pin::new(&mut given_up).poll(&mut cx).is_pending());
+        drop(given_up);
+
+        drop(permit);
+        assert!(semaphore.state.lock().unwrap().waiting.is_empty());
+        match Pin::new(&mut waiting).poll(&mut cx) {
+            Poll::Ready(_permit) => assert_eq!(semaphore.running(), 1),
+            Poll::Pending => panic!("Released slot was not handed to the waiting validation"),
+        }
+        assert_eq!(semaphore.running(), 0);
+    }
+}
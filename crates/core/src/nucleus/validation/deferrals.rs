@@ -0,0 +1,41 @@
+//! Dependencies that validations got deferred on.
+//!
+//! A validator that can't decide before certain entries are held defers its decision
+//! (see `ValidationError::UnresolvedDependencies`). The dependencies are kept here, by
+//! entry address, until the holding loop queues the pending validation again. From then
+//! on, holding one of the dependencies lets the validation run again right away.
+use holochain_locksmith::Mutex;
+use holochain_persistence_api::cas::content::Address;
+use std::collections::HashMap;
+
+pub struct Deferrals {
+    dependencies: Mutex<HashMap<Address, Vec<Address>>>,
+}
+
+impl Default for Deferrals {
+    fn default() -> Self {
+        Deferrals {
+            dependencies: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Deferrals {
+    /// Remembers what the validation of the entry at `address` waits for,
+    /// replacing what an earlier validation waited for.
+    pub(crate) fn record(&self, address: Address, dependencies: Vec<Address>) {
+        self.dependencies
+            .lock()
+            .expect("Deferrals poisoned")
+            .insert(address, dependencies);
+    }
+
+    /// Returns and forgets what the validation of the entry at `address` waits for.
+    pub(crate) fn take(&self, address: &Address) -> Vec<Address> {
+        self.dependencies
+            .lock()
+            .expect("Deferrals poisoned")
+            .remove(address)
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,91 @@
+//! Every decision of an app entry validator gets broadcast to the streams
+//! handed out by `Context::validation_stream()`, e.g. to keep an audit log.
+//!
+//! Each stream is a bounded channel. Events for a stream that is full get dropped
+//! for that stream, so a slow reader never holds up validation.
+use crate::nucleus::validation::ValidationResult;
+use futures::{channel::mpsc, Stream};
+use holochain_core_types::entry::entry_type::AppEntryType;
+use holochain_locksmith::Mutex;
+use holochain_persistence_api::cas::content::Address;
+use std::time::Duration;
+
+/// Number of events a validation stream buffers for its reader.
+pub const VALIDATION_STREAM_CAPACITY: usize = 100;
+
+/// One validation of an app entry.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationEvent {
+    pub entry_type: AppEntryType,
+    pub address: Address,
+    pub outcome: ValidationResult,
+    /// How long running the validation callback took
+    pub duration: Duration,
+}
+
+pub struct ValidationEvents {
+    subscribers: Mutex<Vec<mpsc::Sender<ValidationEvent>>>,
+}
+
+impl Default for ValidationEvents {
+    fn default() -> Self {
+        ValidationEvents {
+            subscribers: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl ValidationEvents {
+    pub(crate) fn subscribe(&self) -> impl Stream<Item = ValidationEvent> {
+        let (sender, receiver) = mpsc::channel(VALIDATION_STREAM_CAPACITY);
+        self.subscribers
+            .lock()
+            .expect("ValidationEvents poisoned")
+            .push(sender);
+        receiver
+    }
+
+    /// Sends the event to every stream, forgetting the streams that got dropped.
+    pub(crate) fn emit(&self, event: ValidationEvent) {
+        let mut subscribers = self.subscribers.lock().expect("ValidationEvents poisoned");
+        subscribers.retain(|subscriber| !subscriber.is_closed());
+        for subscriber in subscribers.iter_mut() {
+            // A full stream misses this event
+            let _ = subscriber.try_send(event.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::{
+        instance::tests::test_instance_and_context_by_name, workflows::author_entry::author_entry,
+    };
+    use futures::{executor::block_on, StreamExt};
+    use holochain_core_types::entry::{entry_type::EntryType, test_entry, test_entry_with_value};
+    use holochain_persistence_api::cas::content::AddressableContent;
+    use test_utils::create_test_dna_with_wat;
+
+    #[test]
+    fn validations_show_up_on_validation_stream() {
+        let netname = Some("validations_show_up_on_validation_stream");
+        let mut dna = create_test_dna_with_wat("test_zome", None);
+        dna.uuid = netname.unwrap().to_string();
+        let (_instance, context) =
+            test_instance_and_context_by_name(dna, "alice_validation_stream", netname).unwrap();
+        let mut stream = Box::pin(context.validation_stream());
+
+        let entries = vec![test_entry(), test_entry_with_value("\"second\"")];
+        for entry in entries.iter() {
+            let result = context.block_on(author_entry(entry, None, &context, &vec![]));
+            assert!(result.is_ok(), "author_entry() result = {:?}", result);
+        }
+
+        for entry in entries.iter() {
+            let event = block_on(stream.next()).expect("Stream ended");
+            assert_eq!(event.address, entry.address());
+            assert_eq!(EntryType::App(event.entry_type), entry.entry_type());
+            assert_eq!(event.outcome, Ok(()));
+        }
+    }
+}
@@ -0,0 +1,40 @@
+//! Links that app validators asked for while validating locally authored entries.
+//!
+//! Validation of an entry happens before it gets committed, so the hints are kept
+//! here, by entry address, until the authoring workflow has committed the entry
+//! and creates the links.
+use holochain_locksmith::Mutex;
+use holochain_persistence_api::cas::content::Address;
+use holochain_wasm_utils::api_serialization::validation::LinkSpec;
+use std::collections::HashMap;
+
+pub struct IndexHints {
+    hints: Mutex<HashMap<Address, Vec<LinkSpec>>>,
+}
+
+impl Default for IndexHints {
+    fn default() -> Self {
+        IndexHints {
+            hints: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl IndexHints {
+    /// Remembers the hints for the entry at `address`, replacing those of an earlier validation.
+    pub(crate) fn record(&self, address: Address, hints: Vec<LinkSpec>) {
+        self.hints
+            .lock()
+            .expect("IndexHints poisoned")
+            .insert(address, hints);
+    }
+
+    /// Returns and forgets the hints recorded for the entry at `address`.
+    pub(crate) fn take(&self, address: &Address) -> Vec<LinkSpec> {
+        self.hints
+            .lock()
+            .expect("IndexHints poisoned")
+            .remove(address)
+            .unwrap_or_default()
+    }
+}
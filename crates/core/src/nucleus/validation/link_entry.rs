@@ -105,11 +105,25 @@ pub async fn validate_link_entry(
         _ => Err(ValidationError::Fail("Entry is not link".to_string())),
     }?;
 
+    let agent_link_count = match entry {
+        Entry::LinkAdd(ref link_add) => context.state().map_or(0, |state| {
+            state
+                .dht()
+                .agent_link_count(link.link_type(), link_add.agent_id(), &address)
+        }),
+        _ => 0,
+    };
+
+    let target_entry_type = target.entry_type().to_string();
     let params = LinkValidationArgs {
         entry_type: link_definition_path.entry_type_name,
         link,
         direction: link_definition_path.direction,
         validation_data,
+        base_entry: Some(base),
+        target_entry: Some(target),
+        agent_link_count,
+        target_type: Some(target_entry_type),
     };
     let call = CallbackFnCall::new(
         &link_definition_path.zome_name,
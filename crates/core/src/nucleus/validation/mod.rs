@@ -12,9 +12,15 @@ use std::sync::Arc;
 
 mod agent_entry;
 mod app_entry;
+pub mod batch;
 pub mod build_from_dht;
+pub mod concurrency;
+pub mod deferrals;
+pub mod events;
 mod header_address;
+pub mod index_hints;
 mod link_entry;
+pub mod pool;
 mod provenances;
 mod remove_entry;
 
@@ -142,6 +148,7 @@ pub fn process_validation_err(
                        addr,
                        dependencies,
             );
+            context.deferrals.record(addr, dependencies);
             HolochainError::ValidationPending
         }
         ValidationError::Fail(_) => {
@@ -0,0 +1,233 @@
+//! A dedicated set of worker threads that run validation callbacks.
+//!
+//! Running the WASM validation callback on a worker means the future awaiting
+//! the result only waits for a message, so a slow validator doesn't keep the
+//! calling thread busy.
+//!
+//! Dropping that future cancels the validation: a validation that didn't start yet
+//! never runs, and one that is running sees [validation_cancelled](validation_cancelled)
+//! turn true, which fails its oracle queries and entry fetches right away.
+use futures::{channel::oneshot, executor::ThreadPool, Future};
+use holochain_core_types::error::HolochainError;
+use holochain_locksmith::RwLock;
+use std::{
+    cell::RefCell,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+thread_local! {
+    /// Set while a worker runs a validation, to whether its future got dropped.
+    static CANCELLED: RefCell<Option<Arc<AtomicBool>>> = RefCell::new(None);
+}
+
+/// Whether the future awaiting the validation running on this thread got dropped.
+/// Always false outside of validations.
+pub fn validation_cancelled() -> bool {
+    CANCELLED.with(|cancelled| {
+        cancelled
+            .borrow()
+            .as_ref()
+            .map(|cancelled| cancelled.load(Ordering::SeqCst))
+            .unwrap_or(false)
+    })
+}
+
+/// Cancels the validation once the future owning it gets dropped.
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Number of validation workers a context starts with.
+pub const DEFAULT_VALIDATION_WORKERS: usize = 4;
+
+/// Runs validations on a configurable number of worker threads.
+pub struct ValidationPool {
+    workers: RwLock<(usize, ThreadPool)>,
+}
+
+impl ValidationPool {
+    /// Creates a pool with `workers` threads (at least one).
+    pub fn new(workers: usize) -> Self {
+        let workers = workers.max(1);
+        ValidationPool {
+            workers: RwLock::new((workers, Self::create_thread_pool(workers))),
+        }
+    }
+
+    fn create_thread_pool(workers: usize) -> ThreadPool {
+        ThreadPool::builder()
+            .pool_size(workers)
+            .name_prefix("validation-worker-")
+            .create()
+            .expect("Could not create thread pool for validations")
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers.read().expect("ValidationPool poisoned").0
+    }
+
+    /// Replaces the worker threads with `workers` new ones (at least one).
+    /// Validations already submitted finish on the old workers.
+    pub fn set_workers(&self, workers: usize) {
+        let workers = workers.max(1);
+        *self.workers.write().expect("ValidationPool poisoned") =
+            (workers, Self::create_thread_pool(workers));
+    }
+
+    /// Runs `validation` on one of the workers.
+    /// The returned future resolves to its result, dropping it cancels the validation.
+    /// Panics if `validation` panicked, just as if it had run on the awaiting thread.
+    /// Fails if the worker went away without sending the result.
+    pub fn run<T, F>(&self, validation: F) -> impl Future<Output = Result<T, HolochainError>>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let cancel_on_drop = CancelOnDrop(cancelled.clone());
+        self.workers
+            .read()
+            .expect("ValidationPool poisoned")
+            .1
+            .spawn_ok(async move {
+                if cancelled.load(Ordering::SeqCst) {
+                    return;
+                }
+                CANCELLED.with(|current| current.replace(Some(cancelled)));
+                // Catching the panic keeps the worker thread alive for the next validation
+                let result = panic::catch_unwind(AssertUnwindSafe(validation));
+                CANCELLED.with(|current| current.replace(None));
+                // The receiver might be gone already, nobody is interested in the result then
+                let _ = sender.send(result);
+            });
+        async move {
+            let _cancel_on_drop = cancel_on_drop;
+            match receiver.await {
+                Ok(Ok(result)) => Ok(result),
+                Ok(Err(panic)) => panic::resume_unwind(panic),
+                Err(_) => Err(HolochainError::ErrorGeneric(
+                    "Validation worker dropped the result".to_string(),
+                )),
+            }
+        }
+    }
+}
+
+impl Default for ValidationPool {
+    fn default() -> Self {
+        ValidationPool::new(DEFAULT_VALIDATION_WORKERS)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::nucleus::validation::concurrency::ValidationSemaphore;
+    use futures::{executor::block_on, future::join_all};
+    use std::{
+        collections::HashSet,
+        sync::{atomic::AtomicUsize, mpsc},
+        thread,
+        time::Duration,
+    };
+
+    #[test]
+    fn validations_complete_across_workers() {
+        let pool = ValidationPool::new(3);
+
+        let validations: Vec<_> = (0..12)
+            .map(|i| {
+                pool.run(move || {
+                    // pretend to be a slow validator
+                    thread::sleep(Duration::from_millis(20));
+                    (i, thread::current().name().map(String::from))
+                })
+            })
+            .collect();
+        let results = block_on(join_all(validations))
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .expect("Workers send back every result");
+
+        let completed: Vec<_> = results.iter().map(|(i, _)| *i).collect();
+        assert_eq!(completed, (0..12).collect::<Vec<_>>());
+
+        let threads: HashSet<_> = results
+            .into_iter()
+            .map(|(_, name)| name.expect("Worker threads are named"))
+            .collect();
+        assert!(threads
+            .iter()
+            .all(|name| name.starts_with("validation-worker-")));
+        assert!(
+            threads.len() > 1 && threads.len() <= 3,
+            "validations ran on {} threads",
+            threads.len()
+        );
+    }
+
+    #[test]
+    fn can_change_number_of_workers() {
+        let pool = ValidationPool::default();
+        assert_eq!(pool.workers(), DEFAULT_VALIDATION_WORKERS);
+        pool.set_workers(0);
+        assert_eq!(pool.workers(), 1);
+        assert_eq!(block_on(pool.run(|| 7)), Ok(7));
+    }
+
+    #[test]
+    fn panicking_validation_panics_awaiting_thread_and_keeps_worker() {
+        let pool = ValidationPool::new(1);
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            block_on(pool.run(|| panic!("bad validator")))
+        }));
+        assert!(result.is_err());
+        assert_eq!(block_on(pool.run(|| 7)), Ok(7));
+    }
+
+    #[test]
+    fn dropped_validation_gets_aborted_and_releases_its_permit() {
+        let pool = ValidationPool::new(1);
+        let semaphore = Arc::new(ValidationSemaphore::new(Some(1)));
+        let (started_sender, started) = mpsc::channel();
+
+        let in_flight = {
+            let semaphore = semaphore.clone();
+            pool.run(move || {
+                let _permit = block_on(semaphore.acquire());
+                started_sender.send(()).unwrap();
+                // like a validator waiting for an oracle that is slow to answer
+                while !validation_cancelled() {
+                    thread::sleep(Duration::from_millis(1));
+                }
+            })
+        };
+        let queued_runs = Arc::new(AtomicUsize::new(0));
+        let queued = {
+            let queued_runs = queued_runs.clone();
+            pool.run(move || queued_runs.fetch_add(1, Ordering::SeqCst))
+        };
+
+        started
+            .recv_timeout(Duration::from_secs(5))
+            .expect("Validation did not start");
+        drop(in_flight);
+        drop(queued);
+
+        // The single worker only gets to this once the aborted validation returned
+        assert_eq!(
+            block_on(pool.run(move || (semaphore.running(), validation_cancelled()))),
+            Ok((0, false))
+        );
+        assert_eq!(queued_runs.load(Ordering::SeqCst), 0);
+    }
+}
@@ -4,13 +4,15 @@ use crate::{
         actions::{
             get_entry::get_entry_from_dht, run_validation_callback::run_validation_callback,
         },
-        validation::{entry_to_validation_data, ValidationError, ValidationResult},
+        validation::{
+            app_entry::entry_validation_args, entry_to_validation_data, ValidationError,
+            ValidationResult,
+        },
         CallbackFnCall,
     },
 };
 use holochain_core_types::{entry::Entry, validation::ValidationData};
 use holochain_persistence_api::cas::content::AddressableContent;
-use holochain_wasm_utils::api_serialization::validation::EntryValidationArgs;
 use std::sync::Arc;
 
 #[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
@@ -38,12 +40,10 @@ pub async fn validate_remove_entry(
         .get_zome_name_for_app_entry_type(&app_entry_type)
         .ok_or(ValidationError::NotImplemented)?;
 
-    let params = EntryValidationArgs {
-        validation_data: entry_to_validation_data(context.clone(), &entry, None, validation_data)
-            .map_err(|_| {
-            ValidationError::Fail("Could not get entry validation".to_string())
-        })?,
-    };
+    let validation_data = entry_to_validation_data(context.clone(), &entry, None, validation_data)
+        .map_err(|_| ValidationError::Fail("Could not get entry validation".to_string()))?;
+    let params =
+        entry_validation_args(context, &dna, &zome_name, &entry.address(), validation_data);
 
     let call = CallbackFnCall::new(&zome_name, "__hdk_validate_app_entry", params);
     run_validation_callback(entry.address(), call, context).await
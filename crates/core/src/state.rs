@@ -28,6 +28,15 @@ use holochain_persistence_api::{
 };
 
 use crate::dht::dht_store::DhtStoreSnapshot;
+#[cfg(test)]
+use crate::{
+    dht::aspect_map::AspectMap,
+    network::actions::{NetworkActionResponse, Response},
+};
+#[cfg(test)]
+use holochain_net::{
+    connection::net_connection::NetHandler, p2p_config::P2pConfig, p2p_network::P2pNetwork,
+};
 use std::{convert::TryInto, sync::Arc, time::SystemTime};
 
 pub const ACTION_PRUNE_MS: u64 = 60000;
@@ -79,7 +88,7 @@ impl State {
                 ChainStore::new(chain_cas.clone()),
                 context.agent_id.address(),
             )),
-            dht: Arc::new(DhtStore::new(dht_cas.clone(), eav)),
+            dht: Arc::new(DhtStore::new(dht_cas.clone(), eav).with_context_config(&context)),
             network: Arc::new(NetworkState::new()),
             conductor_api: context.conductor_api.clone(),
         }
@@ -107,6 +116,7 @@ impl State {
         mut nucleus_state: NucleusState,
         dht_store: DhtStore,
     ) -> Self {
+        let dht_store = dht_store.with_context_config(&context);
         let cas = context.dht_storage.clone();
         //let eav = context.eav_storage.clone();
 
@@ -365,6 +375,57 @@ impl StateWrapper {
             .conductor_api
             .clone()
     }
+
+    /// Replaces the DHT's holding map, bypassing the action pipeline.
+    #[cfg(test)]
+    pub(crate) fn set_holding_map(&mut self, holding_map: AspectMap) {
+        let state = self.state.as_mut().expect("Tried to use dropped state");
+        let mut dht = (*state.dht).clone();
+        dht.set_holding_map(holding_map);
+        state.dht = Arc::new(dht);
+    }
+
+    /// Initializes the network state with an in-memory network that nobody else is on,
+    /// bypassing the action pipeline. Keeps the network if it was initialized already.
+    #[cfg(test)]
+    pub(crate) fn set_network_initialized(&mut self) {
+        let state = self.state.as_mut().expect("Tried to use dropped state");
+        if state.network.initialized().is_ok() {
+            return;
+        }
+        let mut network = (*state.network).clone();
+        network.network = Some(
+            P2pNetwork::new(
+                NetHandler::new(Box::new(|_| Ok(()))),
+                P2pConfig::new_with_unique_memory_backend(),
+                None,
+                None,
+                None,
+            )
+            .expect("Could not create test network"),
+        );
+        network.dna_address = Some(Address::from("test-dna"));
+        network.agent_id = Some("test-agent".to_string());
+        state.network = Arc::new(network);
+    }
+
+    /// Records `response` as the network's response to the action with id `action_id`,
+    /// bypassing the action pipeline. Initializes the network, see `set_network_initialized()`.
+    #[cfg(test)]
+    pub(crate) fn set_network_action_response(
+        &mut self,
+        action_id: &str,
+        response: NetworkActionResponse,
+    ) {
+        self.set_network_initialized();
+        let state = self.state.as_mut().expect("Tried to use dropped state");
+        let mut network = (*state.network).clone();
+        network.actions.insert(
+            ActionWrapper::key_for_id(action_id),
+            Response::from(response),
+        );
+        state.network = Arc::new(network);
+    }
 }
 
 impl From<State> for StateWrapper {
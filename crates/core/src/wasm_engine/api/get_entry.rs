@@ -1,7 +1,10 @@
 use crate::{
+    nucleus::validation::pool::validation_cancelled,
     wasm_engine::{api::ZomeApiResult, Runtime},
     workflows::get_entry_result::get_entry_result_workflow,
 };
+use holochain_core_types::error::HolochainError;
+use holochain_json_api::json::JsonString;
 use holochain_wasm_utils::api_serialization::get_entry::GetEntryArgs;
 use std::convert::TryFrom;
 use wasmi::{RuntimeArgs, RuntimeValue};
@@ -9,6 +12,7 @@ use wasmi::{RuntimeArgs, RuntimeValue};
 /// ZomeApiFunction::GetAppEntry function code
 /// args: [0] encoded MemoryAllocation as u64
 /// Expected complex argument: GetEntryArgs
+/// Fails right away when called by a validator whose validation got cancelled.
 /// Returns an HcApiReturnCode as I64
 #[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
 pub fn invoke_get_entry(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
@@ -27,6 +31,11 @@ pub fn invoke_get_entry(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiRes
             return ribosome_error_code!(ArgumentDeserializationFailed);
         }
     };
+    if validation_cancelled() {
+        return runtime.store_result::<JsonString>(Err(HolochainError::ErrorGeneric(
+            "Validation got cancelled".to_string(),
+        )));
+    }
     // Create workflow future and block on it
     let result = context.block_on(get_entry_result_workflow(&context, &input));
     // Store result in wasm memory
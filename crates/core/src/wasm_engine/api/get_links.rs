@@ -117,19 +117,21 @@ pub mod tests {
     pub fn add_links(initialized_context: Arc<Context>, links: Vec<Link>) {
         links.iter().for_each(|link| {
             println!("adding link {:?}", link);
+            let link_data = LinkData::add_from_link(link, test_chain_header(), test_agent_id());
+            let entry = Entry::LinkAdd(link_data.clone());
             assert!(initialized_context //commit the AddLink entry first
-                .block_on(commit_entry(
-                    link.add_entry(test_chain_header(), test_agent_id()),
-                    None,
-                    &initialized_context
-                ))
+                .block_on(commit_entry(entry.clone(), None, &initialized_context))
                 .is_ok());
+            // the aspect has to come with the header that got committed for the entry
+            let header = initialized_context
+                .state()
+                .unwrap()
+                .agent()
+                .get_most_recent_header_for_entry(&entry)
+                .expect("The committed AddLink entry must have a header");
             assert!(initialized_context
                 .block_on(hold_aspect(
-                    EntryAspect::LinkAdd(
-                        LinkData::add_from_link(link, test_chain_header(), test_agent_id()),
-                        test_chain_header(),
-                    ),
+                    EntryAspect::LinkAdd(link_data, header),
                     initialized_context.clone()
                 ))
                 .is_ok());
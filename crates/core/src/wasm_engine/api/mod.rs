@@ -24,6 +24,7 @@ pub mod send;
 pub mod sign;
 pub mod sleep;
 pub mod update_entry;
+pub mod validation_oracle;
 pub mod verify_signature;
 
 use crate::wasm_engine::{
@@ -53,6 +54,7 @@ use crate::wasm_engine::{
         sign::invoke_sign_one_time,
         sleep::invoke_sleep,
         update_entry::invoke_update_entry,
+        validation_oracle::invoke_validation_oracle_query,
         verify_signature::invoke_verify_signature,
     },
     runtime::Runtime,
@@ -154,6 +156,10 @@ link_zome_api! {
 
     ///send a meta
     "hc_meta",Meta,invoke_meta;
+
+    /// Ask the validation oracle set up by the conductor
+    /// validation_oracle_query(request: JsonString) -> JsonString
+    "hc_validation_oracle_query", ValidationOracleQuery, invoke_validation_oracle_query;
 }
 
 #[cfg(test)]
@@ -1,10 +1,27 @@
-use crate::wasm_engine::{api::ZomeApiResult, Runtime};
-use std::{convert::TryFrom, thread, time::Duration};
+use crate::{
+    context::Context,
+    wasm_engine::{api::ZomeApiResult, Runtime},
+};
+use holochain_core_types::{
+    eav::{Attribute, EaviQuery, EntityAttributeValueIndex},
+    error::{HcResult, HolochainError},
+};
+use holochain_persistence_api::{cas::content::Address, eav::IndexFilter};
+use std::{
+    convert::TryFrom,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 use wasmi::{RuntimeArgs, RuntimeValue};
 
 /// ZomeApiFunction::Sleep function code
 /// args: [0] encoded MemoryAllocation as u64
 /// Expected argument: u64
+/// Sleeps at most as long as the context's `FutureTimeouts::sleep_max`.
 /// Returns an HcApiReturnCode as I64
 #[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
 pub fn invoke_sleep(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
@@ -15,7 +32,199 @@ pub fn invoke_sleep(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult
         Err(..) => return ribosome_error_code!(ArgumentDeserializationFailed),
     };
 
-    thread::sleep(Duration::from_nanos(nanos));
+    let context = runtime.context()?;
+    context
+        .timer_provider()
+        .sleep(capped_sleep(&context, Duration::from_nanos(nanos)));
 
     ribosome_success!()
 }
+
+/// The clock the `sleep` zome API functions sleep by, see `Context::set_timer_provider()`.
+pub trait TimerProvider: Send + Sync {
+    /// Time since the UNIX epoch
+    fn now(&self) -> Duration;
+    fn sleep(&self, duration: Duration);
+}
+
+/// Sleeps for real, by the system clock. The default.
+#[derive(Default)]
+pub struct RealTimer;
+
+impl TimerProvider for RealTimer {
+    fn now(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// Virtual time for tests: sleeping advances the time instead of waiting,
+/// so even long sleeps return right away.
+#[derive(Default)]
+pub struct VirtualTimer {
+    nanos: AtomicU64,
+}
+
+impl VirtualTimer {
+    pub fn starting_at(now: Duration) -> Self {
+        VirtualTimer {
+            nanos: AtomicU64::new(now.as_nanos() as u64),
+        }
+    }
+
+    /// Lets time pass without anybody sleeping.
+    pub fn advance(&self, duration: Duration) {
+        self.nanos
+            .fetch_add(duration.as_nanos() as u64, Ordering::SeqCst);
+    }
+}
+
+impl TimerProvider for VirtualTimer {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::SeqCst))
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+/// Shortens `duration` to the context's `FutureTimeouts::sleep_max`, if there is one.
+fn capped_sleep(context: &Context, duration: Duration) -> Duration {
+    context
+        .future_timeouts()
+        .sleep_max
+        .map(|sleep_max| duration.min(sleep_max))
+        .unwrap_or(duration)
+}
+
+/// Sleeps for `nanos`, but remembers the wake up deadline under `key` in the
+/// instance's persistent EAV storage. If a deadline was already recorded under `key`
+/// (e.g. before the instance got restarted), only the time remaining until that deadline
+/// is slept. A key stands for one sleep, once its deadline passed it doesn't sleep anymore.
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+pub fn invoke_sleep_persistent(context: &Arc<Context>, nanos: u64, key: String) -> HcResult<()> {
+    let timer = context.timer_provider();
+    let remaining = persistent_sleep_remaining(context, nanos, &key, timer.now())?;
+    timer.sleep(capped_sleep(context, remaining));
+    Ok(())
+}
+
+fn sleep_entity(key: &str) -> Address {
+    Address::from(format!("sleep-deadline:{}", key))
+}
+
+/// Time left until the deadline stored under `key`.
+/// Stores a deadline `nanos` from `now` if there is none yet.
+fn persistent_sleep_remaining(
+    context: &Arc<Context>,
+    nanos: u64,
+    key: &str,
+    now: Duration,
+) -> HcResult<Duration> {
+    let entity = sleep_entity(key);
+    let stored = context
+        .eav_storage
+        .read()
+        .unwrap()
+        .fetch_eavi(&EaviQuery::new(
+            Some(entity.clone()).into(),
+            Some(Attribute::SleepDeadline).into(),
+            None.into(),
+            IndexFilter::LatestByAttribute,
+            None,
+        ))?;
+
+    let deadline = match stored.into_iter().next() {
+        Some(eavi) => String::from(eavi.value())
+            .parse::<u64>()
+            .map(Duration::from_nanos)
+            .map_err(|_| {
+                HolochainError::ErrorGeneric(format!("Invalid sleep deadline stored for {}", key))
+            })?,
+        None => {
+            let deadline = now + Duration::from_nanos(nanos);
+            let eavi = EntityAttributeValueIndex::new(
+                &entity,
+                &Attribute::SleepDeadline,
+                &Address::from(deadline.as_nanos().to_string()),
+            )?;
+            context.eav_storage.write().unwrap().add_eavi(&eavi)?;
+            deadline
+        }
+    };
+    Ok(deadline.checked_sub(now).unwrap_or_default())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::instance::tests::test_context;
+
+    #[test]
+    fn persistent_sleep_resumes_with_remaining_time_after_restart() {
+        let context = test_context("alice", None);
+        let start = Duration::from_secs(1_000);
+        let nanos = Duration::from_secs(10).as_nanos() as u64;
+
+        assert_eq!(
+            persistent_sleep_remaining(&context, nanos, "nightly-job", start),
+            Ok(Duration::from_secs(10))
+        );
+
+        // The instance restarts 4 seconds later with the same persistent storage
+        // and the sleep gets invoked again.
+        let mut restarted = (*test_context("alice", None)).clone();
+        restarted.eav_storage = context.eav_storage.clone();
+        let restarted = Arc::new(restarted);
+        let later = start + Duration::from_secs(4);
+
+        assert_eq!(
+            persistent_sleep_remaining(&restarted, nanos, "nightly-job", later),
+            Ok(Duration::from_secs(6))
+        );
+        assert_eq!(
+            persistent_sleep_remaining(
+                &restarted,
+                nanos,
+                "nightly-job",
+                start + Duration::from_secs(11)
+            ),
+            Ok(Duration::from_secs(0))
+        );
+        // other keys are separate sleeps
+        assert_eq!(
+            persistent_sleep_remaining(&restarted, nanos, "other-job", later),
+            Ok(Duration::from_secs(10))
+        );
+    }
+
+    #[test]
+    fn long_sleep_resolves_right_away_in_virtual_time() {
+        let context = test_context("alice", None);
+        let start = Duration::from_secs(1_000);
+        let timer = Arc::new(VirtualTimer::starting_at(start));
+        context.set_timer_provider(timer.clone());
+        let ten_hours = Duration::from_secs(10 * 60 * 60);
+
+        let started = std::time::Instant::now();
+        assert_eq!(
+            invoke_sleep_persistent(&context, ten_hours.as_nanos() as u64, "long-job".into()),
+            Ok(())
+        );
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(timer.now(), start + ten_hours);
+
+        // The deadline passed in virtual time, so the same sleep doesn't sleep anymore
+        assert_eq!(
+            invoke_sleep_persistent(&context, ten_hours.as_nanos() as u64, "long-job".into()),
+            Ok(())
+        );
+        assert_eq!(timer.now(), start + ten_hours);
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{
+    nucleus::validation::pool::validation_cancelled,
+    wasm_engine::{api::ZomeApiResult, Runtime},
+};
+use holochain_core_types::error::HolochainError;
+use holochain_json_api::json::JsonString;
+use std::sync::Arc;
+use wasmi::RuntimeArgs;
+
+/// Answers the requests validators make to an external oracle, e.g. a bridged service.
+/// Set by the conductor with `Context::set_validation_oracle()`.
+pub type ValidationOracle = Arc<dyn Fn(JsonString) -> Result<JsonString, String> + Send + Sync>;
+
+/// ZomeApiFunction::ValidationOracleQuery function code
+/// args: [0] encoded MemoryAllocation as u64
+/// Expected complex argument: the request as JsonString
+/// Fails if no oracle is set or the oracle could not answer the request,
+/// and without asking the oracle if the validation got cancelled.
+/// Returns an HcApiReturnCode as I64
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+pub fn invoke_validation_oracle_query(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    let context = runtime.context()?;
+    let request = runtime.load_json_string_from_args(&args);
+
+    let result = match context.validation_oracle() {
+        _ if validation_cancelled() => Err(HolochainError::ErrorGeneric(
+            "Validation got cancelled".to_string(),
+        )),
+        Some(oracle) => oracle(request).map_err(|error| {
+            log_warn!(
+                context,
+                "zome: validation oracle could not answer request: {}",
+                error
+            );
+            HolochainError::ErrorGeneric(format!("Validation oracle failed: {}", error))
+        }),
+        None => Err(HolochainError::ErrorGeneric(
+            "No validation oracle configured".to_string(),
+        )),
+    };
+
+    runtime.store_result(result)
+}
+
+#[cfg(test)]
+#[cfg(not(windows))]
+pub mod tests {
+    use super::*;
+    use crate::{
+        context::Context,
+        wasm_engine::{
+            api::{
+                tests::{test_zome_api_function, test_zome_api_function_call},
+                ZomeApiFunction,
+            },
+            Defn,
+        },
+    };
+    use holochain_core_types::error::ZomeApiInternalResult;
+    use std::convert::TryFrom;
+
+    /// Approves amounts up to 100, like a payment service limiting transfers would
+    fn stub_oracle() -> ValidationOracle {
+        Arc::new(|request: JsonString| {
+            let request: serde_json::Value =
+                serde_json::from_str(&String::from(request)).map_err(|e| e.to_string())?;
+            let amount = request["amount"]
+                .as_u64()
+                .ok_or_else(|| "request without amount".to_string())?;
+            Ok(JsonString::from_json(&format!(
+                r#"{{"approved":{}}}"#,
+                amount <= 100
+            )))
+        })
+    }
+
+    /// A validator for transfers that leaves the decision to the oracle
+    fn validate_transfer(context: Arc<Context>, amount: u64) -> Result<(), String> {
+        let call_result = test_zome_api_function_call(
+            context,
+            format!(r#"{{"amount":{}}}"#, amount).into_bytes(),
+        );
+        let result = ZomeApiInternalResult::try_from(call_result).map_err(|e| e.to_string())?;
+        if !result.ok {
+            return Err(result.error);
+        }
+        let response: serde_json::Value =
+            serde_json::from_str(&result.value).map_err(|e| e.to_string())?;
+        if response["approved"] == serde_json::Value::Bool(true) {
+            Ok(())
+        } else {
+            Err(format!("Oracle did not approve transfer of {}", amount))
+        }
+    }
+
+    #[test]
+    fn validator_decides_by_oracle_response() {
+        let (_, context) = test_zome_api_function(
+            ZomeApiFunction::ValidationOracleQuery.as_str(),
+            r#"{"amount":1}"#.as_bytes().to_vec(),
+        );
+        // Without an oracle validators can't get an answer
+        assert!(validate_transfer(context.clone(), 1).is_err());
+
+        context.set_validation_oracle(Some(stub_oracle()));
+        assert_eq!(validate_transfer(context.clone(), 50), Ok(()));
+        assert_eq!(
+            validate_transfer(context.clone(), 500),
+            Err("Oracle did not approve transfer of 500".to_string())
+        );
+    }
+}
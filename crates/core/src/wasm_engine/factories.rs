@@ -1,6 +1,6 @@
 use crate::wasm_engine::api::ZomeApiFunction;
 use holochain_core_types::error::HolochainError;
-use std::{str::FromStr, sync::Arc};
+use std::{cell::RefCell, str::FromStr, sync::Arc};
 use wasmi::{
     self, Error as InterpreterError, FuncInstance, FuncRef, ImportsBuilder, Module,
     ModuleImportResolver, ModuleInstance, ModuleRef, NopExternals, Signature, ValueType,
@@ -12,10 +12,51 @@ pub fn wasm_module_factory(wasm: Arc<Vec<u8>>) -> Result<Module, HolochainError>
     wasmi::Module::from_buffer(&*wasm).map_err(|e| HolochainError::ErrorGeneric(e.into()))
 }
 
+/// Names of the host functions the module imports, i.e. what a host has to provide
+/// to run it. Only the Zome API functions a zome actually calls end up as imports of its WASM.
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+pub fn wasm_required_host_functions(module: &Module) -> Vec<String> {
+    struct RecordingImportResolver(RefCell<Vec<String>>);
+    impl ModuleImportResolver for RecordingImportResolver {
+        fn resolve_func(
+            &self,
+            field_name: &str,
+            signature: &Signature,
+        ) -> Result<FuncRef, InterpreterError> {
+            self.0.borrow_mut().push(field_name.to_string());
+            Ok(FuncInstance::alloc_host(signature.clone(), 0))
+        }
+    }
+
+    let resolver = RecordingImportResolver(RefCell::new(Vec::new()));
+    let mut imports = ImportsBuilder::new();
+    imports.push_resolver("env", &resolver);
+    // Instantiating resolves all the imports, the instance itself is not needed
+    let _ = ModuleInstance::new(&module, &imports);
+    resolver.0.into_inner()
+}
+
+/// The host functions the module imports that are not Zome API functions of this host.
+pub fn missing_host_functions(module: &Module) -> Vec<String> {
+    wasm_required_host_functions(module)
+        .into_iter()
+        .filter(|name| ZomeApiFunction::from_str(name).is_err())
+        .collect()
+}
+
 /// Creates a runnable WASM module instance from a module reference.
 /// Adds the Holochain specific API functions as imports.
+/// Fails without instantiating the module if it imports host functions we don't provide.
 #[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
 pub fn wasm_instance_factory(module: &Module) -> Result<ModuleRef, HolochainError> {
+    let missing = missing_host_functions(module);
+    if !missing.is_empty() {
+        return Err(HolochainError::RibosomeFailed(format!(
+            "Module imports host functions this conductor doesn't provide: {}",
+            missing.join(", ")
+        )));
+    }
+
     // invoke_index and resolve_func work together to enable callable host functions
     // within WASM modules, which is how the core API functions
     // read about the Externals trait for more detail
@@ -73,3 +114,51 @@ pub fn wasm_instance_factory(module: &Module) -> Result<ModuleRef, HolochainErro
         .run_start(&mut NopExternals)
         .map_err(|_| HolochainError::RibosomeFailed("Module failed to start".to_string()))
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn module_importing(functions: &[&str]) -> Module {
+        let imports: String = functions
+            .iter()
+            .map(|name| {
+                format!(
+                    "(import \"env\" \"{}\" (func (param i64) (result i64)))",
+                    name
+                )
+            })
+            .collect();
+        let wasm = wabt::wat2wasm(format!("(module {})", imports)).unwrap();
+        wasm_module_factory(Arc::new(wasm)).unwrap()
+    }
+
+    #[test]
+    fn required_host_functions_are_the_ones_the_module_imports() {
+        let module = module_importing(&["hc_debug", "hc_validation_oracle_query"]);
+        assert_eq!(
+            wasm_required_host_functions(&module),
+            vec![
+                "hc_debug".to_string(),
+                "hc_validation_oracle_query".to_string()
+            ]
+        );
+        assert!(missing_host_functions(&module).is_empty());
+
+        let module = module_importing(&["hc_debug"]);
+        assert_eq!(
+            wasm_required_host_functions(&module),
+            vec!["hc_debug".to_string()]
+        );
+    }
+
+    #[test]
+    fn module_importing_unknown_host_function_does_not_get_instantiated() {
+        let module = module_importing(&["hc_debug", "hc_teleport"]);
+        assert_eq!(
+            missing_host_functions(&module),
+            vec!["hc_teleport".to_string()]
+        );
+        assert!(wasm_instance_factory(&module).is_err());
+    }
+}
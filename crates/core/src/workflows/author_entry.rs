@@ -8,16 +8,20 @@ use crate::{
     },
 };
 
+use futures::future::{FutureExt, LocalBoxFuture};
 use holochain_core_types::{
     entry::Entry,
     error::HolochainError,
+    link::{link_data::LinkData, Link, LinkActionKind},
     signature::Provenance,
-    validation::{EntryLifecycle, ValidationData},
+    validation::{EntryLifecycle, ValidationData, ValidationOrigin},
 };
 
 use holochain_persistence_api::cas::content::{Address, AddressableContent};
 
-use holochain_wasm_utils::api_serialization::commit_entry::CommitEntryResult;
+use holochain_wasm_utils::api_serialization::{
+    commit_entry::CommitEntryResult, validation::LinkSpec,
+};
 
 use crate::wasm_engine::callback::links_utils::get_link_entries;
 use std::{sync::Arc, vec::Vec};
@@ -28,6 +32,42 @@ pub async fn author_entry<'a>(
     maybe_link_update_delete: Option<Address>,
     context: &'a Arc<Context>,
     provenances: &'a Vec<Provenance>,
+) -> Result<CommitEntryResult, HolochainError> {
+    author_entry_with_origin(
+        entry,
+        maybe_link_update_delete,
+        context,
+        provenances,
+        ValidationOrigin::Local,
+    )
+    .await
+}
+
+/// Like [author_entry](author_entry), but for entries the system produces itself.
+/// This is the only way entries of a derived type (see `EntryTypeDef::derived`)
+/// get onto the source chain, their validators see `ValidationOrigin::System`.
+#[holochain_tracing_macros::newrelic_autotrace(HOLOCHAIN_CORE)]
+pub async fn author_derived_entry<'a>(
+    entry: &'a Entry,
+    maybe_link_update_delete: Option<Address>,
+    context: &'a Arc<Context>,
+) -> Result<CommitEntryResult, HolochainError> {
+    author_entry_with_origin(
+        entry,
+        maybe_link_update_delete,
+        context,
+        &vec![],
+        ValidationOrigin::System,
+    )
+    .await
+}
+
+async fn author_entry_with_origin<'a>(
+    entry: &'a Entry,
+    maybe_link_update_delete: Option<Address>,
+    context: &'a Arc<Context>,
+    provenances: &'a Vec<Provenance>,
+    origin: ValidationOrigin,
 ) -> Result<CommitEntryResult, HolochainError> {
     let address = entry.address();
     log_debug!(
@@ -47,10 +87,7 @@ pub async fn author_entry<'a>(
 
     // 1. Build the context needed for validation of the entry
     let validation_package = build_validation_package(&entry, context.clone(), provenances)?;
-    let validation_data = ValidationData {
-        package: validation_package,
-        lifecycle: EntryLifecycle::Chain,
-    };
+    let validation_data = ValidationData::new(validation_package, EntryLifecycle::Chain, origin);
 
     // 2. Validate the entry
     log_debug!(
@@ -106,8 +143,50 @@ pub async fn author_entry<'a>(
         address
     );
 
+    // 6. Create the links the entry's validator asked for
+    let index_hints = context.index_hints.take(&address);
+    if !index_hints.is_empty() {
+        log_debug!(
+            context,
+            "workflow/authoring_entry/{}: creating {} index links...",
+            address,
+            index_hints.len()
+        );
+        author_index_links(index_hints, context.clone()).await?;
+    }
+
     Ok(CommitEntryResult::new(addr))
 }
+
+/// Authors a link for every index hint.
+/// Boxed, since authoring a link runs through `author_entry` again.
+fn author_index_links(
+    index_hints: Vec<LinkSpec>,
+    context: Arc<Context>,
+) -> LocalBoxFuture<'static, Result<(), HolochainError>> {
+    async move {
+        for hint in index_hints {
+            let top_chain_header = context
+                .state()
+                .ok_or_else(|| HolochainError::ErrorGeneric("State not initialized".to_string()))?
+                .agent()
+                .top_chain_header()
+                .ok_or_else(|| {
+                    HolochainError::ErrorGeneric("No header to link the entry from".to_string())
+                })?;
+            let link = Link::new(&hint.base, &hint.target, &hint.link_type, &hint.tag);
+            let link_add = LinkData::from_link(
+                &link,
+                LinkActionKind::ADD,
+                top_chain_header,
+                context.agent_id.clone(),
+            );
+            author_entry(&Entry::LinkAdd(link_add), None, &context, &vec![]).await?;
+        }
+        Ok(())
+    }
+    .boxed_local()
+}
 // TODO: Bring the old in-memory network up to speed and turn on this test again!
 
 #[cfg(test)]
@@ -7,7 +7,7 @@ use crate::{nucleus::validation::process_validation_err, workflows::validation_p
 use holochain_core_types::{
     error::HolochainError,
     network::entry_aspect::EntryAspect,
-    validation::{EntryLifecycle, ValidationData},
+    validation::{EntryLifecycle, ValidationData, ValidationOrigin},
 };
 
 use holochain_persistence_api::cas::content::AddressableContent;
@@ -37,10 +37,11 @@ pub async fn hold_entry_workflow(
     log_debug!(context, "workflow/hold_entry: got validation package");
 
     // 2. Create validation data struct
-    let validation_data = ValidationData {
-        package: validation_package,
-        lifecycle: EntryLifecycle::Dht,
-    };
+    let validation_data = ValidationData::new(
+        validation_package,
+        EntryLifecycle::Dht,
+        ValidationOrigin::Remote,
+    );
 
     // 3. Validate the entry
     validate_entry(
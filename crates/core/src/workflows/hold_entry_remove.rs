@@ -7,7 +7,7 @@ use crate::{nucleus::validation::process_validation_err, workflows::validation_p
 use holochain_core_types::{
     error::HolochainError,
     network::entry_aspect::EntryAspect,
-    validation::{EntryLifecycle, ValidationData},
+    validation::{EntryLifecycle, ValidationData, ValidationOrigin},
 };
 use holochain_persistence_api::cas::content::AddressableContent;
 use std::sync::Arc;
@@ -30,10 +30,11 @@ pub async fn hold_remove_workflow(
         .ok_or_else(|| "Could not get validation package from source".to_string())?;
 
     // 2. Create validation data struct
-    let validation_data = ValidationData {
-        package: validation_package,
-        lifecycle: EntryLifecycle::Meta,
-    };
+    let validation_data = ValidationData::new(
+        validation_package,
+        EntryLifecycle::Meta,
+        ValidationOrigin::Remote,
+    );
 
     // 3. Validate the entry
     validate_entry(
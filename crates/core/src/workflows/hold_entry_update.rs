@@ -8,7 +8,7 @@ use crate::{
 use holochain_core_types::{
     error::HolochainError,
     network::entry_aspect::EntryAspect,
-    validation::{EntryLifecycle, ValidationData},
+    validation::{EntryLifecycle, ValidationData, ValidationOrigin},
 };
 use holochain_persistence_api::cas::content::AddressableContent;
 use std::sync::Arc;
@@ -38,10 +38,11 @@ pub async fn hold_update_workflow(
         .ok_or_else(|| "Could not get link update from header".to_string())?;
 
     // 2. Create validation data struct
-    let validation_data = ValidationData {
-        package: validation_package,
-        lifecycle: EntryLifecycle::Meta,
-    };
+    let validation_data = ValidationData::new(
+        validation_package,
+        EntryLifecycle::Meta,
+        ValidationOrigin::Remote,
+    );
 
     // 3. Validate the entry
     validate_entry(entry.clone(), Some(link.clone()), validation_data, &context)
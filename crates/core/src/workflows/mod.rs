@@ -25,12 +25,15 @@ pub mod respond_validation_package_request;
 
 use crate::{
     context::Context,
-    dht::pending_validations::{PendingValidation, ValidatingWorkflow},
+    dht::{
+        actions::hold_aspect::reject_unvalidated_aspect,
+        pending_validations::{PendingValidation, PendingValidationStruct, ValidatingWorkflow},
+    },
     network::{
         actions::get_validation_package::get_validation_package, entry_with_header::EntryWithHeader,
     },
     nucleus::{
-        actions::build_validation_package::build_validation_package,
+        actions::build_validation_package::{build_validation_package, resolve_dht_parts_locally},
         validation::build_from_dht::try_make_validation_package_dht,
     },
     wasm_engine::callback::{
@@ -44,10 +47,11 @@ use crate::{
 };
 use holochain_core_types::{
     error::HolochainError,
+    network::entry_aspect::EntryAspect,
     validation::{ValidationPackage, ValidationPackageDefinition},
 };
-use holochain_persistence_api::cas::content::AddressableContent;
-use std::sync::Arc;
+use holochain_persistence_api::cas::content::{Address, AddressableContent};
+use std::{convert::TryFrom, sync::Arc};
 
 /// Try to create a ValidationPackage for the given entry without calling out to some other node.
 /// I.e. either create it just from/with the header if `ValidationPackageDefinition` is `Entry`,
@@ -148,6 +152,8 @@ async fn validation_package(
                 "validation_package:{} - Successfully retrieved from author",
                 entry_with_header.entry.address()
             );
+            let package =
+                resolve_dht_parts_locally(&context, package, &validation_package_definition)?;
             return Ok(Some(package));
         }
         response => log_debug!(
@@ -193,14 +199,26 @@ async fn validation_package(
 
 #[cfg(test)]
 pub mod tests {
-    use super::validation_package;
+    use super::{validate_and_hold, validate_held_aspect, validation_package};
     use crate::{
-        network::entry_with_header::EntryWithHeader, nucleus::actions::tests::*,
+        context::Context,
+        dht::actions::hold_aspect::{hold_aspect_unvalidated, HoldPolicy},
+        instance::tests::test_instance_and_context_by_name,
+        network::{
+            entry_with_header::EntryWithHeader,
+            test_utils::{test_wat_always_invalid, test_wat_always_valid},
+        },
+        nucleus::actions::tests::*,
         workflows::author_entry::author_entry,
     };
-    use holochain_core_types::entry::Entry;
+    use holochain_core_types::{
+        entry::{test_entry, Entry},
+        network::entry_aspect::EntryAspect,
+    };
     use holochain_json_api::json::JsonString;
-    use std::{thread, time};
+    use holochain_persistence_api::cas::content::AddressableContent;
+    use std::{sync::Arc, thread, time};
+    use test_utils::create_test_dna_with_wat;
 
     #[test]
     fn test_simulate_packge_direct_from_author() {
@@ -246,6 +264,135 @@ pub mod tests {
             2
         );
     }
+
+    /// An aspect of an entry authored by alice on her own network.
+    fn authored_aspect() -> EntryAspect {
+        let netname = Some("validate_and_hold_author");
+        let dna = create_test_dna_with_wat("test_zome", Some(&test_wat_always_valid()));
+        let (_instance, context) =
+            test_instance_and_context_by_name(dna, "alice_validate_and_hold", netname).unwrap();
+        let entry = test_entry();
+        context
+            .block_on(author_entry(&entry, None, &context, &vec![]))
+            .unwrap();
+        let header = context
+            .state()
+            .unwrap()
+            .agent()
+            .get_most_recent_header_for_entry(&entry)
+            .expect("There must be a header in the author's source chain after commit");
+        EntryAspect::Content(entry, header)
+    }
+
+    fn holds(context: &Arc<Context>, aspect: &EntryAspect) -> bool {
+        context
+            .state()
+            .unwrap()
+            .dht()
+            .get_holding_map()
+            .contains(aspect)
+    }
+
+    #[test]
+    fn validate_and_hold_holds_valid_aspect() {
+        let aspect = authored_aspect();
+        let dna = create_test_dna_with_wat("test_zome", Some(&test_wat_always_valid()));
+        let (_instance, context) = test_instance_and_context_by_name(
+            dna,
+            "bob_validate_and_hold",
+            Some("validate_and_hold_valid"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            context.block_on(validate_and_hold(aspect.clone(), context.clone())),
+            Ok(())
+        );
+        assert!(holds(&context, &aspect));
+    }
+
+    #[test]
+    fn validate_and_hold_holds_nothing_if_validation_fails() {
+        let aspect = authored_aspect();
+        let dna = create_test_dna_with_wat("test_zome", Some(&test_wat_always_invalid()));
+        let (_instance, context) = test_instance_and_context_by_name(
+            dna,
+            "carol_validate_and_hold",
+            Some("validate_and_hold_invalid"),
+        )
+        .unwrap();
+
+        assert!(context
+            .block_on(validate_and_hold(aspect.clone(), context.clone()))
+            .is_err());
+        assert!(!holds(&context, &aspect));
+    }
+
+    fn is_unvalidated(context: &Arc<Context>, aspect: &EntryAspect) -> bool {
+        context
+            .state()
+            .unwrap()
+            .dht()
+            .unvalidated_aspect(&aspect.address())
+            .is_some()
+    }
+
+    #[test]
+    fn lazily_held_aspect_gets_held_once_validated() {
+        let aspect = authored_aspect();
+        let dna = create_test_dna_with_wat("test_zome", Some(&test_wat_always_valid()));
+        let (_instance, context) =
+            test_instance_and_context_by_name(dna, "bob_lazy_hold", Some("lazy_hold_valid"))
+                .unwrap();
+        context.set_hold_policy(HoldPolicy::LazyValidate);
+
+        assert_eq!(
+            context.block_on(hold_aspect_unvalidated(aspect.clone(), context.clone())),
+            Ok(())
+        );
+        assert!(is_unvalidated(&context, &aspect));
+        assert!(!holds(&context, &aspect));
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        context.on_held(
+            aspect.clone(),
+            Box::new(move || sender.send(()).expect("Test receiver dropped")),
+        );
+
+        assert_eq!(
+            context.block_on(validate_held_aspect(aspect.address(), context.clone())),
+            Ok(())
+        );
+        assert!(!is_unvalidated(&context, &aspect));
+        assert!(holds(&context, &aspect));
+        receiver
+            .recv_timeout(std::time::Duration::from_secs(5))
+            .expect("on_held callback did not run for the promoted aspect");
+    }
+
+    #[test]
+    fn lazily_held_aspect_gets_rejected_if_invalid() {
+        let aspect = authored_aspect();
+        let dna = create_test_dna_with_wat("test_zome", Some(&test_wat_always_invalid()));
+        let (_instance, context) =
+            test_instance_and_context_by_name(dna, "carol_lazy_hold", Some("lazy_hold_invalid"))
+                .unwrap();
+        context.set_hold_policy(HoldPolicy::LazyValidate);
+
+        context
+            .block_on(hold_aspect_unvalidated(aspect.clone(), context.clone()))
+            .unwrap();
+        assert!(context
+            .block_on(validate_held_aspect(aspect.address(), context.clone()))
+            .is_err());
+        assert!(!is_unvalidated(&context, &aspect));
+        assert!(!holds(&context, &aspect));
+        assert!(context
+            .state()
+            .unwrap()
+            .dht()
+            .hold_rejection(&aspect.address())
+            .is_some());
+    }
 }
 
 /// Runs the given pending validation using the right holding workflow
@@ -272,3 +419,46 @@ pub async fn run_holding_workflow(
         }
     }
 }
+
+/// Validates the given aspect and holds it, in one go instead of queueing it
+/// as a pending validation first.
+/// The hold only gets dispatched once the aspect is valid, and the DHT reducer
+/// either holds an aspect completely or rejects it, so if either step fails nothing is held.
+pub async fn validate_and_hold(
+    aspect: EntryAspect,
+    context: Arc<Context>,
+) -> Result<(), HolochainError> {
+    let pending = PendingValidationStruct::try_from(aspect)?;
+    run_holding_workflow(Arc::new(pending), context).await
+}
+
+/// Validates the aspect with the given address that got stored unvalidated
+/// (see `HoldPolicy::LazyValidate`). A valid aspect gets held, an invalid one rejected.
+/// If validation can't be done yet, e.g. because dependencies are missing, the aspect
+/// stays unvalidated and the error is returned. Aspects that are held already are fine.
+pub async fn validate_held_aspect(
+    address: Address,
+    context: Arc<Context>,
+) -> Result<(), HolochainError> {
+    let dht = context
+        .state()
+        .ok_or_else(|| HolochainError::ErrorGeneric("State not initialized".to_string()))?
+        .dht();
+    let aspect = match dht.unvalidated_aspect(&address) {
+        Some(aspect) => aspect.clone(),
+        None if dht.get_holding_map().contains_address(&address) => return Ok(()),
+        None => {
+            return Err(match dht.hold_rejection(&address) {
+                Some(reason) => HolochainError::ValidationFailed(reason.clone()),
+                None => HolochainError::ErrorGeneric(format!("Aspect {} is not held", address)),
+            })
+        }
+    };
+    match validate_and_hold(aspect.clone(), context.clone()).await {
+        Err(HolochainError::ValidationFailed(reason)) => {
+            reject_unvalidated_aspect(aspect, reason.clone(), context).await?;
+            Err(HolochainError::ValidationFailed(reason))
+        }
+        result => result,
+    }
+}
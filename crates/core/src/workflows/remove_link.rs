@@ -9,7 +9,7 @@ use holochain_core_types::{
     entry::Entry,
     error::HolochainError,
     network::entry_aspect::EntryAspect,
-    validation::{EntryLifecycle, ValidationData},
+    validation::{EntryLifecycle, ValidationData, ValidationOrigin},
 };
 use holochain_persistence_api::cas::content::AddressableContent;
 use std::sync::Arc;
@@ -47,10 +47,11 @@ pub async fn remove_link_workflow(
     log_debug!(context, "workflow/remove_link: got validation package!");
 
     // 2. Create validation data struct
-    let validation_data = ValidationData {
-        package: validation_package,
-        lifecycle: EntryLifecycle::Meta,
-    };
+    let validation_data = ValidationData::new(
+        validation_package,
+        EntryLifecycle::Meta,
+        ValidationOrigin::Remote,
+    );
 
     // 3. Validate the entry
     log_debug!(context, "workflow/remove_link: validate...");
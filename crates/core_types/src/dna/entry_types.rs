@@ -43,6 +43,11 @@ pub struct LinksTo {
     /// The type of this links_to entry
     #[serde(default)]
     pub link_type: String,
+
+    /// Which kind of validation package the link's validation asks for
+    /// (e.g. "ChainFull"), as reported by the zome
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validation_package: Option<String>,
 }
 
 impl Default for LinksTo {
@@ -51,6 +56,7 @@ impl Default for LinksTo {
         LinksTo {
             target_type: String::new(),
             link_type: String::new(),
+            validation_package: None,
         }
     }
 }
@@ -73,6 +79,11 @@ pub struct LinkedFrom {
     /// The link_type of this links_to entry
     #[serde(default)]
     pub link_type: String,
+
+    /// Which kind of validation package the link's validation asks for
+    /// (e.g. "ChainFull"), as reported by the zome
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validation_package: Option<String>,
 }
 
 impl Default for LinkedFrom {
@@ -81,6 +92,7 @@ impl Default for LinkedFrom {
         LinkedFrom {
             base_type: String::new(),
             link_type: String::new(),
+            validation_package: None,
         }
     }
 }
@@ -137,12 +149,26 @@ pub struct EntryTypeDef {
     /// An array of link definitions for links pointing to entries of this type
     #[serde(default)]
     pub linked_from: Vec<LinkedFrom>,
+
+    /// Version since which the entry type is deprecated, if it is.
+    /// New entries of a deprecated type can't be created, existing ones can still be updated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated_since: Option<String>,
+
+    /// Whether entries of this type are derived from other entries, like a materialized view.
+    /// Entries of a derived type can only be produced by the system, not committed directly.
+    #[serde(default, skip_serializing_if = "is_not_derived")]
+    pub derived: bool,
 }
 
 fn empty_properties() -> JsonString {
     JsonString::empty_object()
 }
 
+fn is_not_derived(derived: &bool) -> bool {
+    !*derived
+}
+
 impl Default for EntryTypeDef {
     fn default() -> Self {
         EntryTypeDef {
@@ -150,6 +176,8 @@ impl Default for EntryTypeDef {
             sharing: Sharing::default(),
             links_to: Vec::default(),
             linked_from: Vec::default(),
+            deprecated_since: None,
+            derived: false,
         }
     }
 }
@@ -159,6 +187,10 @@ impl EntryTypeDef {
     pub fn new() -> Self {
         Default::default()
     }
+
+    pub fn is_deprecated(&self) -> bool {
+        self.deprecated_since.is_some()
+    }
 }
 
 #[cfg(test)]
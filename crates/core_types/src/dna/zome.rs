@@ -11,6 +11,8 @@ use crate::{
 };
 
 use holochain_json_api::{error::JsonError, json::JsonString};
+use holochain_persistence_api::cas::content::Address;
+use multihash::Hash;
 
 use dna::entry_types::{self, deserialize_entry_types, serialize_entry_types, EntryTypeDef};
 use std::collections::BTreeMap;
@@ -37,6 +39,11 @@ pub type ZomeEntryTypes = BTreeMap<EntryType, EntryTypeDef>;
 pub type ZomeTraits = BTreeMap<String, TraitFns>;
 pub type ZomeFnDeclarations = Vec<FnDeclaration>;
 
+/// The checksum of a zome definition: the SHA-256 multihash of its JSON.
+pub fn definition_checksum(definition_json: &str) -> Address {
+    Address::encode_from_str(definition_json, Hash::SHA2256)
+}
+
 /// Represents an individual "zome".
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, DefaultJson)]
 pub struct Zome {
@@ -47,6 +47,7 @@ pub enum Attribute {
     RemovedLink(String, String),
     PendingEntry,
     Target,
+    SleepDeadline,
 }
 
 impl Default for Attribute {
@@ -86,6 +87,7 @@ impl fmt::Display for Attribute {
             }
             Attribute::PendingEntry => write!(f, "pending-entry"),
             Attribute::Target => write!(f, "target"),
+            Attribute::SleepDeadline => write!(f, "sleep-deadline"),
         }
     }
 }
@@ -119,6 +121,7 @@ impl TryFrom<&str> for Attribute {
                 "link_remove" => Ok(LinkRemove),
                 "pending-entry" => Ok(PendingEntry),
                 "target" => Ok(Target),
+                "sleep-deadline" => Ok(SleepDeadline),
                 a => Err(AttributeError::Unrecognized(a.to_string())),
             }
         }
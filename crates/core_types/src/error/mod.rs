@@ -117,9 +117,35 @@ pub enum HolochainError {
     DnaHashMismatch(HashString, HashString),
     EntryNotFoundLocally,
     EntryIsPrivate,
+    NetworkNotInitialized,
+    WouldBlock,
+    IntegrityCheckFailed,
+    Cancelled(CancelReason),
+    MemoryPressure,
     List(Vec<HolochainError>),
 }
 
+/// Why a cancellable future (e.g. `publish_cancellable()`) stopped before it completed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Hash, PartialOrd, Ord)]
+pub enum CancelReason {
+    /// The caller cancelled it through its cancel handle
+    User,
+    /// The instance got shut down
+    Shutdown,
+    /// It did not complete within its timeout
+    Timeout,
+}
+
+impl fmt::Display for CancelReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CancelReason::User => write!(f, "cancelled by the caller"),
+            CancelReason::Shutdown => write!(f, "instance was shut down"),
+            CancelReason::Timeout => write!(f, "timed out"),
+        }
+    }
+}
+
 pub type HcResult<T> = Result<T, HolochainError>;
 
 impl HolochainError {
@@ -167,6 +193,11 @@ impl fmt::Display for HolochainError {
                 f,
                 "The requested entry is private and should not be shared via gossip"
             ),
+            NetworkNotInitialized => write!(f, "Network not initialized"),
+            WouldBlock => write!(f, "Action channel is full, would block"),
+            IntegrityCheckFailed => write!(f, "Aspect does not hash to its claimed address"),
+            Cancelled(reason) => write!(f, "Cancelled: {}", reason),
+            MemoryPressure => write!(f, "Memory pressure is too high to hold more aspects"),
             List(list) => {
                 //most windows system know that \n is a newline so we should be good.
                 let error_list = list
@@ -422,6 +453,26 @@ mod tests {
                 HolochainError::EntryIsPrivate,
                 "The requested entry is private and should not be shared via gossip",
             ),
+            (
+                HolochainError::NetworkNotInitialized,
+                "Network not initialized",
+            ),
+            (
+                HolochainError::WouldBlock,
+                "Action channel is full, would block",
+            ),
+            (
+                HolochainError::IntegrityCheckFailed,
+                "Aspect does not hash to its claimed address",
+            ),
+            (
+                HolochainError::Cancelled(CancelReason::Shutdown),
+                "Cancelled: instance was shut down",
+            ),
+            (
+                HolochainError::MemoryPressure,
+                "Memory pressure is too high to hold more aspects",
+            ),
         ] {
             assert_eq!(output, &input.to_string());
         }
@@ -158,6 +158,10 @@ pub enum RibosomeErrorCode {
     MismatchWasmCallDataType        = 11 << 32,
     EntryNotFound                   = 12 << 32,
     WorkflowFailed                  = 13 << 32,
+    EntryTypeMismatch               = 14 << 32,
+    ValidationPackageFailed         = 15 << 32,
+    ValidatorFailed                 = 16 << 32,
+    DefinitionTooLarge              = 17 << 32,
 }
 
 #[rustfmt::skip]
@@ -177,6 +181,10 @@ impl RibosomeErrorCode {
             MismatchWasmCallDataType        => "Mismatched WasmCallData type",
             EntryNotFound                   => "Entry Could Not Be Found",
             WorkflowFailed                  => "Workflow failed",
+            EntryTypeMismatch               => "Entry type mismatch",
+            ValidationPackageFailed         => "Validation package failed",
+            ValidatorFailed                 => "Validator failed",
+            DefinitionTooLarge              => "Definition too large",
         }
     }
 }
@@ -208,6 +216,11 @@ impl From<HolochainError> for RibosomeErrorCode {
             HolochainError::DnaHashMismatch(_, _) => RibosomeErrorCode::Unspecified,
             HolochainError::EntryNotFoundLocally => RibosomeErrorCode::Unspecified,
             HolochainError::EntryIsPrivate => RibosomeErrorCode::Unspecified,
+            HolochainError::NetworkNotInitialized => RibosomeErrorCode::Unspecified,
+            HolochainError::WouldBlock => RibosomeErrorCode::Unspecified,
+            HolochainError::IntegrityCheckFailed => RibosomeErrorCode::Unspecified,
+            HolochainError::Cancelled(_) => RibosomeErrorCode::Unspecified,
+            HolochainError::MemoryPressure => RibosomeErrorCode::Unspecified,
             HolochainError::List(_) => RibosomeErrorCode::Unspecified,
         }
     }
@@ -240,6 +253,10 @@ impl RibosomeErrorCode {
             10 => UnknownEntryType,
             12 => EntryNotFound,
             13 => WorkflowFailed,
+            14 => EntryTypeMismatch,
+            15 => ValidationPackageFailed,
+            16 => ValidatorFailed,
+            17 => DefinitionTooLarge,
             1 | _ => Unspecified,
         }
     }
@@ -295,6 +312,10 @@ impl FromStr for RibosomeErrorCode {
             "Unknown entry type" => Ok(RibosomeErrorCode::UnknownEntryType),
             "Entry Could Not Be Found" => Ok(EntryNotFound),
             "Workflow failed" => Ok(WorkflowFailed),
+            "Entry type mismatch" => Ok(EntryTypeMismatch),
+            "Validation package failed" => Ok(ValidationPackageFailed),
+            "Validator failed" => Ok(ValidatorFailed),
+            "Definition too large" => Ok(DefinitionTooLarge),
             _ => Err(HolochainError::ErrorGeneric(String::from(
                 "Unknown RibosomeErrorCode",
             ))),
@@ -319,7 +340,7 @@ pub mod tests {
     fn error_conversion() {
         // TODO could use strum crate to iteratively
         // gather all known codes.
-        for code in 1..=13 {
+        for code in 1..=17 {
             let mut err = RibosomeErrorCode::from_code_int(code);
 
             let err_str = err.as_str().to_owned();
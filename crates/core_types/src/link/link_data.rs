@@ -62,6 +62,11 @@ impl LinkData {
         &self.link
     }
 
+    /// The agent who created the link.
+    pub fn agent_id(&self) -> &AgentId {
+        &self.agent_id
+    }
+
     pub fn from_link(
         link: &Link,
         action_kind: LinkActionKind,
@@ -0,0 +1,161 @@
+//! Aspects too large for a single message can be sent in chunks.
+//! Every chunk travels together with a manifest that lists the hash of each chunk
+//! and the address of the whole aspect, so each chunk can be verified on arrival
+//! and the reassembled aspect can be checked before it gets held.
+//! On the wire, a chunk is sent as an [AspectChunkMessage](AspectChunkMessage)
+//! in place of the aspect.
+
+use crate::{error::HolochainError, network::entry_aspect::EntryAspect};
+use holochain_json_api::{error::JsonError, json::JsonString};
+use holochain_persistence_api::cas::content::{Address, AddressableContent};
+use multihash::Hash;
+use std::convert::TryFrom;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AspectChunkManifest {
+    /// Address of the complete aspect
+    pub aspect_address: Address,
+    /// Hash of every chunk, in order
+    pub chunk_hashes: Vec<Address>,
+}
+
+/// One piece of an aspect's JSON serialization.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AspectChunk {
+    /// Position of this chunk in the manifest
+    pub index: usize,
+    pub data: String,
+}
+
+/// A chunk together with the manifest it belongs to.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, DefaultJson)]
+pub struct AspectChunkMessage {
+    pub manifest: AspectChunkManifest,
+    pub chunk: AspectChunk,
+}
+
+impl AspectChunk {
+    pub fn hash(&self) -> Address {
+        Address::encode_from_str(&self.data, Hash::SHA2256)
+    }
+}
+
+impl AspectChunkManifest {
+    /// Splits the aspect's JSON into chunks of at most `chunk_size` bytes
+    /// (never splitting a character) and builds the matching manifest.
+    pub fn split(aspect: &EntryAspect, chunk_size: usize) -> (Self, Vec<AspectChunk>) {
+        let json = String::from(JsonString::from(aspect.clone()));
+        let chunk_size = chunk_size.max(4);
+        let mut chunks = Vec::new();
+        let mut current = String::new();
+        for c in json.chars() {
+            if current.len() + c.len_utf8() > chunk_size {
+                chunks.push(AspectChunk {
+                    index: chunks.len(),
+                    data: std::mem::replace(&mut current, String::new()),
+                });
+            }
+            current.push(c);
+        }
+        if !current.is_empty() {
+            chunks.push(AspectChunk {
+                index: chunks.len(),
+                data: current,
+            });
+        }
+        let manifest = AspectChunkManifest {
+            aspect_address: aspect.address(),
+            chunk_hashes: chunks.iter().map(AspectChunk::hash).collect(),
+        };
+        (manifest, chunks)
+    }
+
+    /// Identifies the manifest, so chunks of the same aspect split differently
+    /// (e.g. by senders with different chunk sizes) don't get mixed up.
+    pub fn hash(&self) -> Address {
+        let hashes: Vec<String> = self.chunk_hashes.iter().map(Address::to_string).collect();
+        Address::encode_from_str(
+            &format!("{}:{}", self.aspect_address, hashes.join(",")),
+            Hash::SHA2256,
+        )
+    }
+
+    /// Checks that the chunk belongs into this manifest and has the expected hash.
+    pub fn verify(&self, chunk: &AspectChunk) -> Result<(), HolochainError> {
+        let expected = self.chunk_hashes.get(chunk.index).ok_or_else(|| {
+            HolochainError::ValidationFailed(format!(
+                "Chunk {} is out of range for aspect {} with {} chunks",
+                chunk.index,
+                self.aspect_address,
+                self.chunk_hashes.len()
+            ))
+        })?;
+        if *expected == chunk.hash() {
+            Ok(())
+        } else {
+            Err(HolochainError::ValidationFailed(format!(
+                "Hash of chunk {} does not match the manifest of aspect {}",
+                chunk.index, self.aspect_address
+            )))
+        }
+    }
+
+    /// Puts the chunks (ordered by index) back together and makes sure
+    /// the result is the aspect the manifest promised.
+    pub fn reassemble(&self, chunks: &[AspectChunk]) -> Result<EntryAspect, HolochainError> {
+        let json: String = chunks.iter().map(|chunk| chunk.data.as_str()).collect();
+        let aspect = EntryAspect::try_from(JsonString::from_json(&json))?;
+        if aspect.address() == self.aspect_address {
+            Ok(aspect)
+        } else {
+            Err(HolochainError::ValidationFailed(format!(
+                "Reassembled chunks do not match aspect address {}",
+                self.aspect_address
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{chain_header::test_chain_header, entry::test_entry};
+
+    fn test_aspect() -> EntryAspect {
+        EntryAspect::Content(test_entry(), test_chain_header())
+    }
+
+    #[test]
+    fn split_chunks_verify_and_reassemble() {
+        let aspect = test_aspect();
+        let (manifest, chunks) = AspectChunkManifest::split(&aspect, 64);
+        assert!(chunks.len() > 1);
+        assert_eq!(manifest.chunk_hashes.len(), chunks.len());
+        for chunk in chunks.iter() {
+            assert_eq!(manifest.verify(chunk), Ok(()));
+        }
+        assert_eq!(manifest.reassemble(&chunks), Ok(aspect));
+    }
+
+    #[test]
+    fn corrupt_chunk_fails_verification() {
+        let (manifest, mut chunks) = AspectChunkManifest::split(&test_aspect(), 64);
+        chunks[1].data.push('x');
+        assert!(manifest.verify(&chunks[1]).is_err());
+
+        let out_of_range = AspectChunk {
+            index: chunks.len(),
+            data: String::new(),
+        };
+        assert!(manifest.verify(&out_of_range).is_err());
+    }
+
+    #[test]
+    fn manifests_of_different_splits_have_different_hashes() {
+        let (small, _) = AspectChunkManifest::split(&test_aspect(), 32);
+        let (large, _) = AspectChunkManifest::split(&test_aspect(), 64);
+        assert_eq!(small.aspect_address, large.aspect_address);
+        assert_ne!(small.hash(), large.hash());
+        assert_eq!(small.hash(), small.clone().hash());
+    }
+}
@@ -118,6 +118,23 @@ impl EntryAspect {
             EntryAspect::Header(header) => header.address(),
         })
     }
+
+    /// Checks that the entry this aspect carries hashes to the entry address its header
+    /// claims, i.e. that the aspect was not tampered with on its way.
+    /// Headers and deletions don't carry an entry, there is nothing to check for them.
+    pub fn verify_integrity(&self) -> Result<(), HolochainError> {
+        let entry = match self {
+            EntryAspect::Content(entry, _) | EntryAspect::Update(entry, _) => entry.clone(),
+            EntryAspect::LinkAdd(link_data, _) => Entry::LinkAdd(link_data.clone()),
+            EntryAspect::LinkRemove(link_remove, _) => Entry::LinkRemove(link_remove.clone()),
+            EntryAspect::Header(_) | EntryAspect::Deletion(_) => return Ok(()),
+        };
+        if entry.address() == *self.header().entry_address() {
+            Ok(())
+        } else {
+            Err(HolochainError::IntegrityCheckFailed)
+        }
+    }
 }
 
 fn format_header(header: &ChainHeader) -> String {
@@ -1,2 +1,3 @@
+pub mod aspect_chunk;
 pub mod entry_aspect;
 pub mod query;
@@ -9,15 +9,27 @@ use crate::{
         Entry,
     },
     error::HolochainError,
-    link::link_data::LinkData,
+    link::{link_data::LinkData, Link},
+    time::Iso8601,
 };
 
 use holochain_json_api::{error::JsonError, json::JsonString};
-use holochain_persistence_api::cas::content::Address;
+use holochain_persistence_api::cas::content::{Address, AddressableContent};
+use multihash::Hash;
+use serde::de::DeserializeOwned;
 
 use chain_header::test_chain_header;
 
-use std::convert::TryFrom;
+use std::{convert::TryFrom, fmt};
+
+/// A link between two entries, given by all of its parts.
+#[derive(Deserialize, Debug, Serialize, DefaultJson, Clone, PartialEq)]
+pub struct LinkSpec {
+    pub base: Address,
+    pub target: Address,
+    pub link_type: String,
+    pub tag: String,
+}
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, DefaultJson)]
 pub struct ValidationPackage {
@@ -25,6 +37,14 @@ pub struct ValidationPackage {
     pub source_chain_entries: Option<Vec<Entry>>,
     pub source_chain_headers: Option<Vec<ChainHeader>>,
     pub custom: Option<String>,
+    /// Those of the links asked for with `ValidationPackageDefinition::WithLinks`
+    /// that exist
+    #[serde(default)]
+    pub links: Option<Vec<LinkSpec>>,
+    /// Those of the entries asked for with `ValidationPackageDefinition::WithReferences`
+    /// that exist
+    #[serde(default)]
+    pub references: Option<Vec<Entry>>,
 }
 
 impl ValidationPackage {
@@ -34,6 +54,53 @@ impl ValidationPackage {
             source_chain_entries: None,
             source_chain_headers: None,
             custom: None,
+            links: None,
+            references: None,
+        }
+    }
+
+    /// Whether the package says that the given link exists.
+    /// Always false unless the package was built for `ValidationPackageDefinition::WithLinks`.
+    pub fn has_link(&self, link: &LinkSpec) -> bool {
+        self.links
+            .as_ref()
+            .map(|links| links.contains(link))
+            .unwrap_or(false)
+    }
+
+    /// Adds the data of `other` to this package, e.g. for
+    /// `ValidationPackageDefinition::Combined`. Items that are in both packages are kept once.
+    /// If both have a custom payload, the one of this package is kept.
+    pub fn union(&mut self, other: ValidationPackage) {
+        union_into(&mut self.source_chain_entries, other.source_chain_entries);
+        union_into(&mut self.source_chain_headers, other.source_chain_headers);
+        union_into(&mut self.links, other.links);
+        union_into(&mut self.references, other.references);
+        if self.custom.is_none() {
+            self.custom = other.custom;
+        }
+    }
+
+    /// The referenced entry at `address`, if the package says it exists.
+    /// Always `None` unless the package was built for
+    /// `ValidationPackageDefinition::WithReferences`.
+    pub fn referenced_entry(&self, address: &Address) -> Option<&Entry> {
+        self.references
+            .as_ref()
+            .and_then(|entries| entries.iter().find(|entry| entry.address() == *address))
+    }
+}
+
+fn union_into<T: PartialEq>(items: &mut Option<Vec<T>>, other: Option<Vec<T>>) {
+    match (items.as_mut(), other) {
+        (_, None) => {}
+        (None, other) => *items = other,
+        (Some(items), Some(other)) => {
+            for item in other {
+                if !items.contains(&item) {
+                    items.push(item);
+                }
+            }
         }
     }
 }
@@ -50,6 +117,63 @@ pub enum ValidationPackageDefinition {
     ChainFull,
     /// sending something custom
     Custom(String),
+    /// send the header for the entry, along with which of the given links exist,
+    /// for entries that are only valid if some other link exists
+    WithLinks(Vec<LinkSpec>),
+    /// send the header for the entry, along with those of the entries at the given
+    /// addresses that exist, for entries that reference other entries
+    WithReferences(Vec<Address>),
+    /// send everything the given definitions ask for in one package,
+    /// for entries that need heterogeneous context, e.g. chain entries and some links
+    Combined(Vec<ValidationPackageDefinition>),
+}
+
+impl ValidationPackageDefinition {
+    /// The name of the variant, without any custom payload.
+    pub fn discriminant(&self) -> &'static str {
+        match self {
+            ValidationPackageDefinition::Entry => "Entry",
+            ValidationPackageDefinition::ChainEntries => "ChainEntries",
+            ValidationPackageDefinition::ChainHeaders => "ChainHeaders",
+            ValidationPackageDefinition::ChainFull => "ChainFull",
+            ValidationPackageDefinition::Custom(_) => "Custom",
+            ValidationPackageDefinition::WithLinks(_) => "WithLinks",
+            ValidationPackageDefinition::WithReferences(_) => "WithReferences",
+            ValidationPackageDefinition::Combined(_) => "Combined",
+        }
+    }
+
+    /// The definitions that make up this one: the parts of a `Combined` definition,
+    /// with nested `Combined` definitions flattened, or just this one otherwise.
+    pub fn parts(&self) -> Vec<ValidationPackageDefinition> {
+        match self {
+            ValidationPackageDefinition::Combined(definitions) => definitions
+                .iter()
+                .flat_map(ValidationPackageDefinition::parts)
+                .collect(),
+            other => vec![other.clone()],
+        }
+    }
+
+    /// A cheaper form of this definition for when only a digest is needed:
+    /// custom payloads get replaced by their hash, all other variants
+    /// are small already and stay as they are.
+    pub fn digest(self) -> Self {
+        match self {
+            ValidationPackageDefinition::Custom(payload) => ValidationPackageDefinition::Custom(
+                String::from(Address::encode_from_str(&payload, Hash::SHA2256)),
+            ),
+            ValidationPackageDefinition::Combined(definitions) => {
+                ValidationPackageDefinition::Combined(
+                    definitions
+                        .into_iter()
+                        .map(ValidationPackageDefinition::digest)
+                        .collect(),
+                )
+            }
+            other => other,
+        }
+    }
 }
 
 ///This struct carries information needed for Validating Entry Data,
@@ -94,6 +218,125 @@ pub enum LinkValidationData {
     },
 }
 
+impl LinkValidationData {
+    /// The link that is being added or removed.
+    pub fn link(&self) -> &Link {
+        match self {
+            LinkValidationData::LinkAdd { link, .. } => link.link(),
+            LinkValidationData::LinkRemove { link, .. } => link.link(),
+        }
+    }
+
+    /// Address of the entry the link starts from.
+    pub fn base(&self) -> &Address {
+        self.link().base()
+    }
+
+    /// Address of the entry the link points to.
+    pub fn target(&self) -> &Address {
+        self.link().target()
+    }
+}
+
+impl<T> EntryValidationData<T> {
+    /// The validation data of whichever variant this is.
+    pub fn validation_data(&self) -> &ValidationData {
+        match self {
+            EntryValidationData::Create {
+                validation_data, ..
+            } => validation_data,
+            EntryValidationData::Modify {
+                validation_data, ..
+            } => validation_data,
+            EntryValidationData::Delete {
+                validation_data, ..
+            } => validation_data,
+        }
+    }
+
+    /// Mutable access to the validation data of whichever variant this is.
+    pub fn validation_data_mut(&mut self) -> &mut ValidationData {
+        match self {
+            EntryValidationData::Create {
+                validation_data, ..
+            } => validation_data,
+            EntryValidationData::Modify {
+                validation_data, ..
+            } => validation_data,
+            EntryValidationData::Delete {
+                validation_data, ..
+            } => validation_data,
+        }
+    }
+}
+
+/// Field of an app entry's content that declares the version of the validation rules
+/// the entry follows, like `{"content": "hello", "rule_version": 2}`.
+pub const RULE_VERSION_FIELD: &str = "rule_version";
+
+impl EntryValidationData<Entry> {
+    /// The entry under validation: the new entry of an update, the deleted entry of a deletion.
+    pub fn entry_under_validation(&self) -> &Entry {
+        match self {
+            EntryValidationData::Create { entry, .. } => entry,
+            EntryValidationData::Modify { new_entry, .. } => new_entry,
+            EntryValidationData::Delete { old_entry, .. } => old_entry,
+        }
+    }
+
+    /// The version of the validation rules the entry under validation declares
+    /// in its [RULE_VERSION_FIELD](RULE_VERSION_FIELD), if it is an app entry that declares one.
+    pub fn declared_rule_version(&self) -> Option<u32> {
+        match self.entry_under_validation() {
+            Entry::App(_, value) => {
+                serde_json::from_str::<serde_json::Value>(&String::from(value.clone()))
+                    .ok()?
+                    .get(RULE_VERSION_FIELD)?
+                    .as_u64()
+                    .and_then(|version| u32::try_from(version).ok())
+            }
+            _ => None,
+        }
+    }
+
+    /// The content of the entry under validation deserialized into `T`. Saves validators
+    /// from each handling malformed content their own way, the failure turns into the
+    /// validator's error with `?`.
+    pub fn typed<T: DeserializeOwned>(&self) -> Result<T, ValidationFailure> {
+        match self.entry_under_validation() {
+            Entry::App(entry_type, value) => serde_json::from_str(&String::from(value.clone()))
+                .map_err(|error| {
+                    ValidationFailure(format!(
+                        "Content of {} entry does not have the expected structure: {}",
+                        String::from(entry_type.clone()),
+                        error
+                    ))
+                }),
+            other => Err(ValidationFailure(format!(
+                "Only app entries have content to validate, not {} entries",
+                other.entry_type()
+            ))),
+        }
+    }
+}
+
+/// Why a validator could not read the entry under validation, see
+/// [EntryValidationData::typed](EntryValidationData::typed).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ValidationFailure(pub String);
+
+impl fmt::Display for ValidationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<ValidationFailure> for String {
+    fn from(failure: ValidationFailure) -> String {
+        failure.0
+    }
+}
+
 impl TryFrom<EntryValidationData<Entry>> for EntryType {
     type Error = HolochainError;
     fn try_from(entry_validation: EntryValidationData<Entry>) -> Result<Self, Self::Error> {
@@ -127,23 +370,62 @@ pub struct ValidationData {
     /// In which lifecycle of the entry creation are we running
     /// this validation callback?
     pub lifecycle: EntryLifecycle,
+    /// Was the entry authored by this agent or did it reach us
+    /// from another node over the network?
+    #[serde(default)]
+    pub origin: ValidationOrigin,
+    /// The properties of the DNA, for validators that depend on
+    /// DNA level configuration. Only filled in for entry validation.
+    #[serde(default = "JsonString::empty_object")]
+    pub dna_properties: JsonString,
+    /// Seed for pseudo random numbers, derived from the address of the entry
+    /// under validation so every node validating the entry gets the same one.
+    #[serde(default)]
+    pub seed: u64,
+    /// When the entry was committed, according to its chain header.
+    /// Only filled in for entry validation.
+    #[serde(default)]
+    pub timestamp: Option<Iso8601>,
+    /// Name of the zome whose validator is running, e.g. to namespace keys with.
+    /// Only filled in for entry validation.
+    #[serde(default)]
+    pub zome_name: Option<String>,
+    /// Hash of the DNA the validator is running in.
+    /// Only filled in for entry validation.
+    #[serde(default)]
+    pub dna_hash: Option<Address>,
 }
 
 impl Default for ValidationData {
     fn default() -> Self {
-        Self {
-            package: ValidationPackage {
-                chain_header: test_chain_header(),
-                source_chain_entries: None,
-                source_chain_headers: None,
-                custom: None,
-            },
-            lifecycle: EntryLifecycle::default(),
-        }
+        Self::new(
+            ValidationPackage::only_header(test_chain_header()),
+            EntryLifecycle::default(),
+            ValidationOrigin::default(),
+        )
     }
 }
 
 impl ValidationData {
+    /// Validation data without the entry validation context (DNA properties, seed, etc.).
+    /// The HDK fills that in from the `EntryValidationArgs` before calling entry validators.
+    pub fn new(
+        package: ValidationPackage,
+        lifecycle: EntryLifecycle,
+        origin: ValidationOrigin,
+    ) -> Self {
+        ValidationData {
+            package,
+            lifecycle,
+            origin,
+            dna_properties: JsonString::empty_object(),
+            seed: 0,
+            timestamp: None,
+            zome_name: None,
+            dna_hash: None,
+        }
+    }
+
     /// The list of authors that have signed this entry.
     pub fn sources(&self) -> Vec<Address> {
         self.package
@@ -153,6 +435,42 @@ impl ValidationData {
             .map(|provenance| provenance.source())
             .collect()
     }
+
+    /// Pseudo random numbers that are the same on every node validating the same entry.
+    pub fn rng(&self) -> ValidationRng {
+        ValidationRng::new(self.seed)
+    }
+}
+
+/// The validation seed for the entry with the given address.
+pub fn validation_seed(address: &Address) -> u64 {
+    // FNV-1a, which (unlike std's hasher) is guaranteed to stay the same across platforms and versions
+    String::from(address.clone())
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// A small deterministic random number generator (SplitMix64) for validators.
+/// Don't use it for anything that needs to be unpredictable.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationRng {
+    state: u64,
+}
+
+impl ValidationRng {
+    pub fn new(seed: u64) -> Self {
+        ValidationRng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -168,6 +486,36 @@ impl Default for EntryLifecycle {
     }
 }
 
+/// Tells a validation callback where the data under validation comes from.
+/// Validators can use this to skip checks that only make sense for data
+/// received from other agents.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum ValidationOrigin {
+    /// The entry is being committed to this agent's own source chain.
+    Local,
+    /// The entry was published to us by another node.
+    Remote,
+    /// The system produces the entry on this agent's own source chain,
+    /// e.g. an entry of a derived type (see `EntryTypeDef::derived`).
+    System,
+}
+
+impl Default for ValidationOrigin {
+    fn default() -> Self {
+        ValidationOrigin::Local
+    }
+}
+
+/// The locale validation failure messages should be presented in, e.g. "de-CH".
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+pub struct Locale(pub String);
+
+impl From<&str> for Locale {
+    fn from(locale: &str) -> Self {
+        Locale(locale.to_string())
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum EntryAction {
     Create,
@@ -186,3 +534,112 @@ pub enum LinkAction {
     Create,
     Delete,
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn combined_definition_round_trips_through_json() {
+        let definition = ValidationPackageDefinition::Combined(vec![
+            ValidationPackageDefinition::ChainEntries,
+            ValidationPackageDefinition::WithLinks(vec![LinkSpec {
+                base: Address::from("base"),
+                target: Address::from("target"),
+                link_type: "likes".to_string(),
+                tag: "".to_string(),
+            }]),
+            ValidationPackageDefinition::Combined(vec![ValidationPackageDefinition::Custom(
+                "payload".to_string(),
+            )]),
+        ]);
+
+        let json = JsonString::from(definition.clone());
+        assert_eq!(
+            ValidationPackageDefinition::try_from(json),
+            Ok(definition.clone())
+        );
+        assert_eq!(definition.discriminant(), "Combined");
+        assert_eq!(
+            definition
+                .parts()
+                .iter()
+                .map(ValidationPackageDefinition::discriminant)
+                .collect::<Vec<_>>(),
+            vec!["ChainEntries", "WithLinks", "Custom"]
+        );
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Post {
+        content: String,
+        likes: u32,
+    }
+
+    fn create(entry: Entry) -> EntryValidationData<Entry> {
+        EntryValidationData::Create {
+            entry,
+            validation_data: ValidationData::default(),
+        }
+    }
+
+    #[test]
+    fn typed_deserializes_entry_content() {
+        let post = create(Entry::App(
+            AppEntryType::from("post"),
+            JsonString::from_json(r#"{"content":"hello","likes":3}"#),
+        ));
+        assert_eq!(
+            post.typed::<Post>(),
+            Ok(Post {
+                content: "hello".to_string(),
+                likes: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn declared_rule_version_is_read_from_entry_content() {
+        let post = |json: &str| {
+            create(Entry::App(
+                AppEntryType::from("post"),
+                JsonString::from_json(json),
+            ))
+        };
+        assert_eq!(
+            post(r#"{"content":"hello","rule_version":2}"#).declared_rule_version(),
+            Some(2)
+        );
+        assert_eq!(post(r#"{"content":"hello"}"#).declared_rule_version(), None);
+        assert_eq!(
+            post(r#"{"content":"hello","rule_version":"two"}"#).declared_rule_version(),
+            None
+        );
+    }
+
+    #[test]
+    fn typed_fails_cleanly_on_type_mismatch() {
+        let post = create(Entry::App(
+            AppEntryType::from("post"),
+            JsonString::from_json(r#"{"content":"hello","likes":"many"}"#),
+        ));
+        let failure = post.typed::<Post>().expect_err("likes is not a number");
+        assert!(
+            failure
+                .0
+                .starts_with("Content of post entry does not have the expected structure: "),
+            "unexpected failure: {}",
+            failure
+        );
+
+        let deletion = create(Entry::Deletion(
+            crate::entry::deletion_entry::DeletionEntry::new(Address::from("deleted")),
+        ));
+        assert_eq!(
+            deletion.typed::<Post>(),
+            Err(ValidationFailure(
+                "Only app entries have content to validate, not %deletion entries".to_string()
+            ))
+        );
+    }
+}
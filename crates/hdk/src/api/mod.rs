@@ -40,6 +40,7 @@ mod send;
 mod sign;
 mod sleep;
 mod update_remove;
+mod validation_oracle;
 mod version;
 
 pub use self::{
@@ -70,6 +71,7 @@ pub use self::{
     sign::{sign, sign_one_time, verify_signature},
     sleep::sleep,
     update_remove::{remove_entry, update_agent, update_entry},
+    validation_oracle::validation_oracle_query,
     version::{version, version_hash},
 };
 
@@ -196,6 +198,7 @@ def_api_fns! {
     hc_commit_capability_grant, CommitCapabilityGrant;
     hc_commit_capability_claim, CommitCapabilityClaim;
     hc_emit_signal, EmitSignal;
+    hc_validation_oracle_query, ValidationOracleQuery;
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -0,0 +1,27 @@
+use crate::{error::ZomeApiResult, Dispatch};
+use holochain_json_api::json::JsonString;
+
+/// Asks the oracle the conductor set up for validation, e.g. a bridged service,
+/// and returns its response. Validators can't reach such services otherwise.
+/// Fails if the conductor has no oracle set up or the oracle could not answer.
+/// # Examples
+/// ```rust
+/// # #[macro_use]
+/// # extern crate hdk;
+/// # extern crate holochain_json_api;
+/// # use hdk::error::ZomeApiResult;
+/// # use holochain_json_api::json::JsonString;
+///
+/// # fn main() {
+/// pub fn transfer_approved(amount: u64) -> ZomeApiResult<JsonString> {
+///     hdk::validation_oracle_query(JsonString::from_json(&format!(
+///         r#"{{"amount":{}}}"#,
+///         amount
+///     )))
+/// }
+///
+/// # }
+/// ```
+pub fn validation_oracle_query(request: JsonString) -> ZomeApiResult<JsonString> {
+    Dispatch::ValidationOracleQuery.with_input(request)
+}
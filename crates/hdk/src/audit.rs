@@ -0,0 +1,92 @@
+//! A single place that sees every call the host makes into the zome's `__hdk_` exports,
+//! e.g. to keep a security audit log.
+//!
+//! Every export reports its name and the size of its input to the installed
+//! [ExportAuditor](ExportAuditor) before decoding anything.
+//! Without an auditor, auditing costs a single atomic load.
+
+use holochain_core_types::{
+    bits_n_pieces::u64_split_bits,
+    error::{RibosomeEncodedValue, RibosomeEncodingBits},
+};
+use lazy_static::lazy_static;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    RwLock,
+};
+
+/// Receives every invocation of an `__hdk_` export.
+pub trait ExportAuditor: Send + Sync {
+    /// `input_size` is the length in bytes of the input allocation, 0 if there is none.
+    fn audit(&self, export_name: &str, input_size: usize);
+}
+
+lazy_static! {
+    static ref AUDITOR: RwLock<Option<Box<dyn ExportAuditor>>> = RwLock::new(None);
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Installs the auditor that sees all export invocations from now on.
+/// Passing `None` switches auditing off.
+pub fn set_export_auditor(auditor: Option<Box<dyn ExportAuditor>>) {
+    let mut current = AUDITOR.write().expect("Export auditor poisoned");
+    ENABLED.store(auditor.is_some(), Ordering::SeqCst);
+    *current = auditor;
+}
+
+/// Reports the invocation of `export_name` to the installed auditor, if any.
+/// Called by every export first thing, with its still encoded input.
+pub fn audit_export(export_name: &str, encoded_allocation_of_input: RibosomeEncodingBits) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let input_size = match RibosomeEncodedValue::from(encoded_allocation_of_input) {
+        RibosomeEncodedValue::Allocation(_) => {
+            u64_split_bits(encoded_allocation_of_input).1 as usize
+        }
+        _ => 0,
+    };
+    if let Some(auditor) = AUDITOR.read().expect("Export auditor poisoned").as_ref() {
+        auditor.audit(export_name, input_size);
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::meta::{__hdk_hdk_version, __hdk_validate_link};
+    use holochain_core_types::bits_n_pieces::u64_merge_bits;
+    use std::sync::{Arc, Mutex};
+
+    struct RecordingAuditor(Arc<Mutex<Vec<(String, usize)>>>);
+
+    impl ExportAuditor for RecordingAuditor {
+        fn audit(&self, export_name: &str, input_size: usize) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((export_name.to_string(), input_size));
+        }
+    }
+
+    #[test]
+    fn auditor_sees_export_invocations_with_input_sizes() {
+        let audited = Arc::new(Mutex::new(Vec::new()));
+        set_export_auditor(Some(Box::new(RecordingAuditor(audited.clone()))));
+
+        // Allocations out of bounds, so the exports bail out right after auditing
+        __hdk_hdk_version(u64_merge_bits(u32::max_value(), 42));
+        __hdk_validate_link(u64_merge_bits(u32::max_value(), 7));
+        set_export_auditor(None);
+        __hdk_hdk_version(u64_merge_bits(u32::max_value(), 3));
+
+        assert_eq!(
+            *audited.lock().unwrap(),
+            vec![
+                ("__hdk_hdk_version".to_string(), 42),
+                ("__hdk_validate_link".to_string(), 7),
+            ]
+        );
+    }
+}
@@ -5,19 +5,153 @@ use crate::error::{ZomeApiError, ZomeApiResult};
 use holochain_core_types::{
     agent::AgentId,
     dna::entry_types::EntryTypeDef,
-    entry::{entry_type::EntryType, AppEntryValue, Entry},
+    entry::{
+        entry_type::{AppEntryType, EntryType},
+        AppEntryValue, Entry,
+    },
+    time::Period,
     validation::{EntryValidationData, LinkValidationData, ValidationPackageDefinition},
 };
-use holochain_wasm_utils::api_serialization::validation::LinkDirection;
-use std::convert::TryFrom;
+use holochain_persistence_api::cas::content::Address;
+use holochain_wasm_utils::api_serialization::validation::{LinkDirection, LinkSpec};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    panic::{self, AssertUnwindSafe},
+    time::Duration,
+};
 
 pub type PackageCreator = Box<dyn FnMut() -> ValidationPackageDefinition + Sync>;
 
 pub type Validator = Box<dyn FnMut(EntryValidationData<Entry>) -> Result<(), String> + Sync>;
 
+/// Fills in defaults of an entry before it gets validated.
+pub type EntryDefaulter = Box<dyn FnMut(Entry) -> Result<Entry, String> + Sync>;
+
+thread_local! {
+    /// Validation packages built so far, by entry type, see
+    /// [cached_validation_package](ValidatingEntryType::cached_validation_package).
+    /// Every export sets up a fresh zome definition, so the packages can't be kept there.
+    /// A zome instance runs on a single thread, so this caches them for the whole instance.
+    static PACKAGE_CACHE: RefCell<HashMap<EntryType, ValidationPackageDefinition>> =
+        RefCell::new(HashMap::new());
+}
+
+/// How many new entries of a type an agent may commit within any `period`,
+/// see `rate_limit` in [entry!](entry!).
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateLimit {
+    pub max_entries: usize,
+    pub period: Duration,
+}
+
+impl RateLimit {
+    pub fn new(max_entries: usize, period: Duration) -> Self {
+        RateLimit {
+            max_entries,
+            period,
+        }
+    }
+
+    pub fn per_hour(max_entries: usize) -> Self {
+        RateLimit::new(max_entries, Duration::from_secs(60 * 60))
+    }
+}
+
 pub type AgentValidator = Box<dyn FnMut(EntryValidationData<AgentId>) -> Result<(), String> + Sync>;
+
+/// Validates invariants that span all entries committed together, like debits
+/// having to equal credits. Gets handed the entries in commit order.
+pub type GroupValidator = Box<dyn FnMut(Vec<Entry>) -> Result<(), String> + Sync>;
 pub type LinkValidator = Box<dyn FnMut(LinkValidationData) -> Result<(), String> + Sync>;
 
+/// Link validator that also gets handed the base and the target entry of the link, in that order.
+/// They are `None` if the host didn't send them along.
+pub type LinkedEntriesValidator =
+    Box<dyn FnMut(LinkValidationData, Option<Entry>, Option<Entry>) -> Result<(), String> + Sync>;
+
+/// All the problems a validator found with an entry.
+/// Validators that check several things (like the fields of a form) can collect every
+/// failure and report them at once. Converted into the validator's `String` error,
+/// the failures are encoded as a JSON array, which is what ends up in the failure response:
+/// ```rust,ignore
+/// validation: |validation_data: hdk::EntryValidationData<Form>| {
+///     let mut errors = Vec::new();
+///     // ... push a message for every invalid field
+///     ValidationFailures::from(errors).into_result()
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ValidationFailures(pub Vec<String>);
+
+impl ValidationFailures {
+    /// Ok if no failures were collected, the encoded failures otherwise.
+    pub fn into_result(self) -> Result<(), String> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(self.into())
+        }
+    }
+
+    /// Reads back failures from a validation failure response.
+    /// A plain string (from a validator that reported a single error) becomes a single failure.
+    pub fn from_failure_string(failure: &str) -> Self {
+        serde_json::from_str::<Vec<String>>(failure)
+            .map(ValidationFailures)
+            .unwrap_or_else(|_| ValidationFailures(vec![failure.to_string()]))
+    }
+}
+
+impl From<Vec<String>> for ValidationFailures {
+    fn from(failures: Vec<String>) -> Self {
+        ValidationFailures(failures)
+    }
+}
+
+impl From<ValidationFailures> for String {
+    fn from(failures: ValidationFailures) -> String {
+        serde_json::to_string(&failures.0).expect("Vec<String> always serializes")
+    }
+}
+
+/// Entries a validator asked for through [ValidationResult::NeedData](ValidationResult::NeedData),
+/// by address. `None` means the entry could not be found.
+pub type ValidationDependencies = HashMap<Address, Option<Entry>>;
+
+pub type DependencyValidator =
+    Box<dyn FnMut(EntryValidationData<Entry>, &ValidationDependencies) -> ValidationResult + Sync>;
+
+/// Validator that, for valid entries, returns the links the host should create
+/// once the entry got committed (index hints). An empty list means nothing to index.
+pub type IndexingValidator =
+    Box<dyn FnMut(EntryValidationData<Entry>) -> Result<Vec<LinkSpec>, String> + Sync>;
+
+/// Outcome of a validator that is able to ask for more data while validating.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValidationResult {
+    Valid,
+    Invalid(String),
+    /// The validator can't decide without the entry at the given address.
+    /// It gets called again with that entry added to its dependencies.
+    NeedData(Address),
+    /// The validator can't decide until the entries at the given addresses are held,
+    /// e.g. because the entry references data that did not arrive yet.
+    /// The host keeps the entry pending and validates it again once they are held.
+    Deferred(Vec<Address>),
+}
+
+impl From<Result<(), String>> for ValidationResult {
+    fn from(result: Result<(), String>) -> Self {
+        match result {
+            Ok(()) => ValidationResult::Valid,
+            Err(reason) => ValidationResult::Invalid(reason),
+        }
+    }
+}
+
 /// This struct represents a complete entry type definition.
 /// It wraps [EntryTypeDef](holochain_core_types::dna::entry_types::EntryTypeDef) defined in the DNA crate
 /// which only represents the static parts that show up in the JSON definition
@@ -38,10 +172,292 @@ pub struct ValidatingEntryType {
     pub package_creator: PackageCreator,
     /// This is the validation callback that is used to determine if an entry is valid.
     pub validator: Validator,
+    /// Optional validation callback that can request entries it depends on.
+    /// If set, it is used instead of `validator`.
+    pub dependency_validator: Option<DependencyValidator>,
+    /// Optional validation callback that returns index hints for valid entries.
+    /// If set, it is used instead of `validator`, but not instead of `dependency_validator`.
+    pub indexing_validator: Option<IndexingValidator>,
+    /// Optional callback that fills in defaults of the entry before the validator sees it.
+    pub defaulter: Option<EntryDefaulter>,
+    /// Whether the description has to be valid JSON, see
+    /// [with_json_description](ValidatingEntryType::with_json_description).
+    pub json_description: bool,
+    /// Validators for older versions of the validation rules, by rule version, see
+    /// [with_validator_version](ValidatingEntryType::with_validator_version).
+    pub versioned_validators: BTreeMap<u32, Validator>,
+    /// JSON pointers (like `/owner`) of the fields updates must not change, see
+    /// [with_immutable_fields](ValidatingEntryType::with_immutable_fields).
+    pub immutable_fields: Vec<String>,
+    /// How many new entries of this type an agent may commit, see
+    /// [with_rate_limit](ValidatingEntryType::with_rate_limit).
+    pub rate_limit: Option<RateLimit>,
 
     pub links: Vec<ValidatingLinkDefinition>,
 }
 
+impl ValidatingEntryType {
+    /// Replaces the entry type's validator with one that may return
+    /// [ValidationResult::NeedData](ValidationResult::NeedData) to get hold of
+    /// entries it needs before it can decide.
+    pub fn with_dependency_validator(mut self, validator: DependencyValidator) -> Self {
+        self.dependency_validator = Some(validator);
+        self
+    }
+
+    /// Replaces the entry type's validator with one that also tells which links
+    /// should point to (or from) the entry once it got committed, see
+    /// [IndexingValidator](IndexingValidator).
+    pub fn with_indexing_validator(mut self, validator: IndexingValidator) -> Self {
+        self.indexing_validator = Some(validator);
+        self
+    }
+
+    /// Adds the validator for entries that declare to follow version `rule_version`
+    /// of the validation rules. Entries without a rule version get the regular validator.
+    /// Once an entry type has versioned validators, entries declaring a version
+    /// it has no validator for are invalid.
+    pub fn with_validator_version(mut self, rule_version: u32, validator: Validator) -> Self {
+        self.versioned_validators.insert(rule_version, validator);
+        self
+    }
+
+    /// Rejects updates that change any of the fields at the given JSON pointers
+    /// (like `/created_at`), before the validator runs. See `immutable_fields` in [entry!](entry!).
+    pub fn with_immutable_fields(mut self, pointers: Vec<String>) -> Self {
+        self.immutable_fields = pointers;
+        self
+    }
+
+    /// Fails if the validation data is an update that changes an immutable field.
+    /// A field missing on both sides counts as unchanged.
+    pub fn check_immutable_fields(
+        &self,
+        validation_data: &EntryValidationData<Entry>,
+    ) -> Result<(), String> {
+        if self.immutable_fields.is_empty() {
+            return Ok(());
+        }
+        let (new_entry, old_entry) = match validation_data {
+            EntryValidationData::Modify {
+                new_entry,
+                old_entry,
+                ..
+            } => (new_entry, old_entry),
+            _ => return Ok(()),
+        };
+        let (new_value, old_value) = match (new_entry, old_entry) {
+            (Entry::App(_, new_value), Entry::App(_, old_value)) => (
+                app_entry_json(new_value.clone())?,
+                app_entry_json(old_value.clone())?,
+            ),
+            _ => return Ok(()),
+        };
+        match self
+            .immutable_fields
+            .iter()
+            .find(|pointer| new_value.pointer(pointer) != old_value.pointer(pointer))
+        {
+            Some(pointer) => Err(format!(
+                "Field {} of entry type {} is immutable",
+                pointer, self.name
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Rejects new entries of this type from agents that already committed
+    /// `rate_limit.max_entries` of them within `rate_limit.period` before, see `rate_limit`
+    /// in [entry!](entry!). The validation package gets the agent's chain headers for that.
+    pub fn with_rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+
+    /// Fails if the validation data is a new entry that exceeds the rate limit, going by
+    /// the timestamps of the agent's earlier commits of this type in the validation package.
+    pub fn check_rate_limit(
+        &self,
+        validation_data: &EntryValidationData<Entry>,
+    ) -> Result<(), String> {
+        let rate_limit = match (&self.rate_limit, validation_data) {
+            (Some(rate_limit), EntryValidationData::Create { .. }) => rate_limit,
+            _ => return Ok(()),
+        };
+        let package = &validation_data.validation_data().package;
+        let headers = package.source_chain_headers.as_ref().ok_or_else(|| {
+            format!(
+                "Validation package lacks chain headers for the rate limit of entry type {}",
+                self.name
+            )
+        })?;
+        let committed_at = package.chain_header.timestamp();
+        let window_start = (committed_at - rate_limit.period).map_err(|error| error.to_string())?;
+        let recent_commits = headers
+            .iter()
+            .filter(|header| {
+                *header.entry_type() == self.name
+                    && *header.timestamp() > window_start
+                    && header.timestamp() <= committed_at
+            })
+            .count();
+        if recent_commits >= rate_limit.max_entries {
+            Err(format!(
+                "Agent exceeded the rate limit of {} entries of type {} per {}",
+                rate_limit.max_entries,
+                self.name,
+                Period::from(rate_limit.period)
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Runs only the package creator, i.e. tells which validation package validating
+    /// an entry of this type would need, without validating anything.
+    /// A panicking package creator is reported as error instead of taking the caller down.
+    pub fn build_validation_package(&mut self) -> Result<ValidationPackageDefinition, String> {
+        let package_creator = &mut self.package_creator;
+        let package =
+            panic::catch_unwind(AssertUnwindSafe(|| (*package_creator)())).map_err(|payload| {
+                let reason = payload
+                    .downcast_ref::<&str>()
+                    .map(|reason| reason.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "unknown reason".to_string());
+                format!(
+                    "Package creator of entry type {} panicked: {}",
+                    self.name, reason
+                )
+            })?;
+        Ok(match self.rate_limit {
+            Some(_) => with_chain_headers(package),
+            None => package,
+        })
+    }
+
+    /// Like [build_validation_package](ValidatingEntryType::build_validation_package),
+    /// but the package creator only runs if no package of the entry type is cached yet.
+    /// A successfully built package gets cached for the following calls, also of later
+    /// definitions of the zome.
+    pub fn cached_validation_package(&mut self) -> Result<ValidationPackageDefinition, String> {
+        if let Some(package) = PACKAGE_CACHE.with(|cache| cache.borrow().get(&self.name).cloned()) {
+            return Ok(package);
+        }
+        let package = self.build_validation_package()?;
+        PACKAGE_CACHE.with(|cache| {
+            cache
+                .borrow_mut()
+                .insert(self.name.clone(), package.clone())
+        });
+        Ok(package)
+    }
+
+    /// Sets the callback that fills in defaults of entries of this type
+    /// before they get validated. See `validation_default` in [entry!](entry!).
+    pub fn with_defaults(mut self, defaulter: EntryDefaulter) -> Self {
+        self.defaulter = Some(defaulter);
+        self
+    }
+
+    /// Marks the entry type as deprecated since the given version.
+    /// Creating new entries of this type fails validation from then on,
+    /// while existing entries can still be updated.
+    pub fn with_deprecated_since(mut self, version: &str) -> Self {
+        self.entry_type_definition.deprecated_since = Some(version.to_string());
+        self
+    }
+
+    /// Marks the entry type as derived from other entries.
+    /// Entries of this type fail validation when committed directly,
+    /// only the system can produce them.
+    pub fn with_derived(mut self) -> Self {
+        self.entry_type_definition.derived = true;
+        self
+    }
+
+    /// Requires the description given to [entry!](entry!) to be valid JSON.
+    /// Without this, the description is an opaque string. With it, defining the entry type
+    /// (see [ZomeDefinition::try_define](crate::meta::ZomeDefinition::try_define)) fails
+    /// for malformed JSON instead of putting a broken description into the zome definition.
+    pub fn with_json_description(mut self) -> Self {
+        self.json_description = true;
+        self
+    }
+
+    /// Fails with the parse error if the description is required to be JSON but isn't.
+    pub fn check_description(&self) -> Result<(), String> {
+        if !self.json_description {
+            return Ok(());
+        }
+        let description = String::from(self.entry_type_definition.properties.clone());
+        serde_json::from_str::<serde_json::Value>(&description)
+            .map(|_| ())
+            .map_err(|error| error.to_string())
+    }
+
+    /// Returns the entry with its defaults filled in, which is the form of the entry
+    /// that should be committed.
+    pub fn apply_defaults(&mut self, entry: Entry) -> Result<Entry, String> {
+        match self.defaulter {
+            Some(ref mut defaulter) => (*defaulter)(entry),
+            None => Ok(entry),
+        }
+    }
+
+    /// Fills in the defaults of the new entry in the given validation data.
+    /// Entries that are being deleted are left as they were stored.
+    pub fn apply_defaults_to(
+        &mut self,
+        validation_data: EntryValidationData<Entry>,
+    ) -> Result<EntryValidationData<Entry>, String> {
+        if self.defaulter.is_none() {
+            return Ok(validation_data);
+        }
+        match validation_data {
+            EntryValidationData::Create {
+                entry,
+                validation_data,
+            } => Ok(EntryValidationData::Create {
+                entry: self.apply_defaults(entry)?,
+                validation_data,
+            }),
+            EntryValidationData::Modify {
+                new_entry,
+                old_entry,
+                old_entry_header,
+                validation_data,
+            } => Ok(EntryValidationData::Modify {
+                new_entry: self.apply_defaults(new_entry)?,
+                old_entry,
+                old_entry_header,
+                validation_data,
+            }),
+            delete => Ok(delete),
+        }
+    }
+}
+
+fn app_entry_json(value: AppEntryValue) -> Result<serde_json::Value, String> {
+    serde_json::from_str(&String::from(value)).map_err(|error| error.to_string())
+}
+
+/// `package`, asking for the chain headers too unless it does already.
+fn with_chain_headers(package: ValidationPackageDefinition) -> ValidationPackageDefinition {
+    let has_headers = package.parts().iter().any(|part| match part {
+        ValidationPackageDefinition::ChainHeaders | ValidationPackageDefinition::ChainFull => true,
+        _ => false,
+    });
+    if has_headers {
+        package
+    } else {
+        ValidationPackageDefinition::Combined(vec![
+            package,
+            ValidationPackageDefinition::ChainHeaders,
+        ])
+    }
+}
+
 /// Similar to ValidatingEntryType, this provides the dynamic aspects of link definitions,
 /// the validation callbacks, and thus completes the structs in the DNA crate.
 /// The [entry! macro](entry!) expects an array of links that are represented by
@@ -62,6 +478,190 @@ pub struct ValidatingLinkDefinition {
     pub package_creator: PackageCreator,
     /// This is the validation callback that is used to determine if a link is valid.
     pub validator: LinkValidator,
+    /// Optional validation callback that also sees the linked entries.
+    /// If set, it is used instead of `validator`.
+    pub linked_entries_validator: Option<LinkedEntriesValidator>,
+    /// Optional JSON schema the tags of these links have to conform to,
+    /// or why the schema given to [with_tag_schema](ValidatingLinkDefinition::with_tag_schema) is invalid.
+    pub tag_schema: Option<Result<serde_json::Value, String>>,
+    /// Optional maximum number of these links a single agent may create, across all bases.
+    pub max_links_per_agent: Option<usize>,
+    /// Whether the link stands for a symmetric relationship, i.e. gets validated
+    /// the same way no matter in which direction it is looked at.
+    pub symmetric: bool,
+    /// Optional entry type the targets of these links must have.
+    pub target_type: Option<String>,
+}
+
+impl ValidatingLinkDefinition {
+    /// Replaces the link's validator with one that also receives the base and the target
+    /// entry, e.g. to compare their contents.
+    pub fn with_linked_entries_validator(mut self, validator: LinkedEntriesValidator) -> Self {
+        self.linked_entries_validator = Some(validator);
+        self
+    }
+
+    /// Requires the tags of these links to conform to the given JSON schema
+    /// (see [json_schema](crate::json_schema) for the supported keywords).
+    /// Links with other tags get rejected before the validator runs.
+    /// If `schema` is not valid JSON, all of these links get rejected, saying so.
+    pub fn with_tag_schema(mut self, schema: &str) -> Self {
+        let link_type = self.link_type.clone();
+        self.tag_schema = Some(serde_json::from_str(schema).map_err(|error| {
+            format!(
+                "Tag schema of {} links is not valid JSON: {}",
+                link_type, error
+            )
+        }));
+        self
+    }
+
+    /// Requires the targets of these links to be entries of the given type.
+    /// Links pointing at other entries get rejected before the validator runs.
+    pub fn with_target_type(mut self, target_type: &str) -> Self {
+        self.target_type = Some(target_type.to_string());
+        self
+    }
+
+    /// Checks the type of the link's target against the required one, if there is one.
+    /// `None` means the type of the target is not known.
+    pub fn check_target_type(&self, target_type: Option<&str>) -> Result<(), String> {
+        match (&self.target_type, target_type) {
+            (None, _) => Ok(()),
+            (Some(expected), Some(actual)) if expected == actual => Ok(()),
+            (Some(expected), Some(actual)) => Err(format!(
+                "Target of {} link has to be a {} entry, not a {} entry",
+                self.link_type, expected, actual
+            )),
+            (Some(expected), None) => Err(format!(
+                "Target of {} link has to be a {} entry, but its type is unknown",
+                self.link_type, expected
+            )),
+        }
+    }
+
+    /// Makes the definition match links of its type in both directions,
+    /// for relationships like "peer" that have no direction.
+    pub fn with_symmetric(mut self) -> Self {
+        self.symmetric = true;
+        self
+    }
+
+    /// Whether this definition is the one for links of `link_type` in `direction`.
+    pub fn matches(&self, link_type: &str, direction: &LinkDirection) -> bool {
+        self.link_type == link_type && (self.symmetric || self.direction == *direction)
+    }
+
+    /// Limits how many of these links each agent may create, no matter from which base.
+    /// Links beyond that get rejected before the validator runs.
+    pub fn with_agent_quota(mut self, max_links_per_agent: usize) -> Self {
+        self.max_links_per_agent = Some(max_links_per_agent);
+        self
+    }
+
+    /// Checks that an agent who created `agent_link_count` of these links before
+    /// may create another one.
+    pub fn check_agent_quota(&self, agent_link_count: usize) -> Result<(), String> {
+        match self.max_links_per_agent {
+            Some(max) if agent_link_count >= max => Err(format!(
+                "Agent already created the maximum of {} {} links",
+                max, self.link_type
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks `tag` against the tag schema, if there is one.
+    /// Tags holding a JSON object or array are checked as that object or array,
+    /// all other tags are checked as strings.
+    pub fn check_tag(&self, tag: &str) -> Result<(), String> {
+        let schema = match self.tag_schema {
+            Some(Ok(ref schema)) => schema,
+            Some(Err(ref error)) => return Err(error.clone()),
+            None => return Ok(()),
+        };
+        let tag_value = match serde_json::from_str(tag) {
+            Ok(value @ serde_json::Value::Object(_)) | Ok(value @ serde_json::Value::Array(_)) => {
+                value
+            }
+            _ => serde_json::Value::String(tag.to_string()),
+        };
+        crate::json_schema::check(schema, &tag_value).map_err(|violation| {
+            format!(
+                "Tag {:?} of {} link violates the tag schema: {}",
+                tag, self.link_type, violation
+            )
+        })
+    }
+}
+
+/// The name of an app entry type, as created by [define_entry_name!](define_entry_name!).
+/// Using these instead of string literals makes sure the name given to [entry!](entry!)
+/// and the one used when creating entries can't drift apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EntryName(&'static str);
+
+impl EntryName {
+    pub const fn new(name: &'static str) -> Self {
+        EntryName(name)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        self.0
+    }
+}
+
+impl std::fmt::Display for EntryName {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<EntryName> for AppEntryType {
+    fn from(name: EntryName) -> Self {
+        AppEntryType::from(name.0)
+    }
+}
+
+impl From<EntryName> for EntryType {
+    fn from(name: EntryName) -> Self {
+        EntryType::App(AppEntryType::from(name))
+    }
+}
+
+/// Defines a constant of type [EntryName](entry_definition::EntryName) that can be used
+/// as the `name` in [entry!](entry!) and wherever an [AppEntryType](holochain_core_types::entry::entry_type::AppEntryType)
+/// is needed, e.g. when creating an entry to commit.
+/// ```rust
+/// # #[macro_use]
+/// # extern crate hdk;
+/// # use hdk::holochain_core_types::entry::{entry_type::AppEntryType, Entry};
+/// # use hdk::holochain_json_api::json::JsonString;
+/// define_entry_name!(POST, "post");
+///
+/// # fn main() {
+/// let entry = Entry::App(POST.into(), JsonString::from_json("{}"));
+/// assert_eq!(entry.entry_type().to_string(), "post");
+/// # }
+/// ```
+/// A misspelled name does not compile:
+/// ```compile_fail
+/// # #[macro_use]
+/// # extern crate hdk;
+/// # use hdk::holochain_core_types::entry::{entry_type::AppEntryType, Entry};
+/// # use hdk::holochain_json_api::json::JsonString;
+/// define_entry_name!(POST, "post");
+///
+/// # fn main() {
+/// let entry = Entry::App(POTS.into(), JsonString::from_json("{}"));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! define_entry_name {
+    ($const_name:ident, $name:expr) => {
+        pub const $const_name: $crate::entry_definition::EntryName =
+            $crate::entry_definition::EntryName::new($name);
+    };
 }
 
 /// The `entry` macro is a helper for creating `ValidatingEntryType` definitions
@@ -76,6 +676,8 @@ pub struct ValidatingLinkDefinition {
 /// 5. validation_package: `validation_package` is a special identifier, which declares which data is required from peers
 ///      when attempting to validate entries of this type.
 ///      Possible values are found within [ValidationPackageDefinition](ValidationPackageDefinition)
+///      It can be left out for entry types that only need the entry itself, which is the same as
+///      `validation_package: || hdk::ValidationPackageDefinition::Entry`.
 /// 6. validation: `validation` is a callback function which will be called any time that a
 ///      (DHT) node processes or stores this entry, triggered through actions such as [commit_entry](api::commit_entry()), [update_entry](api::update_entry()), [remove_entry](api::remove_entry()).
 ///      It always expects two arguments, the first of which is the entry attempting to be validated,
@@ -86,6 +688,30 @@ pub struct ValidatingLinkDefinition {
 ///     to define an association pointing from this entry type to another, or one that points back from
 ///     the other entry type to this one.
 ///     See [link!](link!), [to!](to!) and [from!](from!) for more details.
+///
+/// Optionally, a `validation_default: |entry: NativeType| ...` callback can be given between
+/// `validation_package` and `validation`. It receives the deserialized entry and returns it with missing
+/// (optional) fields filled in. The validator only ever sees the defaulted entry.
+/// To also store the defaulted form, commit the result of
+/// [ZomeDefinition::with_entry_defaults](meta::ZomeDefinition::with_entry_defaults).
+///
+/// Optionally, `validation_versions: { 1 => |validation_data: hdk::EntryValidationData<NativeType>| ..., }`
+/// can follow `validation` with the validators of older rule versions.
+/// Entries that declare one of these versions get validated by its validator instead of `validation`,
+/// see [with_validator_version](entry_definition::ValidatingEntryType::with_validator_version).
+///
+/// Optionally, `immutable_fields: ["/owner", "/created_at"],` can be given between
+/// `validation_package` and `validation` with JSON pointers of fields that updates must not change.
+/// Updates changing any of them get rejected before `validation` runs,
+/// see [with_immutable_fields](entry_definition::ValidatingEntryType::with_immutable_fields).
+///
+/// Optionally, `rate_limit: hdk::entry_definition::RateLimit::per_hour(10),` can be given between
+/// `validation_package` and `validation` to reject new entries from agents that already committed
+/// that many entries of the type within the last hour, going by the chain headers that get added
+/// to the validation package, see [with_rate_limit](entry_definition::ValidatingEntryType::with_rate_limit).
+///
+/// These annotations can be combined. The ones between `validation_package` and `validation`
+/// can be given in any order.
 /// # Examples
 /// The following is a standalone Rust file that exports a function which can be called
 /// to get a `ValidatingEntryType` of a "post".
@@ -171,12 +797,97 @@ pub struct ValidatingLinkDefinition {
 
 #[macro_export]
 macro_rules! entry {
+    // The optional annotations between `validation_package` and `validation` get munched
+    // one at a time, in any order, turning each into a call on the built `ValidatingEntryType`.
     (
+        @annotations [ $($definition:tt)* ] [ $($annotation:tt)* ]
+        validation_default: | $default_entry:ident : $default_type:ty | $entry_default:expr,
+        $($rest:tt)*
+    ) => (
+        $crate::entry!(
+            @annotations [ $($definition)* ] [
+                $($annotation)*
+                .with_defaults(Box::new(|entry: $crate::holochain_core_types::entry::Entry| {
+                    $crate::entry_definition::fill_entry_defaults(entry, |$default_entry: $default_type| {
+                        $entry_default
+                    })
+                }))
+            ]
+            $($rest)*
+        )
+    );
+
+    (
+        @annotations [ $($definition:tt)* ] [ $($annotation:tt)* ]
+        immutable_fields: [ $( $pointer:expr ),* $(,)* ],
+        $($rest:tt)*
+    ) => (
+        $crate::entry!(
+            @annotations [ $($definition)* ] [
+                $($annotation)*
+                .with_immutable_fields(vec![ $( String::from($pointer) ),* ])
+            ]
+            $($rest)*
+        )
+    );
+
+    (
+        @annotations [ $($definition:tt)* ] [ $($annotation:tt)* ]
+        rate_limit: $rate_limit:expr,
+        $($rest:tt)*
+    ) => (
+        $crate::entry!(
+            @annotations [ $($definition)* ] [
+                $($annotation)*
+                .with_rate_limit($rate_limit)
+            ]
+            $($rest)*
+        )
+    );
+
+    (
+        @annotations [ $($definition:tt)* ] [ $($annotation:tt)* ]
+        validation: | $validation_data:ident : hdk::EntryValidationData<$native_type:ty> | $entry_validation:expr
+        $(
+            ,
+            validation_versions: {
+                $(
+                    $rule_version:expr => | $versioned_data:ident : hdk::EntryValidationData<$versioned_type:ty> | $versioned_validation:expr
+                ),* $(,)*
+            }
+        )*
+
+        $(
+            ,
+            links : [
+                $( $link_expr:expr ),*
+            ]
+        )*
+    ) => (
+        $crate::entry!(
+            @definition $($definition)*
+            validation: | $validation_data : hdk::EntryValidationData<$native_type> | $entry_validation
+
+            $(
+                ,
+                links : [
+                    $( $link_expr ),*
+                ]
+            )*
+        )
+        $($annotation)*
+        $($(
+            .with_validator_version($rule_version, $crate::entry_validator!(
+                | $versioned_data : hdk::EntryValidationData<$versioned_type> | $versioned_validation
+            ))
+        )*)*
+    );
+
+    (
+        @definition
         name: $name:expr,
         description: $properties:expr,
         sharing: $sharing:expr,
-       // $(native_type: $native_type:ty,)*
-
         validation_package: || $package_creator:expr,
         validation: | $validation_data:ident : hdk::EntryValidationData<$native_type:ty> | $entry_validation:expr
 
@@ -201,6 +912,7 @@ macro_rules! entry {
                             $crate::holochain_core_types::dna::entry_types::LinksTo{
                                 target_type: $link_expr.other_entry_type,
                                 link_type: $link_expr.link_type,
+                                validation_package: None,
                             }
                         );
                     },
@@ -209,6 +921,7 @@ macro_rules! entry {
                             $crate::holochain_core_types::dna::entry_types::LinkedFrom{
                                 base_type: $link_expr.other_entry_type,
                                 link_type: $link_expr.link_type,
+                                validation_package: None,
                             }
                         );
                     }
@@ -220,29 +933,22 @@ macro_rules! entry {
                 $package_creator
             });
 
-            let validator = Box::new(|validation_data: $crate::holochain_wasm_utils::holochain_core_types::validation::EntryValidationData<$crate::holochain_core_types::entry::Entry>| {
-                let $validation_data = $crate::entry_definition::entry_to_native_type::<$native_type>(validation_data.clone())?;
-                use std::convert::TryFrom;
-                let e_type = $crate::holochain_core_types::entry::entry_type::EntryType::try_from(validation_data)?;
-                match e_type {
-                    $crate::holochain_core_types::entry::entry_type::EntryType::App(_) => {
-                        $entry_validation
-                    },
-                    $crate::holochain_core_types::entry::entry_type::EntryType::Deletion =>
-                    {
-                        $entry_validation
-                    }
-                    _ => {
-                        Err(String::from("Schema validation failed"))?
-                    }
-                }
-            });
+            let validator = $crate::entry_validator!(
+                | $validation_data : hdk::EntryValidationData<$native_type> | $entry_validation
+            );
 
             $crate::entry_definition::ValidatingEntryType {
                 name: $crate::holochain_core_types::entry::entry_type::EntryType::App($crate::holochain_core_types::entry::entry_type::AppEntryType::from($name.to_string())),
                 entry_type_definition: entry_type,
                 package_creator,
                 validator,
+                dependency_validator: None,
+                indexing_validator: None,
+                defaulter: None,
+                json_description: false,
+                versioned_validators: std::collections::BTreeMap::new(),
+                immutable_fields: Vec::new(),
+                rate_limit: None,
                 links: vec![
                     $($(
                         $link_expr
@@ -251,6 +957,69 @@ macro_rules! entry {
             }
         }
     );
+
+    (
+        name: $name:expr,
+        description: $properties:expr,
+        sharing: $sharing:expr,
+
+        validation_package: || $package_creator:expr,
+        $($rest:tt)+
+    ) => (
+        $crate::entry!(
+            @annotations [
+                name: $name,
+                description: $properties,
+                sharing: $sharing,
+                validation_package: || $package_creator,
+            ] []
+            $($rest)+
+        )
+    );
+
+    (
+        name: $name:expr,
+        description: $properties:expr,
+        sharing: $sharing:expr,
+
+        $($rest:tt)+
+    ) => (
+        $crate::entry!(
+            name: $name,
+            description: $properties,
+            sharing: $sharing,
+
+            validation_package: || {
+                $crate::holochain_core_types::validation::ValidationPackageDefinition::Entry
+            },
+            $($rest)+
+        )
+    );
+}
+
+/// Turns a validator on the native type of an entry into a [Validator](entry_definition::Validator).
+#[doc(hidden)]
+#[macro_export]
+macro_rules! entry_validator {
+    (| $validation_data:ident : hdk::EntryValidationData<$native_type:ty> | $entry_validation:expr) => (
+        Box::new(|validation_data: $crate::holochain_wasm_utils::holochain_core_types::validation::EntryValidationData<$crate::holochain_core_types::entry::Entry>| {
+            let $validation_data = $crate::entry_definition::entry_to_native_type::<$native_type>(validation_data.clone())?;
+            use std::convert::TryFrom;
+            let e_type = $crate::holochain_core_types::entry::entry_type::EntryType::try_from(validation_data)?;
+            match e_type {
+                $crate::holochain_core_types::entry::entry_type::EntryType::App(_) => {
+                    $entry_validation
+                },
+                $crate::holochain_core_types::entry::entry_type::EntryType::Deletion =>
+                {
+                    $entry_validation
+                }
+                _ => {
+                    Err(String::from("Schema validation failed"))?
+                }
+            }
+        })
+    );
 }
 
 /// The `link` macro is a helper for creating `ValidatingEntryType` definitions
@@ -276,6 +1045,16 @@ macro_rules! entry {
 ///         It always expects three arguments, the first being the base and the second the target of the link.
 ///         The third is the validation `context`, which offers a variety of metadata useful for validation.
 ///         See [ValidationData](ValidationData) for more details.
+///
+/// Optionally, `tag_schema:` can follow `link_type` with a JSON schema (as string) that
+/// the tags of these links have to conform to; links with other tags get rejected before
+/// `validation` runs. See [with_tag_schema](entry_definition::ValidatingLinkDefinition::with_tag_schema).
+///
+/// Optionally, `target_type:` can follow `link_type` with the entry type the link's target has to have;
+/// links pointing at entries of other types get rejected before `validation` runs.
+/// See [with_target_type](entry_definition::ValidatingLinkDefinition::with_target_type).
+///
+/// Both can be given, in any order.
 #[macro_export]
 macro_rules! link {
     (
@@ -304,9 +1083,68 @@ macro_rules! link {
                 link_type: String::from($link_type),
                 package_creator,
                 validator,
+                linked_entries_validator: None,
+                tag_schema: None,
+                max_links_per_agent: None,
+                symmetric: false,
+                target_type: None,
             }
         }
     );
+
+    // The optional keys between `link_type` and `validation_package` get munched
+    // one at a time, in any order, turning each into a call on the built `ValidatingLinkDefinition`.
+    (
+        @options [ $($definition:tt)* ] [ $($option:tt)* ]
+        tag_schema: $tag_schema:expr,
+        $($rest:tt)*
+    ) => (
+        $crate::link!(
+            @options [ $($definition)* ] [ $($option)* .with_tag_schema($tag_schema) ]
+            $($rest)*
+        )
+    );
+
+    (
+        @options [ $($definition:tt)* ] [ $($option:tt)* ]
+        target_type: $target_type:expr,
+        $($rest:tt)*
+    ) => (
+        $crate::link!(
+            @options [ $($definition)* ] [ $($option)* .with_target_type($target_type) ]
+            $($rest)*
+        )
+    );
+
+    (
+        @options [ $($definition:tt)* ] [ $($option:tt)* ]
+        validation_package: || $package_creator:expr,
+        validation: | $validation_data:ident : hdk::LinkValidationData | $link_validation:expr
+    ) => (
+        $crate::link!(
+            $($definition)*
+
+            validation_package: || $package_creator,
+            validation: | $validation_data : hdk::LinkValidationData | $link_validation
+        )
+        $($option)*
+    );
+
+    (
+        direction: $direction:expr,
+        other_type: $other_type:expr,
+        link_type: $link_type:expr,
+        $($rest:tt)+
+    ) => (
+        $crate::link!(
+            @options [
+                direction: $direction,
+                other_type: $other_type,
+                link_type: $link_type,
+            ] []
+            $($rest)+
+        )
+    );
 }
 
 /// The `to` macro is a helper for creating `ValidatingEntryType` definitions
@@ -318,19 +1156,15 @@ macro_rules! to {
     (
         $other_type:expr,
         link_type: $link_type:expr,
-
-        validation_package: || $package_creator:expr,
-        validation: | $validation_data:ident : hdk::LinkValidationData | $link_validation:expr
+        $($rest:tt)+
     ) => (
-        link!(
+        $crate::link!(
             direction: $crate::LinkDirection::To,
             other_type: $other_type,
             link_type: $link_type,
-
-            validation_package: || $package_creator,
-            validation: | $validation_data : hdk::LinkValidationData | $link_validation
+            $($rest)+
         )
-    )
+    );
 }
 
 /// The `from` macro is a helper for creating `ValidatingEntryType` definitions
@@ -342,19 +1176,40 @@ macro_rules! from {
     (
         $other_type:expr,
         link_type: $link_type:expr,
-
-        validation_package: || $package_creator:expr,
-        validation: |  $validation_data:ident : hdk::LinkValidationData | $link_validation:expr
+        $($rest:tt)+
     ) => (
-        link!(
+        $crate::link!(
             direction: $crate::LinkDirection::From,
             other_type: $other_type,
             link_type: $link_type,
-
-            validation_package: || $package_creator,
-            validation: |  $validation_data : hdk::LinkValidationData | $link_validation
+            $($rest)+
         )
-    )
+    );
+}
+
+/// Used by `validation_default` in [entry!](entry!) to run `fill` on the native form of an app entry.
+/// Other entries are returned unchanged.
+pub fn fill_entry_defaults<T, F>(entry: Entry, fill: F) -> Result<Entry, String>
+where
+    T: TryFrom<AppEntryValue> + Into<AppEntryValue> + Clone,
+    F: FnOnce(T) -> T,
+{
+    match entry {
+        Entry::App(app_entry_type, entry_value) => {
+            let native_type = convert_entry_validation_to_native::<T>(Entry::App(
+                app_entry_type.clone(),
+                entry_value,
+            ))?;
+            Ok(Entry::App(app_entry_type, fill(native_type).into()))
+        }
+        entry => Ok(entry),
+    }
+}
+
+/// Converts an app entry into its native type, e.g. a linked entry given to a
+/// [LinkedEntriesValidator](LinkedEntriesValidator).
+pub fn entry_to_native<T: TryFrom<AppEntryValue> + Clone>(entry: Entry) -> ZomeApiResult<T> {
+    convert_entry_validation_to_native::<T>(entry)
 }
 
 //could not turn this to try_from
@@ -420,3 +1275,166 @@ fn convert_entry_validation_to_native<T: TryFrom<AppEntryValue> + Clone>(
         )),
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use crate::prelude::*;
+    use holochain_core_types::entry::entry_type::AppEntryType;
+
+    define_entry_name!(POST, "post");
+
+    #[test]
+    fn entry_name_constant_works_in_entry_macro_and_entries() {
+        #[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+        pub struct Post {
+            content: String,
+        }
+
+        let definition = entry!(
+            name: POST,
+            description: "a post",
+            sharing: Sharing::Public,
+
+            validation_package: || {
+                ValidationPackageDefinition::Entry
+            },
+
+            validation: |_validation_data: hdk::EntryValidationData<Post>| {
+                Ok(())
+            }
+        );
+
+        let entry = Entry::App(
+            POST.into(),
+            Post {
+                content: "hello".to_string(),
+            }
+            .into(),
+        );
+
+        assert_eq!(definition.name, EntryType::from(POST));
+        assert_eq!(entry.entry_type(), definition.name);
+        assert_eq!(AppEntryType::from(POST), AppEntryType::from("post"));
+    }
+
+    #[test]
+    fn build_validation_package_runs_package_creator_only() {
+        #[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+        pub struct Post {
+            content: String,
+        }
+
+        let mut definition = entry!(
+            name: "packaged_post",
+            description: "a post",
+            sharing: Sharing::Public,
+
+            validation_package: || {
+                ValidationPackageDefinition::Entry
+            },
+
+            validation: |_validation_data: hdk::EntryValidationData<Post>| {
+                Err("The validator must not run".to_string())
+            }
+        );
+
+        assert_eq!(
+            definition.build_validation_package(),
+            Ok(ValidationPackageDefinition::Entry)
+        );
+    }
+
+    #[test]
+    fn entry_macro_combines_annotations_in_any_order() {
+        use crate::entry_definition::RateLimit;
+
+        #[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+        pub struct Post {
+            content: String,
+            owner: Option<String>,
+        }
+
+        let definition = entry!(
+            name: "annotated_post",
+            description: "a post",
+            sharing: Sharing::Public,
+
+            validation_package: || {
+                ValidationPackageDefinition::Entry
+            },
+
+            rate_limit: RateLimit::per_hour(10),
+            immutable_fields: ["/owner"],
+            validation_default: |post: Post| Post {
+                owner: post.owner.or_else(|| Some("nobody".to_string())),
+                ..post
+            },
+
+            validation: |_validation_data: hdk::EntryValidationData<Post>| {
+                Ok(())
+            },
+
+            validation_versions: {
+                1 => |_validation_data: hdk::EntryValidationData<Post>| {
+                    Ok(())
+                },
+            }
+        );
+
+        assert_eq!(
+            definition
+                .rate_limit
+                .map(|rate_limit| rate_limit.max_entries),
+            Some(10)
+        );
+        assert_eq!(definition.immutable_fields, vec!["/owner".to_string()]);
+        assert!(definition.defaulter.is_some());
+        assert!(definition.versioned_validators.contains_key(&1));
+    }
+
+    #[test]
+    fn link_macros_combine_options_in_any_order() {
+        let link = to!(
+            "post",
+            link_type: "tagged",
+            target_type: "post",
+            tag_schema: r#"{"type": "string"}"#,
+
+            validation_package: || {
+                ValidationPackageDefinition::Entry
+            },
+
+            validation: |_validation_data: hdk::LinkValidationData| {
+                Ok(())
+            }
+        );
+
+        assert_eq!(link.target_type, Some("post".to_string()));
+        assert!(link.check_tag("draft").is_ok());
+        assert!(link.check_target_type(Some("comment")).is_err());
+    }
+
+    #[test]
+    fn invalid_tag_schema_rejects_links_instead_of_panicking() {
+        let link = to!(
+            "post",
+            link_type: "tagged",
+            tag_schema: r#"{"type": "#,
+
+            validation_package: || {
+                ValidationPackageDefinition::Entry
+            },
+
+            validation: |_validation_data: hdk::LinkValidationData| {
+                Ok(())
+            }
+        );
+
+        let error = link.check_tag("draft").unwrap_err();
+        assert!(
+            error.starts_with("Tag schema of tagged links is not valid JSON"),
+            "unexpected error: {}",
+            error
+        );
+    }
+}
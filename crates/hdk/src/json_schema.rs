@@ -0,0 +1,155 @@
+//! A small JSON schema checker, good enough for the schemas of link tags
+//! (see [ValidatingLinkDefinition::with_tag_schema](crate::entry_definition::ValidatingLinkDefinition::with_tag_schema)).
+//!
+//! Supported keywords are `type`, `enum`, `const`, `minLength`, `maxLength`, `minimum`,
+//! `maximum`, `properties`, `required` and `items`. All other keywords are ignored.
+
+use serde_json::Value;
+
+/// Checks `value` against `schema`.
+/// Returns a description of the first violation found.
+pub fn check(schema: &Value, value: &Value) -> Result<(), String> {
+    check_at("", schema, value)
+}
+
+fn check_at(path: &str, schema: &Value, value: &Value) -> Result<(), String> {
+    let schema = match schema {
+        Value::Bool(true) => return Ok(()),
+        Value::Bool(false) => return Err(format!("{} is not allowed", describe(path))),
+        Value::Object(schema) => schema,
+        _ => return Err("Schema must be an object or a boolean".to_string()),
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(type_name) => vec![type_name.as_str()],
+            Value::Array(type_names) => type_names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.iter().any(|type_name| has_type(value, type_name)) {
+            return Err(format!(
+                "{} must be of type {}",
+                describe(path),
+                allowed.join(" or ")
+            ));
+        }
+    }
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.contains(value) {
+            return Err(format!("{} must be one of {:?}", describe(path), options));
+        }
+    }
+    if let Some(constant) = schema.get("const") {
+        if constant != value {
+            return Err(format!("{} must be {}", describe(path), constant));
+        }
+    }
+
+    if let Value::String(string) = value {
+        let length = string.chars().count() as u64;
+        if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+            if length < min {
+                return Err(format!("{} is shorter than {}", describe(path), min));
+            }
+        }
+        if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+            if length > max {
+                return Err(format!("{} is longer than {}", describe(path), max));
+            }
+        }
+    }
+    if let Some(number) = value.as_f64() {
+        if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+            if number < min {
+                return Err(format!("{} is less than {}", describe(path), min));
+            }
+        }
+        if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+            if number > max {
+                return Err(format!("{} is greater than {}", describe(path), max));
+            }
+        }
+    }
+    if let Value::Object(object) = value {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for key in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(key) {
+                    return Err(format!("{} is missing", describe(&join(path, key))));
+                }
+            }
+        }
+        if let Some(Value::Object(properties)) = schema.get("properties") {
+            for (key, property_schema) in properties {
+                if let Some(property) = object.get(key) {
+                    check_at(&join(path, key), property_schema, property)?;
+                }
+            }
+        }
+    }
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (index, item) in items.iter().enumerate() {
+            check_at(&join(path, &index.to_string()), item_schema, item)?;
+        }
+    }
+    Ok(())
+}
+
+fn has_type(value: &Value, type_name: &str) -> bool {
+    match type_name {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        _ => false,
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn describe(path: &str) -> String {
+    if path.is_empty() {
+        "Value".to_string()
+    } else {
+        format!("\"{}\"", path)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn checks_supported_keywords() {
+        let schema = json!({
+            "type": "object",
+            "required": ["lang"],
+            "properties": {
+                "lang": { "enum": ["en", "de"] },
+                "priority": { "type": "integer", "minimum": 0, "maximum": 9 },
+                "labels": { "type": "array", "items": { "type": "string", "maxLength": 8 } }
+            }
+        });
+        assert_eq!(
+            check(
+                &schema,
+                &json!({"lang": "en", "priority": 3, "labels": ["news"]})
+            ),
+            Ok(())
+        );
+        assert!(check(&schema, &json!("en")).is_err());
+        assert!(check(&schema, &json!({"priority": 3})).is_err());
+        assert!(check(&schema, &json!({"lang": "fr"})).is_err());
+        assert!(check(&schema, &json!({"lang": "en", "priority": 10})).is_err());
+        assert!(check(&schema, &json!({"lang": "en", "labels": ["much too long"]})).is_err());
+    }
+}
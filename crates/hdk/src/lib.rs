@@ -27,18 +27,26 @@ pub use serde_derive;
 pub use serde_json;
 
 pub mod api;
+pub mod audit;
 pub mod utils;
 #[macro_use]
 pub mod entry_definition;
 pub mod error;
 pub mod global_fns;
 pub mod init_globals;
+pub mod json_schema;
 pub mod macros;
 
 pub use holochain_wasm_utils::api_serialization::{validation::*, THIS_INSTANCE};
 
 pub mod meta;
+pub mod metrics;
 pub mod prelude;
+pub mod translation;
+pub mod validation_config;
+pub mod validation_progress;
+pub mod validation_signals;
+pub mod validation_span;
 
 pub use crate::api::*;
 pub use holochain_core_types::validation::*;
@@ -19,6 +19,25 @@ macro_rules! load_json {
     }};
 }
 
+/// Decodes the JSON input of an `__hdk_` export into the given type.
+/// On failure, the export returns the code of an
+/// [ExportFailure::DecodeError](meta/enum.ExportFailure.html), or of an
+/// `ExportFailure::MemoryError` if the input could not even be read.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! decode_args {
+    ($args_type:ty, $encoded_allocation_of_input:expr, $export_name:expr) => {{
+        match $crate::meta::load_export_args::<$args_type>(
+            $encoded_allocation_of_input,
+            $export_name,
+            stringify!($args_type),
+        ) {
+            Ok(input) => input,
+            Err(failure) => return failure.into(),
+        }
+    }};
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! load_string {
@@ -136,6 +155,8 @@ macro_rules! load_string {
 /// # pub fn hc_commit_capability_claim(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
 /// # #[no_mangle]
 /// # pub fn hc_emit_signal(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
+/// # #[no_mangle]
+/// # pub fn hc_validation_oracle_query(_: RibosomeEncodingBits) -> RibosomeEncodingBits { RibosomeEncodedValue::Success.into() }
 ///
 /// # fn main() {
 ///
@@ -233,6 +254,11 @@ macro_rules! define_zome {
             $agent_validation_expr:expr
         }
 
+        $(
+            validate_group: |$group_validation_param:ident : Vec<Entry>| {
+                $group_validation_expr:expr
+            }
+        )*
 
         $(
             receive : |$receive_from:ident, $receive_param:ident| {
@@ -272,6 +298,14 @@ macro_rules! define_zome {
                 $agent_validation_expr
             });
             zd.define_agent_validator(validator);
+
+            $(
+                let group_validator = Box::new(|entries: Vec<hdk::holochain_core_types::entry::Entry>| {
+                    let $group_validation_param = entries;
+                    $group_validation_expr
+                });
+                zd.define_group_validator(group_validator);
+            )*
         }
 
         #[no_mangle]
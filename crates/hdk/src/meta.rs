@@ -4,38 +4,67 @@
 
 use crate::{
     api::G_MEM_STACK,
-    entry_definition::{AgentValidator, ValidatingEntryType},
+    audit::audit_export,
+    entry_definition::{
+        AgentValidator, GroupValidator, ValidatingEntryType, ValidationDependencies,
+        ValidationResult, Validator,
+    },
+    error::ZomeApiResult,
+    metrics::{
+        increment_counter, time_in_ms, VALIDATION_FAIL, VALIDATION_PACKAGE_ASSEMBLY_MS,
+        VALIDATION_PASS,
+    },
+    translation::translate,
+    validation_progress::reset_validation_progress,
+    validation_signals::take_validation_signals,
+    validation_span::{in_validation_span, operation_name},
 };
 use holochain_core_types::{
     dna::{
         entry_types::{deserialize_entry_types, serialize_entry_types},
-        zome::{ZomeEntryTypes, ZomeFnDeclarations, ZomeTraits},
+        zome::{definition_checksum, ZomeEntryTypes, ZomeFnDeclarations, ZomeTraits},
+    },
+    entry::{
+        entry_type::{AppEntryType, EntryType},
+        Entry,
+    },
+    error::{HolochainError, RibosomeEncodedValue, RibosomeEncodingBits, RibosomeErrorCode},
+    validation::{
+        EntryValidationData, ValidationOrigin, ValidationPackage, ValidationPackageDefinition,
     },
-    entry::entry_type::{AppEntryType, EntryType},
-    error::{RibosomeEncodedValue, RibosomeEncodingBits},
 };
 use holochain_json_derive::DefaultJson;
+use lazy_static::lazy_static;
 use serde_derive::{Deserialize, Serialize};
 
 use holochain_json_api::{error::JsonError, json::JsonString};
+use holochain_persistence_api::cas::content::Address;
 
 use holochain_wasm_utils::{
+    api_serialization::meta::{JsonDefinitionArgs, MemoryReport},
     api_serialization::validation::{
-        AgentIdValidationArgs, EntryValidationArgs, LinkValidationArgs, LinkValidationPackageArgs,
+        AgentIdValidationArgs, CommitGroupValidationArgs, DeferredValidation, EntryValidationArgs,
+        EntryValidationPackageArgs, EntryValidationSuccess, LinkDirection, LinkSpec,
+        LinkValidationArgs, LinkValidationPackageArgs, PackageDetail,
     },
-    holochain_core_types::error::RibosomeErrorCode,
     memory::{
-        allocation::AllocationError,
-        ribosome::{load_ribosome_encoded_json, return_code_for_allocation_result},
+        allocation::{AllocationResult, WasmAllocation},
+        ribosome::load_ribosome_encoded_string,
+        MemoryInt,
     },
 };
-use std::{collections::BTreeMap, convert::TryFrom};
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    convert::TryFrom,
+    sync::Mutex,
+};
 
 trait Ribosome {
     fn define_entry_type(&mut self, name: String, entry_type: ValidatingEntryType);
 }
 
-#[derive(Debug, Serialize, Deserialize, DefaultJson, Default)]
+#[derive(Debug, Serialize, Deserialize, DefaultJson, Default, Clone)]
 struct PartialZome {
     #[serde(serialize_with = "serialize_entry_types")]
     #[serde(deserialize_with = "deserialize_entry_types")]
@@ -44,11 +73,71 @@ struct PartialZome {
     fn_declarations: ZomeFnDeclarations,
 }
 
+/// A zome definition together with the checksum of its JSON, as returned by
+/// `__hdk_get_json_definition` when asked for one.
+#[derive(Debug, Serialize, Deserialize, DefaultJson)]
+struct ChecksummedPartialZome {
+    definition: PartialZome,
+    checksum: Address,
+}
+
+impl PartialZome {
+    /// Entry types, traits and functions all serialize in a fixed order,
+    /// so the same definition always gets the same checksum.
+    fn checksum(&self) -> Address {
+        definition_checksum(&String::from(JsonString::from(self.clone())))
+    }
+
+    fn with_checksum(self) -> ChecksummedPartialZome {
+        ChecksummedPartialZome {
+            checksum: self.checksum(),
+            definition: self,
+        }
+    }
+
+    /// Whether the definition in `wrapper` still matches its checksum.
+    pub fn verify_checksum(wrapper: &ChecksummedPartialZome) -> bool {
+        wrapper.definition.checksum() == wrapper.checksum
+    }
+}
+
 #[allow(improper_ctypes)]
 #[derive(Default)]
 pub struct ZomeDefinition {
     pub entry_types: Vec<ValidatingEntryType>,
     pub agent_entry_validator: Option<AgentValidator>,
+    pub group_validator: Option<GroupValidator>,
+    pub fallback_validator: Option<Validator>,
+}
+
+lazy_static! {
+    /// Held while `zome_setup` runs, see [ZomeDefinition::set_up](ZomeDefinition::set_up).
+    static ref ZOME_SETUP: Mutex<()> = Mutex::new(());
+}
+
+thread_local! {
+    /// Whether `zome_setup` runs on this thread, so setting up the zome again from
+    /// within it fails instead of waiting for `ZOME_SETUP` forever.
+    static IN_ZOME_SETUP: Cell<bool> = Cell::new(false);
+}
+
+/// Marks this thread as running `zome_setup` until dropped, even if the setup panics.
+struct ZomeSetupGuard;
+
+impl ZomeSetupGuard {
+    fn enter() -> Result<ZomeSetupGuard, String> {
+        if IN_ZOME_SETUP.with(Cell::get) {
+            return Err("The zome can't be set up again from within zome_setup".to_string());
+        }
+        IN_ZOME_SETUP.with(|in_setup| in_setup.set(true));
+        Ok(ZomeSetupGuard)
+    }
+}
+
+impl Drop for ZomeSetupGuard {
+    fn drop(&mut self) {
+        IN_ZOME_SETUP.with(|in_setup| in_setup.set(false));
+    }
 }
 
 impl ZomeDefinition {
@@ -56,14 +145,194 @@ impl ZomeDefinition {
         ZomeDefinition::default()
     }
 
+    /// A fresh definition of the zome, set up by its `zome_setup`.
+    /// Every export sets up its own definition, so exports never share one.
+    /// The host may call exports from several threads at once though, so `zome_setup`
+    /// only ever runs on one thread at a time, in case it touches shared state.
+    /// Its thread local state (like the validation config) stays with the calling thread.
+    pub(crate) fn set_up() -> ZomeDefinition {
+        ZomeDefinition::set_up_with(|zd| unsafe { zome_setup(zd) })
+    }
+
+    /// Like [set_up](ZomeDefinition::set_up), but fails if called from within `zome_setup`,
+    /// e.g. by [with_entry_defaults](ZomeDefinition::with_entry_defaults).
+    fn try_set_up() -> Result<ZomeDefinition, String> {
+        ZomeDefinition::try_set_up_with(|zd| unsafe { zome_setup(zd) })
+    }
+
+    fn set_up_with<S: FnOnce(&mut ZomeDefinition)>(setup: S) -> ZomeDefinition {
+        ZomeDefinition::try_set_up_with(setup).expect("Exports don't run within zome_setup")
+    }
+
+    fn try_set_up_with<S: FnOnce(&mut ZomeDefinition)>(setup: S) -> Result<ZomeDefinition, String> {
+        // Checked before locking, the lock is held by this very thread on re-entry
+        let _in_setup = ZomeSetupGuard::enter()?;
+        // A setup that panicked on another thread left no definition behind to worry about
+        let _setting_up = ZOME_SETUP
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut zd = ZomeDefinition::new();
+        setup(&mut zd);
+        Ok(zd)
+    }
+
+    /// Adds the entry type to the zome.
+    /// Panics if the entry type can't be defined, see [try_define](ZomeDefinition::try_define).
     #[allow(dead_code)]
     pub fn define(&mut self, entry_type: ValidatingEntryType) {
+        if let Err(error) = self.try_define(entry_type) {
+            panic!("{}", error);
+        }
+    }
+
+    /// Adds the entry type to the zome, unless its name is empty or only whitespace,
+    /// or its description is required to be JSON but isn't.
+    pub fn try_define(&mut self, entry_type: ValidatingEntryType) -> Result<(), HolochainError> {
+        if let EntryType::App(ref app_entry_type) = entry_type.name {
+            if String::from(app_entry_type.clone()).trim().is_empty() {
+                return Err(HolochainError::ErrorGeneric(
+                    "Entry types need a name, but entry! was given an empty one".to_string(),
+                ));
+            }
+        }
+        entry_type.check_description().map_err(|error| {
+            HolochainError::ErrorGeneric(format!(
+                "Description of entry type {} is not valid JSON: {}",
+                String::from(entry_type.name.clone()),
+                error
+            ))
+        })?;
         self.entry_types.push(entry_type);
+        Ok(())
+    }
+
+    /// Runs the package creator of every entry type once and caches the packages,
+    /// so that validations don't have to build them anymore. The cache outlives the
+    /// definition, later exports get the packages from it too.
+    /// Returns the package (or the reason it could not be built) of every entry type.
+    pub fn prewarm_packages(
+        &mut self,
+    ) -> Vec<(EntryType, Result<ValidationPackageDefinition, String>)> {
+        self.entry_types
+            .iter_mut()
+            .map(|entry_type| {
+                (
+                    entry_type.name.clone(),
+                    entry_type.cached_validation_package(),
+                )
+            })
+            .collect()
     }
 
     pub fn define_agent_validator(&mut self, agent_validator: AgentValidator) {
         self.agent_entry_validator = Some(agent_validator);
     }
+
+    /// Sets the validator that gets to see all entries of a commit group at once,
+    /// after each of them passed its own validation.
+    pub fn define_group_validator(&mut self, group_validator: GroupValidator) {
+        self.group_validator = Some(group_validator);
+    }
+
+    /// Sets the validator for entries of types the zome doesn't define, e.g. types
+    /// that a newer version of the zome added. It can accept such entries (maybe with
+    /// a warning) or reject them. Without it, validating them fails with `UnknownEntryType`.
+    pub fn define_fallback_validator(&mut self, fallback_validator: Validator) {
+        self.fallback_validator = Some(fallback_validator);
+    }
+
+    /// Registers a typed config that all validators of the zome can borrow with
+    /// [ValidationArgs::config](crate::validation_config::ValidationArgs::config).
+    /// Meant to be called once in `zome_setup`.
+    pub fn set_validation_config<T: 'static>(&mut self, config: T) {
+        crate::validation_config::register(config);
+    }
+
+    /// Names of the traits the zome declares, without their functions.
+    /// Cheaper to check for a trait's presence than going through the whole trait map.
+    pub fn trait_names() -> Vec<String> {
+        unsafe { __list_traits() }.keys().cloned().collect()
+    }
+
+    /// Fills in the defaults of the entry's type (see `validation_default` in [entry!](entry!)).
+    /// Entries of types without defaults are returned unchanged.
+    pub fn apply_entry_defaults(&mut self, entry: Entry) -> Result<Entry, String> {
+        let entry_type = entry.entry_type();
+        match self
+            .entry_types
+            .iter_mut()
+            .find(|validating_entry_type| validating_entry_type.name == entry_type)
+        {
+            Some(validating_entry_type) => validating_entry_type.apply_defaults(entry),
+            None => Ok(entry),
+        }
+    }
+
+    /// The entry with the defaults of this zome's entry types filled in.
+    /// Committing this form makes the stored entry the one the validator saw.
+    /// Fails if called from within `zome_setup`.
+    pub fn with_entry_defaults(entry: Entry) -> Result<Entry, String> {
+        let mut zd = ZomeDefinition::try_set_up()?;
+        zd.apply_entry_defaults(entry)
+    }
+
+    /// Runs the validator of `entry_type` with the given package instead of a rebuilt one,
+    /// to reproduce locally how a node that validated the entry with `package` decided.
+    /// Dependency validators only get the entries the package references.
+    pub fn replay_validation(
+        entry_type: &EntryType,
+        validation_data: EntryValidationData<Entry>,
+        package: ValidationPackage,
+    ) -> Result<(), String> {
+        let zd = ZomeDefinition::try_set_up()?;
+        replay_validation(zd, entry_type, validation_data, package)
+    }
+}
+
+/// See [ZomeDefinition::replay_validation](ZomeDefinition::replay_validation).
+fn replay_validation(
+    zd: ZomeDefinition,
+    entry_type: &EntryType,
+    mut validation_data: EntryValidationData<Entry>,
+    package: ValidationPackage,
+) -> Result<(), String> {
+    let entry_type_name = String::from(entry_type.clone());
+    match EntryType::try_from(validation_data.clone()) {
+        Ok(ref validated_type) if validated_type == entry_type => (),
+        _ => {
+            return Err(format!(
+                "Validation data is not about an entry of type {}",
+                entry_type_name
+            ))
+        }
+    }
+    let entry_type_definition = zd
+        .entry_types
+        .into_iter()
+        .find(|validating_entry_type| validating_entry_type.name == *entry_type)
+        .ok_or_else(|| format!("Entry type {} is not defined", entry_type_name))?;
+
+    let references = package.clone();
+    validation_data.validation_data_mut().package = package;
+    let operation = operation_name(&validation_data);
+    let mut fetch = |address: &Address| -> ZomeApiResult<Option<Entry>> {
+        Ok(references.referenced_entry(address).cloned())
+    };
+    run_entry_validator(
+        entry_type_definition,
+        validation_data,
+        None,
+        &entry_type_name,
+        operation,
+        &mut fetch,
+    )
+    .map_err(|_| {
+        format!(
+            "Validator of entry type {} could not be run",
+            entry_type_name
+        )
+    })?
+    .map(|_| ())
 }
 
 #[allow(improper_ctypes)]
@@ -73,119 +342,552 @@ extern "C" {
     fn __list_functions() -> ZomeFnDeclarations;
 }
 
+/// Turns the input of an export into its argument type,
+/// with an error that says which export got what kind of garbage.
+#[doc(hidden)]
+pub fn decode_export_args<T>(
+    export_name: &str,
+    expected_type: &str,
+    input: String,
+) -> Result<T, HolochainError>
+where
+    T: TryFrom<JsonString>,
+    T::Error: Into<HolochainError>,
+{
+    T::try_from(JsonString::from_json(&input)).map_err(|error| {
+        HolochainError::SerializationError(format!(
+            "{}: could not decode input as {}: {}",
+            export_name,
+            expected_type,
+            error.into()
+        ))
+    })
+}
+
+/// Every way an `__hdk_` export can fail, apart from a validator rejecting its input,
+/// which gets reported to the host as the validator's fail string.
+/// Each failure has its own [RibosomeErrorCode](RibosomeErrorCode) that never changes,
+/// so the host can tell them apart by code alone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFailure {
+    /// The zome defines no entry type, link or callback matching the input
+    NoMatchingType,
+    /// An update would change the type of the entry
+    TypeMismatch,
+    /// The validator could not come to a verdict, e.g. because a dependency could not be fetched
+    ValidatorError,
+    /// The validation package could not be handed to the host
+    PackageError,
+    /// The input could not be decoded into what the export expects
+    DecodeError,
+    /// Input or output could not be moved through the wasm memory
+    MemoryError,
+    /// The zome definition is larger than the host accepts
+    DefinitionTooLarge,
+}
+
+impl ExportFailure {
+    pub fn code(self) -> RibosomeErrorCode {
+        match self {
+            ExportFailure::NoMatchingType => RibosomeErrorCode::UnknownEntryType,
+            ExportFailure::TypeMismatch => RibosomeErrorCode::EntryTypeMismatch,
+            ExportFailure::ValidatorError => RibosomeErrorCode::ValidatorFailed,
+            ExportFailure::PackageError => RibosomeErrorCode::ValidationPackageFailed,
+            ExportFailure::DecodeError => RibosomeErrorCode::ArgumentDeserializationFailed,
+            ExportFailure::MemoryError => RibosomeErrorCode::OutOfMemory,
+            ExportFailure::DefinitionTooLarge => RibosomeErrorCode::DefinitionTooLarge,
+        }
+    }
+}
+
+impl From<ExportFailure> for RibosomeEncodedValue {
+    fn from(failure: ExportFailure) -> Self {
+        RibosomeEncodedValue::Failure(failure.code())
+    }
+}
+
+impl From<ExportFailure> for RibosomeEncodingBits {
+    fn from(failure: ExportFailure) -> Self {
+        RibosomeEncodedValue::from(failure).into()
+    }
+}
+
+/// Used by [decode_args!](macro.decode_args.html) to read and decode the input of an export.
+#[doc(hidden)]
+pub fn load_export_args<T>(
+    encoded_allocation_of_input: RibosomeEncodingBits,
+    export_name: &str,
+    expected_type: &str,
+) -> Result<T, ExportFailure>
+where
+    T: TryFrom<JsonString>,
+    T::Error: Into<HolochainError>,
+{
+    let input = load_ribosome_encoded_string(encoded_allocation_of_input)
+        .map_err(|_| ExportFailure::MemoryError)?;
+    decode_export_args(export_name, expected_type, input).map_err(|_| ExportFailure::DecodeError)
+}
+
+/// Sets up the wasm memory for an export from the allocation of its input.
+fn init_export_memory(
+    encoded_allocation_of_input: RibosomeEncodingBits,
+) -> Result<WasmAllocation, ExportFailure> {
+    crate::global_fns::init_global_memory_from_ribosome_encoding(encoded_allocation_of_input)
+        .map_err(|_| ExportFailure::MemoryError)
+}
+
+/// The encoding of an export's output that got written to the wasm memory.
+fn export_output(written: AllocationResult) -> RibosomeEncodingBits {
+    match written {
+        Ok(allocation) => RibosomeEncodedValue::from(allocation).into(),
+        Err(_) => ExportFailure::MemoryError.into(),
+    }
+}
+
+/// Writes the validation package the host asked for, if there is one.
+fn package_output(package: Option<ValidationPackageDefinition>) -> RibosomeEncodingBits {
+    match package {
+        None => ExportFailure::NoMatchingType.into(),
+        Some(package) => match crate::global_fns::write_json(package) {
+            Ok(allocation) => RibosomeEncodedValue::from(allocation).into(),
+            Err(_) => ExportFailure::PackageError.into(),
+        },
+    }
+}
+
+/// Writes the fail string of a validator that rejected the input.
+fn fail_string_output(fail_string: &str) -> RibosomeEncodingBits {
+    export_output(crate::global_fns::write_json(JsonString::from_json(
+        fail_string,
+    )))
+}
+
 #[no_mangle]
 pub extern "C" fn __hdk_get_validation_package_for_entry_type(
     encoded_allocation_of_input: RibosomeEncodingBits,
 ) -> RibosomeEncodingBits {
-    let allocation = match crate::global_fns::init_global_memory_from_ribosome_encoding(
+    audit_export(
+        "__hdk_get_validation_package_for_entry_type",
         encoded_allocation_of_input,
-    ) {
+    );
+
+    let allocation = match init_export_memory(encoded_allocation_of_input) {
         Ok(allocation) => allocation,
-        Err(allocation_error) => return allocation_error.as_ribosome_encoding(),
+        Err(failure) => return failure.into(),
     };
 
-    let mut zd = ZomeDefinition::new();
-    unsafe { zome_setup(&mut zd) };
+    let zd = ZomeDefinition::set_up();
 
-    let name = allocation.read_to_string();
+    let args = entry_validation_package_args(allocation.read_to_string());
+    match validation_package_for_entry_type(zd, args) {
+        Ok(package) => package_output(Some(package)),
+        Err(failure) => failure.into(),
+    }
+}
+
+/// The host may send just the entry type name, which means a full package.
+fn entry_validation_package_args(input: String) -> EntryValidationPackageArgs {
+    EntryValidationPackageArgs::try_from(JsonString::from_json(&input)).unwrap_or_else(|_| {
+        EntryValidationPackageArgs {
+            entry_type: input,
+            detail_level: PackageDetail::Full,
+        }
+    })
+}
 
-    match zd
+/// Builds the validation package of the requested entry type, like
+/// [ValidatingEntryType::build_validation_package](ValidatingEntryType::build_validation_package)
+/// does, unless it is cached already, and trims it down to a digest if that is all
+/// the host asked for.
+fn validation_package_for_entry_type(
+    zd: ZomeDefinition,
+    args: EntryValidationPackageArgs,
+) -> Result<ValidationPackageDefinition, ExportFailure> {
+    let entry_type_name = args.entry_type;
+    let entry_type = EntryType::App(AppEntryType::from(entry_type_name.clone()));
+    let mut entry_type_definition = zd
         .entry_types
         .into_iter()
-        .find(|ref validating_entry_type| {
-            validating_entry_type.name == EntryType::App(AppEntryType::from(name.clone()))
-        }) {
-        None => RibosomeEncodedValue::Failure(RibosomeErrorCode::CallbackFailed).into(),
-        Some(mut entry_type_definition) => {
-            let package = (*entry_type_definition.package_creator)();
-            return_code_for_allocation_result(crate::global_fns::write_json(package)).into()
-        }
-    }
+        .find(|validating_entry_type| validating_entry_type.name == entry_type)
+        .ok_or(ExportFailure::NoMatchingType)?;
+    let package = time_in_ms(VALIDATION_PACKAGE_ASSEMBLY_MS, &entry_type_name, || {
+        entry_type_definition.cached_validation_package()
+    })
+    .map_err(|_| ExportFailure::PackageError)?;
+    Ok(match args.detail_level {
+        PackageDetail::Full => package,
+        PackageDetail::Digest => package.digest(),
+    })
 }
 
 #[no_mangle]
 pub extern "C" fn __hdk_validate_app_entry(
     encoded_allocation_of_input: RibosomeEncodingBits,
 ) -> RibosomeEncodingBits {
-    if let Err(allocation_error) =
-        crate::global_fns::init_global_memory_from_ribosome_encoding(encoded_allocation_of_input)
-    {
-        return allocation_error.as_ribosome_encoding();
+    audit_export("__hdk_validate_app_entry", encoded_allocation_of_input);
+
+    if let Err(failure) = init_export_memory(encoded_allocation_of_input) {
+        return failure.into();
     }
 
-    let mut zd = ZomeDefinition::new();
-    unsafe { zome_setup(&mut zd) };
+    let zd = ZomeDefinition::set_up();
 
     // Deserialize input
-    let input: EntryValidationArgs = match load_ribosome_encoded_json(encoded_allocation_of_input) {
-        Ok(v) => v,
-        Err(e) => return RibosomeEncodedValue::from(e).into(),
-    };
+    let input = crate::decode_args!(
+        EntryValidationArgs,
+        encoded_allocation_of_input,
+        "__hdk_validate_app_entry"
+    );
 
-    let entry_type = match EntryType::try_from(input.validation_data.clone()) {
-        Ok(v) => v,
-        Err(e) => return RibosomeEncodedValue::from(e).into(),
-    };
+    // Signals and deferrals left over from an earlier call don't belong to this validation
+    take_validation_signals();
+    take_deferred_dependencies();
+    let result = validate_app_entry(zd, input, crate::api::get_entry);
+    forward_validation_signals(&result, |name, arguments| {
+        crate::api::emit_signal(name, arguments)
+    });
+    match result {
+        Err(encoded_error) => encoded_error.into(),
+        Ok(Ok(index_hints)) => {
+            if index_hints.is_empty() {
+                RibosomeEncodedValue::Success.into()
+            } else {
+                export_output(crate::global_fns::write_json(
+                    EntryValidationSuccess::IndexHints(index_hints),
+                ))
+            }
+        }
+        Ok(Err(fail_string)) => match take_deferred_dependencies() {
+            Some(dependencies) => {
+                export_output(crate::global_fns::write_json(DeferredValidation {
+                    dependencies,
+                }))
+            }
+            None => fail_string_output(&fail_string),
+        },
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn __hdk_validate_commit_group(
+    encoded_allocation_of_input: RibosomeEncodingBits,
+) -> RibosomeEncodingBits {
+    audit_export("__hdk_validate_commit_group", encoded_allocation_of_input);
+
+    if let Err(failure) = init_export_memory(encoded_allocation_of_input) {
+        return failure.into();
+    }
+
+    // Deserialize input
+    let input = crate::decode_args!(
+        CommitGroupValidationArgs,
+        encoded_allocation_of_input,
+        "__hdk_validate_commit_group"
+    );
+
+    take_validation_signals();
+    let result = validate_commit_group(ZomeDefinition::set_up, input, crate::api::get_entry);
+    forward_validation_signals(&result, |name, arguments| {
+        crate::api::emit_signal(name, arguments)
+    });
+    match result {
+        Err(encoded_error) => encoded_error.into(),
+        Ok(Ok(())) => RibosomeEncodedValue::Success.into(),
+        Ok(Err(fail_string)) => fail_string_output(&fail_string),
+    }
+}
+
+/// Hands the signals the validator emitted with
+/// [emit_on_success](crate::validation_signals::emit_on_success) to `emit` if the
+/// validation succeeded. They get dropped if it failed.
+fn forward_validation_signals<T, E>(
+    result: &Result<Result<T, String>, RibosomeEncodedValue>,
+    mut emit: E,
+) where
+    E: FnMut(String, JsonString) -> ZomeApiResult<()>,
+{
+    let signals = take_validation_signals();
+    if let Ok(Ok(_)) = result {
+        for (name, arguments) in signals {
+            // Emitting a signal can't fail the validation it belongs to
+            let _ = emit(name, arguments);
+        }
+    }
+}
+
+/// Validates every entry of the commit group on its own, like
+/// [validate_app_entry](validate_app_entry) does, and then all of them together
+/// with the zome's group validator, if there is one.
+/// The first entry failing its own validation fails the whole group.
+/// `zome_definition` has to return a freshly set up definition of the zome.
+fn validate_commit_group<D, F>(
+    zome_definition: D,
+    input: CommitGroupValidationArgs,
+    mut fetch: F,
+) -> Result<Result<(), String>, RibosomeEncodedValue>
+where
+    D: Fn() -> ZomeDefinition,
+    F: FnMut(&Address) -> ZomeApiResult<Option<Entry>>,
+{
+    let mut entries = Vec::with_capacity(input.entries.len());
+    for entry_args in input.entries {
+        let entry = match &entry_args.validation_data {
+            EntryValidationData::Create { entry, .. } => entry.clone(),
+            EntryValidationData::Modify { new_entry, .. } => new_entry.clone(),
+            EntryValidationData::Delete { old_entry, .. } => old_entry.clone(),
+        };
+        if let Err(fail_string) = validate_app_entry(zome_definition(), entry_args, &mut fetch)? {
+            return Ok(Err(fail_string));
+        }
+        entries.push(entry);
+    }
+
+    match zome_definition().group_validator {
+        Some(mut group_validator) => Ok((*group_validator)(entries)),
+        None => Ok(Ok(())),
+    }
+}
+
+thread_local! {
+    /// What the last dependency validator deferred its decision on, see `ValidationResult::Deferred`.
+    static DEFERRED_DEPENDENCIES: RefCell<Option<Vec<Address>>> = RefCell::new(None);
+}
+
+/// Takes the dependencies of a deferred validation, if the last validation got deferred.
+fn take_deferred_dependencies() -> Option<Vec<Address>> {
+    DEFERRED_DEPENDENCIES.with(|deferred| deferred.replace(None))
+}
+
+/// How often a dependency validator may ask for more data before we give up.
+const MAX_VALIDATION_ITERATIONS: usize = 16;
+
+/// Looks up the validator for the entry type contained in the given args and runs it.
+/// The outer error is returned if no validator could be run at all, the inner
+/// result is the verdict of the validator itself.
+/// Entries requested by a dependency validator are retrieved with `fetch`.
+/// A valid entry comes with the index hints its validator returned, if any.
+fn validate_app_entry<F>(
+    zd: ZomeDefinition,
+    mut input: EntryValidationArgs,
+    mut fetch: F,
+) -> Result<Result<Vec<LinkSpec>, String>, RibosomeEncodedValue>
+where
+    F: FnMut(&Address) -> ZomeApiResult<Option<Entry>>,
+{
+    let entry_type = EntryType::try_from(input.validation_data.clone())
+        .map_err(|_| RibosomeEncodedValue::from(ExportFailure::DecodeError))?;
+
+    // An update must not change the type of an entry, otherwise we would
+    // run the new entry's validator on an old entry of a different type
+    if let EntryValidationData::Modify {
+        new_entry,
+        old_entry,
+        ..
+    } = &input.validation_data
+    {
+        if new_entry.entry_type() != old_entry.entry_type() {
+            return Err(ExportFailure::TypeMismatch.into());
+        }
+    }
+
+    // The host tells us where the entry comes from, what the DNA's properties are,
+    // which seed to use, when the entry got committed and which zome and DNA we are;
+    // make sure validators see that through the validation data they get handed.
+    let validation_data = input.validation_data.validation_data_mut();
+    validation_data.origin = input.origin.clone();
+    validation_data.dna_properties = input.dna_properties.clone();
+    validation_data.seed = input.seed;
+    validation_data.timestamp = input.timestamp.clone();
+    validation_data.zome_name = input.zome_name.clone();
+    validation_data.dna_hash = input.dna_hash.clone();
 
-    match zd
+    let entry_type_definition = match zd
         .entry_types
         .into_iter()
         .find(|ref validating_entry_type| validating_entry_type.name == entry_type)
     {
-        None => RibosomeErrorCode::CallbackFailed as RibosomeEncodingBits,
-        Some(mut entry_type_definition) => {
-            let validation_result = (*entry_type_definition.validator)(input.validation_data);
-
-            match validation_result {
-                Ok(()) => RibosomeEncodedValue::Success.into(),
-                Err(fail_string) => return_code_for_allocation_result(
-                    crate::global_fns::write_json(JsonString::from_json(&fail_string)),
-                )
-                .into(),
+        Some(entry_type_definition) => entry_type_definition,
+        None => {
+            let mut fallback_validator = zd
+                .fallback_validator
+                .ok_or_else(|| RibosomeEncodedValue::from(ExportFailure::NoMatchingType))?;
+            return Ok((*fallback_validator)(input.validation_data)
+                .map(|()| Vec::new())
+                .map_err(|fail_string| translate(fail_string, input.locale.as_ref())));
+        }
+    };
+
+    let entry_type_name = String::from(entry_type);
+    let operation = operation_name(&input.validation_data);
+
+    // Existing entries of a deprecated type can still be updated, just not created
+    let deprecated_since = match input.validation_data {
+        EntryValidationData::Create { .. } => entry_type_definition
+            .entry_type_definition
+            .deprecated_since
+            .clone(),
+        _ => None,
+    };
+    // Entries of a derived type only get onto the source chain through the system
+    let committed_directly = entry_type_definition.entry_type_definition.derived
+        && input.origin == ValidationOrigin::Local;
+
+    let result = match deprecated_since {
+        _ if committed_directly => Err(format!(
+            "Entry type {} is derived, its entries can only be produced by the system",
+            entry_type_name
+        )),
+        Some(version) => Err(format!(
+            "Entry type {} is deprecated since {}, new entries can't be created",
+            entry_type_name, version
+        )),
+        None => {
+            reset_validation_progress();
+            run_entry_validator(
+                entry_type_definition,
+                input.validation_data,
+                input.rule_version,
+                &entry_type_name,
+                operation,
+                &mut fetch,
+            )?
+        }
+    };
+    increment_counter(
+        if result.is_ok() {
+            VALIDATION_PASS
+        } else {
+            VALIDATION_FAIL
+        },
+        &entry_type_name,
+    );
+    Ok(result.map_err(|fail_string| translate(fail_string, input.locale.as_ref())))
+}
+
+/// Runs the (dependency) validator of the given entry type definition,
+/// or the validator of the rule version the entry declares, if the entry type has versions.
+fn run_entry_validator<F>(
+    mut entry_type_definition: ValidatingEntryType,
+    validation_data: EntryValidationData<Entry>,
+    rule_version: Option<u32>,
+    entry_type_name: &str,
+    operation: &'static str,
+    fetch: &mut F,
+) -> Result<Result<Vec<LinkSpec>, String>, RibosomeEncodedValue>
+where
+    F: FnMut(&Address) -> ZomeApiResult<Option<Entry>>,
+{
+    if let Err(fail_string) = entry_type_definition.check_immutable_fields(&validation_data) {
+        return Ok(Err(fail_string));
+    }
+    if let Err(fail_string) = entry_type_definition.check_rate_limit(&validation_data) {
+        return Ok(Err(fail_string));
+    }
+    let validation_data = match entry_type_definition.apply_defaults_to(validation_data) {
+        Ok(validation_data) => validation_data,
+        Err(fail_string) => return Ok(Err(fail_string)),
+    };
+
+    if let Some(rule_version) = rule_version {
+        if !entry_type_definition.versioned_validators.is_empty() {
+            return Ok(
+                match entry_type_definition
+                    .versioned_validators
+                    .get_mut(&rule_version)
+                {
+                    Some(validator) => in_validation_span(entry_type_name, operation, || {
+                        (*validator)(validation_data).map(|_| Vec::new())
+                    }),
+                    None => Err(format!(
+                        "Entry type {} has no validator for rule version {}",
+                        entry_type_name, rule_version
+                    )),
+                },
+            );
+        }
+    }
+
+    let mut dependency_validator = match entry_type_definition.dependency_validator.take() {
+        Some(dependency_validator) => dependency_validator,
+        None => {
+            if let Some(mut indexing_validator) = entry_type_definition.indexing_validator.take() {
+                return Ok(in_validation_span(entry_type_name, operation, || {
+                    (*indexing_validator)(validation_data)
+                }));
+            }
+            let validator = &mut entry_type_definition.validator;
+            return Ok(in_validation_span(entry_type_name, operation, || {
+                (*validator)(validation_data).map(|_| Vec::new())
+            }));
+        }
+    };
+
+    let mut dependencies = ValidationDependencies::new();
+    for _ in 0..MAX_VALIDATION_ITERATIONS {
+        let validation_data = validation_data.clone();
+        let result = in_validation_span(entry_type_name, operation, || {
+            (*dependency_validator)(validation_data, &dependencies)
+        });
+        match result {
+            ValidationResult::Valid => return Ok(Ok(Vec::new())),
+            ValidationResult::Invalid(fail_string) => return Ok(Err(fail_string)),
+            ValidationResult::Deferred(dependencies) => {
+                let fail_string = format!(
+                    "Validation deferred until {} dependencies are held",
+                    dependencies.len()
+                );
+                DEFERRED_DEPENDENCIES.with(|deferred| deferred.replace(Some(dependencies)));
+                return Ok(Err(fail_string));
+            }
+            ValidationResult::NeedData(address) => {
+                if dependencies.contains_key(&address) {
+                    return Ok(Err(format!(
+                        "Validator requested {} again although it was provided",
+                        address
+                    )));
+                }
+                let entry = fetch(&address)
+                    .map_err(|_| RibosomeEncodedValue::from(ExportFailure::ValidatorError))?;
+                dependencies.insert(address, entry);
             }
         }
     }
+    Ok(Err(format!(
+        "Validator still needed data after {} iterations",
+        MAX_VALIDATION_ITERATIONS
+    )))
 }
 
 #[no_mangle]
 pub extern "C" fn __hdk_validate_agent_entry(
     encoded_allocation_of_input: RibosomeEncodingBits,
 ) -> RibosomeEncodingBits {
-    if let Err(allocation_error) =
-        crate::global_fns::init_global_memory_from_ribosome_encoding(encoded_allocation_of_input)
-    {
-        return allocation_error.as_ribosome_encoding();
+    audit_export("__hdk_validate_agent_entry", encoded_allocation_of_input);
+
+    if let Err(failure) = init_export_memory(encoded_allocation_of_input) {
+        return failure.into();
     }
 
-    let mut zd = ZomeDefinition::new();
-    unsafe { zome_setup(&mut zd) };
+    let zd = ZomeDefinition::set_up();
 
     //get the validator code
     let mut validator = match zd.agent_entry_validator {
-        None => {
-            return return_code_for_allocation_result(crate::global_fns::write_json(
-                JsonString::from_json("No agent validation callback registered for zome."),
-            ))
-            .into();
-        }
+        None => return ExportFailure::NoMatchingType.into(),
         Some(v) => v,
     };
 
     // Deserialize input
-    let input: AgentIdValidationArgs = match load_ribosome_encoded_json(encoded_allocation_of_input)
-    {
-        Ok(v) => v,
-        Err(e) => return RibosomeEncodedValue::from(e).into(),
-    };
+    let input = crate::decode_args!(
+        AgentIdValidationArgs,
+        encoded_allocation_of_input,
+        "__hdk_validate_agent_entry"
+    );
 
     let validation_result = (*validator)(input.validation_data);
 
     match validation_result {
         Ok(()) => RibosomeEncodedValue::Success.into(),
-        Err(fail_string) => return_code_for_allocation_result(crate::global_fns::write_json(
-            JsonString::from_json(&fail_string),
-        ))
-        .into(),
+        Err(fail_string) => fail_string_output(&fail_string),
     }
 }
 
@@ -193,174 +895,298 @@ pub extern "C" fn __hdk_validate_agent_entry(
 pub extern "C" fn __hdk_get_validation_package_for_link(
     encoded_allocation_of_input: RibosomeEncodingBits,
 ) -> RibosomeEncodingBits {
-    if let Err(allocation_error) =
-        crate::global_fns::init_global_memory_from_ribosome_encoding(encoded_allocation_of_input)
-    {
-        return allocation_error.as_ribosome_encoding();
-    };
+    audit_export(
+        "__hdk_get_validation_package_for_link",
+        encoded_allocation_of_input,
+    );
+
+    if let Err(failure) = init_export_memory(encoded_allocation_of_input) {
+        return failure.into();
+    }
 
-    let mut zd = ZomeDefinition::new();
-    unsafe { zome_setup(&mut zd) };
+    let zd = ZomeDefinition::set_up();
 
-    let input: LinkValidationPackageArgs =
-        match load_ribosome_encoded_json(encoded_allocation_of_input) {
-            Ok(v) => v,
-            Err(e) => return RibosomeEncodedValue::from(e).into(),
-        };
+    let input = crate::decode_args!(
+        LinkValidationPackageArgs,
+        encoded_allocation_of_input,
+        "__hdk_get_validation_package_for_link"
+    );
 
-    RibosomeEncodingBits::from(
-        zd.entry_types
-            .into_iter()
-            .find(|ref validation_entry_type| {
-                validation_entry_type.name == EntryType::from(input.entry_type.clone())
-            })
-            .and_then(|entry_type| {
-                entry_type.links.into_iter().find(|ref link_definition| {
-                    link_definition.link_type == input.link_type
-                        && link_definition.direction == input.direction
-                })
-            })
-            .and_then(|mut link_definition| {
-                let package = (*link_definition.package_creator)();
-                Some(return_code_for_allocation_result(
-                    crate::global_fns::write_json(package),
-                ))
-            })
-            .unwrap_or(RibosomeEncodedValue::Failure(
-                RibosomeErrorCode::CallbackFailed,
-            )),
-    )
+    package_output(validation_package_for_link(zd, input))
+}
+
+/// Runs the package creator of the requested link definition.
+fn validation_package_for_link(
+    zd: ZomeDefinition,
+    input: LinkValidationPackageArgs,
+) -> Option<ValidationPackageDefinition> {
+    zd.entry_types
+        .into_iter()
+        .find(|ref validation_entry_type| {
+            validation_entry_type.name == EntryType::from(input.entry_type.clone())
+        })
+        .and_then(|entry_type| {
+            entry_type
+                .links
+                .into_iter()
+                .find(|link_definition| link_definition.matches(&input.link_type, &input.direction))
+        })
+        .map(|mut link_definition| (*link_definition.package_creator)())
 }
 
 #[no_mangle]
 pub extern "C" fn __hdk_validate_link(
     encoded_allocation_of_input: RibosomeEncodingBits,
 ) -> RibosomeEncodingBits {
-    if let Err(allocation_error) =
-        crate::global_fns::init_global_memory_from_ribosome_encoding(encoded_allocation_of_input)
-    {
-        return allocation_error.as_ribosome_encoding();
-    };
+    audit_export("__hdk_validate_link", encoded_allocation_of_input);
 
-    let mut zd = ZomeDefinition::new();
-    unsafe { zome_setup(&mut zd) }
+    if let Err(failure) = init_export_memory(encoded_allocation_of_input) {
+        return failure.into();
+    }
 
-    let input: LinkValidationArgs = match load_ribosome_encoded_json(encoded_allocation_of_input) {
-        Ok(v) => v,
-        Err(e) => return RibosomeEncodedValue::from(e).into(),
-    };
+    let zd = ZomeDefinition::set_up();
 
-    RibosomeEncodingBits::from(
-        zd.entry_types
-            .into_iter()
-            .find(|ref validation_entry_type| {
-                validation_entry_type.name == EntryType::from(input.entry_type.clone())
-            })
-            .and_then(|entry_type_definition| {
-                entry_type_definition
-                    .links
-                    .into_iter()
-                    .find(|link_definition| {
-                        link_definition.link_type == *input.link.link_type()
-                            && link_definition.direction == input.direction
-                    })
-            })
-            .and_then(|mut link_definition| {
-                let validation_result = (*link_definition.validator)(input.validation_data);
-                Some(match validation_result {
-                    Ok(()) => RibosomeEncodedValue::Success,
-                    Err(fail_string) => return_code_for_allocation_result(
-                        crate::global_fns::write_json(JsonString::from_json(&fail_string)),
-                    ),
-                })
-            })
-            .unwrap_or(RibosomeEncodedValue::Failure(
-                RibosomeErrorCode::CallbackFailed,
-            )),
-    )
+    let input = crate::decode_args!(
+        LinkValidationArgs,
+        encoded_allocation_of_input,
+        "__hdk_validate_link"
+    );
+
+    match validate_link(zd, input) {
+        None => ExportFailure::NoMatchingType.into(),
+        Some(Ok(())) => RibosomeEncodedValue::Success.into(),
+        Some(Err(fail_string)) => fail_string_output(&fail_string),
+    }
+}
+
+/// Runs the validator of the link definition matching the input,
+/// after checking the link's tag against the definition's tag schema.
+/// Returns `None` if there is no such link definition.
+fn validate_link(zd: ZomeDefinition, input: LinkValidationArgs) -> Option<Result<(), String>> {
+    let LinkValidationArgs {
+        entry_type,
+        link,
+        direction,
+        validation_data,
+        base_entry,
+        target_entry,
+        agent_link_count,
+        target_type,
+    } = input;
+    let target_type = target_type.or_else(|| {
+        target_entry
+            .as_ref()
+            .map(|entry| entry.entry_type().to_string())
+    });
+    zd.entry_types
+        .into_iter()
+        .find(|validation_entry_type| {
+            validation_entry_type.name == EntryType::from(entry_type.clone())
+        })
+        .and_then(|entry_type_definition| {
+            entry_type_definition
+                .links
+                .into_iter()
+                .find(|link_definition| link_definition.matches(link.link_type(), &direction))
+        })
+        .map(|mut link_definition| {
+            link_definition.check_tag(link.tag())?;
+            link_definition.check_agent_quota(agent_link_count)?;
+            link_definition.check_target_type(target_type.as_ref().map(String::as_str))?;
+            match link_definition.linked_entries_validator {
+                Some(ref mut validator) => (*validator)(validation_data, base_entry, target_entry),
+                None => (*link_definition.validator)(validation_data),
+            }
+        })
+}
+
+lazy_static! {
+    static ref CURRENT_HDK_VERSION: String =
+        holochain_core_types::hdk_version::HDK_VERSION.to_string();
+}
+
+/// The version of the HDK the zome was built with, e.g. to log it or to
+/// branch on it. Same as what `__hdk_hdk_version` reports to the host.
+pub fn current_hdk_version() -> &'static str {
+    &CURRENT_HDK_VERSION
 }
 
 #[no_mangle]
 pub extern "C" fn __hdk_hdk_version(
     encoded_allocation_of_input: RibosomeEncodingBits,
 ) -> RibosomeEncodingBits {
-    if let Err(allocation_error) =
-        crate::global_fns::init_global_memory_from_ribosome_encoding(encoded_allocation_of_input)
-    {
-        return allocation_error.as_ribosome_encoding();
+    audit_export("__hdk_hdk_version", encoded_allocation_of_input);
+
+    if let Err(failure) = init_export_memory(encoded_allocation_of_input) {
+        return failure.into();
     }
 
     let mut mem_stack = unsafe {
         match G_MEM_STACK {
             Some(mem_stack) => mem_stack,
             None => {
-                return AllocationError::BadStackAlignment.as_ribosome_encoding();
+                return ExportFailure::MemoryError.into();
             }
         }
     };
 
-    return_code_for_allocation_result(
-        mem_stack.write_string(
-            holochain_core_types::hdk_version::HDK_VERSION
-                .to_string()
-                .as_ref(),
-        ),
-    )
-    .into()
+    export_output(mem_stack.write_string(current_hdk_version()))
 }
 
-#[no_mangle]
-pub extern "C" fn __hdk_get_json_definition(
-    encoded_allocation_of_input: RibosomeEncodingBits,
-) -> RibosomeEncodingBits {
-    if let Err(allocation_error) =
-        crate::global_fns::init_global_memory_from_ribosome_encoding(encoded_allocation_of_input)
-    {
-        return allocation_error.as_ribosome_encoding();
-    }
-
-    let mut zd = ZomeDefinition::new();
-    unsafe { zome_setup(&mut zd) };
-
+/// Collects the static definitions of all entry types in the zome.
+/// Each link definition gets annotated with the kind of validation package
+/// its validation requests, which means calling every link's package creator once.
+fn entry_types_definition(zd: ZomeDefinition) -> ZomeEntryTypes {
     let mut entry_types = BTreeMap::new();
-    for validating_entry_type in zd.entry_types {
+    for mut validating_entry_type in zd.entry_types {
+        let definition = &mut validating_entry_type.entry_type_definition;
+        for link in validating_entry_type.links.iter_mut() {
+            let validation_package = Some(String::from((*link.package_creator)().discriminant()));
+            match link.direction {
+                LinkDirection::To => definition
+                    .links_to
+                    .iter_mut()
+                    .filter(|links_to| {
+                        links_to.target_type == link.other_entry_type
+                            && links_to.link_type == link.link_type
+                    })
+                    .for_each(|links_to| links_to.validation_package = validation_package.clone()),
+                LinkDirection::From => definition
+                    .linked_from
+                    .iter_mut()
+                    .filter(|linked_from| {
+                        linked_from.base_type == link.other_entry_type
+                            && linked_from.link_type == link.link_type
+                    })
+                    .for_each(|linked_from| {
+                        linked_from.validation_package = validation_package.clone()
+                    }),
+            }
+        }
         entry_types.insert(
             validating_entry_type.name,
             validating_entry_type.entry_type_definition,
         );
     }
+    entry_types
+}
+
+#[no_mangle]
+pub extern "C" fn __hdk_get_json_definition(
+    encoded_allocation_of_input: RibosomeEncodingBits,
+) -> RibosomeEncodingBits {
+    audit_export("__hdk_get_json_definition", encoded_allocation_of_input);
+
+    if let Err(failure) = init_export_memory(encoded_allocation_of_input) {
+        return failure.into();
+    }
+
+    let args = crate::decode_args!(
+        JsonDefinitionArgs,
+        encoded_allocation_of_input,
+        "__hdk_get_json_definition"
+    );
 
-    let traits = unsafe { __list_traits() };
-    let fn_declarations = unsafe { __list_functions() };
+    let zd = ZomeDefinition::set_up();
 
-    let partial_zome = PartialZome {
-        entry_types,
-        traits,
-        fn_declarations,
+    let json_string = match json_definition(partial_zome(zd), &args) {
+        Ok(json_string) => json_string,
+        Err(_) => return ExportFailure::DefinitionTooLarge.into(),
     };
 
-    let json_string = JsonString::from(partial_zome);
-
     let mut mem_stack = unsafe {
         match G_MEM_STACK {
             Some(mem_stack) => mem_stack,
             None => {
-                return AllocationError::BadStackAlignment.as_ribosome_encoding();
+                return ExportFailure::MemoryError.into();
             }
         }
     };
 
-    return_code_for_allocation_result(mem_stack.write_string(&String::from(json_string))).into()
+    export_output(mem_stack.write_string(&json_string))
+}
+
+/// The zome's entry types together with its traits and functions.
+fn partial_zome(zd: ZomeDefinition) -> PartialZome {
+    PartialZome {
+        entry_types: entry_types_definition(zd),
+        traits: unsafe { __list_traits() },
+        fn_declarations: unsafe { __list_functions() },
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn __hdk_memory_report(
+    encoded_allocation_of_input: RibosomeEncodingBits,
+) -> RibosomeEncodingBits {
+    audit_export("__hdk_memory_report", encoded_allocation_of_input);
+
+    if let Err(failure) = init_export_memory(encoded_allocation_of_input) {
+        return failure.into();
+    }
+
+    let mut mem_stack = unsafe {
+        match G_MEM_STACK {
+            Some(mem_stack) => mem_stack,
+            None => {
+                return ExportFailure::MemoryError.into();
+            }
+        }
+    };
+
+    let zd = ZomeDefinition::set_up();
+
+    let allocated_bytes = u64::from(MemoryInt::from(mem_stack.top()));
+    export_output(mem_stack.write_json(memory_report(partial_zome(zd), allocated_bytes)))
+}
+
+/// What `__hdk_memory_report` tells the host about the zome.
+fn memory_report(partial_zome: PartialZome, allocated_bytes: u64) -> MemoryReport {
+    MemoryReport {
+        allocated_bytes,
+        definition_bytes: String::from(JsonString::from(partial_zome)).len() as u64,
+    }
+}
+
+/// Serializes the definition of the zome the way the host asked for it.
+/// Fails if it comes out larger than the host's `max_size`.
+fn json_definition(
+    partial_zome: PartialZome,
+    args: &JsonDefinitionArgs,
+) -> Result<String, HolochainError> {
+    let json_string = if args.with_checksum {
+        JsonString::from(partial_zome.with_checksum())
+    } else {
+        JsonString::from(partial_zome)
+    };
+    let json = String::from(json_string);
+    match args.max_size {
+        Some(max_size) if json.len() > max_size => Err(HolochainError::ErrorGeneric(format!(
+            "Zome definition is {} bytes, larger than the maximum of {} bytes",
+            json.len(),
+            max_size
+        ))),
+        _ => Ok(json),
+    }
 }
 
 #[cfg(test)]
 pub mod tests {
-    use crate::{meta::PartialZome, prelude::*, ValidationPackageDefinition};
+    use crate::{
+        error::ZomeApiResult,
+        meta::{ChecksummedPartialZome, PartialZome},
+        prelude::*,
+        ValidationPackageDefinition,
+    };
     use holochain_core_types::dna::{
         entry_types::Sharing,
+        fn_declarations::TraitFns,
         zome::{ZomeFnDeclarations, ZomeTraits},
     };
+    use holochain_core_types::{entry::Entry, validation::ValidationOrigin};
     use holochain_json_api::{error::JsonError, json::JsonString};
+    use holochain_persistence_api::cas::content::Address;
+    use holochain_wasm_utils::api_serialization::validation::EntryValidationArgs;
     use std::collections::BTreeMap;
 
     // Adding empty zome_setup() so that the cfg(test) build can link.
@@ -369,7 +1195,10 @@ pub mod tests {
 
     #[no_mangle]
     pub fn __list_traits() -> ZomeTraits {
-        BTreeMap::new()
+        let mut traits = BTreeMap::new();
+        traits.insert("hc_public".to_string(), TraitFns::new());
+        traits.insert("transfer".to_string(), TraitFns::new());
+        traits
     }
 
     #[no_mangle]
@@ -377,25 +1206,58 @@ pub mod tests {
         Vec::new()
     }
 
-    #[test]
-    fn partial_zome_json() {
-        #[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
-        pub struct Post {
-            content: String,
-            date_created: String,
+    fn no_fetch(address: &Address) -> ZomeApiResult<Option<Entry>> {
+        panic!("Validator unexpectedly requested {}", address)
+    }
+
+    /// The entry most validators in these tests get to see.
+    #[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+    pub struct Post {
+        content: String,
+    }
+
+    /// `entry!` for a public entry type whose validators only need the entry itself.
+    macro_rules! public_entry {
+        (name: $name:expr, description: $description:expr, $($rest:tt)*) => {
+            entry!(
+                name: $name,
+                description: $description,
+                sharing: Sharing::Public,
+
+                validation_package: || {
+                    ValidationPackageDefinition::Entry
+                },
+
+                $($rest)*
+            )
+        };
+    }
+
+    /// A zome defining just the given entry type.
+    fn zome_with(
+        entry_type: crate::entry_definition::ValidatingEntryType,
+    ) -> super::ZomeDefinition {
+        let mut zd = super::ZomeDefinition::new();
+        zd.define(entry_type);
+        zd
+    }
+
+    /// Arguments for validating an entry that reached us from another node.
+    fn remote_args(validation_data: EntryValidationData<Entry>) -> EntryValidationArgs {
+        EntryValidationArgs {
+            validation_data,
+            origin: ValidationOrigin::Remote,
+            ..Default::default()
         }
+    }
 
+    #[test]
+    fn partial_zome_json() {
         let mut entry_types = BTreeMap::new();
 
-        let validating_entry_type = entry!(
+        let validating_entry_type = public_entry!(
             name: "post",
             description: "{\"description\": \"blog entry post\"}",
-            sharing: Sharing::Public,
-
-
-            validation_package: || {
-                ValidationPackageDefinition::Entry
-            },
 
             validation: |_validation_data: hdk::EntryValidationData<Post>| {
                 Ok(())
@@ -417,4 +1279,3138 @@ pub mod tests {
             JsonString::from_json("{\"entry_types\":{\"post\":{\"properties\":\"{\\\"description\\\": \\\"blog entry post\\\"}\",\"sharing\":\"public\",\"links_to\":[],\"linked_from\":[]}},\"traits\":{},\"fn_declarations\":[]}"),
         );
     }
+
+    #[test]
+    fn checksum_verifies_unmodified_definition_only() {
+        use std::convert::TryFrom;
+
+        let partial_zome = PartialZome {
+            traits: __list_traits(),
+            ..Default::default()
+        };
+        let wrapper = partial_zome.with_checksum();
+        assert!(PartialZome::verify_checksum(&wrapper));
+
+        // still verifies after going over the wire
+        let mut wrapper =
+            ChecksummedPartialZome::try_from(JsonString::from(wrapper)).expect("valid wrapper");
+        assert!(PartialZome::verify_checksum(&wrapper));
+
+        wrapper
+            .definition
+            .traits
+            .insert("sneaky".to_string(), TraitFns::new());
+        assert!(!PartialZome::verify_checksum(&wrapper));
+    }
+
+    #[test]
+    fn link_validator_can_reject_self_referential_links() {
+        use holochain_core_types::{
+            agent::test_agent_id,
+            chain_header::test_chain_header,
+            link::{link_data::LinkData, Link, LinkActionKind},
+            validation::{LinkValidationData, ValidationData},
+        };
+        use holochain_wasm_utils::api_serialization::validation::{
+            LinkDirection, LinkValidationArgs,
+        };
+
+        let zome_definition = || {
+            zome_with(public_entry!(
+                name: "post",
+                description: "{\"description\": \"a post\"}",
+
+                validation: |_validation_data: hdk::EntryValidationData<Post>| {
+                    Ok(())
+                },
+
+                links: [
+                    to!(
+                        "post",
+                        link_type: "reply",
+
+                        validation_package: || {
+                            ValidationPackageDefinition::Entry
+                        },
+
+                        validation: |validation_data: hdk::LinkValidationData| {
+                            if validation_data.base() == validation_data.target() {
+                                Err("A post can't reply to itself".to_string())
+                            } else {
+                                Ok(())
+                            }
+                        }
+                    )
+                ]
+            ))
+        };
+
+        let args = |base: &str, target: &str| {
+            let link = Link::new(&Address::from(base), &Address::from(target), "reply", "");
+            LinkValidationArgs {
+                entry_type: "post".to_string(),
+                link: link.clone(),
+                direction: LinkDirection::To,
+                validation_data: LinkValidationData::LinkAdd {
+                    link: LinkData::from_link(
+                        &link,
+                        LinkActionKind::ADD,
+                        test_chain_header(),
+                        test_agent_id(),
+                    ),
+                    validation_data: ValidationData::default(),
+                },
+                base_entry: None,
+                target_entry: None,
+                agent_link_count: 0,
+                target_type: None,
+            }
+        };
+
+        let self_reply = args("QmPost", "QmPost");
+        assert_eq!(self_reply.base(), self_reply.target());
+        assert_eq!(
+            super::validate_link(zome_definition(), self_reply),
+            Some(Err("A post can't reply to itself".to_string())),
+        );
+        assert_eq!(
+            super::validate_link(zome_definition(), args("QmReply", "QmPost")),
+            Some(Ok(())),
+        );
+    }
+
+    #[test]
+    fn link_tag_violating_schema_is_rejected_before_validator_runs() {
+        use holochain_core_types::{
+            agent::test_agent_id,
+            chain_header::test_chain_header,
+            link::{link_data::LinkData, Link, LinkActionKind},
+            validation::{LinkValidationData, ValidationData},
+        };
+        use holochain_wasm_utils::api_serialization::validation::{
+            LinkDirection, LinkValidationArgs,
+        };
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static VALIDATOR_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+        pub struct Document {
+            title: String,
+        }
+
+        let zome_definition = || {
+            zome_with(public_entry!(
+                name: "document",
+                description: "{\"description\": \"a document\"}",
+
+                validation: |_validation_data: hdk::EntryValidationData<Document>| {
+                    Ok(())
+                },
+
+                links: [
+                    to!(
+                        "document",
+                        link_type: "revision",
+                        tag_schema: r#"{"type": "string", "enum": ["draft", "final"]}"#,
+
+                        validation_package: || {
+                            ValidationPackageDefinition::Entry
+                        },
+
+                        validation: |_validation_data: hdk::LinkValidationData| {
+                            VALIDATOR_RUNS.fetch_add(1, Ordering::SeqCst);
+                            Ok(())
+                        }
+                    )
+                ]
+            ))
+        };
+        let args = |tag: &str| {
+            let link = Link::new(
+                &Address::from("QmBase"),
+                &Address::from("QmTarget"),
+                "revision",
+                tag,
+            );
+            LinkValidationArgs {
+                entry_type: "document".to_string(),
+                link: link.clone(),
+                direction: LinkDirection::To,
+                validation_data: LinkValidationData::LinkAdd {
+                    link: LinkData::from_link(
+                        &link,
+                        LinkActionKind::ADD,
+                        test_chain_header(),
+                        test_agent_id(),
+                    ),
+                    validation_data: ValidationData::default(),
+                },
+                base_entry: None,
+                target_entry: None,
+                agent_link_count: 0,
+                target_type: None,
+            }
+        };
+
+        match super::validate_link(zome_definition(), args("published")) {
+            Some(Err(fail_string)) => assert!(
+                fail_string.contains("violates the tag schema"),
+                "unexpected failure: {}",
+                fail_string
+            ),
+            other => panic!("Expected a schema violation, got {:?}", other),
+        }
+        assert_eq!(VALIDATOR_RUNS.load(Ordering::SeqCst), 0);
+
+        assert_eq!(
+            super::validate_link(zome_definition(), args("final")),
+            Some(Ok(()))
+        );
+        assert_eq!(VALIDATOR_RUNS.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn link_beyond_agent_quota_is_rejected() {
+        use holochain_core_types::{
+            agent::test_agent_id,
+            chain_header::test_chain_header,
+            link::{link_data::LinkData, Link, LinkActionKind},
+            validation::{LinkValidationData, ValidationData},
+        };
+        use holochain_wasm_utils::api_serialization::validation::{
+            LinkDirection, LinkValidationArgs,
+        };
+
+        #[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+        pub struct Profile {
+            name: String,
+        }
+
+        let zome_definition = || {
+            zome_with(public_entry!(
+                name: "profile",
+                description: "{\"description\": \"a profile\"}",
+
+                validation: |_validation_data: hdk::EntryValidationData<Profile>| {
+                    Ok(())
+                },
+
+                links: [
+                    to!(
+                        "profile",
+                        link_type: "follows",
+
+                        validation_package: || {
+                            ValidationPackageDefinition::Entry
+                        },
+
+                        validation: |_validation_data: hdk::LinkValidationData| {
+                            Ok(())
+                        }
+                    )
+                    .with_agent_quota(2)
+                ]
+            ))
+        };
+        let args = |agent_link_count: usize| {
+            let link = Link::new(
+                &Address::from("QmFollower"),
+                &Address::from("QmFollowed"),
+                "follows",
+                "",
+            );
+            LinkValidationArgs {
+                entry_type: "profile".to_string(),
+                link: link.clone(),
+                direction: LinkDirection::To,
+                validation_data: LinkValidationData::LinkAdd {
+                    link: LinkData::from_link(
+                        &link,
+                        LinkActionKind::ADD,
+                        test_chain_header(),
+                        test_agent_id(),
+                    ),
+                    validation_data: ValidationData::default(),
+                },
+                base_entry: None,
+                target_entry: None,
+                agent_link_count,
+                target_type: None,
+            }
+        };
+
+        assert_eq!(
+            super::validate_link(zome_definition(), args(1)),
+            Some(Ok(()))
+        );
+        match super::validate_link(zome_definition(), args(2)) {
+            Some(Err(fail_string)) => assert!(
+                fail_string.contains("maximum of 2 follows links"),
+                "unexpected failure: {}",
+                fail_string
+            ),
+            other => panic!("Expected the agent quota to be exceeded, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn link_to_target_of_wrong_type_is_rejected() {
+        use holochain_core_types::{
+            agent::test_agent_id,
+            chain_header::test_chain_header,
+            link::{link_data::LinkData, Link, LinkActionKind},
+            validation::{LinkValidationData, ValidationData},
+        };
+        use holochain_wasm_utils::api_serialization::validation::{
+            LinkDirection, LinkValidationArgs,
+        };
+
+        #[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+        pub struct Profile {
+            name: String,
+        }
+
+        let zome_definition = || {
+            zome_with(public_entry!(
+                name: "profile",
+                description: "{\"description\": \"a profile\"}",
+
+                validation: |_validation_data: hdk::EntryValidationData<Profile>| {
+                    Ok(())
+                },
+
+                links: [
+                    to!(
+                        "profile",
+                        link_type: "follows",
+                        target_type: "profile",
+
+                        validation_package: || {
+                            ValidationPackageDefinition::Entry
+                        },
+
+                        validation: |_validation_data: hdk::LinkValidationData| {
+                            Ok(())
+                        }
+                    )
+                ]
+            ))
+        };
+        let args = |target_type: &str| {
+            let link = Link::new(
+                &Address::from("QmFollower"),
+                &Address::from("QmFollowed"),
+                "follows",
+                "",
+            );
+            LinkValidationArgs {
+                entry_type: "profile".to_string(),
+                link: link.clone(),
+                direction: LinkDirection::To,
+                validation_data: LinkValidationData::LinkAdd {
+                    link: LinkData::from_link(
+                        &link,
+                        LinkActionKind::ADD,
+                        test_chain_header(),
+                        test_agent_id(),
+                    ),
+                    validation_data: ValidationData::default(),
+                },
+                base_entry: None,
+                target_entry: None,
+                agent_link_count: 0,
+                target_type: Some(target_type.to_string()),
+            }
+        };
+
+        assert_eq!(
+            super::validate_link(zome_definition(), args("profile")),
+            Some(Ok(()))
+        );
+        assert_eq!(
+            super::validate_link(zome_definition(), args("comment")),
+            Some(Err(
+                "Target of follows link has to be a profile entry, not a comment entry".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn symmetric_link_validates_in_both_directions() {
+        use holochain_core_types::{
+            agent::test_agent_id,
+            chain_header::test_chain_header,
+            link::{link_data::LinkData, Link, LinkActionKind},
+            validation::{LinkValidationData, ValidationData},
+        };
+        use holochain_wasm_utils::api_serialization::validation::{
+            LinkDirection, LinkValidationArgs, LinkValidationPackageArgs,
+        };
+
+        #[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+        pub struct Person {
+            name: String,
+        }
+
+        let zome_definition = || {
+            zome_with(public_entry!(
+                name: "person",
+                description: "{\"description\": \"a person\"}",
+
+                validation: |_validation_data: hdk::EntryValidationData<Person>| {
+                    Ok(())
+                },
+
+                links: [
+                    to!(
+                        "person",
+                        link_type: "peer",
+
+                        validation_package: || {
+                            ValidationPackageDefinition::ChainFull
+                        },
+
+                        validation: |validation_data: hdk::LinkValidationData| {
+                            if validation_data.base() == validation_data.target() {
+                                Err("Nobody is their own peer".to_string())
+                            } else {
+                                Ok(())
+                            }
+                        }
+                    )
+                    .with_symmetric(),
+                    to!(
+                        "person",
+                        link_type: "manages",
+
+                        validation_package: || {
+                            ValidationPackageDefinition::Entry
+                        },
+
+                        validation: |_validation_data: hdk::LinkValidationData| {
+                            Ok(())
+                        }
+                    )
+                ]
+            ))
+        };
+        let args = |link_type: &str, base: &str, target: &str, direction: LinkDirection| {
+            let link = Link::new(&Address::from(base), &Address::from(target), link_type, "");
+            LinkValidationArgs {
+                entry_type: "person".to_string(),
+                link: link.clone(),
+                direction,
+                validation_data: LinkValidationData::LinkAdd {
+                    link: LinkData::from_link(
+                        &link,
+                        LinkActionKind::ADD,
+                        test_chain_header(),
+                        test_agent_id(),
+                    ),
+                    validation_data: ValidationData::default(),
+                },
+                base_entry: None,
+                target_entry: None,
+                agent_link_count: 0,
+                target_type: None,
+            }
+        };
+
+        for direction in vec![LinkDirection::To, LinkDirection::From] {
+            assert_eq!(
+                super::validate_link(
+                    zome_definition(),
+                    args("peer", "QmAlice", "QmBob", direction.clone())
+                ),
+                Some(Ok(()))
+            );
+            assert_eq!(
+                super::validate_link(
+                    zome_definition(),
+                    args("peer", "QmAlice", "QmAlice", direction.clone())
+                ),
+                Some(Err("Nobody is their own peer".to_string()))
+            );
+            assert_eq!(
+                super::validation_package_for_link(
+                    zome_definition(),
+                    LinkValidationPackageArgs {
+                        entry_type: "person".to_string(),
+                        link_type: "peer".to_string(),
+                        direction,
+                    }
+                ),
+                Some(ValidationPackageDefinition::ChainFull)
+            );
+        }
+
+        // Directional links still only match their own direction
+        assert!(super::validate_link(
+            zome_definition(),
+            args("manages", "QmAlice", "QmBob", LinkDirection::From)
+        )
+        .is_none());
+        assert!(super::validation_package_for_link(
+            zome_definition(),
+            LinkValidationPackageArgs {
+                entry_type: "person".to_string(),
+                link_type: "manages".to_string(),
+                direction: LinkDirection::From,
+            }
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn linked_entries_validator_rejects_decreasing_sequence() {
+        use crate::entry_definition::entry_to_native;
+        use holochain_core_types::{
+            agent::test_agent_id,
+            chain_header::test_chain_header,
+            entry::entry_type::AppEntryType,
+            link::{link_data::LinkData, Link, LinkActionKind},
+            validation::{LinkValidationData, ValidationData},
+        };
+        use holochain_persistence_api::cas::content::AddressableContent;
+        use holochain_wasm_utils::api_serialization::validation::{
+            LinkDirection, LinkValidationArgs,
+        };
+
+        #[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+        pub struct Item {
+            sequence: u32,
+        }
+
+        let zome_definition = || {
+            zome_with(public_entry!(
+                name: "item",
+                description: "{\"description\": \"a list item\"}",
+
+                validation: |_validation_data: hdk::EntryValidationData<Item>| {
+                    Ok(())
+                },
+
+                links: [
+                    to!(
+                        "item",
+                        link_type: "next",
+
+                        validation_package: || {
+                            ValidationPackageDefinition::Entry
+                        },
+
+                        validation: |_validation_data: hdk::LinkValidationData| {
+                            Ok(())
+                        }
+                    )
+                    .with_linked_entries_validator(Box::new(
+                        |_validation_data: LinkValidationData,
+                         base: Option<Entry>,
+                         target: Option<Entry>| {
+                            let sequence = |entry: Option<Entry>| -> Result<u32, String> {
+                                let entry =
+                                    entry.ok_or_else(|| "Linked entry missing".to_string())?;
+                                Ok(entry_to_native::<Item>(entry)?.sequence)
+                            };
+                            if sequence(target)? > sequence(base)? {
+                                Ok(())
+                            } else {
+                                Err("Next item must have a greater sequence number".to_string())
+                            }
+                        },
+                    ))
+                ]
+            ))
+        };
+
+        let item = |sequence: u32| Entry::App(AppEntryType::from("item"), Item { sequence }.into());
+        let args = |base: Entry, target: Entry| {
+            let link = Link::new(&base.address(), &target.address(), "next", "");
+            LinkValidationArgs {
+                entry_type: "item".to_string(),
+                link: link.clone(),
+                direction: LinkDirection::To,
+                validation_data: LinkValidationData::LinkAdd {
+                    link: LinkData::from_link(
+                        &link,
+                        LinkActionKind::ADD,
+                        test_chain_header(),
+                        test_agent_id(),
+                    ),
+                    validation_data: ValidationData::default(),
+                },
+                base_entry: Some(base),
+                target_entry: Some(target),
+                agent_link_count: 0,
+                target_type: None,
+            }
+        };
+
+        assert_eq!(
+            super::validate_link(zome_definition(), args(item(1), item(2))),
+            Some(Ok(())),
+        );
+        assert_eq!(
+            super::validate_link(zome_definition(), args(item(2), item(1))),
+            Some(Err(
+                "Next item must have a greater sequence number".to_string()
+            )),
+        );
+    }
+
+    fn unnamed_entry_type() -> crate::entry_definition::ValidatingEntryType {
+        entry!(
+            name: "  ",
+            description: "a post",
+            sharing: Sharing::Public,
+
+            validation: |_validation_data: hdk::EntryValidationData<Post>| {
+                Ok(())
+            }
+        )
+    }
+
+    #[test]
+    fn entry_type_with_empty_name_is_refused() {
+        let mut zd = super::ZomeDefinition::new();
+        assert!(zd.try_define(unnamed_entry_type()).is_err());
+        assert!(zd.entry_types.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Entry types need a name")]
+    fn defining_entry_type_with_empty_name_panics() {
+        super::ZomeDefinition::new().define(unnamed_entry_type());
+    }
+
+    #[test]
+    fn malformed_json_description_is_refused_when_json_is_required() {
+        use holochain_core_types::error::HolochainError;
+
+        let entry_type = |description: &str| {
+            entry!(
+                name: "described_post",
+                description: description,
+                sharing: Sharing::Public,
+
+                validation: |_validation_data: hdk::EntryValidationData<Post>| {
+                    Ok(())
+                }
+            )
+        };
+        let mut zd = super::ZomeDefinition::new();
+
+        // opaque strings are fine unless JSON is asked for
+        assert_eq!(zd.try_define(entry_type("{\"description\": ")), Ok(()));
+        assert_eq!(
+            zd.try_define(entry_type("{\"description\": \"a post\"}").with_json_description()),
+            Ok(())
+        );
+        match zd.try_define(entry_type("{\"description\": ").with_json_description()) {
+            Err(HolochainError::ErrorGeneric(message)) => assert!(
+                message.starts_with("Description of entry type described_post is not valid JSON"),
+                "unexpected error: {}",
+                message
+            ),
+            other => panic!("Expected a setup error, got {:?}", other),
+        }
+        assert_eq!(zd.entry_types.len(), 2);
+    }
+
+    #[test]
+    fn trait_names_lists_declared_traits() {
+        assert_eq!(
+            super::ZomeDefinition::trait_names(),
+            vec!["hc_public".to_string(), "transfer".to_string()],
+        );
+    }
+
+    #[test]
+    fn unknown_entry_types_go_to_fallback_validator() {
+        use holochain_core_types::{
+            entry::{entry_type::AppEntryType, Entry},
+            validation::{EntryValidationData, ValidationData},
+        };
+
+        let zome_definition = || {
+            let mut zd = zome_with(public_entry!(
+                name: "post",
+                description: "a post",
+
+                validation: |_validation_data: hdk::EntryValidationData<Post>| {
+                    Ok(())
+                }
+            ));
+            // A newer version of the zome adds reactions, those are fine
+            zd.define_fallback_validator(Box::new(
+                |validation_data: EntryValidationData<Entry>| match validation_data {
+                    EntryValidationData::Create {
+                        entry: Entry::App(entry_type, _),
+                        ..
+                    } if String::from(entry_type.clone()) == "reaction" => Ok(()),
+                    _ => Err("Unknown entry type".to_string()),
+                },
+            ));
+            zd
+        };
+
+        let args = |entry_type: &str| {
+            remote_args(EntryValidationData::Create {
+                entry: Entry::App(
+                    AppEntryType::from(entry_type),
+                    Post {
+                        content: "hello".to_string(),
+                    }
+                    .into(),
+                ),
+                validation_data: ValidationData::default(),
+            })
+        };
+
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args("reaction"), no_fetch),
+            Ok(Ok(Vec::new())),
+        );
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args("spam"), no_fetch),
+            Ok(Err("Unknown entry type".to_string())),
+        );
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args("post"), no_fetch),
+            Ok(Ok(Vec::new())),
+        );
+    }
+
+    #[test]
+    fn garbage_export_input_names_export_and_expected_type() {
+        use holochain_core_types::error::HolochainError;
+        use holochain_wasm_utils::api_serialization::validation::EntryValidationArgs;
+
+        let result = super::decode_export_args::<EntryValidationArgs>(
+            "__hdk_validate_app_entry",
+            "EntryValidationArgs",
+            "{\"not\": \"validation args\"".to_string(),
+        );
+        match result {
+            Err(HolochainError::SerializationError(message)) => {
+                assert!(message.starts_with(
+                    "__hdk_validate_app_entry: could not decode input as EntryValidationArgs: "
+                ));
+            }
+            other => panic!("Expected a decode error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn validator_enforces_time_window_from_header_timestamp() {
+        use holochain_core_types::{
+            entry::{entry_type::AppEntryType, Entry},
+            time::Iso8601,
+            validation::{EntryValidationData, ValidationData, ValidationOrigin},
+        };
+        use holochain_wasm_utils::api_serialization::validation::EntryValidationArgs;
+        use std::convert::TryFrom;
+
+        #[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+        pub struct Vote {
+            choice: String,
+        }
+
+        let zome_definition = || {
+            zome_with(public_entry!(
+                name: "vote",
+                description: "a vote in a poll",
+
+                validation: |validation_data: hdk::EntryValidationData<Vote>| {
+                    let opens = Iso8601::try_from("2019-06-01T00:00:00+00:00").unwrap();
+                    let closes = Iso8601::try_from("2019-06-08T00:00:00+00:00").unwrap();
+                    match validation_data {
+                        EntryValidationData::Create { validation_data, .. } => {
+                            match validation_data.timestamp {
+                                Some(ref committed) if *committed >= opens && *committed < closes => {
+                                    Ok(())
+                                }
+                                _ => Err("Vote was cast outside of the voting window".to_string()),
+                            }
+                        }
+                        _ => Ok(()),
+                    }
+                }
+            ))
+        };
+
+        let args = |timestamp: &str| EntryValidationArgs {
+            validation_data: EntryValidationData::Create {
+                entry: Entry::App(
+                    AppEntryType::from("vote"),
+                    Vote {
+                        choice: "yes".to_string(),
+                    }
+                    .into(),
+                ),
+                validation_data: ValidationData::default(),
+            },
+            origin: ValidationOrigin::Remote,
+            timestamp: Some(Iso8601::try_from(timestamp).unwrap()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            super::validate_app_entry(
+                zome_definition(),
+                args("2019-06-03T12:00:00+00:00"),
+                no_fetch
+            ),
+            Ok(Ok(Vec::new())),
+        );
+        assert_eq!(
+            super::validate_app_entry(
+                zome_definition(),
+                args("2019-06-09T12:00:00+00:00"),
+                no_fetch
+            ),
+            Ok(Err("Vote was cast outside of the voting window".to_string())),
+        );
+    }
+
+    #[test]
+    fn replayed_validation_decides_like_with_the_package() {
+        use holochain_core_types::{
+            chain_header::test_chain_header,
+            entry::{entry_type::AppEntryType, Entry},
+            validation::{EntryValidationData, ValidationData, ValidationPackage},
+        };
+
+        let zome_definition = || {
+            zome_with(entry!(
+                name: "post",
+                description: "a post",
+                sharing: Sharing::Public,
+
+                validation_package: || {
+                    ValidationPackageDefinition::ChainEntries
+                },
+
+                validation: |validation_data: hdk::EntryValidationData<Post>| {
+                    match validation_data {
+                        EntryValidationData::Create { validation_data, .. } => {
+                            match validation_data.package.source_chain_entries {
+                                Some(ref entries) if entries.len() > 1 => {
+                                    Err("Only one post per agent".to_string())
+                                }
+                                _ => Ok(()),
+                            }
+                        }
+                        _ => Ok(()),
+                    }
+                }
+            ))
+        };
+
+        let post = Entry::App(
+            AppEntryType::from("post"),
+            Post {
+                content: "hello".to_string(),
+            }
+            .into(),
+        );
+        let create = |package: ValidationPackage| EntryValidationData::Create {
+            entry: post.clone(),
+            validation_data: ValidationData {
+                package,
+                ..ValidationData::default()
+            },
+        };
+        // The package the remote node validated the post with: two posts on the chain already
+        let mut remote_package = ValidationPackage::only_header(test_chain_header());
+        remote_package.source_chain_entries = Some(vec![post.clone(), post.clone()]);
+
+        let remote_outcome = super::validate_app_entry(
+            zome_definition(),
+            remote_args(create(remote_package.clone())),
+            no_fetch,
+        )
+        .unwrap()
+        .map(|_| ());
+        assert_eq!(remote_outcome, Err("Only one post per agent".to_string()));
+
+        // Locally the validation data is built with an empty package,
+        // replaying with the remote package reproduces the remote decision
+        let entry_type = EntryType::App(AppEntryType::from("post"));
+        let local_package = ValidationPackage::only_header(test_chain_header());
+        assert_eq!(
+            super::replay_validation(
+                zome_definition(),
+                &entry_type,
+                create(local_package.clone()),
+                remote_package
+            ),
+            remote_outcome,
+        );
+        assert_eq!(
+            super::replay_validation(
+                zome_definition(),
+                &entry_type,
+                create(local_package.clone()),
+                local_package.clone()
+            ),
+            Ok(()),
+        );
+        assert!(super::replay_validation(
+            zome_definition(),
+            &EntryType::App(AppEntryType::from("comment")),
+            create(local_package.clone()),
+            local_package
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn validator_namespaces_keys_with_zome_name_and_dna_hash() {
+        use holochain_core_types::{
+            entry::{entry_type::AppEntryType, Entry},
+            validation::{EntryValidationData, ValidationData},
+        };
+        use holochain_wasm_utils::api_serialization::validation::EntryValidationArgs;
+
+        #[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+        pub struct Setting {
+            name: String,
+            key: String,
+        }
+
+        let zome_definition = || {
+            zome_with(public_entry!(
+                name: "setting",
+                description: "a setting stored under a key unique to this zome and DNA",
+
+                validation: |validation_data: hdk::EntryValidationData<Setting>| {
+                    match validation_data {
+                        EntryValidationData::Create { entry, validation_data } => {
+                            let expected_key = format!(
+                                "{}/{}/{}",
+                                validation_data.dna_hash.ok_or("No DNA hash")?,
+                                validation_data.zome_name.ok_or("No zome name")?,
+                                entry.name
+                            );
+                            if entry.key == expected_key {
+                                Ok(())
+                            } else {
+                                Err(format!("Setting must be stored under {}", expected_key))
+                            }
+                        }
+                        _ => Ok(()),
+                    }
+                }
+            ))
+        };
+
+        let args = |key: &str| EntryValidationArgs {
+            validation_data: EntryValidationData::Create {
+                entry: Entry::App(
+                    AppEntryType::from("setting"),
+                    Setting {
+                        name: "theme".to_string(),
+                        key: key.to_string(),
+                    }
+                    .into(),
+                ),
+                validation_data: ValidationData::default(),
+            },
+            zome_name: Some("settings".to_string()),
+            dna_hash: Some(Address::from("QmDna")),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args("QmDna/settings/theme"), no_fetch),
+            Ok(Ok(Vec::new())),
+        );
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args("QmDna/profiles/theme"), no_fetch),
+            Ok(Err(
+                "Setting must be stored under QmDna/settings/theme".to_string()
+            )),
+        );
+    }
+
+    #[test]
+    fn watcher_observes_progress_of_long_validator() {
+        use crate::validation_progress::{report_progress, set_progress_observer};
+        use holochain_core_types::{
+            entry::{entry_type::AppEntryType, Entry},
+            validation::{EntryValidationData, ValidationData},
+        };
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+        pub struct Proof {
+            nodes: Vec<String>,
+        }
+
+        let zd = zome_with(public_entry!(
+            name: "proof",
+            description: "a proof that takes a while to verify",
+
+            validation: |validation_data: hdk::EntryValidationData<Proof>| {
+                match validation_data {
+                    EntryValidationData::Create { entry, .. } => {
+                        for (verified, node) in entry.nodes.iter().enumerate() {
+                            if node.is_empty() {
+                                return Err("Empty proof node".to_string());
+                            }
+                            report_progress(verified as u64 + 1);
+                        }
+                        Ok(())
+                    }
+                    _ => Ok(()),
+                }
+            }
+        ));
+
+        let observed = Arc::new(Mutex::new(Vec::new()));
+        let watcher = observed.clone();
+        set_progress_observer(Some(Box::new(move |progress| {
+            watcher.lock().unwrap().push(progress)
+        })));
+
+        let args = remote_args(EntryValidationData::Create {
+            entry: Entry::App(
+                AppEntryType::from("proof"),
+                Proof {
+                    nodes: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+                }
+                .into(),
+            ),
+            validation_data: ValidationData::default(),
+        });
+        let result = super::validate_app_entry(zd, args, no_fetch);
+        set_progress_observer(None);
+
+        assert_eq!(result, Ok(Ok(Vec::new())));
+        assert_eq!(*observed.lock().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn validate_app_entry_passes_origin_to_validator() {
+        use holochain_core_types::{
+            entry::{entry_type::AppEntryType, Entry},
+            validation::{EntryValidationData, ValidationData, ValidationOrigin},
+        };
+        use holochain_wasm_utils::api_serialization::validation::EntryValidationArgs;
+
+        let zome_definition = || {
+            zome_with(public_entry!(
+                name: "post",
+                description: "a post",
+
+                validation: |validation_data: hdk::EntryValidationData<Post>| {
+                    match validation_data {
+                        EntryValidationData::Create { entry, validation_data } => {
+                            // only entries from other agents get the extra check
+                            if validation_data.origin == ValidationOrigin::Remote
+                                && entry.content.is_empty()
+                            {
+                                Err("Remote posts must have content".to_string())
+                            } else {
+                                Ok(())
+                            }
+                        }
+                        _ => Ok(()),
+                    }
+                }
+            ))
+        };
+
+        let args = |origin| EntryValidationArgs {
+            validation_data: EntryValidationData::Create {
+                entry: Entry::App(
+                    AppEntryType::from("post"),
+                    Post {
+                        content: String::new(),
+                    }
+                    .into(),
+                ),
+                validation_data: ValidationData::default(),
+            },
+            origin,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args(ValidationOrigin::Local), no_fetch),
+            Ok(Ok(Vec::new())),
+        );
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args(ValidationOrigin::Remote), no_fetch),
+            Ok(Err("Remote posts must have content".to_string())),
+        );
+    }
+
+    #[test]
+    fn json_definition_contains_link_validation_package() {
+        let zd = zome_with(public_entry!(
+            name: "post",
+            description: "{\"description\": \"a post\"}",
+
+            validation: |_validation_data: hdk::EntryValidationData<Post>| {
+                Ok(())
+            },
+
+            links: [
+                to!(
+                    "post",
+                    link_type: "comments",
+
+                    validation_package: || {
+                        ValidationPackageDefinition::ChainFull
+                    },
+
+                    validation: |_validation_data: hdk::LinkValidationData| {
+                        Ok(())
+                    }
+                )
+            ]
+        ));
+
+        let partial_zome = PartialZome {
+            entry_types: super::entry_types_definition(zd),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            JsonString::from(partial_zome),
+            JsonString::from_json("{\"entry_types\":{\"post\":{\"properties\":\"{\\\"description\\\": \\\"a post\\\"}\",\"sharing\":\"public\",\"links_to\":[{\"target_type\":\"post\",\"link_type\":\"comments\",\"validation_package\":\"ChainFull\"}],\"linked_from\":[]}},\"traits\":{},\"fn_declarations\":[]}"),
+        );
+    }
+
+    #[test]
+    fn digest_validation_package_is_smaller_than_full() {
+        use holochain_wasm_utils::api_serialization::validation::{
+            EntryValidationPackageArgs, PackageDetail,
+        };
+
+        let zome_definition = || {
+            zome_with(entry!(
+                name: "post",
+                description: "{\"description\": \"a post\"}",
+                sharing: Sharing::Public,
+
+                validation_package: || {
+                    ValidationPackageDefinition::Custom("a very large custom package. ".repeat(100))
+                },
+
+                validation: |_validation_data: hdk::EntryValidationData<Post>| {
+                    Ok(())
+                }
+            ))
+        };
+
+        let package_size = |detail_level: PackageDetail| {
+            let package = super::validation_package_for_entry_type(
+                zome_definition(),
+                EntryValidationPackageArgs {
+                    entry_type: "post".to_string(),
+                    detail_level,
+                },
+            )
+            .expect("Package for post should be found");
+            String::from(JsonString::from(package)).len()
+        };
+
+        let full = package_size(PackageDetail::Full);
+        let digest = package_size(PackageDetail::Digest);
+        assert!(
+            digest < full,
+            "digest ({} bytes) should be smaller than full package ({} bytes)",
+            digest,
+            full
+        );
+
+        assert_eq!(
+            super::validation_package_for_entry_type(
+                zome_definition(),
+                EntryValidationPackageArgs {
+                    entry_type: "unknown".to_string(),
+                    detail_level: PackageDetail::Digest,
+                },
+            ),
+            Err(super::ExportFailure::NoMatchingType)
+        );
+    }
+
+    #[test]
+    fn prewarm_runs_every_package_creator_once_and_caches_packages() {
+        use holochain_wasm_utils::api_serialization::validation::{
+            EntryValidationPackageArgs, PackageDetail,
+        };
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static PACKAGE_CREATOR_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+        let zome_definition = || {
+            let mut zd = zome_with(entry!(
+                name: "post",
+                description: "a post",
+                sharing: Sharing::Public,
+
+                validation_package: || {
+                    PACKAGE_CREATOR_RUNS.fetch_add(1, Ordering::SeqCst);
+                    ValidationPackageDefinition::ChainFull
+                },
+
+                validation: |_validation_data: hdk::EntryValidationData<Post>| {
+                    Ok(())
+                }
+            ));
+            zd.define(entry!(
+                name: "comment",
+                description: "a comment",
+                sharing: Sharing::Public,
+
+                validation_package: || {
+                    PACKAGE_CREATOR_RUNS.fetch_add(1, Ordering::SeqCst);
+                    ValidationPackageDefinition::Entry
+                },
+
+                validation: |_validation_data: hdk::EntryValidationData<Post>| {
+                    Ok(())
+                }
+            ));
+            zd
+        };
+
+        let mut zd = zome_definition();
+        assert_eq!(
+            zd.prewarm_packages(),
+            vec![
+                (
+                    EntryType::from("post"),
+                    Ok(ValidationPackageDefinition::ChainFull)
+                ),
+                (
+                    EntryType::from("comment"),
+                    Ok(ValidationPackageDefinition::Entry)
+                ),
+            ]
+        );
+        assert_eq!(PACKAGE_CREATOR_RUNS.load(Ordering::SeqCst), 2);
+
+        // Prewarming again and validating with the fresh definition every export sets up
+        // are served from the cache
+        zd.prewarm_packages();
+        assert_eq!(PACKAGE_CREATOR_RUNS.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            super::validation_package_for_entry_type(
+                zome_definition(),
+                EntryValidationPackageArgs {
+                    entry_type: "post".to_string(),
+                    detail_level: PackageDetail::Full,
+                },
+            ),
+            Ok(ValidationPackageDefinition::ChainFull)
+        );
+        assert_eq!(PACKAGE_CREATOR_RUNS.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn entry_type_without_validation_package_uses_entry() {
+        use holochain_wasm_utils::api_serialization::validation::{
+            EntryValidationPackageArgs, PackageDetail,
+        };
+
+        let zd = zome_with(entry!(
+            name: "note",
+            description: "a note",
+            sharing: Sharing::Public,
+
+            validation: |_validation_data: hdk::EntryValidationData<Post>| {
+                Ok(())
+            }
+        ));
+
+        assert_eq!(
+            super::validation_package_for_entry_type(
+                zd,
+                EntryValidationPackageArgs {
+                    entry_type: "note".to_string(),
+                    detail_level: PackageDetail::Full,
+                },
+            ),
+            Ok(ValidationPackageDefinition::Entry)
+        );
+    }
+
+    #[test]
+    fn validate_app_entry_rejects_update_changing_entry_type() {
+        use holochain_core_types::{
+            chain_header::test_chain_header,
+            entry::{entry_type::AppEntryType, Entry},
+            error::{RibosomeEncodedValue, RibosomeErrorCode},
+            validation::{EntryValidationData, ValidationData},
+        };
+
+        let zd = zome_with(public_entry!(
+            name: "post",
+            description: "a post",
+
+            validation: |_validation_data: hdk::EntryValidationData<Post>| {
+                Ok(())
+            }
+        ));
+
+        let post = || Post {
+            content: "hello".to_string(),
+        };
+        let args = remote_args(EntryValidationData::Modify {
+            new_entry: Entry::App(AppEntryType::from("post"), post().into()),
+            old_entry: Entry::App(AppEntryType::from("comment"), post().into()),
+            old_entry_header: test_chain_header(),
+            validation_data: ValidationData::default(),
+        });
+
+        assert_eq!(
+            super::validate_app_entry(zd, args, no_fetch),
+            Err(RibosomeEncodedValue::Failure(
+                RibosomeErrorCode::EntryTypeMismatch
+            )),
+        );
+    }
+
+    #[test]
+    fn updates_changing_immutable_fields_get_rejected() {
+        use holochain_core_types::{
+            chain_header::test_chain_header,
+            entry::{entry_type::AppEntryType, Entry},
+            validation::{EntryValidationData, ValidationData},
+        };
+
+        let zome_definition = || {
+            zome_with(public_entry!(
+                name: "post",
+                description: "a post",
+
+                immutable_fields: ["/content"],
+
+                validation: |_validation_data: hdk::EntryValidationData<Post>| {
+                    Ok(())
+                }
+            ))
+        };
+        let post = |content: &str| {
+            Entry::App(
+                AppEntryType::from("post"),
+                Post {
+                    content: content.to_string(),
+                }
+                .into(),
+            )
+        };
+        let update = |new_entry: Entry| {
+            remote_args(EntryValidationData::Modify {
+                new_entry,
+                old_entry: post("hello"),
+                old_entry_header: test_chain_header(),
+                validation_data: ValidationData::default(),
+            })
+        };
+
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), update(post("hello")), no_fetch),
+            Ok(Ok(Vec::new())),
+        );
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), update(post("hello again")), no_fetch),
+            Ok(Err(
+                "Field /content of entry type post is immutable".to_string()
+            )),
+        );
+    }
+
+    #[test]
+    fn agent_exceeding_rate_limit_gets_rejected() {
+        use crate::entry_definition::RateLimit;
+        use holochain_core_types::{
+            chain_header::ChainHeader,
+            entry::{
+                entry_type::{AppEntryType, EntryType},
+                Entry,
+            },
+            time::Iso8601,
+            validation::{EntryValidationData, ValidationData},
+        };
+
+        let zome_definition = || {
+            zome_with(public_entry!(
+                name: "post",
+                description: "a post",
+
+                rate_limit: RateLimit::per_hour(2),
+
+                validation: |_validation_data: hdk::EntryValidationData<Post>| {
+                    Ok(())
+                }
+            ))
+        };
+        // The agent's chain headers are what the rate limit is checked against
+        assert_eq!(
+            zome_definition().entry_types[0].build_validation_package(),
+            Ok(ValidationPackageDefinition::Combined(vec![
+                ValidationPackageDefinition::Entry,
+                ValidationPackageDefinition::ChainHeaders,
+            ]))
+        );
+
+        let header = |entry_type: &str, minutes_ago: i64| {
+            ChainHeader::new(
+                &EntryType::from(entry_type.to_string()),
+                &Address::from(format!("{}-{}", entry_type, minutes_ago)),
+                &[],
+                &None,
+                &None,
+                &None,
+                &Iso8601::new(100_000 - minutes_ago * 60, 0),
+            )
+        };
+        let commit = |earlier_commits: Vec<ChainHeader>| {
+            let mut validation_data = ValidationData::default();
+            validation_data.package.chain_header = header("post", 0);
+            validation_data.package.source_chain_headers = Some(earlier_commits);
+            remote_args(EntryValidationData::Create {
+                entry: Entry::App(
+                    AppEntryType::from("post"),
+                    Post {
+                        content: "hello".to_string(),
+                    }
+                    .into(),
+                ),
+                validation_data,
+            })
+        };
+
+        // Posts from more than an hour ago and other entry types don't count
+        assert_eq!(
+            super::validate_app_entry(
+                zome_definition(),
+                commit(vec![
+                    header("post", 10),
+                    header("comment", 20),
+                    header("post", 90)
+                ]),
+                no_fetch
+            ),
+            Ok(Ok(Vec::new())),
+        );
+        assert_eq!(
+            super::validate_app_entry(
+                zome_definition(),
+                commit(vec![header("post", 10), header("post", 50)]),
+                no_fetch
+            ),
+            Ok(Err(
+                "Agent exceeded the rate limit of 2 entries of type post per 1h".to_string()
+            )),
+        );
+    }
+
+    #[test]
+    fn host_gets_chain_headers_for_rate_limited_entry_type() {
+        use crate::entry_definition::RateLimit;
+
+        let zd = zome_with(public_entry!(
+            name: "post",
+            description: "a post",
+
+            rate_limit: RateLimit::per_hour(2),
+
+            validation: |_validation_data: hdk::EntryValidationData<Post>| {
+                Ok(())
+            }
+        ));
+
+        // What __hdk_get_validation_package_for_entry_type answers a host asking for "post"
+        let package = super::validation_package_for_entry_type(
+            zd,
+            super::entry_validation_package_args("post".to_string()),
+        )
+        .expect("Package for post should be found");
+        assert!(package
+            .parts()
+            .contains(&ValidationPackageDefinition::ChainHeaders));
+    }
+
+    #[test]
+    fn group_validator_rejects_unbalanced_commit_group() {
+        use crate::entry_definition::entry_to_native;
+        use holochain_core_types::{
+            entry::{entry_type::AppEntryType, Entry},
+            validation::{EntryValidationData, ValidationData},
+        };
+        use holochain_wasm_utils::api_serialization::validation::CommitGroupValidationArgs;
+
+        #[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+        pub struct Posting {
+            debit: bool,
+            amount: u64,
+        }
+
+        let zome_definition = || {
+            let mut zd = zome_with(public_entry!(
+                name: "posting",
+                description: "one side of a booking",
+
+                validation: |validation_data: hdk::EntryValidationData<Posting>| {
+                    match validation_data {
+                        EntryValidationData::Create { entry, .. } if entry.amount == 0 => {
+                            Err("Postings must have an amount".to_string())
+                        }
+                        _ => Ok(()),
+                    }
+                }
+            ));
+            zd.define_group_validator(Box::new(|entries: Vec<Entry>| {
+                let mut balance: i128 = 0;
+                for entry in entries {
+                    let posting = entry_to_native::<Posting>(entry).map_err(String::from)?;
+                    if posting.debit {
+                        balance += i128::from(posting.amount);
+                    } else {
+                        balance -= i128::from(posting.amount);
+                    }
+                }
+                if balance == 0 {
+                    Ok(())
+                } else {
+                    Err("Debits and credits don't balance".to_string())
+                }
+            }));
+            zd
+        };
+        let group = |postings: Vec<(bool, u64)>| CommitGroupValidationArgs {
+            entries: postings
+                .into_iter()
+                .map(|(debit, amount)| {
+                    remote_args(EntryValidationData::Create {
+                        entry: Entry::App(
+                            AppEntryType::from("posting"),
+                            Posting { debit, amount }.into(),
+                        ),
+                        validation_data: ValidationData::default(),
+                    })
+                })
+                .collect(),
+        };
+
+        assert_eq!(
+            super::validate_commit_group(
+                zome_definition,
+                group(vec![(true, 10), (false, 10)]),
+                no_fetch
+            ),
+            Ok(Ok(()))
+        );
+        // Both postings are valid on their own, but not together
+        assert_eq!(
+            super::validate_commit_group(
+                zome_definition,
+                group(vec![(true, 10), (false, 5)]),
+                no_fetch
+            ),
+            Ok(Err("Debits and credits don't balance".to_string()))
+        );
+        // Individual validation still runs first
+        assert_eq!(
+            super::validate_commit_group(
+                zome_definition,
+                group(vec![(true, 0), (false, 0)]),
+                no_fetch
+            ),
+            Ok(Err("Postings must have an amount".to_string()))
+        );
+    }
+
+    #[test]
+    fn comment_without_membership_link_in_package_is_rejected() {
+        use holochain_core_types::{
+            entry::{entry_type::AppEntryType, Entry},
+            validation::{EntryValidationData, LinkSpec, ValidationData},
+        };
+        use holochain_wasm_utils::api_serialization::validation::{
+            EntryValidationPackageArgs, PackageDetail,
+        };
+
+        fn membership() -> LinkSpec {
+            LinkSpec {
+                base: Address::from("QmClub"),
+                target: Address::from("QmAlice"),
+                link_type: "membership".to_string(),
+                tag: String::new(),
+            }
+        }
+
+        let zome_definition = || {
+            zome_with(entry!(
+                name: "comment",
+                description: "a comment",
+                sharing: Sharing::Public,
+
+                validation_package: || {
+                    ValidationPackageDefinition::WithLinks(vec![membership()])
+                },
+
+                validation: |validation_data: hdk::EntryValidationData<Post>| {
+                    match validation_data {
+                        EntryValidationData::Create { validation_data, .. } => {
+                            if validation_data.package.has_link(&membership()) {
+                                Ok(())
+                            } else {
+                                Err("Only members may comment".to_string())
+                            }
+                        }
+                        _ => Ok(()),
+                    }
+                }
+            ))
+        };
+
+        assert_eq!(
+            super::validation_package_for_entry_type(
+                zome_definition(),
+                EntryValidationPackageArgs {
+                    entry_type: "comment".to_string(),
+                    detail_level: PackageDetail::Full,
+                },
+            ),
+            Ok(ValidationPackageDefinition::WithLinks(vec![membership()]))
+        );
+
+        let args = |links: Vec<LinkSpec>| {
+            let mut validation_data = ValidationData::default();
+            validation_data.package.links = Some(links);
+            remote_args(EntryValidationData::Create {
+                entry: Entry::App(
+                    AppEntryType::from("comment"),
+                    Post {
+                        content: "hi".to_string(),
+                    }
+                    .into(),
+                ),
+                validation_data,
+            })
+        };
+
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args(Vec::new()), no_fetch),
+            Ok(Err("Only members may comment".to_string()))
+        );
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args(vec![membership()]), no_fetch),
+            Ok(Ok(Vec::new()))
+        );
+    }
+
+    #[test]
+    fn review_validates_only_with_existing_book_reference() {
+        use holochain_core_types::{
+            entry::{entry_type::AppEntryType, Entry},
+            validation::{EntryValidationData, ValidationData},
+        };
+        use holochain_persistence_api::cas::content::AddressableContent;
+
+        #[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+        pub struct Review {
+            book: Address,
+        }
+
+        fn book() -> Entry {
+            Entry::App(
+                AppEntryType::from("book"),
+                JsonString::from_json("\"Dune\""),
+            )
+        }
+
+        let zome_definition = || {
+            zome_with(entry!(
+                name: "review",
+                description: "a review of a book",
+                sharing: Sharing::Public,
+
+                validation_package: || {
+                    ValidationPackageDefinition::WithReferences(vec![book().address()])
+                },
+
+                validation: |validation_data: hdk::EntryValidationData<Review>| {
+                    match validation_data {
+                        EntryValidationData::Create { entry, validation_data } => {
+                            match validation_data.package.referenced_entry(&entry.book) {
+                                Some(Entry::App(entry_type, _))
+                                    if *entry_type == AppEntryType::from("book") => Ok(()),
+                                Some(_) => Err("The reviewed entry is not a book".to_string()),
+                                None => Err("The reviewed book does not exist".to_string()),
+                            }
+                        }
+                        _ => Ok(()),
+                    }
+                }
+            ))
+        };
+
+        let args = |references: Vec<Entry>| {
+            let mut validation_data = ValidationData::default();
+            validation_data.package.references = Some(references);
+            remote_args(EntryValidationData::Create {
+                entry: Entry::App(
+                    AppEntryType::from("review"),
+                    Review {
+                        book: book().address(),
+                    }
+                    .into(),
+                ),
+                validation_data,
+            })
+        };
+
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args(Vec::new()), no_fetch),
+            Ok(Err("The reviewed book does not exist".to_string()))
+        );
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args(vec![book()]), no_fetch),
+            Ok(Ok(Vec::new()))
+        );
+    }
+
+    #[test]
+    fn deprecated_entry_type_rejects_create_but_allows_update() {
+        use holochain_core_types::{
+            chain_header::test_chain_header,
+            entry::{entry_type::AppEntryType, Entry},
+            validation::{EntryValidationData, ValidationData},
+        };
+
+        let zome_definition = || {
+            zome_with(
+                public_entry!(
+                    name: "post",
+                    description: "a post",
+
+                    validation: |_validation_data: hdk::EntryValidationData<Post>| {
+                        Ok(())
+                    }
+                )
+                .with_deprecated_since("0.2.0"),
+            )
+        };
+        let post = |content: &str| {
+            Entry::App(
+                AppEntryType::from("post"),
+                Post {
+                    content: content.to_string(),
+                }
+                .into(),
+            )
+        };
+        let args = |validation_data| remote_args(None);
+
+        let create = args(EntryValidationData::Create {
+            entry: post("hello"),
+            validation_data: ValidationData::default(),
+        });
+        match super::validate_app_entry(zome_definition(), create, no_fetch) {
+            Ok(Err(fail_string)) => assert!(
+                fail_string.contains("deprecated since 0.2.0"),
+                "unexpected failure: {}",
+                fail_string
+            ),
+            other => panic!("Expected the create to be rejected, got {:?}", other),
+        }
+
+        let update = args(EntryValidationData::Modify {
+            new_entry: post("hello again"),
+            old_entry: post("hello"),
+            old_entry_header: test_chain_header(),
+            validation_data: ValidationData::default(),
+        });
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), update, no_fetch),
+            Ok(Ok(Vec::new()))
+        );
+
+        let entry_types = super::entry_types_definition(zome_definition());
+        assert_eq!(
+            entry_types
+                .values()
+                .next()
+                .and_then(|definition| definition.deprecated_since.clone()),
+            Some("0.2.0".to_string())
+        );
+    }
+
+    #[test]
+    fn memory_report_accounts_for_definition() {
+        #[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+        pub struct Memo {
+            text: String,
+        }
+
+        let zd = zome_with(entry!(
+            name: "memo",
+            description: "a memo with a rather long description to take up some space",
+            sharing: Sharing::Private,
+
+            validation_package: || {
+                ValidationPackageDefinition::Entry
+            },
+
+            validation: |_validation_data: hdk::EntryValidationData<Memo>| {
+                Ok(())
+            }
+        ));
+
+        let report = super::memory_report(super::partial_zome(zd), 128);
+        assert_eq!(report.allocated_bytes, 128);
+        // at least the description has to be in there, but it's no more than a few KiB
+        assert!(report.definition_bytes > 60, "report = {:?}", report);
+        assert!(report.definition_bytes < 4096, "report = {:?}", report);
+
+        let empty = super::memory_report(super::partial_zome(super::ZomeDefinition::new()), 0);
+        assert!(empty.definition_bytes < report.definition_bytes);
+    }
+
+    #[test]
+    fn derived_entry_type_rejects_direct_commit() {
+        use holochain_core_types::{
+            entry::{entry_type::AppEntryType, Entry},
+            validation::{EntryValidationData, ValidationData, ValidationOrigin},
+        };
+        use holochain_wasm_utils::api_serialization::validation::EntryValidationArgs;
+
+        #[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+        pub struct PostCount {
+            count: u32,
+        }
+
+        let zome_definition = || {
+            zome_with(
+                public_entry!(
+                    name: "post_count",
+                    description: "number of posts, maintained by the system",
+
+                    validation: |_validation_data: hdk::EntryValidationData<PostCount>| {
+                        Ok(())
+                    }
+                )
+                .with_derived(),
+            )
+        };
+        let args = |origin| EntryValidationArgs {
+            validation_data: EntryValidationData::Create {
+                entry: Entry::App(
+                    AppEntryType::from("post_count"),
+                    PostCount { count: 3 }.into(),
+                ),
+                validation_data: ValidationData::default(),
+            },
+            origin,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args(ValidationOrigin::Local), no_fetch),
+            Ok(Err(
+                "Entry type post_count is derived, its entries can only be produced by the system"
+                    .to_string()
+            ))
+        );
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args(ValidationOrigin::System), no_fetch),
+            Ok(Ok(Vec::new()))
+        );
+
+        let entry_types = super::entry_types_definition(zome_definition());
+        let definition = entry_types.values().next().unwrap();
+        assert!(definition.derived);
+        assert!(String::from(JsonString::from(definition.clone())).contains("\"derived\":true"));
+    }
+
+    #[test]
+    fn validate_app_entry_resolves_requested_dependency() {
+        use crate::entry_definition::ValidationResult;
+        use holochain_core_types::{
+            entry::{entry_type::AppEntryType, test_entry},
+            validation::{EntryValidationData, ValidationData},
+        };
+        use holochain_persistence_api::cas::content::AddressableContent;
+        use std::convert::TryFrom;
+
+        #[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+        pub struct Comment {
+            content: String,
+            post: Address,
+        }
+
+        let post = test_entry();
+        let post_address = post.address();
+
+        let zd = zome_with(
+            public_entry!(
+                name: "comment",
+                description: "a comment",
+
+                validation: |_validation_data: hdk::EntryValidationData<Comment>| {
+                    Ok(())
+                }
+            )
+            .with_dependency_validator(Box::new(|validation_data, dependencies| {
+                match validation_data {
+                    EntryValidationData::Create {
+                        entry: Entry::App(_, value),
+                        ..
+                    } => {
+                        let comment = Comment::try_from(value).unwrap();
+                        match dependencies.get(&comment.post) {
+                            None => ValidationResult::NeedData(comment.post),
+                            Some(Some(_)) => ValidationResult::Valid,
+                            Some(None) => ValidationResult::Invalid("Post not found".into()),
+                        }
+                    }
+                    _ => ValidationResult::Invalid("Unexpected validation data".into()),
+                }
+            })),
+        );
+
+        let args = remote_args(EntryValidationData::Create {
+            entry: Entry::App(
+                AppEntryType::from("comment"),
+                Comment {
+                    content: "nice".to_string(),
+                    post: post_address.clone(),
+                }
+                .into(),
+            ),
+            validation_data: ValidationData::default(),
+        });
+
+        let mut fetched = Vec::new();
+        let result = super::validate_app_entry(zd, args, |address| {
+            fetched.push(address.clone());
+            Ok(Some(post.clone()))
+        });
+
+        assert_eq!(result, Ok(Ok(Vec::new())));
+        assert_eq!(fetched, vec![post_address]);
+    }
+
+    #[test]
+    fn validate_app_entry_records_span_per_validation() {
+        use crate::validation_span::{
+            set_validation_span_recorder, ValidationSpan, ValidationSpanRecorder,
+        };
+        use holochain_core_types::{
+            entry::entry_type::AppEntryType,
+            validation::{EntryValidationData, ValidationData},
+        };
+        use holochain_wasm_utils::api_serialization::validation::EntryValidationArgs;
+        use std::{
+            sync::{Arc, Mutex},
+            time::{Duration, Instant},
+        };
+
+        struct TestRecorder {
+            start: Instant,
+            spans: Arc<Mutex<Vec<ValidationSpan>>>,
+        }
+
+        impl ValidationSpanRecorder for TestRecorder {
+            fn now(&self) -> Duration {
+                self.start.elapsed()
+            }
+            fn record(&self, span: ValidationSpan) {
+                self.spans.lock().unwrap().push(span);
+            }
+        }
+
+        let zome_definition = || {
+            zome_with(public_entry!(
+                name: "traced_post",
+                description: "a post",
+
+                validation: |_validation_data: hdk::EntryValidationData<Post>| {
+                    Ok(())
+                }
+            ))
+        };
+        let args = || EntryValidationArgs {
+            validation_data: EntryValidationData::Create {
+                entry: Entry::App(
+                    AppEntryType::from("traced_post"),
+                    Post {
+                        content: "traced".to_string(),
+                    }
+                    .into(),
+                ),
+                validation_data: ValidationData::default(),
+            },
+            ..Default::default()
+        };
+
+        let spans = Arc::new(Mutex::new(Vec::new()));
+        set_validation_span_recorder(Some(Box::new(TestRecorder {
+            start: Instant::now(),
+            spans: spans.clone(),
+        })));
+
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args(), no_fetch),
+            Ok(Ok(Vec::new()))
+        );
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args(), no_fetch),
+            Ok(Ok(Vec::new()))
+        );
+        set_validation_span_recorder(None);
+
+        // other tests might validate concurrently, so only look at our entry type
+        let traced: Vec<ValidationSpan> = spans
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|span| span.entry_type == "traced_post")
+            .cloned()
+            .collect();
+        assert_eq!(traced.len(), 2);
+        assert!(traced.iter().all(|span| span.operation == "create"));
+    }
+
+    #[test]
+    fn validation_default_fills_missing_field_before_validation() {
+        use holochain_core_types::{
+            entry::entry_type::AppEntryType,
+            validation::{EntryValidationData, ValidationData},
+        };
+        use holochain_wasm_utils::api_serialization::validation::EntryValidationArgs;
+
+        let zome_definition = || {
+            zome_with(public_entry!(
+                name: "titled_post",
+                description: "a post",
+
+                validation_default: |post: Post| {
+                    if post.content.is_empty() {
+                        Post {
+                            content: "untitled".to_string(),
+                        }
+                    } else {
+                        post
+                    }
+                },
+
+                validation: |validation_data: hdk::EntryValidationData<Post>| {
+                    match validation_data {
+                        EntryValidationData::Create { entry, .. } if entry.content.is_empty() => {
+                            Err("content is missing".to_string())
+                        }
+                        _ => Ok(()),
+                    }
+                }
+            ))
+        };
+        let entry = Entry::App(
+            AppEntryType::from("titled_post"),
+            Post {
+                content: String::new(),
+            }
+            .into(),
+        );
+        let args = EntryValidationArgs {
+            validation_data: EntryValidationData::Create {
+                entry: entry.clone(),
+                validation_data: ValidationData::default(),
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args, no_fetch),
+            Ok(Ok(Vec::new()))
+        );
+
+        // the form to commit is the defaulted one
+        assert_eq!(
+            zome_definition().apply_entry_defaults(entry),
+            Ok(Entry::App(
+                AppEntryType::from("titled_post"),
+                Post {
+                    content: "untitled".to_string(),
+                }
+                .into(),
+            ))
+        );
+    }
+
+    #[test]
+    fn validator_can_read_dna_properties() {
+        use holochain_core_types::{
+            entry::entry_type::AppEntryType,
+            validation::{EntryValidationData, ValidationData},
+        };
+        use holochain_wasm_utils::api_serialization::validation::EntryValidationArgs;
+
+        #[derive(Deserialize)]
+        struct Properties {
+            max_length: usize,
+        }
+
+        let zome_definition = || {
+            zome_with(entry!(
+                name: "limited_post",
+                description: "a post",
+                sharing: Sharing::Public,
+
+                validation: |validation_data: hdk::EntryValidationData<Post>| {
+                    match validation_data {
+                        EntryValidationData::Create { entry, validation_data } => {
+                            let properties: Properties = serde_json::from_str(&String::from(
+                                validation_data.dna_properties,
+                            ))
+                            .map_err(|error| error.to_string())?;
+                            if entry.content.len() > properties.max_length {
+                                Err(format!("Posts can't be longer than {}", properties.max_length))
+                            } else {
+                                Ok(())
+                            }
+                        }
+                        _ => Ok(()),
+                    }
+                }
+            ))
+        };
+        let args = |content: &str| EntryValidationArgs {
+            validation_data: EntryValidationData::Create {
+                entry: Entry::App(
+                    AppEntryType::from("limited_post"),
+                    Post {
+                        content: content.to_string(),
+                    }
+                    .into(),
+                ),
+                validation_data: ValidationData::default(),
+            },
+            dna_properties: JsonString::from_json("{\"max_length\": 10}"),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args("short"), no_fetch),
+            Ok(Ok(Vec::new()))
+        );
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args("much too long"), no_fetch),
+            Ok(Err("Posts can't be longer than 10".to_string()))
+        );
+    }
+
+    #[test]
+    fn validations_of_same_entry_get_same_random_values() {
+        use holochain_core_types::{
+            entry::entry_type::AppEntryType,
+            validation::{validation_seed, EntryValidationData, ValidationData, ValidationOrigin},
+        };
+        use holochain_persistence_api::cas::content::AddressableContent;
+        use holochain_wasm_utils::api_serialization::validation::EntryValidationArgs;
+
+        // reports the random value it drew as failure, so the test can see it
+        let zome_definition = || {
+            zome_with(entry!(
+                name: "challenge",
+                description: "a challenge",
+                sharing: Sharing::Public,
+
+                validation: |validation_data: hdk::EntryValidationData<Post>| {
+                    Err(validation_data.validation_data().rng().next_u64().to_string())
+                }
+            ))
+        };
+        let random_value_for = |content: &str| {
+            let entry = Entry::App(
+                AppEntryType::from("challenge"),
+                Post {
+                    content: content.to_string(),
+                }
+                .into(),
+            );
+            let args = EntryValidationArgs {
+                validation_data: EntryValidationData::Create {
+                    entry: entry.clone(),
+                    validation_data: ValidationData::default(),
+                },
+                origin: ValidationOrigin::Remote,
+                seed: validation_seed(&entry.address()),
+                ..Default::default()
+            };
+            let expected = args.rng().next_u64().to_string();
+            let result = super::validate_app_entry(zome_definition(), args, no_fetch);
+            assert_eq!(result, Ok(Err(expected.clone())));
+            expected
+        };
+
+        assert_eq!(random_value_for("solve me"), random_value_for("solve me"));
+        assert_ne!(random_value_for("solve me"), random_value_for("or me"));
+    }
+
+    #[test]
+    fn package_assembly_time_is_sampled_per_entry_type() {
+        use crate::metrics::{set_metrics_sink, MetricsSink, VALIDATION_PACKAGE_ASSEMBLY_MS};
+        use holochain_wasm_utils::api_serialization::validation::{
+            EntryValidationPackageArgs, PackageDetail,
+        };
+        use std::{
+            sync::{
+                atomic::{AtomicU64, Ordering},
+                Arc, Mutex,
+            },
+            time::Duration,
+        };
+
+        /// Its clock advances by 5ms whenever it gets read
+        struct CapturingSink {
+            millis: AtomicU64,
+            samples: Arc<Mutex<Vec<(String, String, f64)>>>,
+        }
+
+        impl MetricsSink for CapturingSink {
+            fn increment(&self, _metric: &str, _tag: &str) {}
+
+            fn now(&self) -> Option<Duration> {
+                Some(Duration::from_millis(
+                    self.millis.fetch_add(5, Ordering::SeqCst),
+                ))
+            }
+
+            fn record(&self, metric: &str, tag: &str, value: f64) {
+                self.samples
+                    .lock()
+                    .unwrap()
+                    .push((metric.to_string(), tag.to_string(), value));
+            }
+        }
+
+        let zd = zome_with(entry!(
+            name: "heavy_package_post",
+            description: "a post",
+            sharing: Sharing::Public,
+
+            validation_package: || {
+                ValidationPackageDefinition::ChainFull
+            },
+
+            validation: |_validation_data: hdk::EntryValidationData<Post>| {
+                Ok(())
+            }
+        ));
+
+        let samples = Arc::new(Mutex::new(Vec::new()));
+        set_metrics_sink(Some(Box::new(CapturingSink {
+            millis: AtomicU64::new(0),
+            samples: samples.clone(),
+        })));
+        let package = super::validation_package_for_entry_type(
+            zd,
+            EntryValidationPackageArgs {
+                entry_type: "heavy_package_post".to_string(),
+                detail_level: PackageDetail::Full,
+            },
+        );
+        set_metrics_sink(None);
+
+        assert_eq!(package, Ok(ValidationPackageDefinition::ChainFull));
+        // other tests might record samples concurrently, so only look at our entry type
+        let recorded: Vec<(String, f64)> = samples
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, tag, _)| tag == "heavy_package_post")
+            .map(|(metric, _, value)| (metric.clone(), *value))
+            .collect();
+        assert_eq!(
+            recorded,
+            vec![(VALIDATION_PACKAGE_ASSEMBLY_MS.to_string(), 5.0)]
+        );
+    }
+
+    #[test]
+    fn validate_app_entry_counts_passes_and_failures() {
+        use crate::metrics::{set_metrics_sink, MetricsSink, VALIDATION_FAIL, VALIDATION_PASS};
+        use holochain_core_types::{
+            entry::entry_type::AppEntryType,
+            validation::{EntryValidationData, ValidationData},
+        };
+        use holochain_wasm_utils::api_serialization::validation::EntryValidationArgs;
+        use std::sync::{Arc, Mutex};
+
+        struct CapturingSink {
+            increments: Arc<Mutex<Vec<(String, String)>>>,
+        }
+
+        impl MetricsSink for CapturingSink {
+            fn increment(&self, metric: &str, tag: &str) {
+                self.increments
+                    .lock()
+                    .unwrap()
+                    .push((metric.to_string(), tag.to_string()));
+            }
+        }
+
+        let zome_definition = || {
+            zome_with(public_entry!(
+                name: "metered_post",
+                description: "a post",
+
+                validation: |validation_data: hdk::EntryValidationData<Post>| {
+                    match validation_data {
+                        EntryValidationData::Create { entry, .. } => {
+                            if entry.content.is_empty() {
+                                Err("content is empty".to_string())
+                            } else {
+                                Ok(())
+                            }
+                        }
+                        _ => Ok(()),
+                    }
+                }
+            ))
+        };
+        let args = |content: &str| EntryValidationArgs {
+            validation_data: EntryValidationData::Create {
+                entry: Entry::App(
+                    AppEntryType::from("metered_post"),
+                    Post {
+                        content: content.to_string(),
+                    }
+                    .into(),
+                ),
+                validation_data: ValidationData::default(),
+            },
+            ..Default::default()
+        };
+
+        let increments = Arc::new(Mutex::new(Vec::new()));
+        set_metrics_sink(Some(Box::new(CapturingSink {
+            increments: increments.clone(),
+        })));
+
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args("counted"), no_fetch),
+            Ok(Ok(Vec::new()))
+        );
+        assert!(
+            super::validate_app_entry(zome_definition(), args(""), no_fetch)
+                .unwrap()
+                .is_err()
+        );
+        set_metrics_sink(None);
+
+        // other tests might validate concurrently, so only look at our entry type
+        let metered: Vec<String> = increments
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, entry_type)| entry_type == "metered_post")
+            .map(|(metric, _)| metric.clone())
+            .collect();
+        assert_eq!(
+            metered,
+            vec![VALIDATION_PASS.to_string(), VALIDATION_FAIL.to_string()]
+        );
+    }
+
+    #[test]
+    fn validate_app_entry_translates_failure_messages() {
+        use crate::translation::set_message_translator;
+        use holochain_core_types::{
+            entry::entry_type::AppEntryType,
+            validation::{EntryValidationData, Locale, ValidationData},
+        };
+        use holochain_wasm_utils::api_serialization::validation::EntryValidationArgs;
+
+        let zome_definition = || {
+            zome_with(public_entry!(
+                name: "localized_post",
+                description: "a post",
+
+                validation: |validation_data: hdk::EntryValidationData<Post>| {
+                    match validation_data {
+                        EntryValidationData::Create { entry, .. } => {
+                            if entry.content.is_empty() {
+                                Err("error.content_empty".to_string())
+                            } else {
+                                Ok(())
+                            }
+                        }
+                        _ => Ok(()),
+                    }
+                }
+            ))
+        };
+        let args = |locale: Option<&str>| EntryValidationArgs {
+            validation_data: EntryValidationData::Create {
+                entry: Entry::App(
+                    AppEntryType::from("localized_post"),
+                    Post {
+                        content: String::new(),
+                    }
+                    .into(),
+                ),
+                validation_data: ValidationData::default(),
+            },
+            locale: locale.map(Locale::from),
+            ..Default::default()
+        };
+
+        // Without a translator the message stays as the validator wrote it
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args(Some("de")), no_fetch),
+            Ok(Err("error.content_empty".to_string()))
+        );
+
+        set_message_translator(Some(Box::new(|message: &str, locale: &Locale| {
+            match (message, locale.0.as_str()) {
+                ("error.content_empty", "de") => "Der Beitrag ist leer".to_string(),
+                _ => message.to_string(),
+            }
+        })));
+        let translated = super::validate_app_entry(zome_definition(), args(Some("de")), no_fetch);
+        let without_locale = super::validate_app_entry(zome_definition(), args(None), no_fetch);
+        set_message_translator(None);
+
+        assert_eq!(translated, Ok(Err("Der Beitrag ist leer".to_string())));
+        assert_eq!(without_locale, Ok(Err("error.content_empty".to_string())));
+    }
+
+    #[test]
+    fn validate_app_entry_reports_all_failures() {
+        use crate::entry_definition::ValidationFailures;
+        use holochain_core_types::{
+            entry::entry_type::AppEntryType,
+            validation::{EntryValidationData, ValidationData},
+        };
+        use holochain_wasm_utils::api_serialization::validation::EntryValidationArgs;
+
+        #[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+        pub struct Form {
+            name: String,
+            email: String,
+            age: u8,
+        }
+
+        let zd = zome_with(public_entry!(
+            name: "form",
+            description: "a form",
+
+            validation: |validation_data: hdk::EntryValidationData<Form>| {
+                match validation_data {
+                    EntryValidationData::Create { entry, .. } => {
+                        let mut errors = Vec::new();
+                        if entry.name.is_empty() {
+                            errors.push("name is empty".to_string());
+                        }
+                        if !entry.email.contains('@') {
+                            errors.push("email is invalid".to_string());
+                        }
+                        if entry.age < 18 {
+                            errors.push("age is below 18".to_string());
+                        }
+                        ValidationFailures::from(errors).into_result()
+                    }
+                    _ => Ok(()),
+                }
+            }
+        ));
+
+        let args = EntryValidationArgs {
+            validation_data: EntryValidationData::Create {
+                entry: Entry::App(
+                    AppEntryType::from("form"),
+                    Form {
+                        name: String::new(),
+                        email: "nope".to_string(),
+                        age: 12,
+                    }
+                    .into(),
+                ),
+                validation_data: ValidationData::default(),
+            },
+            ..Default::default()
+        };
+
+        let fail_string = match super::validate_app_entry(zd, args, no_fetch) {
+            Ok(Err(fail_string)) => fail_string,
+            other => panic!("Expected validation failure, got {:?}", other),
+        };
+
+        // this is the payload that gets written into the failure response
+        let response = JsonString::from_json(&fail_string);
+        assert_eq!(
+            ValidationFailures::from_failure_string(&String::from(response)),
+            ValidationFailures(vec![
+                "name is empty".to_string(),
+                "email is invalid".to_string(),
+                "age is below 18".to_string(),
+            ]),
+        );
+    }
+
+    #[test]
+    fn successful_validation_returns_index_hints() {
+        use holochain_core_types::{
+            entry::entry_type::AppEntryType,
+            validation::{EntryValidationData, ValidationData},
+        };
+        use holochain_persistence_api::cas::content::AddressableContent;
+        use holochain_wasm_utils::api_serialization::validation::{EntryValidationArgs, LinkSpec};
+
+        let anchor = Address::from("all_posts");
+        let hint_target = |entry: &Entry| LinkSpec {
+            base: Address::from("all_posts"),
+            target: entry.address(),
+            link_type: "indexed_post".to_string(),
+            tag: String::new(),
+        };
+
+        let zd = zome_with(
+            public_entry!(
+                name: "post",
+                description: "a post",
+
+                validation: |_validation_data: hdk::EntryValidationData<Post>| {
+                    Ok(())
+                }
+            )
+            .with_indexing_validator(Box::new(move |validation_data| {
+                match validation_data {
+                    EntryValidationData::Create { entry, .. } => Ok(vec![hint_target(&entry)]),
+                    _ => Ok(Vec::new()),
+                }
+            })),
+        );
+
+        let entry = Entry::App(
+            AppEntryType::from("post"),
+            Post {
+                content: "indexed".to_string(),
+            }
+            .into(),
+        );
+        let args = EntryValidationArgs {
+            validation_data: EntryValidationData::Create {
+                entry: entry.clone(),
+                validation_data: ValidationData::default(),
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            super::validate_app_entry(zd, args, no_fetch),
+            Ok(Ok(vec![LinkSpec {
+                base: anchor,
+                target: entry.address(),
+                link_type: "indexed_post".to_string(),
+                tag: String::new(),
+            }]))
+        );
+    }
+
+    #[test]
+    fn deferred_validation_succeeds_once_dependency_arrived() {
+        use crate::entry_definition::ValidationResult;
+        use holochain_core_types::{
+            entry::entry_type::AppEntryType,
+            validation::{EntryValidationData, ValidationData},
+        };
+        use std::sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        };
+
+        #[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+        pub struct Comment {
+            post: Address,
+        }
+
+        let post_held = Arc::new(AtomicBool::new(false));
+        let zome_definition = || {
+            let post_held = post_held.clone();
+            zome_with(
+                public_entry!(
+                    name: "comment",
+                    description: "a comment",
+
+                    validation: |_validation_data: hdk::EntryValidationData<Comment>| {
+                        Ok(())
+                    }
+                )
+                .with_dependency_validator(Box::new(
+                    move |_validation_data, _dependencies| {
+                        if post_held.load(Ordering::SeqCst) {
+                            ValidationResult::Valid
+                        } else {
+                            ValidationResult::Deferred(vec![Address::from("QmPost")])
+                        }
+                    },
+                )),
+            )
+        };
+        let args = || {
+            remote_args(EntryValidationData::Create {
+                entry: Entry::App(
+                    AppEntryType::from("comment"),
+                    Comment {
+                        post: Address::from("QmPost"),
+                    }
+                    .into(),
+                ),
+                validation_data: ValidationData::default(),
+            })
+        };
+
+        assert!(
+            match super::validate_app_entry(zome_definition(), args(), no_fetch) {
+                Ok(Err(_)) => true,
+                _ => false,
+            }
+        );
+        assert_eq!(
+            super::take_deferred_dependencies(),
+            Some(vec![Address::from("QmPost")])
+        );
+
+        // The host validates again once it holds the post
+        post_held.store(true, Ordering::SeqCst);
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args(), no_fetch),
+            Ok(Ok(Vec::new()))
+        );
+        assert_eq!(super::take_deferred_dependencies(), None);
+    }
+
+    #[test]
+    fn successful_validation_emits_signal_on_signal_channel() {
+        use holochain_core_types::{
+            entry::entry_type::AppEntryType,
+            validation::{EntryValidationData, ValidationData},
+        };
+        use std::sync::mpsc;
+
+        let zome_definition = || {
+            zome_with(public_entry!(
+                name: "message",
+                description: "a message",
+
+                validation: |validation_data: hdk::EntryValidationData<Post>| {
+                    crate::validation_signals::emit_on_success(
+                        "new_message_received",
+                        JsonString::empty_object(),
+                    );
+                    match validation_data {
+                        EntryValidationData::Create { entry, .. } if entry.content.is_empty() => {
+                            Err("Message is empty".to_string())
+                        }
+                        _ => Ok(()),
+                    }
+                }
+            ))
+        };
+        let args = |content: &str| {
+            remote_args(EntryValidationData::Create {
+                entry: Entry::App(
+                    AppEntryType::from("message"),
+                    Post {
+                        content: content.to_string(),
+                    }
+                    .into(),
+                ),
+                validation_data: ValidationData::default(),
+            })
+        };
+        let (signal_tx, signal_rx) = mpsc::channel();
+        let emit = |name: String, arguments: JsonString| {
+            signal_tx.send((name, arguments)).unwrap();
+            Ok(())
+        };
+
+        let result = super::validate_app_entry(zome_definition(), args("hello"), no_fetch);
+        assert_eq!(result, Ok(Ok(Vec::new())));
+        super::forward_validation_signals(&result, emit);
+        assert_eq!(
+            signal_rx.try_iter().collect::<Vec<_>>(),
+            vec![(
+                "new_message_received".to_string(),
+                JsonString::empty_object()
+            )]
+        );
+
+        // A rejected entry doesn't notify anybody
+        let result = super::validate_app_entry(zome_definition(), args(""), no_fetch);
+        assert_eq!(result, Ok(Err("Message is empty".to_string())));
+        super::forward_validation_signals(&result, emit);
+        assert_eq!(signal_rx.try_iter().count(), 0);
+    }
+
+    #[test]
+    fn current_hdk_version_is_the_hdk_version() {
+        assert_eq!(
+            crate::prelude::current_hdk_version(),
+            holochain_core_types::hdk_version::HDK_VERSION.to_string()
+        );
+    }
+
+    #[test]
+    fn export_failures_map_to_distinct_stable_codes() {
+        use super::ExportFailure;
+        use holochain_core_types::error::{
+            RibosomeEncodedValue, RibosomeEncodingBits, RibosomeErrorCode,
+        };
+
+        let codes = vec![
+            (
+                ExportFailure::NoMatchingType,
+                RibosomeErrorCode::UnknownEntryType,
+            ),
+            (
+                ExportFailure::TypeMismatch,
+                RibosomeErrorCode::EntryTypeMismatch,
+            ),
+            (
+                ExportFailure::ValidatorError,
+                RibosomeErrorCode::ValidatorFailed,
+            ),
+            (
+                ExportFailure::PackageError,
+                RibosomeErrorCode::ValidationPackageFailed,
+            ),
+            (
+                ExportFailure::DecodeError,
+                RibosomeErrorCode::ArgumentDeserializationFailed,
+            ),
+            (ExportFailure::MemoryError, RibosomeErrorCode::OutOfMemory),
+            (
+                ExportFailure::DefinitionTooLarge,
+                RibosomeErrorCode::DefinitionTooLarge,
+            ),
+        ];
+        for (failure, code) in codes.iter() {
+            assert_eq!(failure.code(), *code);
+            // the host decodes the same code from what the export returns
+            let bits = RibosomeEncodingBits::from(*failure);
+            assert_eq!(
+                RibosomeEncodedValue::from(bits),
+                RibosomeEncodedValue::Failure(code.clone())
+            );
+        }
+        let mut distinct: Vec<_> = codes.iter().map(|(failure, _)| failure.code()).collect();
+        distinct.sort();
+        distinct.dedup();
+        assert_eq!(distinct.len(), codes.len());
+    }
+
+    #[test]
+    fn failure_branches_yield_their_designated_codes() {
+        use super::ExportFailure;
+        use crate::{entry_definition::ValidationResult, error::ZomeApiError};
+        use holochain_core_types::{
+            agent::AgentId,
+            bits_n_pieces::u64_merge_bits,
+            entry::entry_type::AppEntryType,
+            error::{RibosomeEncodedValue, RibosomeEncodingBits},
+            validation::{EntryValidationData, ValidationData},
+        };
+        use holochain_wasm_utils::api_serialization::validation::{
+            EntryValidationArgs, LinkDirection, LinkValidationPackageArgs,
+        };
+
+        let zome_definition = || {
+            zome_with(
+                public_entry!(
+                    name: "post",
+                    description: "a post",
+
+                    validation: |_validation_data: hdk::EntryValidationData<Post>| {
+                        Ok(())
+                    }
+                )
+                .with_dependency_validator(Box::new(
+                    |_validation_data, _dependencies| {
+                        ValidationResult::NeedData(Address::from("QmDependency"))
+                    },
+                )),
+            )
+        };
+        let args = |entry| {
+            remote_args(EntryValidationData::Create {
+                entry,
+                validation_data: ValidationData::default(),
+            })
+        };
+        let post = |entry_type: &str| {
+            Entry::App(
+                AppEntryType::from(entry_type),
+                Post {
+                    content: "hello".to_string(),
+                }
+                .into(),
+            )
+        };
+        let failed_fetch = |_: &Address| -> ZomeApiResult<Option<Entry>> {
+            Err(ZomeApiError::Internal("network down".to_string()))
+        };
+
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args(post("comment")), no_fetch),
+            Err(RibosomeEncodedValue::from(ExportFailure::NoMatchingType))
+        );
+        assert_eq!(
+            super::validate_app_entry(
+                zome_definition(),
+                args(Entry::AgentId(AgentId::generate_fake("alice"))),
+                no_fetch
+            ),
+            Err(RibosomeEncodedValue::from(ExportFailure::DecodeError))
+        );
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args(post("post")), failed_fetch),
+            Err(RibosomeEncodedValue::from(ExportFailure::ValidatorError))
+        );
+
+        // There is no wasm memory outside of wasm, so nothing can be read or written
+        let out_of_bounds = u64_merge_bits(u32::max_value(), 42);
+        assert_eq!(
+            super::init_export_memory(out_of_bounds),
+            Err(ExportFailure::MemoryError)
+        );
+        assert_eq!(
+            super::load_export_args::<EntryValidationArgs>(
+                out_of_bounds,
+                "__hdk_validate_app_entry",
+                "EntryValidationArgs"
+            )
+            .map(|_| ()),
+            Err(ExportFailure::MemoryError)
+        );
+        assert_eq!(
+            super::fail_string_output("invalid"),
+            RibosomeEncodingBits::from(ExportFailure::MemoryError)
+        );
+        assert_eq!(
+            super::package_output(Some(ValidationPackageDefinition::Entry)),
+            RibosomeEncodingBits::from(ExportFailure::PackageError)
+        );
+        assert_eq!(
+            super::package_output(super::validation_package_for_link(
+                zome_definition(),
+                LinkValidationPackageArgs {
+                    entry_type: "post".to_string(),
+                    link_type: "comments".to_string(),
+                    direction: LinkDirection::To,
+                },
+            )),
+            RibosomeEncodingBits::from(ExportFailure::NoMatchingType)
+        );
+
+        let garbage = super::decode_export_args::<EntryValidationArgs>(
+            "__hdk_validate_app_entry",
+            "EntryValidationArgs",
+            "{\"not\": \"validation args\"".to_string(),
+        )
+        .map_err(|_| ExportFailure::DecodeError);
+        assert_eq!(garbage.map(|_| ()), Err(ExportFailure::DecodeError));
+    }
+
+    #[test]
+    fn oversized_definition_is_refused() {
+        use holochain_core_types::{
+            dna::entry_types::EntryTypeDef,
+            entry::entry_type::{AppEntryType, EntryType},
+            error::HolochainError,
+        };
+        use holochain_wasm_utils::api_serialization::meta::JsonDefinitionArgs;
+
+        // A pathological zome with thousands of entry types
+        let partial_zome = PartialZome {
+            entry_types: (0..5_000)
+                .map(|i| {
+                    (
+                        EntryType::App(AppEntryType::from(format!("entry_type_{}", i))),
+                        EntryTypeDef::new(),
+                    )
+                })
+                .collect(),
+            traits: BTreeMap::new(),
+            fn_declarations: Vec::new(),
+        };
+        let unlimited =
+            super::json_definition(partial_zome.clone(), &JsonDefinitionArgs::default())
+                .expect("Definition without limit must serialize");
+        assert!(unlimited.len() > 100_000);
+
+        let args = JsonDefinitionArgs {
+            with_checksum: false,
+            max_size: Some(100_000),
+        };
+        assert_eq!(
+            super::json_definition(partial_zome.clone(), &args),
+            Err(HolochainError::ErrorGeneric(format!(
+                "Zome definition is {} bytes, larger than the maximum of 100000 bytes",
+                unlimited.len()
+            )))
+        );
+
+        let args = JsonDefinitionArgs {
+            with_checksum: false,
+            max_size: Some(unlimited.len()),
+        };
+        assert_eq!(super::json_definition(partial_zome, &args), Ok(unlimited));
+    }
+
+    #[test]
+    fn validator_reads_registered_validation_config() {
+        use crate::validation_config::ValidationArgs;
+        use holochain_core_types::{
+            entry::{entry_type::AppEntryType, Entry},
+            validation::{EntryValidationData, ValidationData},
+        };
+
+        struct Limits {
+            max_len: usize,
+        }
+
+        let zome_definition = || {
+            let mut zd = super::ZomeDefinition::new();
+            zd.set_validation_config(Limits { max_len: 5 });
+            zd.define(public_entry!(
+                name: "post",
+                description: "a post",
+
+                validation: |validation_data: hdk::EntryValidationData<Post>| {
+                    let limits = ValidationArgs::config::<Limits>()
+                        .ok_or_else(|| "No limits registered".to_string())?;
+                    match validation_data {
+                        EntryValidationData::Create { entry, .. } => {
+                            if entry.content.len() > limits.max_len {
+                                Err(format!("Posts can't be longer than {}", limits.max_len))
+                            } else {
+                                Ok(())
+                            }
+                        }
+                        _ => Ok(()),
+                    }
+                }
+            ));
+            zd
+        };
+        let args = |content: &str| {
+            remote_args(EntryValidationData::Create {
+                entry: Entry::App(
+                    AppEntryType::from("post"),
+                    Post {
+                        content: content.to_string(),
+                    }
+                    .into(),
+                ),
+                validation_data: ValidationData::default(),
+            })
+        };
+
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args("short"), no_fetch),
+            Ok(Ok(Vec::new()))
+        );
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args("too long"), no_fetch),
+            Ok(Err("Posts can't be longer than 5".to_string()))
+        );
+        // Configs of other types are not handed out
+        assert!(ValidationArgs::config::<String>().is_none());
+    }
+
+    #[test]
+    fn entries_validate_under_the_rule_version_they_declare() {
+        use holochain_core_types::{
+            entry::{entry_type::AppEntryType, Entry},
+            validation::{EntryValidationData, ValidationData, ValidationOrigin},
+        };
+        use holochain_wasm_utils::api_serialization::validation::EntryValidationArgs;
+
+        fn require_content(post: &Post) -> Result<(), String> {
+            if post.content.is_empty() {
+                Err("Posts need content".to_string())
+            } else {
+                Ok(())
+            }
+        }
+
+        let zome_definition = || {
+            zome_with(public_entry!(
+                name: "post",
+                description: "a post",
+
+                validation: |validation_data: hdk::EntryValidationData<Post>| {
+                    match validation_data {
+                        EntryValidationData::Create { entry, .. } => require_content(&entry),
+                        _ => Ok(()),
+                    }
+                },
+
+                validation_versions: {
+                    1 => |_validation_data: hdk::EntryValidationData<Post>| {
+                        Ok(())
+                    },
+                    2 => |validation_data: hdk::EntryValidationData<Post>| {
+                        match validation_data {
+                            EntryValidationData::Create { entry, .. } => require_content(&entry),
+                            _ => Ok(()),
+                        }
+                    },
+                }
+            ))
+        };
+        let args = |rule_version: Option<u32>| EntryValidationArgs {
+            validation_data: EntryValidationData::Create {
+                entry: Entry::App(
+                    AppEntryType::from("post"),
+                    Post {
+                        content: String::new(),
+                    }
+                    .into(),
+                ),
+                validation_data: ValidationData::default(),
+            },
+            origin: ValidationOrigin::Remote,
+            rule_version,
+            ..Default::default()
+        };
+
+        // An old empty post was fine under v1 and stays valid
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args(Some(1)), no_fetch),
+            Ok(Ok(Vec::new()))
+        );
+        // New entries follow v2, which requires content
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args(Some(2)), no_fetch),
+            Ok(Err("Posts need content".to_string()))
+        );
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args(None), no_fetch),
+            Ok(Err("Posts need content".to_string()))
+        );
+        assert_eq!(
+            super::validate_app_entry(zome_definition(), args(Some(3)), no_fetch),
+            Ok(Err(
+                "Entry type post has no validator for rule version 3".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn setting_up_the_zome_from_zome_setup_fails_instead_of_deadlocking() {
+        let mut nested = None;
+        let _zd = super::ZomeDefinition::set_up_with(|_zd| {
+            nested = Some(super::ZomeDefinition::try_set_up_with(|_| ()).map(|_| ()));
+        });
+        assert_eq!(
+            nested,
+            Some(Err(
+                "The zome can't be set up again from within zome_setup".to_string()
+            ))
+        );
+
+        // Once the setup is done, the zome can be set up again
+        assert!(super::ZomeDefinition::try_set_up_with(|_| ()).is_ok());
+    }
+
+    #[test]
+    fn concurrent_exports_set_up_and_validate_independently() {
+        use crate::validation_config::ValidationArgs;
+        use holochain_core_types::{
+            entry::entry_type::AppEntryType,
+            validation::{EntryValidationData, ValidationData},
+        };
+        use std::{
+            sync::atomic::{AtomicUsize, Ordering},
+            thread,
+            time::Duration,
+        };
+
+        static SETTING_UP: AtomicUsize = AtomicUsize::new(0);
+
+        // Like an export: sets up the zome, validates, and collects the signals
+        let export = |agent: &'static str| {
+            let zd = super::ZomeDefinition::set_up_with(|zd| {
+                assert_eq!(
+                    SETTING_UP.fetch_add(1, Ordering::SeqCst),
+                    0,
+                    "zome_setup ran on two threads at once"
+                );
+                thread::sleep(Duration::from_millis(1));
+                zd.set_validation_config(agent.to_string());
+                zd.define(public_entry!(
+                    name: "post",
+                    description: "a post",
+
+                    validation: |_validation_data: hdk::EntryValidationData<Post>| {
+                        let agent = ValidationArgs::config::<String>()
+                            .ok_or_else(|| "No validation config".to_string())?;
+                        crate::validation_signals::emit_on_success(
+                            "validated",
+                            JsonString::from_json(&format!("\"{}\"", agent)),
+                        );
+                        Ok(())
+                    }
+                ));
+                SETTING_UP.fetch_sub(1, Ordering::SeqCst);
+            });
+            let args = remote_args(EntryValidationData::Create {
+                entry: Entry::App(
+                    AppEntryType::from("post"),
+                    Post {
+                        content: "hello".to_string(),
+                    }
+                    .into(),
+                ),
+                validation_data: ValidationData::default(),
+            });
+            super::take_validation_signals();
+            let result = super::validate_app_entry(zd, args, no_fetch);
+            let mut signals = Vec::new();
+            super::forward_validation_signals(&result, |name, arguments| {
+                signals.push((name, arguments));
+                Ok(())
+            });
+            (result, signals)
+        };
+
+        let threads = vec!["alice", "bob"]
+            .into_iter()
+            .map(|agent| {
+                thread::spawn(move || {
+                    for _ in 0..20 {
+                        assert_eq!(
+                            export(agent),
+                            (
+                                Ok(Ok(Vec::new())),
+                                vec![(
+                                    "validated".to_string(),
+                                    JsonString::from_json(&format!("\"{}\"", agent))
+                                )]
+                            )
+                        );
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in threads {
+            handle.join().expect("Concurrent export failed");
+        }
+    }
 }
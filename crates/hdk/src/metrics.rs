@@ -0,0 +1,83 @@
+//! Counters the HDK keeps about what happens inside the zome.
+//!
+//! The HDK has no access to the host's metric publisher, so counters get reported
+//! to a [MetricsSink](MetricsSink) that the zome (or a test) installs.
+//! Without a sink, counting costs a single atomic load.
+
+use lazy_static::lazy_static;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        RwLock,
+    },
+    time::Duration,
+};
+
+/// Counted once for every entry that passed validation, tagged with the entry type.
+pub const VALIDATION_PASS: &str = "validation.pass";
+/// Counted once for every entry that failed validation, tagged with the entry type.
+pub const VALIDATION_FAIL: &str = "validation.fail";
+
+/// Sampled for every validation package a package creator assembles, tagged with the
+/// entry type: how long the package creator took, in milliseconds.
+pub const VALIDATION_PACKAGE_ASSEMBLY_MS: &str = "validation.package_assembly_ms";
+
+/// Receives counter increments and samples.
+pub trait MetricsSink: Send + Sync {
+    fn increment(&self, metric: &str, tag: &str);
+
+    /// Current time, only used to measure durations.
+    /// There is no clock inside WASM, so nothing gets timed unless the sink supplies one.
+    fn now(&self) -> Option<Duration> {
+        None
+    }
+
+    fn record(&self, _metric: &str, _tag: &str, _value: f64) {}
+}
+
+lazy_static! {
+    static ref SINK: RwLock<Option<Box<dyn MetricsSink>>> = RwLock::new(None);
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Installs the sink that receives all counter increments from now on.
+/// Passing `None` switches counting off.
+pub fn set_metrics_sink(sink: Option<Box<dyn MetricsSink>>) {
+    let mut current = SINK.write().expect("Metrics sink poisoned");
+    ENABLED.store(sink.is_some(), Ordering::SeqCst);
+    *current = sink;
+}
+
+/// Increments the given counter on the installed sink, if any.
+pub fn increment_counter(metric: &str, tag: &str) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Some(sink) = SINK.read().expect("Metrics sink poisoned").as_ref() {
+        sink.increment(metric, tag);
+    }
+}
+
+/// Runs `f` and records how long it took, in milliseconds, as a sample of the given metric.
+pub fn time_in_ms<R, F: FnOnce() -> R>(metric: &str, tag: &str, f: F) -> R {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return f();
+    }
+    let start = now();
+    let result = f();
+    if let (Some(start), Some(end)) = (start, now()) {
+        if let Some(sink) = SINK.read().expect("Metrics sink poisoned").as_ref() {
+            let duration = end.checked_sub(start).unwrap_or_default();
+            sink.record(metric, tag, duration.as_micros() as f64 / 1000.0);
+        }
+    }
+    result
+}
+
+fn now() -> Option<Duration> {
+    SINK.read()
+        .expect("Metrics sink poisoned")
+        .as_ref()
+        .and_then(|sink| sink.now())
+}
@@ -3,7 +3,7 @@
 //! by simply adding use hdk::prelude::*;
 
 // macros
-pub use crate::{define_zome, entry, from, link, load_json, to};
+pub use crate::{define_entry_name, define_zome, entry, from, link, load_json, to};
 
 // derive macros
 pub use holochain_json_derive::DefaultJson;
@@ -32,5 +32,6 @@ pub use crate::{
         get_links::{GetLinksOptions, GetLinksResult, GetLinksResultCount, LinksStatusRequestKind},
         QueryArgsOptions, QueryResult,
     },
+    meta::current_hdk_version,
     EntryValidationData, LinkValidationData, ValidationPackageDefinition,
 };
@@ -0,0 +1,38 @@
+//! Translation of validation failure messages.
+//!
+//! Validators write their failure messages in one language. A zome that serves several
+//! locales can install a [MessageTranslator](MessageTranslator) which gets every failure
+//! message together with the locale the host asked for (see
+//! [EntryValidationArgs::locale](holochain_wasm_utils::api_serialization::validation::EntryValidationArgs::locale)).
+//! Without a translator, or without a locale, messages are passed on unchanged.
+
+use holochain_core_types::validation::Locale;
+use lazy_static::lazy_static;
+use std::sync::RwLock;
+
+/// Turns a failure message into its counterpart in the given locale.
+pub type MessageTranslator = Box<dyn Fn(&str, &Locale) -> String + Send + Sync>;
+
+lazy_static! {
+    static ref TRANSLATOR: RwLock<Option<MessageTranslator>> = RwLock::new(None);
+}
+
+/// Installs the translator that gets applied to all failure messages from now on.
+/// Passing `None` removes it.
+pub fn set_message_translator(translator: Option<MessageTranslator>) {
+    *TRANSLATOR.write().expect("Message translator poisoned") = translator;
+}
+
+/// Translates `message` to `locale` with the installed translator, if there is one.
+pub fn translate(message: String, locale: Option<&Locale>) -> String {
+    match (
+        TRANSLATOR
+            .read()
+            .expect("Message translator poisoned")
+            .as_ref(),
+        locale,
+    ) {
+        (Some(translator), Some(locale)) => translator(&message, locale),
+        _ => message,
+    }
+}
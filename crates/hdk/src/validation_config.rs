@@ -0,0 +1,52 @@
+//! Typed configuration that validators can borrow instead of parsing the
+//! DNA properties on every call.
+//!
+//! The zome registers the config with
+//! [ZomeDefinition::set_validation_config](crate::meta::ZomeDefinition::set_validation_config)
+//! in `zome_setup`, validators get it back with [ValidationArgs::config](ValidationArgs::config).
+
+use std::{any::Any, cell::RefCell, rc::Rc};
+
+thread_local! {
+    static VALIDATION_CONFIG: RefCell<Option<Rc<dyn Any>>> = RefCell::new(None);
+}
+
+/// Gives validators access to what the zome registered for them.
+pub struct ValidationArgs;
+
+impl ValidationArgs {
+    /// The registered validation config, if there is one and it is a `T`.
+    pub fn config<T: 'static>() -> Option<Rc<T>> {
+        VALIDATION_CONFIG
+            .with(|config| config.borrow().clone())
+            .and_then(|config| config.downcast::<T>().ok())
+    }
+}
+
+/// Makes `config` the validation config of the calling thread, replacing the one
+/// registered before. The replaced config is freed once no validator holds it anymore,
+/// so setting the zome up on every call keeps only one config per thread around.
+pub(crate) fn register<T: 'static>(config: T) {
+    let config: Rc<dyn Any> = Rc::new(config);
+    VALIDATION_CONFIG.with(|current| *current.borrow_mut() = Some(config));
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn registering_again_frees_the_replaced_config() {
+        register("first".to_string());
+        let first = ValidationArgs::config::<String>().expect("config should be registered");
+        let weak = Rc::downgrade(&first);
+        drop(first);
+
+        register("second".to_string());
+        assert!(weak.upgrade().is_none());
+        assert_eq!(
+            ValidationArgs::config::<String>().map(|config| (*config).clone()),
+            Some("second".to_string())
+        );
+    }
+}
@@ -0,0 +1,56 @@
+//! Progress reports of long running validators.
+//!
+//! A validator doing heavy work, like verifying a Merkle proof over many nodes, can call
+//! [report_progress](report_progress) as it goes, so a watchdog can tell a slow validator
+//! from a hung one. Reports go to the [ProgressObserver](ProgressObserver) that the zome
+//! (or a test) installs, and the latest one can be read with
+//! [validation_progress](validation_progress). Every validation starts out at zero.
+
+use lazy_static::lazy_static;
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    RwLock,
+};
+
+/// Gets handed every progress report.
+pub type ProgressObserver = Box<dyn Fn(u64) + Send + Sync>;
+
+lazy_static! {
+    static ref OBSERVER: RwLock<Option<ProgressObserver>> = RwLock::new(None);
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static PROGRESS: AtomicU64 = AtomicU64::new(0);
+
+/// Installs the observer that gets all progress reports from now on.
+/// Passing `None` removes it.
+pub fn set_progress_observer(observer: Option<ProgressObserver>) {
+    let mut current = OBSERVER.write().expect("Progress observer poisoned");
+    ENABLED.store(observer.is_some(), Ordering::SeqCst);
+    *current = observer;
+}
+
+/// Reports how far the running validator got, in whatever unit it counts its work.
+pub fn report_progress(progress: u64) {
+    PROGRESS.store(progress, Ordering::SeqCst);
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Some(observer) = OBSERVER
+        .read()
+        .expect("Progress observer poisoned")
+        .as_ref()
+    {
+        observer(progress);
+    }
+}
+
+/// The progress the running (or last) validator reported last.
+pub fn validation_progress() -> u64 {
+    PROGRESS.load(Ordering::SeqCst)
+}
+
+/// Starts the progress of a new validation at zero.
+pub(crate) fn reset_validation_progress() {
+    PROGRESS.store(0, Ordering::SeqCst);
+}
@@ -0,0 +1,23 @@
+//! Signals that validators emit for entries they accept.
+//!
+//! A validator calls [emit_on_success](emit_on_success), e.g. to notify the UI about
+//! a "new message received", instead of emitting the signal on a separate code path.
+//! The signals are held back until the validation is over: `__hdk_validate_app_entry`
+//! forwards them to the host's signal channel if the entry is valid and drops them otherwise.
+
+use holochain_json_api::json::JsonString;
+use std::cell::RefCell;
+
+thread_local! {
+    static SIGNALS: RefCell<Vec<(String, JsonString)>> = RefCell::new(Vec::new());
+}
+
+/// Emits the signal once the running validation succeeds, like [emit_signal](crate::api::emit_signal).
+pub fn emit_on_success<S: Into<String>, J: Into<JsonString>>(name: S, arguments: J) {
+    SIGNALS.with(|signals| signals.borrow_mut().push((name.into(), arguments.into())));
+}
+
+/// Takes the signals emitted since the last call, in the order they got emitted.
+pub(crate) fn take_validation_signals() -> Vec<(String, JsonString)> {
+    SIGNALS.with(|signals| signals.replace(Vec::new()))
+}
@@ -0,0 +1,92 @@
+//! Lightweight spans around the execution of entry validators.
+//!
+//! Zomes can't use the host's tracing, so the HDK calls back into a
+//! [ValidationSpanRecorder](ValidationSpanRecorder) that the zome (or a test) installs.
+//! As long as no recorder is installed, wrapping a validator costs a single atomic load.
+
+use holochain_core_types::validation::EntryValidationData;
+use lazy_static::lazy_static;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        RwLock,
+    },
+    time::Duration,
+};
+
+/// One finished validator invocation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationSpan {
+    /// Name of the entry type whose validator ran
+    pub entry_type: String,
+    /// "create", "modify" or "delete"
+    pub operation: &'static str,
+    /// How long the validator took, as measured by the recorder's clock
+    pub duration: Duration,
+}
+
+/// Receives validation spans.
+/// Since there is no monotonic clock inside WASM, the recorder also supplies the time.
+pub trait ValidationSpanRecorder: Send + Sync {
+    /// Current time, only used to compute span durations.
+    fn now(&self) -> Duration;
+    fn record(&self, span: ValidationSpan);
+}
+
+lazy_static! {
+    static ref RECORDER: RwLock<Option<Box<dyn ValidationSpanRecorder>>> = RwLock::new(None);
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Installs the recorder that receives all validation spans from now on.
+/// Passing `None` switches span recording off.
+pub fn set_validation_span_recorder(recorder: Option<Box<dyn ValidationSpanRecorder>>) {
+    let mut current = RECORDER.write().expect("Validation span recorder poisoned");
+    ENABLED.store(recorder.is_some(), Ordering::SeqCst);
+    *current = recorder;
+}
+
+/// Name of the operation that is being validated.
+pub fn operation_name<T>(validation_data: &EntryValidationData<T>) -> &'static str {
+    match validation_data {
+        EntryValidationData::Create { .. } => "create",
+        EntryValidationData::Modify { .. } => "modify",
+        EntryValidationData::Delete { .. } => "delete",
+    }
+}
+
+/// Runs `f` and reports a span for it if a recorder is installed.
+pub fn in_validation_span<R, F: FnOnce() -> R>(
+    entry_type: &str,
+    operation: &'static str,
+    f: F,
+) -> R {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return f();
+    }
+    let start = now();
+    let result = f();
+    if let (Some(start), Some(end)) = (start, now()) {
+        if let Some(recorder) = RECORDER
+            .read()
+            .expect("Validation span recorder poisoned")
+            .as_ref()
+        {
+            recorder.record(ValidationSpan {
+                entry_type: entry_type.to_string(),
+                operation,
+                duration: end.checked_sub(start).unwrap_or_default(),
+            });
+        }
+    }
+    result
+}
+
+fn now() -> Option<Duration> {
+    RECORDER
+        .read()
+        .expect("Validation span recorder poisoned")
+        .as_ref()
+        .map(|recorder| recorder.now())
+}
@@ -209,6 +209,11 @@ pub fn hc_emit_signal(_: RibosomeEncodingBits) -> RibosomeEncodingBits {
     RibosomeEncodedValue::Success.into()
 }
 
+#[no_mangle]
+pub fn hc_validation_oracle_query(_: RibosomeEncodingBits) -> RibosomeEncodingBits {
+    RibosomeEncodedValue::Success.into()
+}
+
 #[test]
 fn hash_entry() {
     let (mut hc, _, _) = start_holochain_instance("hash_entry", "alice");
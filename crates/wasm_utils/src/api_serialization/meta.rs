@@ -14,3 +14,24 @@ pub enum MetaResult {
     Version(String),
     Hash(String),
 }
+
+/// Input of `__hdk_get_json_definition`.
+#[derive(Deserialize, Debug, Serialize, DefaultJson, Default)]
+pub struct JsonDefinitionArgs {
+    /// Wrap the definition as `{ definition, checksum }` so the receiver can
+    /// detect a corrupted or tampered definition.
+    #[serde(default)]
+    pub with_checksum: bool,
+    /// Largest definition in bytes the receiver accepts; larger ones are refused.
+    #[serde(default)]
+    pub max_size: Option<usize>,
+}
+
+/// Output of `__hdk_memory_report`, for conductors to monitor how much memory a zome takes.
+#[derive(Deserialize, Debug, Serialize, DefaultJson, Clone, PartialEq)]
+pub struct MemoryReport {
+    /// Bytes allocated on the HDK's wasm memory stack when the report got made.
+    pub allocated_bytes: u64,
+    /// Bytes the zome's definition (entry types, traits and functions) takes up serialized.
+    pub definition_bytes: u64,
+}
@@ -1,15 +1,113 @@
 use holochain_core_types::{
     agent::AgentId,
-    entry::Entry,
+    entry::{entry_type::EntryType, test_entry, Entry},
     link::Link,
-    validation::{EntryValidationData, LinkValidationData},
+    signature::Provenance,
+    time::Iso8601,
+    validation::{
+        EntryValidationData, LinkValidationData, Locale, ValidationData, ValidationOrigin,
+        ValidationRng,
+    },
 };
 
 use holochain_json_api::{error::JsonError, json::*};
+use holochain_persistence_api::cas::content::Address;
 
 #[derive(Deserialize, Debug, Serialize, DefaultJson, Clone)]
 pub struct EntryValidationArgs {
     pub validation_data: EntryValidationData<Entry>,
+    /// Set by the host: whether the entry was authored locally or received
+    /// from the network.
+    #[serde(default)]
+    pub origin: ValidationOrigin,
+    /// Set by the host: the properties of the DNA.
+    #[serde(default = "JsonString::empty_object")]
+    pub dna_properties: JsonString,
+    /// Set by the host: seed derived from the address of the entry under validation.
+    #[serde(default)]
+    pub seed: u64,
+    /// Set by the host: the locale failure messages get translated to, if any.
+    #[serde(default)]
+    pub locale: Option<Locale>,
+    /// Set by the host: the version of the validation rules the entry declares to follow
+    /// in its `rule_version` field, if any (see `EntryValidationData::declared_rule_version()`).
+    /// Picks one of the entry type's versioned validators in the HDK.
+    #[serde(default)]
+    pub rule_version: Option<u32>,
+    /// Set by the host: timestamp of the entry's chain header.
+    #[serde(default)]
+    pub timestamp: Option<Iso8601>,
+    /// Set by the host: name of the zome the validator belongs to.
+    #[serde(default)]
+    pub zome_name: Option<String>,
+    /// Set by the host: hash of the DNA the validator runs in.
+    #[serde(default)]
+    pub dna_hash: Option<Address>,
+}
+
+/// Validating the creation of the test entry without any of the host set fields,
+/// for tests to override what they need.
+impl Default for EntryValidationArgs {
+    fn default() -> Self {
+        EntryValidationArgs {
+            validation_data: EntryValidationData::Create {
+                entry: test_entry(),
+                validation_data: ValidationData::default(),
+            },
+            origin: ValidationOrigin::default(),
+            dna_properties: JsonString::empty_object(),
+            seed: 0,
+            locale: None,
+            rule_version: None,
+            timestamp: None,
+            zome_name: None,
+            dna_hash: None,
+        }
+    }
+}
+
+impl EntryValidationArgs {
+    /// Pseudo random numbers that are the same on every node validating the same entry.
+    pub fn rng(&self) -> ValidationRng {
+        ValidationRng::new(self.seed)
+    }
+
+    /// Address of the agent who authored the entry, taken from the first provenance
+    /// of the header in the validation package.
+    /// `None` for the genesis entries (the DNA and the agent's `AgentId`) and for
+    /// headers without provenances.
+    pub fn author_address(&self) -> Option<Address> {
+        let header = &self.validation_data.validation_data().package.chain_header;
+        match header.entry_type() {
+            EntryType::Dna | EntryType::AgentId => None,
+            _ => header.provenances().first().map(Provenance::source),
+        }
+    }
+}
+
+/// Arguments of `__hdk_validate_commit_group`: the validation arguments of every entry
+/// that gets committed together, in commit order.
+#[derive(Deserialize, Debug, Serialize, DefaultJson, Clone)]
+pub struct CommitGroupValidationArgs {
+    pub entries: Vec<EntryValidationArgs>,
+}
+
+/// Links an entry's validator asks the host to create once the entry got committed
+/// (e.g. to make the entry findable from an index anchor) are described by a `LinkSpec`.
+pub use holochain_core_types::validation::LinkSpec;
+
+/// What `__hdk_validate_app_entry` returns for a valid entry whose validator gave index hints.
+/// Valid entries without hints are still reported as plain success.
+#[derive(Deserialize, Debug, Serialize, DefaultJson, Clone, PartialEq)]
+pub enum EntryValidationSuccess {
+    IndexHints(Vec<LinkSpec>),
+}
+
+/// What `__hdk_validate_app_entry` returns if the validator deferred its decision
+/// until the entries at the given addresses are held.
+#[derive(Deserialize, Debug, Serialize, DefaultJson, Clone, PartialEq)]
+pub struct DeferredValidation {
+    pub dependencies: Vec<Address>,
 }
 
 #[derive(Deserialize, Debug, Serialize, DefaultJson, Clone)]
@@ -17,6 +115,28 @@ pub struct AgentIdValidationArgs {
     pub validation_data: EntryValidationData<AgentId>,
 }
 
+/// How much of a validation package the host asks for.
+#[derive(Deserialize, Debug, Serialize, DefaultJson, PartialEq, Clone)]
+pub enum PackageDetail {
+    /// The package exactly as the package creator assembled it
+    Full,
+    /// A cheaper representation, good enough for quick checks
+    Digest,
+}
+
+impl Default for PackageDetail {
+    fn default() -> Self {
+        PackageDetail::Full
+    }
+}
+
+#[derive(Deserialize, Debug, Serialize, DefaultJson, Clone)]
+pub struct EntryValidationPackageArgs {
+    pub entry_type: String,
+    #[serde(default)]
+    pub detail_level: PackageDetail,
+}
+
 #[derive(Deserialize, Debug, Serialize, DefaultJson, PartialEq, Clone)]
 pub enum LinkDirection {
     To,
@@ -36,4 +156,84 @@ pub struct LinkValidationArgs {
     pub link: Link,
     pub direction: LinkDirection,
     pub validation_data: LinkValidationData,
+    /// The entry the link starts from, if the host sent it along.
+    #[serde(default)]
+    pub base_entry: Option<Entry>,
+    /// The entry the link points to, if the host sent it along.
+    #[serde(default)]
+    pub target_entry: Option<Entry>,
+    /// Set by the host: how many links of this type the link's author created before,
+    /// across all bases.
+    #[serde(default)]
+    pub agent_link_count: usize,
+    /// Set by the host: the type of the entry the link points to.
+    #[serde(default)]
+    pub target_type: Option<String>,
+}
+
+impl LinkValidationArgs {
+    /// Address of the entry the link starts from.
+    pub fn base(&self) -> &Address {
+        self.link.base()
+    }
+
+    /// Address of the entry the link points to.
+    pub fn target(&self) -> &Address {
+        self.link.target()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use holochain_core_types::{
+        chain_header::ChainHeader, entry::entry_type::test_entry_type, signature::Signature,
+        time::test_iso_8601, validation::ValidationPackage,
+    };
+    use holochain_persistence_api::cas::content::AddressableContent;
+
+    fn args_with_author(entry_type: EntryType, author: &str) -> EntryValidationArgs {
+        let header = ChainHeader::new(
+            &entry_type,
+            &test_entry().address(),
+            &[Provenance::new(
+                Address::from(author),
+                Signature::from("sig"),
+            )],
+            &None,
+            &None,
+            &None,
+            &test_iso_8601(),
+        );
+        EntryValidationArgs {
+            validation_data: EntryValidationData::Create {
+                entry: test_entry(),
+                validation_data: ValidationData {
+                    package: ValidationPackage::only_header(header),
+                    ..ValidationData::default()
+                },
+            },
+            origin: ValidationOrigin::Remote,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn validator_only_allows_commits_from_one_author() {
+        let admin = Address::from("admin");
+        let validate = |args: &EntryValidationArgs| match args.author_address() {
+            Some(ref author) if *author == admin => Ok(()),
+            _ => Err("Only the admin can commit this".to_string()),
+        };
+
+        assert_eq!(
+            validate(&args_with_author(test_entry_type(), "admin")),
+            Ok(())
+        );
+        assert!(validate(&args_with_author(test_entry_type(), "mallory")).is_err());
+        assert_eq!(
+            args_with_author(EntryType::AgentId, "admin").author_address(),
+            None
+        );
+    }
 }
@@ -113,12 +113,14 @@ pub fn create_test_dna_with_wasm(zome_name: &str, wasm: Vec<u8>) -> Dna {
     test_entry_def.links_to.push(LinksTo {
         target_type: String::from("testEntryType"),
         link_type: String::from("test-link"),
+        validation_package: None,
     });
 
     let mut test_entry_b_def = EntryTypeDef::new();
     test_entry_b_def.linked_from.push(LinkedFrom {
         base_type: String::from("testEntryType"),
         link_type: String::from("test-link"),
+        validation_package: None,
     });
 
     let mut test_entry_c_def = EntryTypeDef::new();
@@ -458,11 +460,13 @@ pub fn start_holochain_instance<T: Into<String>>(
         test_entry_type.links_to.push(LinksTo {
             target_type: String::from("testEntryType"),
             link_type: String::from("test"),
+            validation_package: None,
         });
 
         test_entry_type.links_to.push(LinksTo {
             target_type: String::from("testEntryType"),
             link_type: String::from("intergration test"),
+            validation_package: None,
         });
     }
 
@@ -472,6 +476,7 @@ pub fn start_holochain_instance<T: Into<String>>(
         link_validator.links_to.push(LinksTo {
             target_type: String::from("link_validator"),
             link_type: String::from("longer"),
+            validation_package: None,
         });
         entry_types.insert(EntryType::from("link_validator"), link_validator);
     }